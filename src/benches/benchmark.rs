@@ -3,7 +3,7 @@ extern crate criterion;
 extern crate evtx;
 
 use criterion::Criterion;
-use evtx::EvtxParser;
+use evtx::{EvtxParser, ParserSettings};
 
 // first chunk has 90 records
 fn process_90_records(buffer: &'static [u8]) {
@@ -32,6 +32,14 @@ fn process_90_records_json(buffer: &'static [u8]) {
     }
 }
 
+fn process_all_records(buffer: &'static [u8], settings: ParserSettings) {
+    let mut parser = EvtxParser::from_buffer(buffer.to_vec())
+        .unwrap()
+        .with_configuration(settings);
+
+    assert_eq!(parser.records().filter(|r| r.is_ok()).count(), 2261);
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     let evtx_file = include_bytes!("../../samples/security.evtx");
     // ~11ms before strings cache
@@ -44,6 +52,22 @@ fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("read 90 records json", move |b| {
         b.iter(|| process_90_records_json(evtx_file))
     });
+
+    // ~10% faster without checksums.
+    c.bench_function("read all records", move |b| {
+        b.iter(|| process_all_records(evtx_file, ParserSettings::new().num_threads(1)))
+    });
+
+    c.bench_function("read all records without checksums", move |b| {
+        b.iter(|| {
+            process_all_records(
+                evtx_file,
+                ParserSettings::new()
+                    .num_threads(1)
+                    .compute_checksums(false),
+            )
+        })
+    });
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -1,37 +1,142 @@
 use crate::err::{EvtxError, Result};
+use crate::evtx_record::RecordId;
+use crate::message_table::MessageParams;
 
-use crate::binxml::value_variant::BinXmlValue;
+use crate::binxml::value_variant::{BinXmlValue, NullRendering};
 use crate::model::deserialized::{
     BinXMLDeserializedTokens, BinXmlTemplateRef, TemplateSubstitutionDescriptor,
 };
-use crate::model::xml::{XmlElementBuilder, XmlModel, XmlPIBuilder};
+use crate::model::xml::{XmlAttribute, XmlElement, XmlElementBuilder, XmlModel, XmlPIBuilder};
+use crate::utils::{datetime_to_filetime, resolve_keyword_names, resolve_level_name};
 use crate::xml_output::BinXmlOutput;
 use log::{debug, trace, warn};
 use std::borrow::{BorrowMut, Cow};
 
 use std::mem;
 
-use crate::binxml::name::{BinXmlName, BinXmlNameRef};
+use crate::binxml::name::{BinXmlName, BinXmlNameLink, BinXmlNameRef};
 use crate::binxml::tokens::read_template_definition;
-use crate::EvtxChunk;
+use crate::{EvtxChunk, MultiFragmentMode, ParserSettings};
 use std::io::{Cursor, Seek, SeekFrom};
 
-pub fn parse_tokens<'a, T: BinXmlOutput>(
+/// Expands the templates of a record and passes its elements to `visitor`.
+/// Returns the maximum depth of nested elements, and the SID of the `UserID`
+/// of the `<Security>` element of the record. Also collects the message parameters
+/// of the record, if `message_params` is given.
+///
+/// Records with templates or elements which are nested deeper than `ParserSettings::max_depth`
+/// are rejected with `EvtxError::MaxDepthExceeded`.
+///
+/// The SID and the message parameters are collected before any of the transforms of `ParserSettings`
+/// (such as `sort_event_data` or `omit_empty_elements`) are applied,
+/// so the `%N` placeholders of the message always refer to the original `<Data>` elements.
+pub(crate) fn parse_record<'a, T: BinXmlOutput>(
     tokens: Vec<BinXMLDeserializedTokens<'a>>,
     chunk: &'a EvtxChunk<'a>,
+    record_id: RecordId,
     visitor: &mut T,
-) -> Result<()> {
-    let expanded_tokens = expand_templates(tokens, chunk)?;
-    let record_model = create_record_model(expanded_tokens, chunk)?;
+    message_params: Option<&mut MessageParams>,
+) -> Result<(usize, Option<String>)> {
+    let expanded_tokens = expand_templates(tokens, chunk, record_id)?;
+    let mut record_model = create_record_model(expanded_tokens, chunk)?;
+    let security_user_id = security_user_id(&record_model);
+
+    if let Some(message_params) = message_params {
+        visit_record_model(record_model.clone(), chunk, record_id, message_params)?;
+    }
+
+    if chunk.settings.get_multi_fragment() == MultiFragmentMode::FirstOnly {
+        record_model = first_fragment(record_model);
+    }
+
+    if chunk.settings.get_null_value() != NullRendering::JsonNull {
+        record_model = render_null_values(record_model, chunk.settings.get_null_value());
+    }
+
+    if chunk.settings.should_omit_empty_elements() {
+        record_model = omit_empty_elements(record_model);
+    }
+
+    if chunk.settings.should_sort_event_data() {
+        record_model = sort_event_data(record_model);
+    }
+
+    if chunk.settings.should_render_raw_filetimes() {
+        render_raw_filetimes(&mut record_model);
+    }
+
+    if chunk.settings.should_render_level_names() || chunk.settings.should_decode_keywords() {
+        annotate_system_elements(&mut record_model, &chunk.settings);
+    }
+
+    let depth = visit_record_model(record_model, chunk, record_id, visitor)?;
+
+    Ok((depth, security_user_id))
+}
+
+/// The SID of the `UserID` attribute of the `<Security>` element of `<System>`, if it has one.
+/// SIDs are returned as strings (ex. `S-1-5-18`), regardless of `resolve_well_known_sids`.
+fn security_user_id(record_model: &[XmlModel]) -> Option<String> {
+    let mut depth = 0;
+    let mut in_system = false;
+
+    for token in record_model {
+        match token {
+            XmlModel::OpenElement(element) => {
+                depth += 1;
+
+                match (depth, element.name.as_str()) {
+                    (2, "System") => in_system = true,
+                    (3, "Security") if in_system => {
+                        let user_id = element
+                            .attributes
+                            .iter()
+                            .find(|attribute| attribute.name.as_str() == "UserID")?;
+
+                        return match user_id.value.as_ref() {
+                            BinXmlValue::SidType(sid) => Some(sid.to_string()),
+                            BinXmlValue::StringType(sid) if !sid.is_empty() => Some(sid.clone()),
+                            _ => None,
+                        };
+                    }
+                    _ => {}
+                }
+            }
+            XmlModel::CloseElement => {
+                if depth == 2 && in_system {
+                    return None;
+                }
+                depth -= 1;
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
 
+/// Passes the elements of `record_model` to `visitor`, and returns the maximum depth of nested elements.
+fn visit_record_model<T: BinXmlOutput>(
+    record_model: Vec<XmlModel>,
+    chunk: &EvtxChunk,
+    record_id: RecordId,
+    visitor: &mut T,
+) -> Result<usize> {
     visitor.visit_start_of_stream()?;
 
     let mut stack = vec![];
+    let mut depth = 0;
 
     for owned_token in record_model {
         match owned_token {
             XmlModel::OpenElement(open_element) => {
                 stack.push(open_element);
+                depth = depth.max(stack.len());
+
+                if depth > chunk.settings.get_max_depth() {
+                    return Err(EvtxError::MaxDepthExceeded { record_id, depth });
+                }
+
                 visitor.visit_open_start_element(stack.last().ok_or({
                     EvtxError::FailedToCreateRecordModel(
                         "Invalid parser state - expected stack to be non-empty",
@@ -51,12 +156,336 @@ pub fn parse_tokens<'a, T: BinXmlOutput>(
             XmlModel::StartOfStream => {}
             XmlModel::PI(pi) => visitor.visit_processing_instruction(&pi)?,
             XmlModel::EntityRef(entity) => visitor.visit_entity_reference(&entity)?,
+            XmlModel::CData(cdata) => visitor.visit_cdata_section(cdata)?,
         };
     }
 
     visitor.visit_end_of_stream()?;
 
-    Ok(())
+    Ok(depth)
+}
+
+/// Drops everything after the first root element of the record (and its children).
+fn first_fragment(mut record_model: Vec<XmlModel>) -> Vec<XmlModel> {
+    let mut depth = 0;
+
+    for (i, token) in record_model.iter().enumerate() {
+        match token {
+            XmlModel::OpenElement(_) => depth += 1,
+            XmlModel::CloseElement => {
+                depth -= 1;
+                if depth == 0 {
+                    record_model.truncate(i + 1);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    record_model
+}
+
+/// Adds friendly names as attributes of elements of `<System>`, according to `settings`:
+/// a `Name` attribute to `<Level>` elements with a known level (ex. `<Level Name="Error">2</Level>`),
+/// and a `Names` attribute to `<Keywords>` elements with known keyword bits.
+fn annotate_system_elements(record_model: &mut [XmlModel], settings: &ParserSettings) {
+    let mut stack: Vec<usize> = vec![];
+
+    for i in 0..record_model.len() {
+        match &record_model[i] {
+            XmlModel::OpenElement(_) => stack.push(i),
+            XmlModel::CloseElement => {
+                stack.pop();
+                continue;
+            }
+            _ => continue,
+        }
+
+        let name = match stack.as_slice() {
+            [.., parent, _] => match (&record_model[*parent], &record_model[i]) {
+                (XmlModel::OpenElement(parent), XmlModel::OpenElement(element))
+                    if parent.name.as_str() == "System" =>
+                {
+                    element.name.as_str()
+                }
+                _ => continue,
+            },
+            _ => continue,
+        };
+
+        let value = match record_model.get(i + 1) {
+            Some(XmlModel::Value(value)) => value.as_cow_str(),
+            _ => continue,
+        };
+
+        let attribute = match name {
+            "Level" if settings.should_render_level_names() => value
+                .parse::<u64>()
+                .ok()
+                .and_then(resolve_level_name)
+                .map(|level_name| ("Name", BinXmlValue::StringType(level_name.to_string()))),
+            "Keywords" if settings.should_decode_keywords() => value
+                .strip_prefix("0x")
+                .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+                .map(resolve_keyword_names)
+                .filter(|names| !names.is_empty())
+                .map(|names| {
+                    let names = names.iter().map(|name| name.to_string()).collect();
+                    ("Names", BinXmlValue::StringArrayType(names))
+                }),
+            _ => None,
+        };
+
+        if let (Some((name, value)), XmlModel::OpenElement(element)) =
+            (attribute, &mut record_model[i])
+        {
+            element.attributes.push(XmlAttribute {
+                name: Cow::Owned(BinXmlName::from_string(name.to_string())),
+                value: Cow::Owned(value),
+            });
+        }
+    }
+}
+
+/// Replaces null values with empty strings, or drops them (see `ParserSettings::null_value`).
+/// Null attributes are kept as they are, and are rendered as empty strings by the outputs,
+/// which tell them apart from attributes which are empty strings.
+fn render_null_values(record_model: Vec<XmlModel>, null_value: NullRendering) -> Vec<XmlModel> {
+    let is_null = |value: &BinXmlValue| matches!(value, BinXmlValue::NullType);
+    let empty_string = || Cow::Owned(BinXmlValue::StringType(String::new()));
+
+    match null_value {
+        NullRendering::JsonNull => record_model,
+        NullRendering::EmptyString => record_model
+            .into_iter()
+            .map(|token| match token {
+                XmlModel::Value(value) if is_null(&value) => XmlModel::Value(empty_string()),
+                token => token,
+            })
+            .collect(),
+        NullRendering::OmitKey => {
+            let mut kept = Vec::with_capacity(record_model.len());
+            // The position of every open element in `kept`, and whether it had a null value
+            // and any other content so far.
+            let mut stack: Vec<(usize, bool, bool)> = vec![];
+
+            for token in record_model {
+                let has_content = match token {
+                    XmlModel::OpenElement(mut element) => {
+                        element
+                            .attributes
+                            .retain(|attribute| !is_null(&attribute.value));
+                        stack.push((kept.len(), false, false));
+                        kept.push(XmlModel::OpenElement(element));
+                        continue;
+                    }
+                    XmlModel::Value(ref value) if is_null(value) => {
+                        if let Some((_, has_null, _)) = stack.last_mut() {
+                            *has_null = true;
+                        }
+                        continue;
+                    }
+                    XmlModel::CloseElement => {
+                        if let Some((position, true, false)) = stack.pop() {
+                            kept.truncate(position);
+                            continue;
+                        }
+                        true
+                    }
+                    _ => true,
+                };
+
+                if let (true, Some((_, _, parent_has_content))) = (has_content, stack.last_mut()) {
+                    *parent_has_content = true;
+                }
+
+                kept.push(token);
+            }
+
+            kept
+        }
+    }
+}
+
+/// Replaces FileTime and SysTime values (in text and attributes) with their raw FILETIME
+/// (see `ParserSettings::raw_filetimes`).
+fn render_raw_filetimes(record_model: &mut [XmlModel]) {
+    fn raw_filetime(value: &mut Cow<BinXmlValue>) {
+        let raw = match value.as_ref() {
+            BinXmlValue::FileTimeArrayType(tms) | BinXmlValue::SysTimeArrayType(tms) => tms
+                .iter()
+                .map(datetime_to_filetime)
+                .collect::<Option<Vec<u64>>>()
+                .map(BinXmlValue::UInt64ArrayType),
+            value => value.filetime_raw().map(BinXmlValue::UInt64Type),
+        };
+
+        if let Some(raw) = raw {
+            *value = Cow::Owned(raw);
+        }
+    }
+
+    for token in record_model {
+        match token {
+            XmlModel::OpenElement(element) => {
+                for attribute in element.attributes.iter_mut() {
+                    raw_filetime(&mut attribute.value);
+                }
+            }
+            XmlModel::Value(value) => raw_filetime(value),
+            _ => {}
+        }
+    }
+}
+
+/// Drops elements without content and attributes (see `ParserSettings::omit_empty_elements`),
+/// including elements which only contained dropped elements.
+fn omit_empty_elements(record_model: Vec<XmlModel>) -> Vec<XmlModel> {
+    let mut kept = Vec::with_capacity(record_model.len());
+    // The position of every open element in `kept`, and whether it has any content so far.
+    let mut stack: Vec<(usize, bool)> = vec![];
+
+    for token in record_model {
+        let has_content = match &token {
+            XmlModel::OpenElement(element) => {
+                let has_attributes = element
+                    .attributes
+                    .iter()
+                    .any(|attribute| !is_blank(&attribute.value));
+                stack.push((kept.len(), has_attributes));
+                kept.push(token);
+                continue;
+            }
+            XmlModel::CloseElement => match stack.pop() {
+                Some((position, false)) => {
+                    kept.truncate(position);
+                    continue;
+                }
+                Some((_, true)) => true,
+                // Invalid structures are reported when the model is visited.
+                None => false,
+            },
+            XmlModel::Value(value) => !is_blank(value),
+            XmlModel::PI(_) | XmlModel::EntityRef(_) | XmlModel::CData(_) => true,
+            XmlModel::StartOfStream | XmlModel::EndOfStream => false,
+        };
+
+        if let (true, Some((_, parent_has_content))) = (has_content, stack.last_mut()) {
+            *parent_has_content = true;
+        }
+
+        kept.push(token);
+    }
+
+    kept
+}
+
+/// Sorts the children of the `<EventData>` element of the record by their `Name` attribute
+/// (see `ParserSettings::sort_event_data`).
+fn sort_event_data(record_model: Vec<XmlModel>) -> Vec<XmlModel> {
+    let mut sorted = Vec::with_capacity(record_model.len());
+    let mut tokens = record_model.into_iter();
+    let mut depth = 0;
+
+    while let Some(token) = tokens.next() {
+        match &token {
+            XmlModel::OpenElement(element) => {
+                // `<EventData>` is a child of the root `<Event>`.
+                if depth == 1 && element.name.as_str() == "EventData" {
+                    sorted.push(token);
+                    sorted.extend(sorted_children(&mut tokens));
+                    continue;
+                }
+
+                depth += 1;
+            }
+            XmlModel::CloseElement => depth -= 1,
+            _ => {}
+        }
+
+        sorted.push(token);
+    }
+
+    sorted
+}
+
+/// Takes the children of an element up to its closing token (included), sorted by their `Name`
+/// attribute. Children without a `Name` (including text) are moved after the others,
+/// keeping their order.
+fn sorted_children<'a>(tokens: &mut impl Iterator<Item = XmlModel<'a>>) -> Vec<XmlModel<'a>> {
+    let mut children: Vec<Vec<XmlModel>> = vec![];
+    let mut close_element = None;
+    let mut depth = 0;
+
+    for token in tokens {
+        match &token {
+            XmlModel::CloseElement if depth == 0 => {
+                close_element = Some(token);
+                break;
+            }
+            XmlModel::CloseElement => depth -= 1,
+            XmlModel::OpenElement(_) => {
+                if depth == 0 {
+                    children.push(vec![]);
+                }
+                depth += 1;
+            }
+            _ if depth == 0 => children.push(vec![]),
+            _ => {}
+        }
+
+        if let Some(child) = children.last_mut() {
+            child.push(token);
+        }
+    }
+
+    // The sort is stable, so children with the same name (or without one) keep their order.
+    children.sort_by_cached_key(|child| match child.first() {
+        Some(XmlModel::OpenElement(element)) => {
+            let name = element
+                .attributes
+                .iter()
+                .find(|attribute| attribute.name.as_str() == "Name")
+                .map(|attribute| attribute.value.as_cow_str().into_owned());
+
+            (name.is_none(), name)
+        }
+        _ => (true, None),
+    });
+
+    children
+        .into_iter()
+        .flatten()
+        .chain(close_element)
+        .collect()
+}
+
+/// True for null values, and strings which are empty or only whitespace.
+fn is_blank(value: &BinXmlValue) -> bool {
+    match value {
+        BinXmlValue::NullType => true,
+        BinXmlValue::StringType(s) => s.trim().is_empty(),
+        BinXmlValue::AnsiStringType(s) => s.trim().is_empty(),
+        _ => false,
+    }
+}
+
+/// An `<UnparsedToken name="..." offset="0x..."/>` element, in place of a token which could not be parsed.
+fn unparsed_token_placeholder<'a>(name: &str, offset: u64) -> XmlModel<'a> {
+    let attribute = |attribute_name: &str, value: String| XmlAttribute {
+        name: Cow::Owned(BinXmlName::from_string(attribute_name.to_owned())),
+        value: Cow::Owned(BinXmlValue::StringType(value)),
+    };
+
+    XmlModel::OpenElement(XmlElement {
+        name: Cow::Owned(BinXmlName::from_string("UnparsedToken".to_owned())),
+        attributes: vec![
+            attribute("name", name.to_owned()),
+            attribute("offset", format!("0x{:08x}", offset)),
+        ],
+        offset,
+    })
 }
 
 pub fn create_record_model<'a>(
@@ -98,11 +527,11 @@ pub fn create_record_model<'a>(
                     Some(builder) => model.push(XmlModel::OpenElement(builder.finish()?)),
                 };
             }
-            Cow::Owned(BinXMLDeserializedTokens::CDATASection)
-            | Cow::Borrowed(BinXMLDeserializedTokens::CDATASection) => {
-                return Err(EvtxError::FailedToCreateRecordModel(
-                    "Unimplemented - CDATA",
-                ));
+            Cow::Owned(BinXMLDeserializedTokens::CDATASection(cdata)) => {
+                model.push(XmlModel::CData(Cow::Owned(cdata)))
+            }
+            Cow::Borrowed(BinXMLDeserializedTokens::CDATASection(cdata)) => {
+                model.push(XmlModel::CData(Cow::Borrowed(cdata)))
             }
             Cow::Owned(BinXMLDeserializedTokens::CharRef)
             | Cow::Borrowed(BinXMLDeserializedTokens::CharRef) => {
@@ -110,6 +539,17 @@ pub fn create_record_model<'a>(
                     "Unimplemented - CharacterReference",
                 ));
             }
+            Cow::Owned(BinXMLDeserializedTokens::Unparsed { name, offset })
+            | Cow::Borrowed(&BinXMLDeserializedTokens::Unparsed { name, offset }) => {
+                // A placeholder element cannot be placed inside of a start element.
+                if current_element.is_some() {
+                    warn!("Unparsed token `{}` at offset {} was dropped", name, offset);
+                    continue;
+                }
+
+                model.push(unparsed_token_placeholder(name, offset));
+                model.push(XmlModel::CloseElement);
+            }
             Cow::Owned(BinXMLDeserializedTokens::EntityRef(ref entity))
             | Cow::Borrowed(BinXMLDeserializedTokens::EntityRef(ref entity)) => {
                 model.push(XmlModel::EntityRef(expand_string_ref(&entity.name, chunk)?))
@@ -196,6 +636,7 @@ pub fn create_record_model<'a>(
                 );
                 let mut builder = XmlElementBuilder::new();
                 builder.name(expand_string_ref(&elem.name, chunk)?);
+                builder.offset(elem.offset);
                 current_element = Some(builder);
             }
             Cow::Owned(BinXMLDeserializedTokens::Value(value)) => {
@@ -251,7 +692,12 @@ fn expand_string_ref<'a>(
             let cursor_ref = cursor.borrow_mut();
             try_seek!(cursor_ref, string_ref.offset, "Cache missed string")?;
 
-            let string = BinXmlName::from_stream(cursor_ref)?;
+            // Like in the string cache, the name is preceded by a link to the next string.
+            let _ = BinXmlNameLink::from_stream(cursor_ref)?;
+            let string = BinXmlName::from_stream_with_decoding(
+                cursor_ref,
+                chunk.settings.should_decode_strings_lossily(),
+            )?;
             Ok(Cow::Owned(string))
         }
     }
@@ -262,6 +708,7 @@ fn expand_token_substitution<'a>(
     substitution_descriptor: &TemplateSubstitutionDescriptor,
     chunk: &'a EvtxChunk<'a>,
     stack: &mut Vec<Cow<'a, BinXMLDeserializedTokens<'a>>>,
+    nesting: &Nesting,
 ) -> Result<()> {
     if substitution_descriptor.ignore {
         return Ok(());
@@ -276,12 +723,13 @@ fn expand_token_substitution<'a>(
             value,
             BinXMLDeserializedTokens::Value(BinXmlValue::NullType),
         );
-        _expand_templates(Cow::Owned(value), chunk, stack)?;
+        _expand_templates(Cow::Owned(value), chunk, stack, nesting)?;
     } else {
         _expand_templates(
             Cow::Owned(BinXMLDeserializedTokens::Value(BinXmlValue::NullType)),
             chunk,
             stack,
+            nesting,
         )?;
     }
 
@@ -292,6 +740,7 @@ fn expand_template<'a>(
     mut template: BinXmlTemplateRef<'a>,
     chunk: &'a EvtxChunk<'a>,
     stack: &mut Vec<Cow<'a, BinXMLDeserializedTokens<'a>>>,
+    nesting: &Nesting,
 ) -> Result<()> {
     if let Some(template_def) = chunk
         .template_table
@@ -300,9 +749,15 @@ fn expand_template<'a>(
         // We expect to find all the templates in the template cache.
         for token in template_def.tokens.iter() {
             if let BinXMLDeserializedTokens::Substitution(ref substitution_descriptor) = token {
-                expand_token_substitution(&mut template, substitution_descriptor, chunk, stack)?;
+                expand_token_substitution(
+                    &mut template,
+                    substitution_descriptor,
+                    chunk,
+                    stack,
+                    nesting,
+                )?;
             } else {
-                _expand_templates(Cow::Borrowed(token), chunk, stack)?;
+                _expand_templates(Cow::Borrowed(token), chunk, stack, nesting)?;
             }
         }
     } else {
@@ -312,6 +767,9 @@ fn expand_template<'a>(
             "Template in offset {} was not found in cache",
             template.template_def_offset
         );
+        chunk
+            .settings
+            .report_template_miss(template.template_def_offset);
 
         let mut cursor = Cursor::new(chunk.data);
 
@@ -321,9 +779,15 @@ fn expand_template<'a>(
 
         for token in template_def.tokens {
             if let BinXMLDeserializedTokens::Substitution(ref substitution_descriptor) = token {
-                expand_token_substitution(&mut template, substitution_descriptor, chunk, stack)?;
+                expand_token_substitution(
+                    &mut template,
+                    substitution_descriptor,
+                    chunk,
+                    stack,
+                    nesting,
+                )?;
             } else {
-                _expand_templates(Cow::Owned(token), chunk, stack)?;
+                _expand_templates(Cow::Owned(token), chunk, stack, nesting)?;
             }
         }
     };
@@ -331,34 +795,62 @@ fn expand_template<'a>(
     Ok(())
 }
 
+/// The number of templates (and binxml fragments) the expanded tokens are nested in,
+/// which is limited by `ParserSettings::max_depth` so crafted records cannot exhaust the stack.
+struct Nesting {
+    record_id: RecordId,
+    depth: usize,
+}
+
+impl Nesting {
+    fn enter(&self, chunk: &EvtxChunk) -> Result<Nesting> {
+        let depth = self.depth + 1;
+
+        if depth > chunk.settings.get_max_depth() {
+            return Err(EvtxError::MaxDepthExceeded {
+                record_id: self.record_id,
+                depth,
+            });
+        }
+
+        Ok(Nesting {
+            record_id: self.record_id,
+            depth,
+        })
+    }
+}
+
 fn _expand_templates<'a>(
     token: Cow<'a, BinXMLDeserializedTokens<'a>>,
     chunk: &'a EvtxChunk<'a>,
     stack: &mut Vec<Cow<'a, BinXMLDeserializedTokens<'a>>>,
+    nesting: &Nesting,
 ) -> Result<()> {
     match token {
         // Owned values can be consumed when flatting, and passed on as owned.
         Cow::Owned(BinXMLDeserializedTokens::Value(BinXmlValue::BinXmlType(tokens))) => {
+            let nesting = nesting.enter(chunk)?;
             for token in tokens.into_iter() {
-                _expand_templates(Cow::Owned(token), chunk, stack)?;
+                _expand_templates(Cow::Owned(token), chunk, stack, &nesting)?;
             }
         }
 
         Cow::Borrowed(BinXMLDeserializedTokens::Value(BinXmlValue::BinXmlType(tokens))) => {
+            let nesting = nesting.enter(chunk)?;
             for token in tokens.iter() {
-                _expand_templates(Cow::Borrowed(token), chunk, stack)?;
+                _expand_templates(Cow::Borrowed(token), chunk, stack, &nesting)?;
             }
         }
         // Actual template handling.
         Cow::Owned(BinXMLDeserializedTokens::TemplateInstance(template)) => {
-            expand_template(template, chunk, stack)?;
+            expand_template(template, chunk, stack, &nesting.enter(chunk)?)?;
         }
         Cow::Borrowed(BinXMLDeserializedTokens::TemplateInstance(template)) => {
             // This can happen if a template has a token which is:
             // 1. Another template.
             // 2. Is not a substitution (because they are `Owned` values).
             // We never actually see this in practice, so we don't mind paying for `clone` here.
-            expand_template(template.clone(), chunk, stack)?;
+            expand_template(template.clone(), chunk, stack, &nesting.enter(chunk)?)?;
         }
 
         _ => stack.push(token),
@@ -367,16 +859,249 @@ fn _expand_templates<'a>(
     Ok(())
 }
 
+/// Where `find_event_id` is in the elements of a record.
+#[derive(Default)]
+struct EventIdSearch<'a> {
+    path: Vec<Cow<'a, BinXmlName>>,
+    /// Set between the start of an element and the end of its attributes,
+    /// where values are the values of the attributes.
+    in_start_tag: bool,
+}
+
+impl EventIdSearch<'_> {
+    fn is_at_event_id(&self) -> bool {
+        !self.in_start_tag
+            && self.path.len() == 3
+            && self.path[1].as_str() == "System"
+            && self.path[2].as_str() == "EventID"
+    }
+}
+
+/// Reads the `<EventID>` of a record from its tokens, the definitions of its templates and their
+/// substitution values, without expanding the templates into elements.
+pub(crate) fn peek_event_id<'a>(
+    tokens: &[BinXMLDeserializedTokens<'a>],
+    chunk: &'a EvtxChunk<'a>,
+    record_id: RecordId,
+) -> Result<Option<u64>> {
+    find_event_id(
+        tokens,
+        &[],
+        chunk,
+        record_id,
+        &mut EventIdSearch::default(),
+        0,
+    )
+}
+
+fn find_event_id<'a>(
+    tokens: &[BinXMLDeserializedTokens<'a>],
+    substitutions: &[BinXMLDeserializedTokens<'a>],
+    chunk: &'a EvtxChunk<'a>,
+    record_id: RecordId,
+    search: &mut EventIdSearch<'a>,
+    depth: usize,
+) -> Result<Option<u64>> {
+    if depth > chunk.settings.get_max_depth() {
+        return Err(EvtxError::MaxDepthExceeded { record_id, depth });
+    }
+
+    for token in tokens {
+        let event_id = match token {
+            BinXMLDeserializedTokens::OpenStartElement(elem) => {
+                search.path.push(expand_string_ref(&elem.name, chunk)?);
+                search.in_start_tag = true;
+                None
+            }
+            BinXMLDeserializedTokens::CloseStartElement => {
+                search.in_start_tag = false;
+                None
+            }
+            BinXMLDeserializedTokens::CloseEmptyElement
+            | BinXMLDeserializedTokens::CloseElement => {
+                search.in_start_tag = false;
+                search.path.pop();
+                None
+            }
+            BinXMLDeserializedTokens::Value(BinXmlValue::BinXmlType(nested)) => {
+                find_event_id(nested, &[], chunk, record_id, search, depth + 1)?
+            }
+            BinXMLDeserializedTokens::Value(value) if search.is_at_event_id() => {
+                value.as_cow_str().trim().parse().ok()
+            }
+            BinXMLDeserializedTokens::Substitution(descriptor) if !descriptor.ignore => {
+                match substitutions.get(descriptor.substitution_index as usize) {
+                    Some(value) => find_event_id(
+                        std::slice::from_ref(value),
+                        &[],
+                        chunk,
+                        record_id,
+                        search,
+                        depth,
+                    )?,
+                    None => None,
+                }
+            }
+            BinXMLDeserializedTokens::TemplateInstance(template) => {
+                let substitutions = &template.substitution_array;
+
+                match chunk
+                    .template_table
+                    .get_template(template.template_def_offset)
+                {
+                    Some(template_def) => find_event_id(
+                        &template_def.tokens,
+                        substitutions,
+                        chunk,
+                        record_id,
+                        search,
+                        depth + 1,
+                    )?,
+                    // Like in `expand_template`, templates missing from the cache are read from the chunk.
+                    None => {
+                        let mut cursor = Cursor::new(chunk.data);
+                        let _ =
+                            cursor.seek(SeekFrom::Start(u64::from(template.template_def_offset)));
+                        let template_def = read_template_definition(
+                            &mut cursor,
+                            Some(chunk),
+                            chunk.settings.get_ansi_codec(),
+                        )?;
+
+                        find_event_id(
+                            &template_def.tokens,
+                            substitutions,
+                            chunk,
+                            record_id,
+                            search,
+                            depth + 1,
+                        )?
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        if event_id.is_some() {
+            return Ok(event_id);
+        }
+    }
+
+    Ok(None)
+}
+
 pub fn expand_templates<'a>(
     token_tree: Vec<BinXMLDeserializedTokens<'a>>,
     chunk: &'a EvtxChunk<'a>,
+    record_id: RecordId,
 ) -> Result<Vec<Cow<'a, BinXMLDeserializedTokens<'a>>>> {
     // We can assume the new tree will be at least as big as the old one.
     let mut stack = Vec::with_capacity(token_tree.len());
+    let nesting = Nesting {
+        record_id,
+        depth: 0,
+    };
 
     for token in token_tree {
-        _expand_templates(Cow::Owned(token), chunk, &mut stack)?
+        _expand_templates(Cow::Owned(token), chunk, &mut stack, &nesting)?
     }
 
     Ok(stack)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ensure_env_logger_initialized;
+    use crate::string_cache::StringCache;
+    use crate::EvtxParser;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_names_which_are_not_in_the_string_cache() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let settings = Arc::new(ParserSettings::new());
+
+        let mut chunk_data = parser.chunks().next().unwrap().unwrap();
+        let mut chunk = chunk_data.parse(Arc::clone(&settings)).unwrap();
+        let expected: Vec<_> = chunk
+            .iter()
+            .take(10)
+            .map(|record| record.unwrap().into_xml().unwrap().data)
+            .collect();
+
+        // Every name is then read from the chunk itself, past the link to the next string.
+        let mut chunk = chunk_data.parse(settings).unwrap();
+        chunk.string_cache = StringCache::populate(chunk.data, &[], false).unwrap();
+        let records: Vec<_> = chunk
+            .iter()
+            .take(10)
+            .map(|record| record.unwrap().into_xml().unwrap().data)
+            .collect();
+
+        assert_eq!(records, expected);
+    }
+
+    fn element(name: &str, attributes: Vec<(&str, &str)>) -> XmlModel<'static> {
+        XmlModel::OpenElement(XmlElement {
+            name: Cow::Owned(BinXmlName::from_str(name)),
+            attributes: attributes
+                .into_iter()
+                .map(|(name, value)| XmlAttribute {
+                    name: Cow::Owned(BinXmlName::from_str(name)),
+                    value: Cow::Owned(BinXmlValue::StringType(value.to_owned())),
+                })
+                .collect(),
+            offset: 0,
+        })
+    }
+
+    fn data(name: Option<&str>, value: &str) -> Vec<XmlModel<'static>> {
+        let attributes = name.map(|name| ("Name", name)).into_iter().collect();
+
+        vec![
+            element("Data", attributes),
+            XmlModel::Value(Cow::Owned(BinXmlValue::StringType(value.to_owned()))),
+            XmlModel::CloseElement,
+        ]
+    }
+
+    #[test]
+    fn test_sort_event_data() {
+        let event = |data: Vec<Vec<XmlModel<'static>>>| {
+            let mut record_model = vec![
+                element("Event", vec![]),
+                element("System", vec![]),
+                element("EventID", vec![]),
+                XmlModel::CloseElement,
+                XmlModel::CloseElement,
+                element("EventData", vec![]),
+            ];
+            record_model.extend(data.into_iter().flatten());
+            record_model.extend(vec![XmlModel::CloseElement, XmlModel::CloseElement]);
+
+            record_model
+        };
+
+        let record_model = event(vec![
+            data(None, "1"),
+            data(Some("TargetUserName"), "2"),
+            data(Some("SubjectUserName"), "3"),
+            data(None, "4"),
+            data(Some("SubjectDomainName"), "5"),
+        ]);
+
+        assert_eq!(
+            sort_event_data(record_model),
+            event(vec![
+                data(Some("SubjectDomainName"), "5"),
+                data(Some("SubjectUserName"), "3"),
+                data(Some("TargetUserName"), "2"),
+                data(None, "1"),
+                data(None, "4"),
+            ])
+        );
+    }
+}
@@ -6,7 +6,8 @@ use log::trace;
 use std::io::{Seek, SeekFrom};
 
 use crate::binxml::tokens::{
-    read_open_start_element, read_processing_instruction_data, read_processing_instruction_target,
+    read_cdata_section, read_open_start_element, read_processing_instruction_data,
+    read_processing_instruction_target,
 };
 use crate::binxml::value_variant::BinXmlValue;
 
@@ -19,6 +20,7 @@ use crate::{
 };
 
 use crate::evtx_chunk::EvtxChunk;
+use crate::evtx_parser::DEFAULT_MAX_DEPTH;
 use encoding::EncodingRef;
 
 use std::io::Cursor;
@@ -32,6 +34,7 @@ pub struct IterTokens<'a> {
     eof: bool,
     is_inside_substitution: bool,
     ansi_codec: EncodingRef,
+    depth: usize,
 }
 
 pub struct BinXmlDeserializer<'a> {
@@ -41,6 +44,8 @@ pub struct BinXmlDeserializer<'a> {
     // if called from substitution token with value type: Binary XML (0x21)
     is_inside_substitution: bool,
     ansi_codec: EncodingRef,
+    // The number of fragments (binxml values) this fragment is nested in.
+    depth: usize,
 }
 
 impl<'a> BinXmlDeserializer<'a> {
@@ -57,26 +62,38 @@ impl<'a> BinXmlDeserializer<'a> {
             chunk,
             is_inside_substitution,
             ansi_codec,
+            depth: 0,
         }
     }
 
     /// Returns a tuple of the tokens.
+    ///
+    /// `depth` is the number of fragments this fragment is nested in (0 for a record or a template).
+    /// Fragments nested deeper than `ParserSettings::max_depth` are rejected with
+    /// `DeserializationError::MaxDepthExceeded`, before they are read.
     pub fn read_binxml_fragment(
         cursor: &mut Cursor<&'a [u8]>,
         chunk: Option<&'a EvtxChunk<'a>>,
         data_size: Option<u32>,
         is_inside_substitution: bool,
         ansi_codec: EncodingRef,
+        depth: usize,
     ) -> Result<Vec<BinXMLDeserializedTokens<'a>>> {
         let offset = cursor.position();
 
-        let de = BinXmlDeserializer::init(
+        let max_depth = chunk.map_or(DEFAULT_MAX_DEPTH, |chunk| chunk.settings.get_max_depth());
+        if depth > max_depth {
+            return Err(DeserializationError::MaxDepthExceeded { depth, offset });
+        }
+
+        let mut de = BinXmlDeserializer::init(
             *cursor.get_ref(),
             offset,
             chunk,
             is_inside_substitution,
             ansi_codec,
         );
+        de.depth = depth;
 
         let mut tokens = vec![];
         let mut iterator = de.iter_tokens(data_size)?;
@@ -109,6 +126,7 @@ impl<'a> BinXmlDeserializer<'a> {
             eof: false,
             is_inside_substitution: self.is_inside_substitution,
             ansi_codec: self.ansi_codec,
+            depth: self.depth,
         })
     }
 }
@@ -125,6 +143,16 @@ impl<'a> IterTokens<'a> {
         })
     }
 
+    fn should_decode_strings_lossily(&self) -> bool {
+        self.chunk
+            .is_some_and(|chunk| chunk.settings.should_decode_strings_lossily())
+    }
+
+    fn should_tolerate_unknown_tokens(&self) -> bool {
+        self.chunk
+            .is_some_and(|chunk| chunk.settings.should_tolerate_unknown_tokens())
+    }
+
     fn visit_token(
         &self,
         cursor: &mut Cursor<&'a [u8]>,
@@ -147,15 +175,30 @@ impl<'a> IterTokens<'a> {
             BinXMLRawToken::CloseEmptyElement => Ok(BinXMLDeserializedTokens::CloseEmptyElement),
             BinXMLRawToken::CloseElement => Ok(BinXMLDeserializedTokens::CloseElement),
             BinXMLRawToken::Value => Ok(BinXMLDeserializedTokens::Value(
-                BinXmlValue::from_binxml_stream(cursor, self.chunk, None, self.ansi_codec)?,
+                BinXmlValue::from_binxml_stream(
+                    cursor,
+                    self.chunk,
+                    None,
+                    self.ansi_codec,
+                    self.depth,
+                )?,
             )),
             BinXMLRawToken::Attribute(_token_information) => {
                 Ok(BinXMLDeserializedTokens::Attribute(read_attribute(cursor)?))
             }
-            BinXMLRawToken::CDataSection => Err(DeserializationError::UnimplementedToken {
-                name: "CDataSection",
-                offset: cursor.position(),
-            }),
+            BinXMLRawToken::CDataSection => Ok(BinXMLDeserializedTokens::CDATASection(
+                read_cdata_section(cursor, self.should_decode_strings_lossily())?,
+            )),
+            BinXMLRawToken::CharReference if self.should_tolerate_unknown_tokens() => {
+                let offset = cursor.position() - 1;
+                // The token is followed by the value of the character.
+                try_read!(cursor, u16, "char_reference_value")?;
+
+                Ok(BinXMLDeserializedTokens::Unparsed {
+                    name: "CharReference",
+                    offset,
+                })
+            }
             BinXMLRawToken::CharReference => Err(DeserializationError::UnimplementedToken {
                 name: "CharReference",
                 offset: cursor.position(),
@@ -167,10 +210,10 @@ impl<'a> IterTokens<'a> {
                 read_processing_instruction_target(cursor)?,
             )),
             BinXMLRawToken::ProcessingInstructionData => Ok(BinXMLDeserializedTokens::PIData(
-                read_processing_instruction_data(cursor)?,
+                read_processing_instruction_data(cursor, self.should_decode_strings_lossily())?,
             )),
             BinXMLRawToken::TemplateInstance => Ok(BinXMLDeserializedTokens::TemplateInstance(
-                read_template(cursor, self.chunk, self.ansi_codec)?,
+                read_template(cursor, self.chunk, self.ansi_codec, self.depth)?,
             )),
             BinXMLRawToken::NormalSubstitution => Ok(BinXMLDeserializedTokens::Substitution(
                 read_substitution_descriptor(cursor, false)?,
@@ -314,4 +357,105 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_tolerate_unknown_tokens() {
+        use super::BinXmlDeserializer;
+        use crate::binxml::assemble::create_record_model;
+        use crate::err::DeserializationError;
+        use crate::model::deserialized::BinXMLDeserializedTokens;
+        use crate::model::xml::XmlModel;
+        use std::borrow::Cow;
+        use std::io::Cursor;
+
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../../samples/security.evtx");
+        let mut chunk = EvtxChunkData::new(evtx_file[4096..4096 + 65536].to_vec(), true).unwrap();
+
+        // A fragment header, a character reference to `A` and the end of the stream.
+        let data: &[u8] = &[0x0f, 0x01, 0x01, 0x00, 0x08, 0x41, 0x00, 0x00];
+
+        let settings = Arc::new(ParserSettings::default());
+        let evtx_chunk = chunk.parse(Arc::clone(&settings)).unwrap();
+        let result = BinXmlDeserializer::read_binxml_fragment(
+            &mut Cursor::new(data),
+            Some(&evtx_chunk),
+            None,
+            false,
+            settings.get_ansi_codec(),
+            0,
+        );
+        assert!(matches!(
+            result,
+            Err(DeserializationError::UnimplementedToken {
+                name: "CharReference",
+                ..
+            })
+        ));
+
+        let settings = Arc::new(ParserSettings::new().tolerate_unknown_tokens(true));
+        let evtx_chunk = chunk.parse(Arc::clone(&settings)).unwrap();
+        let tokens = BinXmlDeserializer::read_binxml_fragment(
+            &mut Cursor::new(data),
+            Some(&evtx_chunk),
+            None,
+            false,
+            settings.get_ansi_codec(),
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(
+            tokens[1],
+            BinXMLDeserializedTokens::Unparsed {
+                name: "CharReference",
+                offset: 4
+            }
+        );
+        assert_eq!(tokens[2], BinXMLDeserializedTokens::EndOfStream);
+
+        let model =
+            create_record_model(tokens.into_iter().map(Cow::Owned).collect(), &evtx_chunk).unwrap();
+        match &model[0] {
+            XmlModel::OpenElement(element) => {
+                assert_eq!(element.name.as_str(), "UnparsedToken");
+                assert_eq!(element.attributes[1].value.as_cow_str(), "0x00000004");
+            }
+            other => panic!("Expected a placeholder element, got {:?}", other),
+        }
+        assert_eq!(model[1], XmlModel::CloseElement);
+    }
+
+    #[test]
+    fn test_max_depth_of_nested_fragments() {
+        use crate::binxml::deserializer::BinXmlDeserializer;
+        use crate::err::DeserializationError;
+        use std::io::Cursor;
+
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../../samples/security.evtx");
+        let mut chunk = EvtxChunkData::new(evtx_file[4096..4096 + 65536].to_vec(), true).unwrap();
+        let settings = Arc::new(ParserSettings::new().max_depth(32));
+        let evtx_chunk = chunk.parse(Arc::clone(&settings)).unwrap();
+
+        // Value tokens of binxml type, each nesting the next one.
+        let data = [0x05, 0x21].repeat(100_000);
+
+        let result = BinXmlDeserializer::read_binxml_fragment(
+            &mut Cursor::new(data.as_slice()),
+            Some(&evtx_chunk),
+            None,
+            false,
+            settings.get_ansi_codec(),
+            0,
+        );
+
+        match result {
+            Err(DeserializationError::MaxDepthExceeded { depth, offset }) => {
+                assert_eq!(depth, 33);
+                assert_eq!(offset, 2 * 33);
+            }
+            other => panic!("Expected a `MaxDepthExceeded` error, got {:?}", other),
+        }
+    }
 }
@@ -88,15 +88,27 @@ impl BinXmlName {
         BinXmlName { str: s.to_string() }
     }
 
-    #[cfg(test)]
     pub(crate) fn from_string(s: String) -> Self {
         BinXmlName { str: s }
     }
 
     /// Reads a tuple of (String, Hash, Offset) from a stream.
     pub fn from_stream(cursor: &mut Cursor<&[u8]>) -> Result<Self> {
-        let name = try_read!(cursor, len_prefixed_utf_16_str_nul_terminated, "name")?
-            .unwrap_or_else(|| "".to_string());
+        Self::from_stream_with_decoding(cursor, false)
+    }
+
+    /// Like `from_stream`, but replaces invalid utf16 with U+FFFD if `lossy` is set.
+    pub(crate) fn from_stream_with_decoding(
+        cursor: &mut Cursor<&[u8]>,
+        lossy: bool,
+    ) -> Result<Self> {
+        let name = try_read!(
+            cursor,
+            len_prefixed_utf_16_str_nul_terminated,
+            "name",
+            lossy
+        )?
+        .unwrap_or_else(|| "".to_string());
 
         Ok(BinXmlName { str: name })
     }
@@ -23,11 +23,12 @@ pub fn read_template<'a>(
     cursor: &mut Cursor<&'a [u8]>,
     chunk: Option<&'a EvtxChunk<'a>>,
     ansi_codec: EncodingRef,
+    depth: usize,
 ) -> Result<BinXmlTemplateRef<'a>> {
     trace!("TemplateInstance at {}", cursor.position());
 
     let _ = try_read!(cursor, u8)?;
-    let _template_id = try_read!(cursor, u32)?;
+    let template_id = try_read!(cursor, u32)?;
     let template_definition_data_offset = try_read!(cursor, u32)?;
 
     // Need to skip over the template data.
@@ -78,6 +79,7 @@ pub fn read_template<'a>(
             chunk,
             Some(descriptor.size),
             ansi_codec,
+            depth,
         )?;
 
         trace!("\t {:?}", value);
@@ -109,6 +111,7 @@ pub fn read_template<'a>(
     }
 
     Ok(BinXmlTemplateRef {
+        template_id,
         template_def_offset: template_definition_data_offset,
         substitution_array,
     })
@@ -150,6 +153,7 @@ pub fn read_template_definition<'a>(
         Some(header.data_size),
         false,
         ansi_codec,
+        0,
     ) {
         Ok(tokens) => BinXMLTemplateDefinition { header, tokens },
         Err(e) => {
@@ -203,18 +207,27 @@ pub fn read_processing_instruction_target(
     Ok(BinXMLProcessingInstructionTarget { name })
 }
 
-pub fn read_processing_instruction_data(cursor: &mut Cursor<&[u8]>) -> Result<String> {
+pub fn read_processing_instruction_data(cursor: &mut Cursor<&[u8]>, lossy: bool) -> Result<String> {
     trace!(
         "Offset `0x{:08x}` - ProcessingInstructionTarget",
         cursor.position(),
     );
 
-    let data =
-        try_read!(cursor, len_prefixed_utf_16_str, "pi_data")?.unwrap_or_else(|| "".to_string());
+    let data = try_read!(cursor, len_prefixed_utf_16_str, "pi_data", lossy)?
+        .unwrap_or_else(|| "".to_string());
     trace!("PIData - {}", data,);
     Ok(data)
 }
 
+pub fn read_cdata_section(cursor: &mut Cursor<&[u8]>, lossy: bool) -> Result<String> {
+    trace!("Offset `0x{:08x}` - CDataSection", cursor.position());
+
+    let data = try_read!(cursor, len_prefixed_utf_16_str, "cdata_section", lossy)?
+        .unwrap_or_else(|| "".to_string());
+    trace!("CDATA - {}", data);
+    Ok(data)
+}
+
 pub fn read_substitution_descriptor(
     cursor: &mut Cursor<&[u8]>,
     optional: bool,
@@ -249,9 +262,10 @@ pub fn read_open_start_element(
     has_attributes: bool,
     is_substitution: bool,
 ) -> Result<BinXMLOpenStartElement> {
+    let offset = cursor.position();
     trace!(
         "Offset `0x{:08x}` - OpenStartElement<has_attributes={}, is_substitution={}>",
-        cursor.position(),
+        offset,
         has_attributes,
         is_substitution
     );
@@ -301,5 +315,9 @@ pub fn read_open_start_element(
         0
     };
 
-    Ok(BinXMLOpenStartElement { data_size, name })
+    Ok(BinXMLOpenStartElement {
+        data_size,
+        name,
+        offset,
+    })
 }
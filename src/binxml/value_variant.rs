@@ -9,9 +9,11 @@ use winstructs::guid::Guid;
 
 use crate::model::deserialized::BinXMLDeserializedTokens;
 use crate::utils::{
-    read_ansi_encoded_string, read_len_prefixed_utf16_string, read_null_terminated_utf16_string,
-    read_systemtime, read_utf16_by_size,
+    datetime_to_filetime, read_ansi_encoded_string, read_len_prefixed_utf16_string,
+    read_null_terminated_utf16_string, read_systemtime, read_utf16_by_size, resolve_well_known_sid,
+    truncate_to_microseconds,
 };
+use crate::ParserSettings;
 use chrono::{DateTime, Utc};
 use log::trace;
 use serde_json::{json, Value};
@@ -23,6 +25,136 @@ use winstructs::security::Sid;
 use crate::evtx_chunk::EvtxChunk;
 use std::fmt::Write;
 
+/// Controls how binary values (`BinXmlValue::BinaryType`) are rendered in the output.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BinaryEncoding {
+    /// Lowercase hex, ex. `0a1b`.
+    Hex,
+    /// Uppercase hex, ex. `0A1B` (the default).
+    HexUpper,
+    /// Standard base64, with padding.
+    Base64,
+    /// Standard base64, without padding.
+    Base64NoPad,
+}
+
+impl Default for BinaryEncoding {
+    fn default() -> Self {
+        BinaryEncoding::HexUpper
+    }
+}
+
+impl BinaryEncoding {
+    /// Renders `bytes` using this encoding, an empty buffer is always rendered as an empty string.
+    pub fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            BinaryEncoding::Hex | BinaryEncoding::HexUpper => {
+                // Bytes will be formatted as const length of 2 with '0' padding.
+                let mut repr = String::with_capacity(bytes.len() * 2);
+
+                for b in bytes.iter() {
+                    if self == BinaryEncoding::Hex {
+                        write!(repr, "{:02x}", b)
+                    } else {
+                        write!(repr, "{:02X}", b)
+                    }
+                    .expect("Writing to a String cannot fail");
+                }
+
+                repr
+            }
+            BinaryEncoding::Base64 => base64::encode_config(bytes, base64::STANDARD),
+            BinaryEncoding::Base64NoPad => base64::encode_config(bytes, base64::STANDARD_NO_PAD),
+        }
+    }
+}
+
+/// Controls how GUID values are rendered in the output.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GuidFormat {
+    /// Uppercase, surrounded by braces, ex. `{54849625-5478-4994-A5BA-3E3B0328C30D}`.
+    Braces,
+    /// Lowercase, ex. `54849625-5478-4994-a5ba-3e3b0328c30d`.
+    Hyphenated,
+    /// Uppercase, ex. `54849625-5478-4994-A5BA-3E3B0328C30D` (the default).
+    HyphenatedUpper,
+    /// Lowercase URN, ex. `urn:uuid:54849625-5478-4994-a5ba-3e3b0328c30d`.
+    Urn,
+}
+
+impl Default for GuidFormat {
+    fn default() -> Self {
+        GuidFormat::HyphenatedUpper
+    }
+}
+
+impl GuidFormat {
+    pub fn format(self, guid: &Guid) -> String {
+        // `Guid` is displayed as uppercase and hyphenated.
+        let guid = guid.to_string();
+
+        match self {
+            GuidFormat::Braces => format!("{{{}}}", guid),
+            GuidFormat::Hyphenated => guid.to_ascii_lowercase(),
+            GuidFormat::HyphenatedUpper => guid,
+            GuidFormat::Urn => format!("urn:uuid:{}", guid.to_ascii_lowercase()),
+        }
+    }
+}
+
+/// Controls how null values (`BinXmlValue::NullType`, usually substitutions which were left empty)
+/// are rendered, see `ParserSettings::null_value`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum NullRendering {
+    /// Elements whose only content is a null value, and attributes with a null value, are omitted.
+    OmitKey,
+    /// Null values are empty strings, ex. `"UserID": ""` in JSON and `UserID=""` in XML.
+    EmptyString,
+    /// Elements with a null value are `null` in JSON and empty in XML, attributes with a null
+    /// value are omitted (the default).
+    JsonNull,
+}
+
+impl Default for NullRendering {
+    fn default() -> Self {
+        NullRendering::JsonNull
+    }
+}
+
+/// Controls how values which have more than one textual representation are rendered.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct ValueFormat {
+    pub binary_encoding: BinaryEncoding,
+    pub guid_format: GuidFormat,
+    /// If set, well-known SIDs are rendered as their names (see `resolve_well_known_sid`).
+    pub resolve_well_known_sids: bool,
+    /// If set, 64-bit integers are rendered as strings in JSON.
+    pub large_ints_as_strings: bool,
+}
+
+impl ValueFormat {
+    pub fn from_settings(settings: &ParserSettings) -> Self {
+        ValueFormat {
+            binary_encoding: settings.get_binary_encoding(),
+            guid_format: settings.get_guid_format(),
+            resolve_well_known_sids: settings.should_resolve_well_known_sids(),
+            large_ints_as_strings: settings.should_render_large_ints_as_strings(),
+        }
+    }
+
+    fn format_sid(self, sid: &Sid) -> String {
+        let sid = sid.to_string();
+
+        if self.resolve_well_known_sids {
+            if let Some(name) = resolve_well_known_sid(&sid) {
+                return name.to_owned();
+            }
+        }
+
+        sid
+    }
+}
+
 #[derive(Debug, PartialOrd, PartialEq, Clone)]
 pub enum BinXmlValue<'a> {
     NullType,
@@ -40,7 +172,7 @@ pub enum BinXmlValue<'a> {
     Real32Type(f32),
     Real64Type(f64),
     BoolType(bool),
-    BinaryType(&'a [u8]),
+    BinaryType(Cow<'a, [u8]>),
     GuidType(Guid),
     SizeTType(usize),
     FileTimeType(DateTime<Utc>),
@@ -78,7 +210,7 @@ pub enum BinXmlValue<'a> {
     EvtXmlArrayType,
 }
 
-#[derive(Debug, PartialOrd, PartialEq, Clone)]
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum BinXmlValueType {
     NullType,
     StringType,
@@ -186,11 +318,14 @@ impl BinXmlValueType {
 }
 
 impl<'a> BinXmlValue<'a> {
+    /// Reads a value of the type given by its first byte.
+    /// `depth` is the number of fragments the value is nested in (see `BinXmlDeserializer::read_binxml_fragment`).
     pub fn from_binxml_stream(
         cursor: &mut Cursor<&'a [u8]>,
         chunk: Option<&'a EvtxChunk<'a>>,
         size: Option<u16>,
         ansi_codec: EncodingRef,
+        depth: usize,
     ) -> Result<BinXmlValue<'a>> {
         let value_type_token = try_read!(cursor, u8)?;
 
@@ -201,7 +336,8 @@ impl<'a> BinXmlValue<'a> {
             },
         )?;
 
-        let data = Self::deserialize_value_type(&value_type, cursor, chunk, size, ansi_codec)?;
+        let data =
+            Self::deserialize_value_type(&value_type, cursor, chunk, size, ansi_codec, depth)?;
 
         Ok(data)
     }
@@ -212,6 +348,7 @@ impl<'a> BinXmlValue<'a> {
         chunk: Option<&'a EvtxChunk<'a>>,
         size: Option<u16>,
         ansi_codec: EncodingRef,
+        depth: usize,
     ) -> Result<BinXmlValue<'a>> {
         trace!(
             "Offset `0x{offset:08x} ({offset}): {value_type:?}, {size:?}",
@@ -220,10 +357,12 @@ impl<'a> BinXmlValue<'a> {
             size = size
         );
 
+        let lossy = chunk.is_some_and(|chunk| chunk.settings.should_decode_strings_lossily());
+
         let value = match (value_type, size) {
             (BinXmlValueType::NullType, _) => BinXmlValue::NullType,
             (BinXmlValueType::StringType, Some(sz)) => BinXmlValue::StringType(
-                read_utf16_by_size(cursor, u64::from(sz))
+                read_utf16_by_size(cursor, u64::from(sz), lossy)
                     .map_err(|e| {
                         WrappedIoError::io_error_with_message(
                             e,
@@ -234,11 +373,11 @@ impl<'a> BinXmlValue<'a> {
                     .unwrap_or_else(|| "".to_owned()),
             ),
             (BinXmlValueType::StringType, None) => BinXmlValue::StringType(
-                try_read!(cursor, len_prefixed_utf_16_str, "<string_value>")?
+                try_read!(cursor, len_prefixed_utf_16_str, "<string_value>", lossy)?
                     .unwrap_or_else(|| "".to_string()),
             ),
             (BinXmlValueType::AnsiStringType, Some(sz)) => BinXmlValue::AnsiStringType(Cow::Owned(
-                read_ansi_encoded_string(cursor, u64::from(sz), ansi_codec)?
+                read_ansi_encoded_string(cursor, u64::from(sz), ansi_codec, lossy)?
                     .unwrap_or_else(|| "".to_owned()),
             )),
             // AnsiString are always sized according to docs
@@ -284,7 +423,12 @@ impl<'a> BinXmlValue<'a> {
             }
             (BinXmlValueType::BinXmlType, None) => {
                 let tokens = BinXmlDeserializer::read_binxml_fragment(
-                    cursor, chunk, None, true, ansi_codec,
+                    cursor,
+                    chunk,
+                    None,
+                    true,
+                    ansi_codec,
+                    depth + 1,
                 )?;
 
                 BinXmlValue::BinXmlType(tokens)
@@ -296,6 +440,7 @@ impl<'a> BinXmlValue<'a> {
                     Some(u32::from(sz)),
                     true,
                     ansi_codec,
+                    depth + 1,
                 )?;
 
                 BinXmlValue::BinXmlType(tokens)
@@ -314,11 +459,11 @@ impl<'a> BinXmlValue<'a> {
                     )
                 })?;
 
-                BinXmlValue::BinaryType(bytes)
+                BinXmlValue::BinaryType(Cow::Borrowed(bytes))
             }
             // The array types are always sized.
             (BinXmlValueType::StringArrayType, Some(sz)) => BinXmlValue::StringArrayType(
-                try_read_sized_array!(cursor, null_terminated_utf_16_str, sz),
+                try_read_sized_array!(cursor, null_terminated_utf_16_str, sz, lossy),
             ),
             (BinXmlValueType::Int8ArrayType, Some(sz)) => {
                 BinXmlValue::Int8ArrayType(try_read_sized_array!(cursor, i8, sz))
@@ -394,6 +539,159 @@ impl<'a> BinXmlValue<'a> {
     }
 }
 
+impl<'a> BinXmlValue<'a> {
+    /// The raw FILETIME (the number of 100-nanosecond intervals since 1601-01-01 UTC)
+    /// of a `FileTimeType` or `SysTimeType` value, without the truncation to microseconds
+    /// of its rendering. `None` for other values.
+    pub fn filetime_raw(&self) -> Option<u64> {
+        match self {
+            BinXmlValue::FileTimeType(tm) | BinXmlValue::SysTimeType(tm) => {
+                datetime_to_filetime(tm)
+            }
+            _ => None,
+        }
+    }
+
+    /// The type of the value, as it is encoded in binxml.
+    pub fn value_type(&self) -> BinXmlValueType {
+        match self {
+            BinXmlValue::NullType => BinXmlValueType::NullType,
+            BinXmlValue::StringType(_) => BinXmlValueType::StringType,
+            BinXmlValue::AnsiStringType(_) => BinXmlValueType::AnsiStringType,
+            BinXmlValue::Int8Type(_) => BinXmlValueType::Int8Type,
+            BinXmlValue::UInt8Type(_) => BinXmlValueType::UInt8Type,
+            BinXmlValue::Int16Type(_) => BinXmlValueType::Int16Type,
+            BinXmlValue::UInt16Type(_) => BinXmlValueType::UInt16Type,
+            BinXmlValue::Int32Type(_) => BinXmlValueType::Int32Type,
+            BinXmlValue::UInt32Type(_) => BinXmlValueType::UInt32Type,
+            BinXmlValue::Int64Type(_) => BinXmlValueType::Int64Type,
+            BinXmlValue::UInt64Type(_) => BinXmlValueType::UInt64Type,
+            BinXmlValue::Real32Type(_) => BinXmlValueType::Real32Type,
+            BinXmlValue::Real64Type(_) => BinXmlValueType::Real64Type,
+            BinXmlValue::BoolType(_) => BinXmlValueType::BoolType,
+            BinXmlValue::BinaryType(_) => BinXmlValueType::BinaryType,
+            BinXmlValue::GuidType(_) => BinXmlValueType::GuidType,
+            BinXmlValue::SizeTType(_) => BinXmlValueType::SizeTType,
+            BinXmlValue::FileTimeType(_) => BinXmlValueType::FileTimeType,
+            BinXmlValue::SysTimeType(_) => BinXmlValueType::SysTimeType,
+            BinXmlValue::SidType(_) => BinXmlValueType::SidType,
+            BinXmlValue::HexInt32Type(_) => BinXmlValueType::HexInt32Type,
+            BinXmlValue::HexInt64Type(_) => BinXmlValueType::HexInt64Type,
+            BinXmlValue::EvtHandle => BinXmlValueType::EvtHandle,
+            BinXmlValue::BinXmlType(_) => BinXmlValueType::BinXmlType,
+            BinXmlValue::EvtXml => BinXmlValueType::EvtXmlType,
+            BinXmlValue::StringArrayType(_) => BinXmlValueType::StringArrayType,
+            BinXmlValue::AnsiStringArrayType => BinXmlValueType::AnsiStringArrayType,
+            BinXmlValue::Int8ArrayType(_) => BinXmlValueType::Int8ArrayType,
+            BinXmlValue::UInt8ArrayType(_) => BinXmlValueType::UInt8ArrayType,
+            BinXmlValue::Int16ArrayType(_) => BinXmlValueType::Int16ArrayType,
+            BinXmlValue::UInt16ArrayType(_) => BinXmlValueType::UInt16ArrayType,
+            BinXmlValue::Int32ArrayType(_) => BinXmlValueType::Int32ArrayType,
+            BinXmlValue::UInt32ArrayType(_) => BinXmlValueType::UInt32ArrayType,
+            BinXmlValue::Int64ArrayType(_) => BinXmlValueType::Int64ArrayType,
+            BinXmlValue::UInt64ArrayType(_) => BinXmlValueType::UInt64ArrayType,
+            BinXmlValue::Real32ArrayType(_) => BinXmlValueType::Real32ArrayType,
+            BinXmlValue::Real64ArrayType(_) => BinXmlValueType::Real64ArrayType,
+            BinXmlValue::BoolArrayType(_) => BinXmlValueType::BoolArrayType,
+            BinXmlValue::BinaryArrayType => BinXmlValueType::BinaryArrayType,
+            BinXmlValue::GuidArrayType(_) => BinXmlValueType::GuidArrayType,
+            BinXmlValue::SizeTArrayType => BinXmlValueType::SizeTArrayType,
+            BinXmlValue::FileTimeArrayType(_) => BinXmlValueType::FileTimeArrayType,
+            BinXmlValue::SysTimeArrayType(_) => BinXmlValueType::SysTimeArrayType,
+            BinXmlValue::SidArrayType(_) => BinXmlValueType::SidArrayType,
+            BinXmlValue::HexInt32ArrayType(_) => BinXmlValueType::HexInt32ArrayType,
+            BinXmlValue::HexInt64ArrayType(_) => BinXmlValueType::HexInt64ArrayType,
+            BinXmlValue::EvtArrayHandle => BinXmlValueType::EvtHandleArray,
+            BinXmlValue::BinXmlArrayType => BinXmlValueType::BinXmlArrayType,
+            BinXmlValue::EvtXmlArrayType => BinXmlValueType::EvtXmlArrayType,
+        }
+    }
+
+    /// Returns a copy of the value which does not borrow from the chunk,
+    /// so it can be kept after the parser (or the chunk) is dropped.
+    ///
+    /// Most values are already owned. The values which borrow from the chunk, and are copied, are
+    /// ANSI strings, hex integers (and arrays of them), binary buffers,
+    /// and the tokens of nested binxml (`BinXmlType`).
+    pub fn to_owned_value(&self) -> BinXmlValue<'static> {
+        self.clone().into_static()
+    }
+
+    /// Converts the value to one which does not borrow from the chunk, see `to_owned_value`.
+    pub(crate) fn into_static(self) -> BinXmlValue<'static> {
+        match self {
+            BinXmlValue::NullType => BinXmlValue::NullType,
+            BinXmlValue::StringType(s) => BinXmlValue::StringType(s),
+            BinXmlValue::AnsiStringType(s) => {
+                BinXmlValue::AnsiStringType(Cow::Owned(s.into_owned()))
+            }
+            BinXmlValue::Int8Type(num) => BinXmlValue::Int8Type(num),
+            BinXmlValue::UInt8Type(num) => BinXmlValue::UInt8Type(num),
+            BinXmlValue::Int16Type(num) => BinXmlValue::Int16Type(num),
+            BinXmlValue::UInt16Type(num) => BinXmlValue::UInt16Type(num),
+            BinXmlValue::Int32Type(num) => BinXmlValue::Int32Type(num),
+            BinXmlValue::UInt32Type(num) => BinXmlValue::UInt32Type(num),
+            BinXmlValue::Int64Type(num) => BinXmlValue::Int64Type(num),
+            BinXmlValue::UInt64Type(num) => BinXmlValue::UInt64Type(num),
+            BinXmlValue::Real32Type(num) => BinXmlValue::Real32Type(num),
+            BinXmlValue::Real64Type(num) => BinXmlValue::Real64Type(num),
+            BinXmlValue::BoolType(b) => BinXmlValue::BoolType(b),
+            BinXmlValue::BinaryType(bytes) => {
+                BinXmlValue::BinaryType(Cow::Owned(bytes.into_owned()))
+            }
+            BinXmlValue::GuidType(guid) => BinXmlValue::GuidType(guid),
+            BinXmlValue::SizeTType(sz) => BinXmlValue::SizeTType(sz),
+            BinXmlValue::FileTimeType(tm) => BinXmlValue::FileTimeType(tm),
+            BinXmlValue::SysTimeType(tm) => BinXmlValue::SysTimeType(tm),
+            BinXmlValue::SidType(sid) => BinXmlValue::SidType(sid),
+            BinXmlValue::HexInt32Type(s) => BinXmlValue::HexInt32Type(Cow::Owned(s.into_owned())),
+            BinXmlValue::HexInt64Type(s) => BinXmlValue::HexInt64Type(Cow::Owned(s.into_owned())),
+            BinXmlValue::EvtHandle => BinXmlValue::EvtHandle,
+            BinXmlValue::BinXmlType(tokens) => BinXmlValue::BinXmlType(
+                tokens
+                    .into_iter()
+                    .map(BinXMLDeserializedTokens::into_static)
+                    .collect(),
+            ),
+            BinXmlValue::EvtXml => BinXmlValue::EvtXml,
+            BinXmlValue::StringArrayType(s) => BinXmlValue::StringArrayType(s),
+            BinXmlValue::AnsiStringArrayType => BinXmlValue::AnsiStringArrayType,
+            BinXmlValue::Int8ArrayType(numbers) => BinXmlValue::Int8ArrayType(numbers),
+            BinXmlValue::UInt8ArrayType(numbers) => BinXmlValue::UInt8ArrayType(numbers),
+            BinXmlValue::Int16ArrayType(numbers) => BinXmlValue::Int16ArrayType(numbers),
+            BinXmlValue::UInt16ArrayType(numbers) => BinXmlValue::UInt16ArrayType(numbers),
+            BinXmlValue::Int32ArrayType(numbers) => BinXmlValue::Int32ArrayType(numbers),
+            BinXmlValue::UInt32ArrayType(numbers) => BinXmlValue::UInt32ArrayType(numbers),
+            BinXmlValue::Int64ArrayType(numbers) => BinXmlValue::Int64ArrayType(numbers),
+            BinXmlValue::UInt64ArrayType(numbers) => BinXmlValue::UInt64ArrayType(numbers),
+            BinXmlValue::Real32ArrayType(numbers) => BinXmlValue::Real32ArrayType(numbers),
+            BinXmlValue::Real64ArrayType(numbers) => BinXmlValue::Real64ArrayType(numbers),
+            BinXmlValue::BoolArrayType(bools) => BinXmlValue::BoolArrayType(bools),
+            BinXmlValue::BinaryArrayType => BinXmlValue::BinaryArrayType,
+            BinXmlValue::GuidArrayType(guids) => BinXmlValue::GuidArrayType(guids),
+            BinXmlValue::SizeTArrayType => BinXmlValue::SizeTArrayType,
+            BinXmlValue::FileTimeArrayType(filetimes) => BinXmlValue::FileTimeArrayType(filetimes),
+            BinXmlValue::SysTimeArrayType(systimes) => BinXmlValue::SysTimeArrayType(systimes),
+            BinXmlValue::SidArrayType(sids) => BinXmlValue::SidArrayType(sids),
+            BinXmlValue::HexInt32ArrayType(hex_strings) => BinXmlValue::HexInt32ArrayType(
+                hex_strings
+                    .into_iter()
+                    .map(|s| Cow::Owned(s.into_owned()))
+                    .collect(),
+            ),
+            BinXmlValue::HexInt64ArrayType(hex_strings) => BinXmlValue::HexInt64ArrayType(
+                hex_strings
+                    .into_iter()
+                    .map(|s| Cow::Owned(s.into_owned()))
+                    .collect(),
+            ),
+            BinXmlValue::EvtArrayHandle => BinXmlValue::EvtArrayHandle,
+            BinXmlValue::BinXmlArrayType => BinXmlValue::BinXmlArrayType,
+            BinXmlValue::EvtXmlArrayType => BinXmlValue::EvtXmlArrayType,
+        }
+    }
+}
+
 fn to_delimited_list<N: ToString>(ns: impl AsRef<Vec<N>>) -> String {
     ns.as_ref()
         .iter()
@@ -419,14 +717,10 @@ impl<'c> From<BinXmlValue<'c>> for serde_json::Value {
             BinXmlValue::Real32Type(num) => json!(num),
             BinXmlValue::Real64Type(num) => json!(num),
             BinXmlValue::BoolType(num) => json!(num),
-            BinXmlValue::BinaryType(bytes) => {
-                // Bytes will be formatted as const length of 2 with '0' padding.
-                let repr: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
-                json!(repr)
-            }
+            BinXmlValue::BinaryType(bytes) => json!(BinaryEncoding::default().encode(&bytes)),
             BinXmlValue::GuidType(guid) => json!(guid.to_string()),
             //            BinXmlValue::SizeTType(sz) => json!(sz.to_string()),
-            BinXmlValue::FileTimeType(tm) => json!(tm),
+            BinXmlValue::FileTimeType(tm) => json!(truncate_to_microseconds(&tm)),
             BinXmlValue::SysTimeType(tm) => json!(tm),
             BinXmlValue::SidType(sid) => json!(sid.to_string()),
             BinXmlValue::HexInt32Type(hex_string) => json!(hex_string),
@@ -446,7 +740,10 @@ impl<'c> From<BinXmlValue<'c>> for serde_json::Value {
             BinXmlValue::GuidArrayType(guids) => {
                 json!(guids.iter().map(Guid::to_string).collect::<Vec<String>>())
             }
-            BinXmlValue::FileTimeArrayType(filetimes) => json!(filetimes),
+            BinXmlValue::FileTimeArrayType(filetimes) => json!(filetimes
+                .iter()
+                .map(truncate_to_microseconds)
+                .collect::<Vec<_>>()),
             BinXmlValue::SysTimeArrayType(systimes) => json!(systimes),
             BinXmlValue::SidArrayType(sids) => {
                 json!(sids.iter().map(Sid::to_string).collect::<Vec<String>>())
@@ -482,14 +779,10 @@ impl<'c> From<&'c BinXmlValue<'c>> for serde_json::Value {
             BinXmlValue::Real32Type(num) => json!(num),
             BinXmlValue::Real64Type(num) => json!(num),
             BinXmlValue::BoolType(num) => json!(num),
-            BinXmlValue::BinaryType(bytes) => {
-                // Bytes will be formatted as const length of 2 with '0' padding.
-                let repr: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
-                json!(repr)
-            }
+            BinXmlValue::BinaryType(bytes) => json!(BinaryEncoding::default().encode(bytes)),
             BinXmlValue::GuidType(guid) => json!(guid.to_string()),
             //            BinXmlValue::SizeTType(sz) => json!(sz.to_string()),
-            BinXmlValue::FileTimeType(tm) => json!(tm),
+            BinXmlValue::FileTimeType(tm) => json!(truncate_to_microseconds(tm)),
             BinXmlValue::SysTimeType(tm) => json!(tm),
             BinXmlValue::SidType(sid) => json!(sid.to_string()),
             BinXmlValue::HexInt32Type(hex_string) => json!(hex_string),
@@ -509,7 +802,10 @@ impl<'c> From<&'c BinXmlValue<'c>> for serde_json::Value {
             BinXmlValue::GuidArrayType(guids) => {
                 json!(guids.iter().map(Guid::to_string).collect::<Vec<String>>())
             }
-            BinXmlValue::FileTimeArrayType(filetimes) => json!(filetimes),
+            BinXmlValue::FileTimeArrayType(filetimes) => json!(filetimes
+                .iter()
+                .map(truncate_to_microseconds)
+                .collect::<Vec<_>>()),
             BinXmlValue::SysTimeArrayType(systimes) => json!(systimes),
             BinXmlValue::SidArrayType(sids) => {
                 json!(sids.iter().map(Sid::to_string).collect::<Vec<String>>())
@@ -529,6 +825,55 @@ impl<'c> From<&'c BinXmlValue<'c>> for serde_json::Value {
 }
 
 impl<'a> BinXmlValue<'a> {
+    /// Like `as_cow_str`, but values are rendered according to `format`.
+    pub fn as_cow_str_with_format(&self, format: ValueFormat) -> Cow<str> {
+        match self {
+            BinXmlValue::BinaryType(bytes) => Cow::Owned(format.binary_encoding.encode(bytes)),
+            BinXmlValue::GuidType(guid) => Cow::Owned(format.guid_format.format(guid)),
+            BinXmlValue::GuidArrayType(guids) => Cow::Owned(
+                guids
+                    .iter()
+                    .map(|guid| format.guid_format.format(guid))
+                    .collect::<Vec<String>>()
+                    .join(","),
+            ),
+            BinXmlValue::SidType(sid) => Cow::Owned(format.format_sid(sid)),
+            BinXmlValue::SidArrayType(sids) => Cow::Owned(
+                sids.iter()
+                    .map(|sid| format.format_sid(sid))
+                    .collect::<Vec<String>>()
+                    .join(","),
+            ),
+            _ => self.as_cow_str(),
+        }
+    }
+
+    /// Like the `serde_json::Value` conversion, but values are rendered according to `format`.
+    pub fn to_json_value_with_format(&self, format: ValueFormat) -> Value {
+        match self {
+            BinXmlValue::BinaryType(bytes) => json!(format.binary_encoding.encode(bytes)),
+            BinXmlValue::GuidType(guid) => json!(format.guid_format.format(guid)),
+            BinXmlValue::GuidArrayType(guids) => json!(guids
+                .iter()
+                .map(|guid| format.guid_format.format(guid))
+                .collect::<Vec<String>>()),
+            BinXmlValue::SidType(sid) => json!(format.format_sid(sid)),
+            BinXmlValue::SidArrayType(sids) => json!(sids
+                .iter()
+                .map(|sid| format.format_sid(sid))
+                .collect::<Vec<String>>()),
+            BinXmlValue::Int64Type(num) if format.large_ints_as_strings => json!(num.to_string()),
+            BinXmlValue::UInt64Type(num) if format.large_ints_as_strings => json!(num.to_string()),
+            BinXmlValue::Int64ArrayType(numbers) if format.large_ints_as_strings => {
+                json!(numbers.iter().map(i64::to_string).collect::<Vec<String>>())
+            }
+            BinXmlValue::UInt64ArrayType(numbers) if format.large_ints_as_strings => {
+                json!(numbers.iter().map(u64::to_string).collect::<Vec<String>>())
+            }
+            _ => self.clone().into(),
+        }
+    }
+
     pub fn as_cow_str(&self) -> Cow<str> {
         match self {
             BinXmlValue::NullType => Cow::Borrowed(""),
@@ -545,19 +890,10 @@ impl<'a> BinXmlValue<'a> {
             BinXmlValue::Real32Type(num) => Cow::Owned(num.to_string()),
             BinXmlValue::Real64Type(num) => Cow::Owned(num.to_string()),
             BinXmlValue::BoolType(num) => Cow::Owned(num.to_string()),
-            BinXmlValue::BinaryType(bytes) => {
-                // Bytes will be formatted as const length of 2 with '0' padding.
-                let mut repr = String::with_capacity(bytes.len() * 2);
-
-                for b in bytes.iter() {
-                    write!(repr, "{:02X}", b).expect("Writing to a String cannot fail");
-                }
-
-                Cow::Owned(repr)
-            }
+            BinXmlValue::BinaryType(bytes) => Cow::Owned(BinaryEncoding::default().encode(bytes)),
             BinXmlValue::GuidType(guid) => Cow::Owned(guid.to_string()),
             BinXmlValue::SizeTType(sz) => Cow::Owned(sz.to_string()),
-            BinXmlValue::FileTimeType(tm) => Cow::Owned(tm.to_string()),
+            BinXmlValue::FileTimeType(tm) => Cow::Owned(truncate_to_microseconds(tm).to_string()),
             BinXmlValue::SysTimeType(tm) => Cow::Owned(tm.to_string()),
             BinXmlValue::SidType(sid) => Cow::Owned(sid.to_string()),
             BinXmlValue::HexInt32Type(hex_string) => hex_string.clone(),
@@ -575,7 +911,12 @@ impl<'a> BinXmlValue<'a> {
             BinXmlValue::Real64ArrayType(numbers) => Cow::Owned(to_delimited_list(numbers)),
             BinXmlValue::BoolArrayType(bools) => Cow::Owned(to_delimited_list(bools)),
             BinXmlValue::GuidArrayType(guids) => Cow::Owned(to_delimited_list(guids)),
-            BinXmlValue::FileTimeArrayType(filetimes) => Cow::Owned(to_delimited_list(filetimes)),
+            BinXmlValue::FileTimeArrayType(filetimes) => Cow::Owned(to_delimited_list(
+                filetimes
+                    .iter()
+                    .map(truncate_to_microseconds)
+                    .collect::<Vec<_>>(),
+            )),
             BinXmlValue::SysTimeArrayType(systimes) => Cow::Owned(to_delimited_list(systimes)),
             BinXmlValue::SidArrayType(sids) => Cow::Owned(to_delimited_list(sids)),
             BinXmlValue::HexInt32ArrayType(hex_strings) => Cow::Owned(hex_strings.join(",")),
@@ -591,3 +932,36 @@ impl<'a> BinXmlValue<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding::all::WINDOWS_1252;
+
+    #[test]
+    fn test_to_owned_value_outlives_the_buffer() {
+        let owned: Vec<BinXmlValue<'static>> = {
+            let data = vec![0xde, 0xad, 0xbe, 0xef];
+            let mut cursor = Cursor::new(data.as_slice());
+            let value = BinXmlValue::deserialize_value_type(
+                &BinXmlValueType::BinaryType,
+                &mut cursor,
+                None,
+                Some(4),
+                WINDOWS_1252,
+                0,
+            )
+            .unwrap();
+
+            assert_eq!(value, BinXmlValue::BinaryType(Cow::Borrowed(&data)));
+            vec![value.to_owned_value()]
+        };
+
+        assert_eq!(
+            owned,
+            vec![BinXmlValue::BinaryType(Cow::Owned(vec![
+                0xde, 0xad, 0xbe, 0xef
+            ]))]
+        );
+    }
+}
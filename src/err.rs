@@ -152,6 +152,9 @@ pub enum DeserializationError {
         size: Option<u16>,
         offset: u64,
     },
+
+    #[error("Offset {offset}: Binxml fragment is nested {depth} levels deep, which exceeds the maximum depth")]
+    MaxDepthExceeded { depth: usize, offset: u64 },
 }
 
 // TODO: this should be pub(crate), but we need to make `BinXmlOutput` private to do that.
@@ -166,8 +169,8 @@ pub enum SerializationError {
         source: quick_xml::Error,
     },
 
-    #[error("Building a JSON document failed with message: {message}")]
-    JsonStructureError { message: String },
+    #[error("Offset 0x{offset:08x}: Building a JSON document failed with message: {message}")]
+    JsonStructureError { message: String, offset: u64 },
 
     #[error("`serde_json` failed")]
     JsonError {
@@ -175,6 +178,13 @@ pub enum SerializationError {
         source: serde_json::error::Error,
     },
 
+    #[cfg(feature = "parquet")]
+    #[error("Writing to parquet failed")]
+    ParquetError {
+        #[from]
+        source: parquet::errors::ParquetError,
+    },
+
     #[error("Record data contains invalid UTF-8")]
     RecordContainsInvalidUTF8 {
         #[from]
@@ -192,6 +202,23 @@ pub enum InputError {
         source: std::io::Error,
         path: std::path::PathBuf,
     },
+
+    #[error("Failed to memory map file {}", path.display())]
+    FailedToMapFile {
+        source: std::io::Error,
+        path: std::path::PathBuf,
+    },
+
+    #[cfg(feature = "flate2")]
+    #[error("File {} is not gzip-compressed", path.display())]
+    NotGzipCompressed { path: std::path::PathBuf },
+
+    #[cfg(feature = "flate2")]
+    #[error("Failed to decompress file {}", path.display())]
+    FailedToDecompressFile {
+        source: std::io::Error,
+        path: std::path::PathBuf,
+    },
 }
 
 impl InputError {
@@ -202,6 +229,31 @@ impl InputError {
             path: path.as_ref().to_path_buf(),
         }
     }
+
+    /// Context Convenience for `InputError`
+    pub fn failed_to_map_file<P: AsRef<Path>>(source: io::Error, path: P) -> Self {
+        InputError::FailedToMapFile {
+            source,
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Context Convenience for `InputError`
+    #[cfg(feature = "flate2")]
+    pub fn not_gzip_compressed<P: AsRef<Path>>(path: P) -> Self {
+        InputError::NotGzipCompressed {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Context Convenience for `InputError`
+    #[cfg(feature = "flate2")]
+    pub fn failed_to_decompress_file<P: AsRef<Path>>(source: io::Error, path: P) -> Self {
+        InputError::FailedToDecompressFile {
+            source,
+            path: path.as_ref().to_path_buf(),
+        }
+    }
 }
 
 /// Raised on Invalid/Incomplete data
@@ -217,8 +269,17 @@ pub enum ChunkError {
     #[error("Failed to parse chunk header")]
     FailedToParseChunkHeader(#[from] DeserializationError),
 
-    #[error("chunk data CRC32 invalid")]
-    InvalidChunkChecksum { expected: u32, found: u32 },
+    /// The checksum stored in the header of the chunk (`expected`) doesn't match the one calculated
+    /// over its header or its records (`found`).
+    /// `chunk_number` is 0 for chunks which were not read from a file (see `EvtxChunkData::new`).
+    #[error(
+        "Chunk {chunk_number} CRC32 invalid (stored: {expected:#010x}, calculated: {found:#010x})"
+    )]
+    InvalidChunkChecksum {
+        chunk_number: u64,
+        expected: u32,
+        found: u32,
+    },
 
     #[error("Failed to build string cache")]
     FailedToBuildStringCache { source: DeserializationError },
@@ -253,6 +314,39 @@ pub enum EvtxError {
         source: Box<EvtxError>,
     },
 
+    #[error("File header CRC32 invalid (stored: {expected:#010x}, calculated: {found:#010x})")]
+    InvalidFileHeaderChecksum { expected: u32, found: u32 },
+
+    #[error(
+        "Record {record_id} declares a size of {size} bytes, which exceeds the maximum record size"
+    )]
+    RecordSizeExceeded { record_id: RecordId, size: u32 },
+
+    #[error("Format version {major_version}.{minor_version} of the file is not supported")]
+    UnsupportedFormatVersion {
+        major_version: u16,
+        minor_version: u16,
+    },
+
+    #[error("Record {record_id} is nested {depth} levels deep, which exceeds the maximum depth")]
+    MaxDepthExceeded { record_id: RecordId, depth: usize },
+
+    #[error("Record {record_id} is truncated, it declares {expected} bytes but only {available} bytes remain in the chunk")]
+    TruncatedRecord {
+        record_id: RecordId,
+        available: u32,
+        expected: u32,
+    },
+
+    #[error("Record id {record_id} is past the last record (next record id is {next_record_id})")]
+    RecordIdOutOfRange {
+        record_id: RecordId,
+        next_record_id: RecordId,
+    },
+
+    #[error("Unknown output format `{name}`, expected one of `xml`, `json` or `jsonl`")]
+    UnknownOutputFormat { name: String },
+
     #[error("Calculation Error, reason: {}", .0)]
     CalculationError(String),
 
@@ -2,24 +2,27 @@ use crate::err::{
     ChunkError, DeserializationError, DeserializationResult, EvtxChunkResult, EvtxError,
 };
 
-use crate::evtx_record::{EvtxRecord, EvtxRecordHeader};
+use crate::evtx_record::{
+    EvtxRecord, EvtxRecordHeader, SerializedEvtxRecord, EVTX_RECORD_HEADER_SIZE,
+};
 
 use crc::crc32;
-use log::{debug, info, trace};
+use log::{debug, info, trace, warn};
 use std::{
     io::Cursor,
     io::{Read, Seek, SeekFrom},
 };
 
 use crate::binxml::deserializer::BinXmlDeserializer;
+use crate::evtx_parser::{EVTX_CHUNK_SIZE, EVTX_FILE_HEADER_SIZE};
 use crate::string_cache::StringCache;
 use crate::template_cache::TemplateCache;
-use crate::ParserSettings;
+use crate::{FileOffset, ParserSettings};
 
 use byteorder::{LittleEndian, ReadBytesExt};
 use std::sync::Arc;
 
-const EVTX_CHUNK_HEADER_SIZE: usize = 512;
+pub(crate) const EVTX_CHUNK_HEADER_SIZE: usize = 512;
 
 bitflags! {
     pub struct ChunkFlags: u32 {
@@ -29,6 +32,8 @@ bitflags! {
     }
 }
 
+/// The header of a chunk, as stored in the file.
+/// Can be read without the rest of the chunk with `EvtxParser::chunk_headers`.
 #[derive(Debug)]
 pub struct EvtxChunkHeader {
     pub first_event_record_number: u64,
@@ -36,9 +41,13 @@ pub struct EvtxChunkHeader {
     pub first_event_record_id: u64,
     pub last_event_record_id: u64,
     pub header_size: u32,
+    /// The offset of the last record, from the start of the chunk.
     pub last_event_record_data_offset: u32,
+    /// The offset of the end of the last record, from the start of the chunk.
     pub free_space_offset: u32,
+    /// The CRC32 of the records (from the end of the header to `free_space_offset`).
     pub events_checksum: u32,
+    /// The CRC32 of the header (excluding the checksum itself).
     pub header_chunk_checksum: u32,
     pub flags: ChunkFlags,
     // A list of buckets containing the offsets of all strings in the chunk.
@@ -56,55 +65,304 @@ pub struct EvtxChunkHeader {
 
 /// A struct which owns all the data associated with a chunk.
 /// See EvtxChunk for more.
+///
+/// Since it owns its data, it can be sent to another thread and parsed there:
+///
+/// ```rust
+/// # use evtx::{EvtxParser, ParserSettings};
+/// # use std::sync::Arc;
+/// # let fp = std::path::PathBuf::from(format!("{}/samples/security.evtx", std::env::var("CARGO_MANIFEST_DIR").unwrap()));
+/// let mut parser = EvtxParser::from_path(fp).unwrap();
+/// let settings = Arc::new(ParserSettings::default());
+///
+/// for chunk in parser.chunks() {
+///     let mut chunk = chunk.unwrap();
+///     let settings = Arc::clone(&settings);
+///
+///     std::thread::spawn(move || {
+///         let number = chunk.chunk_number();
+///         for record in chunk.parse(settings).unwrap().iter() {
+///             println!("chunk {:?}: {}", number, record.unwrap().event_record_id);
+///         }
+///     })
+///     .join()
+///     .unwrap();
+/// }
+/// ```
 pub struct EvtxChunkData {
     pub header: EvtxChunkHeader,
     pub data: Vec<u8>,
+    // Only known when the chunk was read from a file (and not constructed from raw bytes).
+    chunk_number: Option<u64>,
+    // Set when the header of the chunk is invalid, and records should be carved from the data.
+    carved: bool,
+    // The result of validating the checksums when the chunk was read, if they were validated.
+    checksum_valid: Option<bool>,
 }
 
 impl EvtxChunkData {
     /// Construct a new chunk from the given data.
     /// Note that even when validate_checksum is set to false, the header magic is still checked.
     pub fn new(data: Vec<u8>, validate_checksum: bool) -> EvtxChunkResult<Self> {
+        Self::new_with_chunk_number(data, validate_checksum, None)
+    }
+
+    /// Like `new`, for a chunk which was read from a file.
+    pub(crate) fn new_with_chunk_number(
+        data: Vec<u8>,
+        validate_checksum: bool,
+        chunk_number: Option<u64>,
+    ) -> EvtxChunkResult<Self> {
         let mut cursor = Cursor::new(data.as_slice());
         let header = EvtxChunkHeader::from_reader(&mut cursor)?;
 
-        let chunk = EvtxChunkData { header, data };
+        let mut chunk = EvtxChunkData {
+            header,
+            data,
+            chunk_number,
+            carved: false,
+            checksum_valid: None,
+        };
+
         if validate_checksum && !chunk.validate_checksum() {
-            // TODO: return checksum here.
+            let (expected, found) = if chunk.validate_header_checksum() {
+                chunk.data_checksums()
+            } else {
+                chunk.header_checksums()
+            };
+
             return Err(ChunkError::InvalidChunkChecksum {
-                expected: 0,
-                found: 0,
+                chunk_number: chunk_number.unwrap_or(0),
+                expected,
+                found,
             });
         }
 
+        if validate_checksum {
+            chunk.checksum_valid = Some(true);
+        }
+
         Ok(chunk)
     }
 
+    /// Parses all the records of a single chunk, for chunks which were already sliced out of a file
+    /// (or carved from memory), without a file header.
+    /// Records are XML-formatted, like `EvtxParser::records`.
+    ///
+    /// `data` must be a whole chunk, starting with the `ElfChnk\x00` magic
+    /// (otherwise `DeserializationError::InvalidEvtxChunkMagic` is returned).
+    /// Checksums are validated if `ParserSettings::validate_checksums` is enabled.
+    pub fn parse_records(
+        data: Vec<u8>,
+        settings: Arc<ParserSettings>,
+    ) -> EvtxChunkResult<impl Iterator<Item = Result<SerializedEvtxRecord<String>, EvtxError>>>
+    {
+        if data.len() != EVTX_CHUNK_SIZE {
+            return Err(ChunkError::IncompleteChunk);
+        }
+
+        let mut chunk_data = EvtxChunkData::new(data, settings.should_validate_checksums())?;
+        let mut chunk = chunk_data.parse(settings)?;
+
+        let mut records = vec![];
+        for record in chunk.iter() {
+            records.push(record.and_then(EvtxRecord::into_xml));
+        }
+
+        Ok(records.into_iter())
+    }
+
+    /// Like `new`, but if the header of the chunk is invalid (cannot be parsed, or has a bad checksum),
+    /// a chunk which carves its records from the data is returned instead of an error.
+    /// The checksum of the header is only checked if `compute_checksums` is set.
+    /// See `ParserSettings::carve_records`.
+    pub(crate) fn new_or_carved(
+        data: Vec<u8>,
+        validate_checksum: bool,
+        compute_checksums: bool,
+    ) -> Self {
+        let data = match EvtxChunkHeader::from_reader(&mut Cursor::new(data.as_slice())) {
+            Ok(header) => {
+                let mut chunk = EvtxChunkData {
+                    header,
+                    data,
+                    chunk_number: None,
+                    carved: false,
+                    checksum_valid: None,
+                };
+
+                if !compute_checksums {
+                    return chunk;
+                }
+
+                if chunk.validate_header_checksum() {
+                    if !validate_checksum {
+                        return chunk;
+                    }
+
+                    if chunk.validate_data_checksum() {
+                        chunk.checksum_valid = Some(true);
+                        return chunk;
+                    }
+                }
+
+                warn!("Chunk has a bad checksum, carving records");
+                chunk.data
+            }
+            Err(err) => {
+                warn!("Chunk has an invalid header ({}), carving records", err);
+                data
+            }
+        };
+
+        EvtxChunkData {
+            header: EvtxChunkHeader::for_carving(),
+            data,
+            chunk_number: None,
+            carved: true,
+            checksum_valid: Some(false),
+        }
+    }
+
+    /// True if the header of this chunk is invalid, and its records are carved from the data.
+    pub fn is_carved(&self) -> bool {
+        self.carved
+    }
+
+    /// Whether both the header and the data checksums of the chunk are valid,
+    /// if they were validated when the chunk was read (see `ParserSettings::validate_checksums`).
+    /// Carved chunks are never valid.
+    ///
+    /// Use `validate_checksum` to calculate the checksums of a chunk on demand.
+    pub fn checksum_valid(&self) -> Option<bool> {
+        self.checksum_valid
+    }
+
+    /// The number of unused bytes at the end of the chunk, after `free_space_offset`.
+    pub fn free_space(&self) -> usize {
+        EVTX_CHUNK_SIZE.saturating_sub(self.header.free_space_offset as usize)
+    }
+
+    /// True if the last record of the chunk (according to its header) ends after
+    /// `free_space_offset`, which suggests that the chunk was being written to when it was read
+    /// (for example, the log of a live system), so it may be worth reading it again later.
+    /// Always false for carved chunks.
+    ///
+    /// NOTE: The `DIRTY` flag of the header is not used, since Windows sets it on every chunk.
+    pub fn is_dirty(&self) -> bool {
+        if self.carved {
+            return false;
+        }
+
+        let free_space_offset = self.header.free_space_offset as usize;
+        matches!(self.last_record_end(), Some(end) if end > free_space_offset)
+    }
+
+    /// The offset of the end of the last record, from the start of the chunk.
+    fn last_record_end(&self) -> Option<usize> {
+        let offset = self.header.last_event_record_data_offset as usize;
+        let record_header =
+            EvtxRecordHeader::from_reader(&mut Cursor::new(self.data.get(offset..)?)).ok()?;
+
+        Some(offset + record_header.data_size as usize)
+    }
+
+    pub(crate) fn with_chunk_number(mut self, chunk_number: u64) -> Self {
+        self.chunk_number = Some(chunk_number);
+        self
+    }
+
+    /// The index of this chunk in the file it was read from.
+    pub fn chunk_number(&self) -> Option<u64> {
+        self.chunk_number
+    }
+
+    /// The offset of this chunk from the start of the file it was read from.
+    pub fn offset(&self) -> Option<FileOffset> {
+        self.chunk_number
+            .map(|n| EVTX_FILE_HEADER_SIZE as FileOffset + n * EVTX_CHUNK_SIZE as FileOffset)
+    }
+
     /// Require that the settings live at least as long as &self.
     pub fn parse(&mut self, settings: Arc<ParserSettings>) -> EvtxChunkResult<EvtxChunk> {
-        EvtxChunk::new(&self.data, &self.header, Arc::clone(&settings))
+        let mut chunk = EvtxChunk::new(&self.data, &self.header, Arc::clone(&settings))?;
+        chunk.carved = self.carved;
+        chunk.chunk_number = self.chunk_number;
+        chunk.checksum_valid = self.checksum_valid;
+
+        Ok(chunk)
+    }
+
+    /// Counts the records in the chunk by walking the record headers,
+    /// without deserializing the binxml data of the records.
+    /// Like `IterChunkRecords`, counting stops at the first invalid record header.
+    pub fn count_records(&self) -> usize {
+        self.record_headers().len()
+    }
+
+    /// Reads the headers of the records in the chunk, without deserializing their binxml data.
+    /// Like `IterChunkRecords`, reading stops at the first invalid record header.
+    pub fn record_headers(&self) -> Vec<EvtxRecordHeader> {
+        self.record_headers_with_offsets()
+            .into_iter()
+            .map(|(_, record_header)| record_header)
+            .collect()
+    }
+
+    /// Like `record_headers`, with the offset of every record from the start of the chunk.
+    pub(crate) fn record_headers_with_offsets(&self) -> Vec<(usize, EvtxRecordHeader)> {
+        let mut record_headers = vec![];
+
+        if self.carved {
+            let mut offset = EVTX_CHUNK_HEADER_SIZE;
+
+            while let Some((record_offset, record_header)) = find_record(&self.data, offset) {
+                offset = record_offset + record_header.data_size as usize;
+                record_headers.push((record_offset, record_header));
+            }
+
+            return record_headers;
+        }
+
+        let mut offset = EVTX_CHUNK_HEADER_SIZE;
+
+        while offset < self.header.free_space_offset as usize && offset < self.data.len() {
+            let mut cursor = Cursor::new(&self.data[offset..]);
+
+            let record_header = match EvtxRecordHeader::from_reader(&mut cursor) {
+                Ok(record_header) => record_header,
+                Err(_) => break,
+            };
+
+            // A size this small cannot hold a record, and would not advance the offset.
+            if (record_header.data_size as usize) < EVTX_RECORD_HEADER_SIZE {
+                break;
+            }
+
+            let record_size = record_header.data_size as usize;
+            let is_last = self.header.last_event_record_id == record_header.event_record_id;
+            record_headers.push((offset, record_header));
+            offset += record_size;
+
+            if is_last {
+                break;
+            }
+        }
+
+        record_headers
     }
 
     pub fn validate_data_checksum(&self) -> bool {
         debug!("Validating data checksum");
 
-        let checksum_disabled = self.header.flags.contains(ChunkFlags::NO_CRC32);
+        let free_space_offset = self.header.free_space_offset as usize;
 
-        let expected_checksum = if !checksum_disabled {
-            self.header.events_checksum
+        // A corrupt header can point outside of the chunk.
+        if free_space_offset < EVTX_CHUNK_HEADER_SIZE || free_space_offset > self.data.len() {
+            return false;
         }
-        else {
-            0
-        };
 
-        let computed_checksum = if !checksum_disabled {
-            crc32::checksum_ieee(
-                &self.data[EVTX_CHUNK_HEADER_SIZE..self.header.free_space_offset as usize],
-            )
-        }
-        else {
-            0
-        };
+        let (expected_checksum, computed_checksum) = self.data_checksums();
 
         debug!(
             "Expected checksum: {:?}, found: {:?}",
@@ -117,14 +375,38 @@ impl EvtxChunkData {
     pub fn validate_header_checksum(&self) -> bool {
         debug!("Validating header checksum");
 
-        let checksum_disabled = self.header.flags.contains(ChunkFlags::NO_CRC32);
+        let (expected_checksum, computed_checksum) = self.header_checksums();
+
+        debug!(
+            "Expected checksum: {:?}, found: {:?}",
+            expected_checksum, computed_checksum
+        );
+
+        computed_checksum == expected_checksum
+    }
 
-        let expected_checksum = if !checksum_disabled {
-            self.header.header_chunk_checksum
+    /// The checksum of the records stored in the header, and the one calculated over the records.
+    fn data_checksums(&self) -> (u32, u32) {
+        if self.header.flags.contains(ChunkFlags::NO_CRC32) {
+            return (0, 0);
+        }
+
+        // A corrupt header can point outside of the chunk, the checksum is then calculated
+        // over the part of the records which is in range.
+        let free_space_offset =
+            (self.header.free_space_offset as usize).clamp(EVTX_CHUNK_HEADER_SIZE, self.data.len());
+
+        (
+            self.header.events_checksum,
+            crc32::checksum_ieee(&self.data[EVTX_CHUNK_HEADER_SIZE..free_space_offset]),
+        )
+    }
+
+    /// The checksum of the header stored in the header, and the one calculated over the header.
+    fn header_checksums(&self) -> (u32, u32) {
+        if self.header.flags.contains(ChunkFlags::NO_CRC32) {
+            return (0, 0);
         }
-        else {
-            0
-        };
 
         let header_bytes_1 = &self.data[..120];
         let header_bytes_2 = &self.data[128..512];
@@ -135,19 +417,10 @@ impl EvtxChunkData {
             .cloned()
             .collect();
 
-        let computed_checksum = if !checksum_disabled {
-            crc32::checksum_ieee(bytes_for_checksum.as_slice())
-        }
-        else {
-            0
-        };
-
-        debug!(
-            "Expected checksum: {:?}, found: {:?}",
-            expected_checksum, computed_checksum
-        );
-
-        computed_checksum == expected_checksum
+        (
+            self.header.header_chunk_checksum,
+            crc32::checksum_ieee(bytes_for_checksum.as_slice()),
+        )
     }
 
     pub fn validate_checksum(&self) -> bool {
@@ -167,6 +440,13 @@ pub struct EvtxChunk<'chunk> {
     pub template_table: TemplateCache<'chunk>,
 
     pub settings: Arc<ParserSettings>,
+    /// True if the header of the chunk is invalid, and records are carved from the data.
+    pub carved: bool,
+    /// The number of the chunk in the file, if it is known (see `EvtxChunkData::chunk_number`).
+    pub chunk_number: Option<u64>,
+    /// Whether both the header and the data checksums of the chunk are valid,
+    /// `None` if they were not validated (see `EvtxChunkData::checksum_valid`).
+    pub checksum_valid: Option<bool>,
 }
 
 impl<'chunk> EvtxChunk<'chunk> {
@@ -179,12 +459,20 @@ impl<'chunk> EvtxChunk<'chunk> {
         let _cursor = Cursor::new(data);
 
         info!("Initializing string cache");
-        let string_cache = StringCache::populate(&data, &header.strings_offsets)
-            .map_err(|e| ChunkError::FailedToBuildStringCache { source: e })?;
+        let string_cache = StringCache::populate(
+            &data,
+            &header.strings_offsets,
+            settings.should_decode_strings_lossily(),
+        )
+        .map_err(|e| ChunkError::FailedToBuildStringCache { source: e })?;
 
         info!("Initializing template cache");
-        let template_table =
-            TemplateCache::populate(data, &header.template_offsets, settings.get_ansi_codec())?;
+        let template_table = TemplateCache::populate(
+            data,
+            &header.template_offsets,
+            settings.get_ansi_codec(),
+            settings.get_shared_template_cache(),
+        )?;
 
         Ok(EvtxChunk {
             header,
@@ -192,6 +480,9 @@ impl<'chunk> EvtxChunk<'chunk> {
             string_cache,
             template_table,
             settings,
+            carved: false,
+            chunk_number: None,
+            checksum_valid: None,
         })
     }
 
@@ -206,6 +497,16 @@ impl<'chunk> EvtxChunk<'chunk> {
             exhausted: false,
         }
     }
+
+    /// Like `iter`, but starts at the record at `offset` (from the start of the chunk).
+    pub(crate) fn iter_from_offset(&mut self, offset: u64) -> IterChunkRecords {
+        IterChunkRecords {
+            settings: Arc::clone(&self.settings),
+            chunk: self,
+            offset_from_chunk_start: offset,
+            exhausted: false,
+        }
+    }
 }
 
 /// An iterator over a chunk, yielding records.
@@ -245,6 +546,10 @@ impl<'a> Iterator for IterChunkRecords<'a> {
             return None;
         }
 
+        if self.chunk.carved {
+            return self.next_carved();
+        }
+
         let mut cursor = Cursor::new(&self.chunk.data[self.offset_from_chunk_start as usize..]);
 
         let record_header = match EvtxRecordHeader::from_reader(&mut cursor) {
@@ -260,6 +565,58 @@ impl<'a> Iterator for IterChunkRecords<'a> {
         info!("Record id - {}", record_header.event_record_id);
         debug!("Record header - {:?}", record_header);
 
+        if record_header.data_size as usize > self.settings.get_max_record_size() {
+            // The offset of the next record is unknown, so the rest of the chunk is skipped.
+            self.exhausted = true;
+
+            return Some(Err(EvtxError::RecordSizeExceeded {
+                record_id: record_header.event_record_id,
+                size: record_header.data_size,
+            }));
+        }
+
+        let available = (self.chunk.data.len() as u64).saturating_sub(self.offset_from_chunk_start);
+        if u64::from(record_header.data_size) > available {
+            self.exhausted = true;
+
+            if self.settings.should_tolerate_truncation() {
+                debug!(
+                    "Record {} is truncated, treating it as the end of the chunk",
+                    record_header.event_record_id
+                );
+                return None;
+            }
+
+            return Some(Err(EvtxError::TruncatedRecord {
+                record_id: record_header.event_record_id,
+                available: available as u32,
+                expected: record_header.data_size,
+            }));
+        }
+
+        let record = self.parse_record(
+            &record_header,
+            self.offset_from_chunk_start + cursor.position(),
+        );
+
+        self.offset_from_chunk_start += u64::from(record_header.data_size);
+
+        if record.is_ok() && self.chunk.header.last_event_record_id == record_header.event_record_id
+        {
+            self.exhausted = true;
+        }
+
+        Some(record)
+    }
+}
+
+impl<'a> IterChunkRecords<'a> {
+    /// Deserializes the binxml data of a record, which starts at `binxml_offset`.
+    fn parse_record(
+        &self,
+        record_header: &EvtxRecordHeader,
+        binxml_offset: u64,
+    ) -> std::result::Result<EvtxRecord<'a>, EvtxError> {
         let binxml_data_size = record_header.record_data_size();
 
         trace!("Need to deserialize {} bytes of binxml", binxml_data_size);
@@ -269,53 +626,135 @@ impl<'a> Iterator for IterChunkRecords<'a> {
         // We avoid creating new references so that `BinXmlDeserializer` can still generate 'a data.
         let deserializer = BinXmlDeserializer::init(
             self.chunk.data,
-            self.offset_from_chunk_start + cursor.position(),
+            binxml_offset,
             Some(self.chunk),
             false,
             self.settings.get_ansi_codec(),
         );
 
-        let mut tokens = vec![];
-        let iter = match deserializer
-            .iter_tokens(Some(binxml_data_size))
-            .map_err(|e| EvtxError::FailedToParseRecord {
+        let to_record_error = |e| match e {
+            DeserializationError::MaxDepthExceeded { depth, .. } => EvtxError::MaxDepthExceeded {
+                record_id: record_header.event_record_id,
+                depth,
+            },
+            e => EvtxError::FailedToParseRecord {
                 record_id: record_header.event_record_id,
                 source: Box::new(EvtxError::DeserializationError(e)),
-            }) {
-            Ok(iter) => iter,
-            Err(err) => return Some(Err(err)),
+            },
         };
 
-        for token in iter {
-            match token.map_err(|e| EvtxError::FailedToParseRecord {
-                source: Box::new(EvtxError::DeserializationError(e)),
-                record_id: record_header.event_record_id,
-            }) {
-                Ok(token) => tokens.push(token),
-                Err(err) => {
-                    self.offset_from_chunk_start += u64::from(record_header.data_size);
-                    return Some(Err(err));
-                }
-            }
-        }
-
-        self.offset_from_chunk_start += u64::from(record_header.data_size);
+        let mut tokens = vec![];
 
-        if self.chunk.header.last_event_record_id == record_header.event_record_id {
-            self.exhausted = true;
+        for token in deserializer
+            .iter_tokens(Some(binxml_data_size))
+            .map_err(to_record_error)?
+        {
+            tokens.push(token.map_err(to_record_error)?);
         }
 
-        Some(Ok(EvtxRecord {
+        let record_offset = binxml_offset as usize - (EVTX_RECORD_HEADER_SIZE - 4);
+        let record_end =
+            (record_offset + record_header.data_size as usize).min(self.chunk.data.len());
+
+        Ok(EvtxRecord {
             chunk: self.chunk,
             event_record_id: record_header.event_record_id,
             timestamp: record_header.timestamp,
+            filetime: record_header.filetime,
             tokens,
             settings: Arc::clone(&self.settings),
-        }))
+            raw: &self.chunk.data[record_offset..record_end],
+            offset: record_offset as u64,
+            size: record_header.data_size,
+            synthetic_record_id: false,
+        })
+    }
+
+    /// Returns the next record which can be parsed successfully, when carving records.
+    fn next_carved(&mut self) -> Option<std::result::Result<EvtxRecord<'a>, EvtxError>> {
+        while let Some((record_offset, record_header)) =
+            find_record(self.chunk.data, self.offset_from_chunk_start as usize)
+        {
+            // The binxml data follows the record header (the size is repeated at the end of the record).
+            let binxml_offset = record_offset + EVTX_RECORD_HEADER_SIZE - 4;
+
+            match self.parse_record(&record_header, binxml_offset as u64) {
+                Ok(record) => {
+                    self.offset_from_chunk_start =
+                        (record_offset + record_header.data_size as usize) as u64;
+
+                    return Some(Ok(record));
+                }
+                Err(err) => {
+                    debug!(
+                        "Failed to parse carved record at offset {}: {}",
+                        record_offset, err
+                    );
+
+                    // Might be a false positive, keep looking right after the magic.
+                    self.offset_from_chunk_start = record_offset as u64 + 4;
+                }
+            }
+        }
+
+        self.exhausted = true;
+        None
     }
 }
 
+/// Scans `data` from `offset` for the magic of a record, and returns the offset and the header
+/// of the first plausible record (its size fits in the data and is repeated at its end).
+fn find_record(data: &[u8], mut offset: usize) -> Option<(usize, EvtxRecordHeader)> {
+    const RECORD_MAGIC: &[u8] = b"\x2a\x2a\x00\x00";
+
+    while offset + EVTX_RECORD_HEADER_SIZE <= data.len() {
+        let candidate = offset
+            + data[offset..]
+                .windows(RECORD_MAGIC.len())
+                .position(|window| window == RECORD_MAGIC)?;
+        offset = candidate + RECORD_MAGIC.len();
+
+        let record_header =
+            match EvtxRecordHeader::from_reader(&mut Cursor::new(&data[candidate..])) {
+                Ok(record_header) => record_header,
+                Err(_) => continue,
+            };
+
+        let size = record_header.data_size as usize;
+
+        if size < EVTX_RECORD_HEADER_SIZE || candidate + size > data.len() {
+            continue;
+        }
+
+        let size_copy = &data[candidate + size - 4..candidate + size];
+
+        if size_copy == record_header.data_size.to_le_bytes() {
+            return Some((candidate, record_header));
+        }
+    }
+
+    None
+}
+
 impl EvtxChunkHeader {
+    /// A header for a chunk which is carved, it has no string or template tables.
+    fn for_carving() -> EvtxChunkHeader {
+        EvtxChunkHeader {
+            first_event_record_number: 0,
+            last_event_record_number: 0,
+            first_event_record_id: 0,
+            last_event_record_id: 0,
+            header_size: 128,
+            last_event_record_data_offset: 0,
+            free_space_offset: EVTX_CHUNK_SIZE as u32,
+            events_checksum: 0,
+            header_chunk_checksum: 0,
+            flags: ChunkFlags::EMPTY,
+            template_offsets: vec![0; 32],
+            strings_offsets: vec![0; 64],
+        }
+    }
+
     pub fn from_reader(input: &mut Cursor<&[u8]>) -> DeserializationResult<EvtxChunkHeader> {
         let mut magic = [0_u8; 8];
         input.take(8).read_exact(&mut magic)?;
@@ -372,8 +811,6 @@ impl EvtxChunkHeader {
 mod tests {
     use super::*;
     use crate::ensure_env_logger_initialized;
-    use crate::evtx_parser::EVTX_CHUNK_SIZE;
-    use crate::evtx_parser::EVTX_FILE_HEADER_SIZE;
 
     use std::io::Cursor;
 
@@ -441,7 +878,94 @@ mod tests {
         let chunk_data =
             evtx_file[EVTX_FILE_HEADER_SIZE..EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_SIZE].to_vec();
 
-        let chunk = EvtxChunkData::new(chunk_data, false).unwrap();
+        let chunk = EvtxChunkData::new(chunk_data.clone(), false).unwrap();
         assert!(chunk.validate_checksum());
+        assert_eq!(chunk.checksum_valid(), None);
+
+        let chunk = EvtxChunkData::new(chunk_data.clone(), true).unwrap();
+        assert_eq!(chunk.checksum_valid(), Some(true));
+
+        let mut corrupted = chunk_data;
+        corrupted[EVTX_CHUNK_HEADER_SIZE + 100] ^= 0xff;
+        let chunk = EvtxChunkData::new_or_carved(corrupted, true, true);
+        assert!(chunk.is_carved());
+        assert_eq!(chunk.checksum_valid(), Some(false));
+    }
+
+    #[test]
+    fn test_parse_records() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let chunk_data =
+            evtx_file[EVTX_FILE_HEADER_SIZE..EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_SIZE].to_vec();
+        let settings = Arc::new(ParserSettings::new());
+
+        let records: Vec<SerializedEvtxRecord<String>> =
+            EvtxChunkData::parse_records(chunk_data.clone(), Arc::clone(&settings))
+                .unwrap()
+                .map(|r| r.unwrap())
+                .collect();
+
+        assert_eq!(records.len(), 91);
+        assert_eq!(records[0].event_record_id, 1);
+        assert_eq!(records[0].chunk_number, None);
+        assert!(records[0].data.contains("<EventID>4608</EventID>"));
+
+        let mut bad_magic = chunk_data;
+        bad_magic[0] = b'X';
+        assert!(matches!(
+            EvtxChunkData::parse_records(bad_magic, Arc::clone(&settings)),
+            Err(ChunkError::FailedToParseChunkHeader(
+                DeserializationError::InvalidEvtxChunkMagic { .. }
+            ))
+        ));
+
+        assert!(matches!(
+            EvtxChunkData::parse_records(vec![0; 100], settings),
+            Err(ChunkError::IncompleteChunk)
+        ));
+    }
+
+    #[test]
+    fn test_is_dirty() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut chunk_data =
+            evtx_file[EVTX_FILE_HEADER_SIZE..EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_SIZE].to_vec();
+
+        let chunk = EvtxChunkData::new(chunk_data.clone(), false).unwrap();
+        assert!(!chunk.is_dirty());
+        assert_eq!(
+            chunk.free_space(),
+            EVTX_CHUNK_SIZE - chunk.header.free_space_offset as usize
+        );
+
+        // Move the free space pointer back into the last record, as if it was still being written.
+        let free_space_offset = chunk.header.free_space_offset - 8;
+        chunk_data[48..52].copy_from_slice(&free_space_offset.to_le_bytes());
+
+        let chunk = EvtxChunkData::new(chunk_data, false).unwrap();
+        assert!(chunk.is_dirty());
+    }
+
+    #[test]
+    fn test_count_records() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let chunk_data =
+            evtx_file[EVTX_FILE_HEADER_SIZE..EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_SIZE].to_vec();
+
+        let mut chunk = EvtxChunkData::new(chunk_data, false).unwrap();
+        let count = chunk.count_records();
+
+        assert_eq!(count, 91);
+        assert_eq!(
+            count,
+            chunk
+                .parse(Arc::new(ParserSettings::default()))
+                .unwrap()
+                .iter()
+                .count()
+        );
     }
 }
@@ -1,9 +1,14 @@
 use crate::err::{DeserializationError, DeserializationResult, WrappedIoError};
 
-use byteorder::ReadBytesExt;
-use std::io::{Read, Seek, SeekFrom};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use crc::crc32;
+use std::io::{self, Read, Seek, SeekFrom, Write};
 
-#[derive(Debug, PartialEq)]
+/// The checksum stored in the file header covers only the first 120 bytes of it.
+const EVTX_FILE_HEADER_CHECKSUM_DATA_SIZE: usize = 120;
+
+/// The parsed `ElfFile` header, located at the very beginning of an evtx file.
+#[derive(Debug, Clone, PartialEq)]
 pub struct EvtxFileHeader {
     pub first_chunk_number: u64,
     pub last_chunk_number: u64,
@@ -16,6 +21,8 @@ pub struct EvtxFileHeader {
     pub flags: HeaderFlags,
     // Checksum is of first 120 bytes of header
     pub checksum: u32,
+    // The checksum we computed over the same 120 bytes while parsing.
+    calculated_checksum: u32,
 }
 
 bitflags! {
@@ -59,8 +66,19 @@ impl EvtxFileHeader {
         };
 
         let checksum = try_read!(stream, u32, "file_header_checksum")?;
+
+        // Go back and read the checksummed part of the header.
+        let mut checksum_data = [0_u8; EVTX_FILE_HEADER_CHECKSUM_DATA_SIZE];
+        stream.seek(SeekFrom::Current(-128)).map_err(|e| {
+            WrappedIoError::io_error_with_message(e, "failed to seek in file_header", stream)
+        })?;
+        stream.read_exact(&mut checksum_data).map_err(|e| {
+            WrappedIoError::io_error_with_message(e, "failed to read file_header data", stream)
+        })?;
+        let calculated_checksum = crc32::checksum_ieee(&checksum_data);
+
         // unused
-        stream.seek(SeekFrom::Current(4096 - 128)).map_err(|e| {
+        stream.seek(SeekFrom::Current(4096 - 120)).map_err(|e| {
             WrappedIoError::io_error_with_message(e, "failed to seek in file_header", stream)
         })?;
 
@@ -75,14 +93,84 @@ impl EvtxFileHeader {
             chunk_count,
             flags,
             checksum,
+            calculated_checksum,
         })
     }
+
+    /// A header for a new file with `chunk_count` chunks, which is not dirty.
+    pub(crate) fn new(chunk_count: u16, next_record_id: u64) -> EvtxFileHeader {
+        let mut header = EvtxFileHeader {
+            first_chunk_number: 0,
+            last_chunk_number: u64::from(chunk_count.saturating_sub(1)),
+            next_record_id,
+            header_size: 128,
+            minor_version: 1,
+            major_version: 3,
+            header_block_size: 4096,
+            chunk_count,
+            flags: HeaderFlags::EMPTY,
+            checksum: 0,
+            calculated_checksum: 0,
+        };
+
+        header.checksum =
+            crc32::checksum_ieee(&header.to_bytes()[..EVTX_FILE_HEADER_CHECKSUM_DATA_SIZE]);
+        header.calculated_checksum = header.checksum;
+
+        header
+    }
+
+    /// Serializes the header, padded to `header_block_size`.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(usize::from(self.header_block_size));
+        self.write_fields(&mut data)
+            .expect("Writing to a Vec cannot fail");
+        data.resize(usize::from(self.header_block_size).max(data.len()), 0);
+
+        data
+    }
+
+    fn write_fields<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(b"ElfFile\x00")?;
+        writer.write_u64::<LittleEndian>(self.first_chunk_number)?;
+        writer.write_u64::<LittleEndian>(self.last_chunk_number)?;
+        writer.write_u64::<LittleEndian>(self.next_record_id)?;
+        writer.write_u32::<LittleEndian>(self.header_size)?;
+        writer.write_u16::<LittleEndian>(self.minor_version)?;
+        writer.write_u16::<LittleEndian>(self.major_version)?;
+        writer.write_u16::<LittleEndian>(self.header_block_size)?;
+        writer.write_u16::<LittleEndian>(self.chunk_count)?;
+        // unused
+        writer.write_all(&[0; 76])?;
+        writer.write_u32::<LittleEndian>(self.flags.bits())?;
+        writer.write_u32::<LittleEndian>(self.checksum)
+    }
+
+    /// The checksum of the header data, as calculated while reading the header.
+    pub fn calculated_checksum(&self) -> u32 {
+        self.calculated_checksum
+    }
+
+    /// Returns true if the checksum stored in the header matches its data.
+    pub fn validate_checksum(&self) -> bool {
+        self.checksum == self.calculated_checksum
+    }
+
+    /// A dirty file was not closed properly, it might contain records which are not
+    /// reflected in the header.
+    pub fn is_dirty(&self) -> bool {
+        self.flags.contains(HeaderFlags::DIRTY)
+    }
+
+    /// A full file has reached its maximum size, and is not being written to.
+    pub fn is_full(&self) -> bool {
+        self.flags.contains(HeaderFlags::FULL)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crc::crc32;
     use std::io::Cursor;
 
     #[test]
@@ -103,7 +191,14 @@ mod tests {
                 chunk_count: 26,
                 flags: HeaderFlags::DIRTY,
                 checksum: crc32::checksum_ieee(&evtx_file[..120]),
+                calculated_checksum: crc32::checksum_ieee(&evtx_file[..120]),
             }
         );
+        assert!(parsing_result.validate_checksum());
+        assert!(parsing_result.is_dirty());
+        assert!(!parsing_result.is_full());
+
+        // Round trip.
+        assert_eq!(parsing_result.to_bytes(), &evtx_file[..4096]);
     }
 }
@@ -1,31 +1,58 @@
-use crate::err::{ChunkError, EvtxError, InputError, Result};
+use crate::binxml::value_variant::{BinaryEncoding, GuidFormat, NullRendering};
+use crate::err::{ChunkError, DeserializationError, EvtxError, InputError, Result};
 
-use crate::evtx_chunk::EvtxChunkData;
+use crate::evtx_chunk::{EvtxChunkData, EvtxChunkHeader, EVTX_CHUNK_HEADER_SIZE};
 use crate::evtx_file_header::EvtxFileHeader;
-use crate::evtx_record::SerializedEvtxRecord;
+use crate::evtx_record::{DualOutput, EvtxRecordHeader, RecordId, SerializedEvtxRecord};
+use crate::evtx_writer::EvtxWriter;
+#[cfg(feature = "flate2")]
+use crate::gzip_input::{decompress_gzip, is_gzip, MAX_DECOMPRESSED_SIZE};
+use crate::message_table::MessageTableProvider;
+use crate::multi_file_parser::MultiFileParser;
+#[cfg(feature = "parquet")]
+use crate::parquet_output::{write_parquet, ParquetSchema};
+use crate::record_filter::RecordFilter;
+#[cfg(feature = "async")]
+use crate::record_stream::RecordStream;
+use crate::schema_summary::{SchemaOutput, SchemaSummary};
+use crate::sequential_reader::SequentialReader;
+use crate::template_cache::SharedTemplateCache;
+use crate::validation::{ChunkValidation, ValidationReport};
+use crate::xml_output::{BinXmlOutput, IndentStyle};
 #[cfg(feature = "multithreading")]
 use rayon::prelude::*;
 
 use log::trace;
-#[cfg(not(feature = "multithreading"))]
 use log::warn;
 
 use log::{debug, info};
+use serde::de::DeserializeOwned;
 use std::fs::File;
-use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
 
-use crate::EvtxRecord;
+use crate::{ChunkOffset, EvtxRecord};
+use chrono::{DateTime, Utc};
 use encoding::all::WINDOWS_1252;
+use encoding::label::encoding_from_windows_code_page;
 use encoding::EncodingRef;
 use std::cmp::max;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fmt::Debug;
 use std::iter::{IntoIterator, Iterator};
-use std::path::Path;
-use std::sync::Arc;
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 
 pub const EVTX_CHUNK_SIZE: usize = 65536;
 pub const EVTX_FILE_HEADER_SIZE: usize = 4096;
+const DEFAULT_MAX_RECORD_SIZE: usize = 2 * 1024 * 1024;
+pub(crate) const DEFAULT_MAX_DEPTH: usize = 256;
+/// The (major, minor) versions of the files the parser was tested against.
+const TESTED_FORMAT_VERSIONS: &[(u16, u16)] = &[(3, 1), (3, 2)];
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
 
 // Stable shim until https://github.com/rust-lang/rust/issues/59359 is merged.
 // Taken from proposed std code.
@@ -96,13 +123,220 @@ pub struct EvtxParser<T: ReadSeek> {
     /// The calculated_chunk_count is the: (<file size> - <header size>) / <chunk size>
     /// This is needed because the chunk count of an EVTX file can be larger than the u16
     /// value stored in the file header.
-    calculated_chunk_count: u64
+    calculated_chunk_count: u64,
+    /// Called for every error skipped by `records_ok`.
+    error_callback: Option<Arc<Mutex<ErrorCallback>>>,
+    /// If set, the next iteration over the records starts at this record id.
+    seek_record_id: Option<u64>,
+    /// The number of records the next iteration skips, without serializing them.
+    skip_records: u64,
+    /// If set, the next iteration yields at most this many records.
+    take_records: Option<usize>,
+    /// Records which don't match are skipped (before being serialized).
+    filter: Arc<RecordFilter>,
+    /// Called with the JSON value of every record, before it is serialized.
+    json_transform: Option<JsonTransform>,
+    /// If set, only chunks in this range are read.
+    chunk_range: Option<Range<u64>>,
+    /// Set when reading the chunks stopped at zero padding.
+    zero_padding: Option<ZeroPaddedChunk>,
+    /// The first chunk after a zeroed chunk which was found not to have a zeroed magic,
+    /// so the zeroed chunks before it are not checked for zero padding again.
+    next_non_zeroed_chunk: Option<u64>,
+    /// Set if the data can only be read forward (see `from_sequential_read`),
+    /// so the chunks after a zeroed chunk cannot be checked for zero padding in advance.
+    forward_only: bool,
+    /// If set, the chunks share template definitions using this cache, whatever the configuration.
+    template_cache: Option<SharedTemplateCache>,
 }
+
+/// The start of all-zero padding at the end of a file, where a chunk was expected.
+///
+/// This is not an error: files are often allocated ahead of time, and the padding just means
+/// that there is no more data. Chunks are read until the first all-zero chunk which is only
+/// followed by chunks with an all-zero magic. Zeroed chunks followed by real chunks (as seen in
+/// dirty files) are skipped instead, and a chunk with a zeroed magic but other data is an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZeroPaddedChunk {
+    pub chunk_number: u64,
+}
+
+/// The formats records can be serialized to, see `EvtxParser::records_with_format`.
+///
+/// Can be parsed from `"xml"`, `"json"` and `"jsonl"` (case-insensitively),
+/// so it can be chosen by a configuration value or a command line flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The same as the data of `EvtxParser::records`.
+    Xml,
+    /// The same as the data of `EvtxParser::records_json` (indented if `ParserSettings::indent` is set).
+    Json,
+    /// Compact JSON, regardless of `ParserSettings::indent`, so every record fits in a single line.
+    JsonLines,
+}
+
+impl FromStr for OutputFormat {
+    type Err = EvtxError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "xml" => Ok(OutputFormat::Xml),
+            "json" => Ok(OutputFormat::Json),
+            "jsonl" | "jsonlines" => Ok(OutputFormat::JsonLines),
+            _ => Err(EvtxError::UnknownOutputFormat {
+                name: s.to_string(),
+            }),
+        }
+    }
+}
+
+/// Controls how records with more than one root element (top-level XML fragment) are serialized,
+/// see `ParserSettings::multi_fragment`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultiFragmentMode {
+    /// The fragments are written one after the other. In JSON, they are merged into one object
+    /// (so repeated root elements are collected into an array, like any other repeated element).
+    Concatenate,
+    /// In JSON, a record is an array with an object for each fragment.
+    /// XML is written as with `Concatenate`.
+    WrapInArray,
+    /// Only the first fragment is kept, in both XML and JSON.
+    FirstOnly,
+}
+
+impl Default for MultiFragmentMode {
+    fn default() -> Self {
+        MultiFragmentMode::Concatenate
+    }
+}
+
+type ErrorCallback = Box<dyn FnMut(&EvtxError) + Send>;
+type JsonTransform = Arc<dyn Fn(&mut serde_json::Value) + Send + Sync>;
+type TemplateMissCallback = Arc<dyn Fn(ChunkOffset) + Send + Sync>;
+
+/// The number of records in the chunk with an id of at least `min_record_id`.
+fn records_from(chunk: &EvtxChunkData, min_record_id: u64) -> u64 {
+    let count = chunk.count_records() as u64;
+
+    // Carved chunks have no record ids in their header.
+    if chunk.is_carved() {
+        return count;
+    }
+
+    count.saturating_sub(min_record_id.saturating_sub(chunk.header.first_event_record_id))
+}
+
+/// The first synthetic record id (see `ParserSettings::synthesize_record_ids`).
+/// It is far above the ids of real records, and still fits in a signed 64-bit integer.
+pub const SYNTHETIC_RECORD_ID_BASE: RecordId = 1 << 62;
+
+/// Assigns synthetic ids to the records of a file, in file order.
+#[derive(Debug)]
+struct SyntheticRecordIds {
+    seen: HashSet<RecordId>,
+    next_id: RecordId,
+}
+
+impl SyntheticRecordIds {
+    fn new() -> Self {
+        SyntheticRecordIds {
+            seen: HashSet::new(),
+            next_id: SYNTHETIC_RECORD_ID_BASE,
+        }
+    }
+
+    /// Returns the synthetic ids of the records of the chunk which need one,
+    /// by their offset from the start of the chunk.
+    fn assign(&mut self, chunk: &EvtxChunkData) -> HashMap<u64, RecordId> {
+        let mut synthetic_ids = HashMap::new();
+
+        for (offset, record_header) in chunk.record_headers_with_offsets() {
+            let record_id = record_header.event_record_id;
+
+            if record_id == 0 || !self.seen.insert(record_id) {
+                synthetic_ids.insert(offset as u64, self.next_id);
+                self.next_id += 1;
+            }
+        }
+
+        synthetic_ids
+    }
+}
+
+/// Passes errors to the callback (if one was set), and yields only the successful items.
+fn skip_errors<U>(
+    results: impl Iterator<Item = Result<U>>,
+    error_callback: Option<Arc<Mutex<ErrorCallback>>>,
+) -> impl Iterator<Item = U> {
+    results.filter_map(move |result| match result {
+        Ok(item) => Some(item),
+        Err(err) => {
+            report_error(error_callback.as_ref(), &err);
+
+            None
+        }
+    })
+}
+
+/// Groups the items into vectors of up to `size` items.
+/// Errors are yielded as they are, after the items which preceded them.
+fn batch_results<U>(
+    mut results: impl Iterator<Item = Result<U>>,
+    size: NonZeroUsize,
+) -> impl Iterator<Item = Result<Vec<U>>> {
+    let size = size.get();
+    let mut pending_error = None;
+
+    std::iter::from_fn(move || {
+        if let Some(err) = pending_error.take() {
+            return Some(Err(err));
+        }
+
+        let mut batch = Vec::with_capacity(size);
+        while batch.len() < size {
+            match results.next() {
+                Some(Ok(item)) => batch.push(item),
+                Some(Err(err)) if batch.is_empty() => return Some(Err(err)),
+                Some(Err(err)) => {
+                    pending_error = Some(err);
+                    break;
+                }
+                None => break,
+            }
+        }
+
+        if batch.is_empty() {
+            None
+        } else {
+            Some(Ok(batch))
+        }
+    })
+}
+
+fn report_error(error_callback: Option<&Arc<Mutex<ErrorCallback>>>, err: &EvtxError) {
+    if let Some(callback) = error_callback {
+        // A panic in a previous call to the callback shouldn't stop us from reporting.
+        let mut callback = callback.lock().unwrap_or_else(|e| e.into_inner());
+        callback(err);
+    }
+}
+
+/// Passes the JSON value of a record to the transform (if one was set with `map_json`).
+fn transform_json(json_transform: Option<&JsonTransform>, value: &mut serde_json::Value) {
+    if let Some(transform) = json_transform {
+        transform(value);
+    }
+}
+
 impl<T: ReadSeek> Debug for EvtxParser<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> ::std::fmt::Result {
         f.debug_struct("EvtxParser")
             .field("header", &self.header)
             .field("config", &self.config)
+            .field("error_callback", &self.error_callback.is_some())
+            .field("filter", &self.filter)
+            .field("json_transform", &self.json_transform.is_some())
+            .field("chunk_range", &self.chunk_range)
             .finish()
     }
 }
@@ -111,8 +345,12 @@ impl<T: ReadSeek> Debug for EvtxParser<T> {
 pub struct ParserSettings {
     /// Controls the number of threads used for parsing chunks concurrently.
     num_threads: usize,
+    /// If set, limits the number of records which are parsed ahead of the consumer.
+    max_buffered_records: Option<usize>,
     /// If enabled, chunk with bad checksums will be skipped.
     validate_checksums: bool,
+    /// If disabled, the checksums of chunks are never calculated.
+    compute_checksums: bool,
     /// If enabled, XML attributes will be separated in JSON
     /// into a separate field. Example:
     /// {
@@ -132,21 +370,150 @@ pub struct ParserSettings {
     ///   }
     /// }
     separate_json_attributes: bool,
+    /// If enabled, every JSON element is an array of its occurrences,
+    /// instead of only elements with siblings of the same name.
+    always_array_repeated: bool,
+    /// If enabled, `<Data Name="...">` elements are keyed by their `Name` in JSON.
+    event_data_by_name: bool,
     /// If true, output will be indented.
     indent: bool,
+    /// Controls the indentation of XML output, when `indent` is enabled.
+    indent_style: IndentStyle,
     /// Controls the ansi codec used to deserialize ansi strings inside the xml document.
     ansi_codec: EncodingRef,
+    /// If enabled, invalid UTF-16 and ansi sequences are replaced with U+FFFD instead of failing the record.
+    lossy_string_decoding: bool,
+    /// If set, template definitions will be shared between chunks.
+    shared_template_cache: Option<SharedTemplateCache>,
+    /// If enabled, chunks are read in the order of their record ids, instead of their order in the file.
+    ordered_output: bool,
+    /// Controls how binary values are rendered.
+    binary_encoding: BinaryEncoding,
+    /// Controls how GUID values are rendered.
+    guid_format: GuidFormat,
+    /// If enabled, integrity errors stop the iteration.
+    strict: bool,
+    /// If enabled, files with a format version which was not tested are an error.
+    reject_untested_versions: bool,
+    /// If enabled, records are carved from chunks with a bad header.
+    carve_records: bool,
+    /// If enabled, well-known SIDs are rendered as their names.
+    resolve_well_known_sids: bool,
+    /// If enabled, 64-bit integers are written as strings in JSON.
+    large_ints_as_strings: bool,
+    /// If enabled, the `<Level>` of the event gets a `Name` attribute.
+    render_level_names: bool,
+    /// If enabled, the `<Keywords>` of the event get a `Names` attribute.
+    decode_keywords: bool,
+    /// If enabled, the root `<Event>` element always has the standard `xmlns` attribute.
+    include_event_namespace: bool,
+    /// Elements and attributes with these names are renamed.
+    field_renames: Arc<HashMap<String, String>>,
+    /// If false, XML records are written without the `<?xml ... ?>` declaration.
+    emit_xml_declaration: bool,
+    /// If enabled, XML is written on a single line, without any whitespace between elements.
+    xml_compact: bool,
+    /// If enabled, documents written by the `dump_*` methods start with a UTF-8 BOM.
+    emit_bom: bool,
+    /// If enabled, XML elements without content are written as self-closing tags.
+    self_closing_empty_elements: bool,
+    /// If enabled, elements without content or attributes are dropped from the output.
+    omit_empty_elements: bool,
+    /// If enabled, the children of `<EventData>` are sorted by their `Name` attribute.
+    sort_event_data: bool,
+    /// If enabled, FileTime and SysTime values are rendered as their raw FILETIME.
+    raw_filetimes: bool,
+    /// Controls how null values are rendered.
+    null_value: NullRendering,
+    /// Controls how records with more than one root element are serialized.
+    multi_fragment: MultiFragmentMode,
+    /// If enabled, serialized records keep a copy of their raw bytes.
+    include_raw_bytes: bool,
+    /// If enabled, serialized records have the id of their template.
+    include_template_id: bool,
+    /// If enabled, records with a zero or duplicated record id are given a synthetic one.
+    synthesize_record_ids: bool,
+    /// If enabled, unimplemented tokens are replaced with a placeholder element.
+    tolerate_unknown_tokens: bool,
+    /// If enabled, serialized records have `RecordStats`.
+    collect_stats: bool,
+    /// Records whose header declares a larger size are rejected.
+    max_record_size: usize,
+    /// Records with templates or elements which are nested deeper are rejected.
+    max_depth: usize,
+    /// If enabled, a record which is cut off by the end of its chunk ends the chunk silently.
+    tolerate_truncation: bool,
+    /// Invoked with the offset of every template which is not found in the template cache.
+    template_miss_callback: Option<TemplateMissCallback>,
+    /// If set, serialized records have the message of their event, rendered from these tables.
+    message_tables: Option<Arc<MessageTableProvider>>,
+    /// If set, chunks are parsed on this pool instead of the global rayon pool.
+    #[cfg(feature = "multithreading")]
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
 }
 
 impl Debug for ParserSettings {
     fn fmt(&self, f: &mut fmt::Formatter) -> ::std::fmt::Result {
-        f.debug_struct("ParserSettings")
+        let mut debug = f.debug_struct("ParserSettings");
+
+        debug
             .field("num_threads", &self.num_threads)
+            .field("max_buffered_records", &self.max_buffered_records)
             .field("validate_checksums", &self.validate_checksums)
+            .field("compute_checksums", &self.compute_checksums)
             .field("separate_json_attributes", &self.separate_json_attributes)
+            .field("always_array_repeated", &self.always_array_repeated)
+            .field("event_data_by_name", &self.event_data_by_name)
             .field("indent", &self.indent)
+            .field("indent_style", &self.indent_style)
             .field("ansi_codec", &self.ansi_codec.name())
-            .finish()
+            .field("lossy_string_decoding", &self.lossy_string_decoding)
+            .field(
+                "shared_template_cache",
+                &self.shared_template_cache.is_some(),
+            )
+            .field("ordered_output", &self.ordered_output)
+            .field("binary_encoding", &self.binary_encoding)
+            .field("guid_format", &self.guid_format)
+            .field("strict", &self.strict)
+            .field("reject_untested_versions", &self.reject_untested_versions)
+            .field("carve_records", &self.carve_records)
+            .field("resolve_well_known_sids", &self.resolve_well_known_sids)
+            .field("large_ints_as_strings", &self.large_ints_as_strings)
+            .field("render_level_names", &self.render_level_names)
+            .field("decode_keywords", &self.decode_keywords)
+            .field("include_event_namespace", &self.include_event_namespace)
+            .field("field_renames", &self.field_renames)
+            .field("emit_xml_declaration", &self.emit_xml_declaration)
+            .field("xml_compact", &self.xml_compact)
+            .field("emit_bom", &self.emit_bom)
+            .field(
+                "self_closing_empty_elements",
+                &self.self_closing_empty_elements,
+            )
+            .field("omit_empty_elements", &self.omit_empty_elements)
+            .field("sort_event_data", &self.sort_event_data)
+            .field("raw_filetimes", &self.raw_filetimes)
+            .field("null_value", &self.null_value)
+            .field("multi_fragment", &self.multi_fragment)
+            .field("include_raw_bytes", &self.include_raw_bytes)
+            .field("include_template_id", &self.include_template_id)
+            .field("synthesize_record_ids", &self.synthesize_record_ids)
+            .field("tolerate_unknown_tokens", &self.tolerate_unknown_tokens)
+            .field("collect_stats", &self.collect_stats)
+            .field("max_record_size", &self.max_record_size)
+            .field("max_depth", &self.max_depth)
+            .field("tolerate_truncation", &self.tolerate_truncation)
+            .field(
+                "template_miss_callback",
+                &self.template_miss_callback.is_some(),
+            )
+            .field("message_tables", &self.message_tables.is_some());
+
+        #[cfg(feature = "multithreading")]
+        debug.field("thread_pool", &self.thread_pool.is_some());
+
+        debug.finish()
     }
 }
 
@@ -154,9 +521,48 @@ impl PartialEq for ParserSettings {
     fn eq(&self, other: &ParserSettings) -> bool {
         self.ansi_codec.name() == other.ansi_codec.name()
             && self.num_threads == other.num_threads
+            && self.max_buffered_records == other.max_buffered_records
             && self.validate_checksums == other.validate_checksums
+            && self.compute_checksums == other.compute_checksums
             && self.separate_json_attributes == other.separate_json_attributes
+            && self.always_array_repeated == other.always_array_repeated
+            && self.event_data_by_name == other.event_data_by_name
             && self.indent == other.indent
+            && self.indent_style == other.indent_style
+            && self.lossy_string_decoding == other.lossy_string_decoding
+            && self.shared_template_cache == other.shared_template_cache
+            && self.ordered_output == other.ordered_output
+            && self.binary_encoding == other.binary_encoding
+            && self.guid_format == other.guid_format
+            && self.strict == other.strict
+            && self.reject_untested_versions == other.reject_untested_versions
+            && self.carve_records == other.carve_records
+            && self.resolve_well_known_sids == other.resolve_well_known_sids
+            && self.large_ints_as_strings == other.large_ints_as_strings
+            && self.render_level_names == other.render_level_names
+            && self.decode_keywords == other.decode_keywords
+            && self.include_event_namespace == other.include_event_namespace
+            && self.field_renames == other.field_renames
+            && self.emit_xml_declaration == other.emit_xml_declaration
+            && self.xml_compact == other.xml_compact
+            && self.emit_bom == other.emit_bom
+            && self.self_closing_empty_elements == other.self_closing_empty_elements
+            && self.omit_empty_elements == other.omit_empty_elements
+            && self.sort_event_data == other.sort_event_data
+            && self.raw_filetimes == other.raw_filetimes
+            && self.null_value == other.null_value
+            && self.multi_fragment == other.multi_fragment
+            && self.include_raw_bytes == other.include_raw_bytes
+            && self.include_template_id == other.include_template_id
+            && self.synthesize_record_ids == other.synthesize_record_ids
+            && self.tolerate_unknown_tokens == other.tolerate_unknown_tokens
+            && self.collect_stats == other.collect_stats
+            && self.max_record_size == other.max_record_size
+            && self.max_depth == other.max_depth
+            && self.tolerate_truncation == other.tolerate_truncation
+            && self.template_miss_callback.is_some() == other.template_miss_callback.is_some()
+            && self.message_tables == other.message_tables
+            && self.has_same_thread_pool(other)
     }
 }
 
@@ -164,10 +570,50 @@ impl Default for ParserSettings {
     fn default() -> Self {
         ParserSettings {
             num_threads: 0,
+            max_buffered_records: None,
             validate_checksums: false,
+            compute_checksums: true,
             separate_json_attributes: false,
+            always_array_repeated: false,
+            event_data_by_name: true,
             indent: true,
+            indent_style: IndentStyle::default(),
             ansi_codec: WINDOWS_1252,
+            lossy_string_decoding: false,
+            shared_template_cache: None,
+            ordered_output: false,
+            binary_encoding: BinaryEncoding::default(),
+            guid_format: GuidFormat::default(),
+            strict: false,
+            reject_untested_versions: false,
+            carve_records: false,
+            resolve_well_known_sids: false,
+            large_ints_as_strings: false,
+            render_level_names: false,
+            decode_keywords: false,
+            include_event_namespace: false,
+            field_renames: Arc::new(HashMap::new()),
+            emit_xml_declaration: true,
+            xml_compact: false,
+            emit_bom: false,
+            self_closing_empty_elements: false,
+            omit_empty_elements: false,
+            sort_event_data: false,
+            raw_filetimes: false,
+            null_value: NullRendering::default(),
+            multi_fragment: MultiFragmentMode::default(),
+            include_raw_bytes: false,
+            include_template_id: false,
+            synthesize_record_ids: false,
+            tolerate_unknown_tokens: false,
+            collect_stats: false,
+            max_record_size: DEFAULT_MAX_RECORD_SIZE,
+            max_depth: DEFAULT_MAX_DEPTH,
+            tolerate_truncation: false,
+            template_miss_callback: None,
+            message_tables: None,
+            #[cfg(feature = "multithreading")]
+            thread_pool: None,
         }
     }
 }
@@ -199,6 +645,51 @@ impl ParserSettings {
         self
     }
 
+    /// Sets a callback which is invoked with the offset (in its chunk) of every template definition
+    /// which is not found in the template cache of the chunk, and has to be parsed inline
+    /// (usually in dirty files, or files with corrupted template tables).
+    /// Can be used to find files which are slow to parse, as these templates are parsed
+    /// again for every record which uses them. The callback may be called from several threads.
+    pub fn on_template_miss(mut self, callback: Box<dyn Fn(ChunkOffset) + Send + Sync>) -> Self {
+        self.template_miss_callback = Some(Arc::from(callback));
+
+        self
+    }
+
+    /// Render the messages of records from the format strings of their providers
+    /// (see `SerializedEvtxRecord::rendered_message`).
+    /// `%1`, `%2`, ... in the format string of an event are replaced with the `<Data>` values
+    /// of the record's `<EventData>`, in the order in which they were logged
+    /// (settings which change the output, such as `sort_event_data`, don't affect the message).
+    pub fn message_tables(mut self, message_tables: MessageTableProvider) -> Self {
+        self.message_tables = Some(Arc::new(message_tables));
+
+        self
+    }
+
+    /// Parse chunks on the given pool instead of the global rayon pool.
+    /// When set, `num_threads` is ignored and the number of threads in the pool is used instead.
+    #[cfg(feature = "multithreading")]
+    pub fn thread_pool(mut self, thread_pool: Arc<rayon::ThreadPool>) -> Self {
+        self.thread_pool = Some(thread_pool);
+
+        self
+    }
+
+    /// Limits the number of records which are parsed (and kept in memory) before the consumer
+    /// of the records iterator asks for them, to bound memory usage with many threads
+    /// and a slow consumer (for example, a network upload).
+    ///
+    /// Records are parsed a chunk at a time, so fewer chunks are parsed concurrently
+    /// (according to the number of records declared in their headers) to stay within `n` records.
+    /// A single chunk with more than `n` records is still parsed as a whole.
+    /// By default, `num_threads` chunks are parsed at a time, regardless of their records.
+    pub fn max_buffered_records(mut self, n: usize) -> Self {
+        self.max_buffered_records = Some(n);
+
+        self
+    }
+
     /// Sets the ansi codec used by the parser.
     pub fn ansi_codec(mut self, ansi_codec: EncodingRef) -> Self {
         self.ansi_codec = ansi_codec;
@@ -206,503 +697,4188 @@ impl ParserSettings {
         self
     }
 
+    /// Sets the ansi codec used by the parser from a Windows code page number (for example, `1251`).
+    /// Unknown code pages are ignored with a warning, and the current codec is kept.
+    ///
+    /// The codec is looked up in the `encoding` crate, like the one given to `ansi_codec`.
+    pub fn ansi_codepage(mut self, codepage: u16) -> Self {
+        match encoding_from_windows_code_page(usize::from(codepage)) {
+            Some(ansi_codec) => self.ansi_codec = ansi_codec,
+            None => warn!(
+                "Unknown ansi code page {}, keeping codec {}",
+                codepage,
+                self.ansi_codec.name()
+            ),
+        }
+
+        self
+    }
+
     pub fn validate_checksums(mut self, validate_checksums: bool) -> Self {
         self.validate_checksums = validate_checksums;
 
         self
     }
 
+    /// If disabled, the CRC32 checksums of chunks are never calculated, for trusted inputs:
+    /// chunks are parsed regardless of their checksums (even with `validate_checksums`
+    /// or in strict mode, so `ChunkError::InvalidChunkChecksum` is never returned),
+    /// and records report `chunk_checksum_valid` as `None`.
+    /// With `carve_records`, only chunks with a header which cannot be parsed are carved.
+    ///
+    /// Calculating the checksums takes about 10% of the time it takes to parse the records
+    /// of a file to XML on a single thread (see the `read all records` benchmarks).
+    ///
+    /// Enabled by default.
+    pub fn compute_checksums(mut self, compute_checksums: bool) -> Self {
+        self.compute_checksums = compute_checksums;
+
+        self
+    }
+
     pub fn separate_json_attributes(mut self, separate: bool) -> Self {
         self.separate_json_attributes = separate;
 
         self
     }
 
+    /// Repeated sibling elements are always collected into JSON arrays.
+    /// If enabled, elements which appear once are arrays as well,
+    /// so the shape of the JSON doesn't depend on the number of occurrences.
+    pub fn always_array_repeated(mut self, always_array: bool) -> Self {
+        self.always_array_repeated = always_array;
+
+        self
+    }
+
+    /// If enabled, `<Data Name="TargetUserName">value</Data>` is written to JSON as
+    /// `"TargetUserName": "value"`. Data elements with the same `Name` are collected into an array,
+    /// and data elements without a `Name` are kept under `Data`.
+    /// If disabled, data elements are written like any other element, with a `Name` attribute.
+    /// Defaults to `true`.
+    pub fn event_data_by_name(mut self, by_name: bool) -> Self {
+        self.event_data_by_name = by_name;
+
+        self
+    }
+
     pub fn indent(mut self, pretty: bool) -> Self {
         self.indent = pretty;
 
         self
     }
 
-    /// Gets the current ansi codec
-    pub fn get_ansi_codec(&self) -> EncodingRef {
-        self.ansi_codec
+    /// Sets the indentation used for XML output. Defaults to two spaces.
+    /// Has no effect when `indent` is disabled.
+    pub fn indent_style(mut self, indent_style: IndentStyle) -> Self {
+        self.indent_style = indent_style;
+
+        self
     }
 
-    pub fn should_separate_json_attributes(&self) -> bool {
-        self.separate_json_attributes
+    /// If disabled, XML records are written without the `<?xml version="1.0" encoding="utf-8"?>`
+    /// declaration, so that they can be concatenated under a single root element.
+    /// Defaults to `true`.
+    pub fn emit_xml_declaration(mut self, emit: bool) -> Self {
+        self.emit_xml_declaration = emit;
+
+        self
     }
 
-    pub fn should_indent(&self) -> bool {
-        self.indent
+    /// If enabled, XML is written on a single line: records are never indented (regardless of
+    /// `indent`), whitespace-only text between elements is dropped, and `dump_xml_document`
+    /// writes no newlines. Whitespace inside text is kept as is.
+    pub fn xml_compact(mut self, compact: bool) -> Self {
+        self.xml_compact = compact;
+
+        self
     }
 
-    pub fn should_validate_checksums(&self) -> bool {
-        self.validate_checksums
+    /// If enabled, `EvtxParser::dump_xml_document` and `EvtxParser::dump_jsonl` start the output
+    /// with a UTF-8 byte order mark (`EF BB BF`), which some Windows tools expect.
+    /// Records themselves never have a BOM. Defaults to `false`.
+    pub fn emit_bom(mut self, emit: bool) -> Self {
+        self.emit_bom = emit;
+
+        self
     }
 
-    pub fn get_num_threads(&self) -> &usize {
-        &self.num_threads
+    /// If enabled, XML elements without children or text are written as `<Tag/>`
+    /// (keeping their attributes), instead of `<Tag></Tag>`.
+    pub fn self_closing_empty_elements(mut self, self_closing: bool) -> Self {
+        self.self_closing_empty_elements = self_closing;
+
+        self
     }
-}
 
-impl EvtxParser<File> {
-    /// Attempts to load an evtx file from a given path, will fail if the path does not exist,
-    /// or if evtx header is invalid.
-    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
-        let path = path
-            .as_ref()
-            .canonicalize()
-            .map_err(|e| InputError::failed_to_open_file(e, &path))?;
+    /// If enabled, elements without any content (no children, and text which is empty or only
+    /// whitespace) and without attributes are dropped, in both XML and JSON.
+    /// Elements which only contained dropped elements are dropped as well.
+    /// Attributes with an empty value don't count, but any other attribute keeps its element.
+    pub fn omit_empty_elements(mut self, omit: bool) -> Self {
+        self.omit_empty_elements = omit;
 
-        let f = File::open(&path).map_err(|e| InputError::failed_to_open_file(e, &path))?;
+        self
+    }
 
-        let cursor = f;
-        Self::from_read_seek(cursor)
+    /// If enabled, the `<Data>` elements of `<EventData>` are sorted by their `Name` attribute,
+    /// in both XML and JSON, so exports of the same events can be compared byte for byte
+    /// (Windows doesn't guarantee the order in which they are logged).
+    /// Elements without a `Name` are moved after the others, keeping their order.
+    pub fn sort_event_data(mut self, sort_event_data: bool) -> Self {
+        self.sort_event_data = sort_event_data;
+
+        self
     }
-}
 
-impl EvtxParser<Cursor<Vec<u8>>> {
-    /// Attempts to load an evtx file from a given path, will fail the evtx header is invalid.
-    pub fn from_buffer(buffer: Vec<u8>) -> Result<Self> {
-        let cursor = Cursor::new(buffer);
-        Self::from_read_seek(cursor)
+    /// If enabled, FileTime and SysTime values (such as the `SystemTime` of `<TimeCreated>`)
+    /// are rendered as their raw FILETIME, the number of 100-nanosecond intervals since
+    /// 1601-01-01 UTC, in both XML and JSON. Datetimes are rendered in microseconds,
+    /// which loses the last digit of most FILETIMEs (see `BinXmlValue::filetime_raw`).
+    pub fn raw_filetimes(mut self, raw_filetimes: bool) -> Self {
+        self.raw_filetimes = raw_filetimes;
+
+        self
     }
-}
 
-impl<T: ReadSeek> EvtxParser<T> {
-    pub fn from_read_seek(mut read_seek: T) -> Result<Self> {
-        let evtx_header = EvtxFileHeader::from_stream(&mut read_seek)?;
+    /// Sets how null values (usually substitutions which were left empty) are rendered,
+    /// in both XML and JSON. Defaults to `NullRendering::JsonNull`.
+    ///
+    /// With `NullRendering::OmitKey`, elements whose only content is a null value are dropped,
+    /// even if they have attributes (elements which only contained them are kept,
+    /// see `omit_empty_elements`).
+    pub fn null_value(mut self, null_value: NullRendering) -> Self {
+        self.null_value = null_value;
 
-        // Because an event log can be larger than u16 MAX * EVTX_CHUNK_SIZE,
-        // We need to calculate the chunk count instead of using the header value
-        // this allows us to continue parsing events past the 4294901760 bytes of
-        // chunk data
-        let stream_size = ReadSeek::stream_len(&mut read_seek)?;
-        let chunk_data_size: u64 = match stream_size.checked_sub(
-            evtx_header.header_block_size.into()
-        ){
-            Some(c) => c,
-            None => {
-                return Err(
-                    EvtxError::calculation_error(
-                        format!(
-                            "Could not calculate valid chunk count because stream size is less \
-                            than evtx header block size. (stream_size: {}, header_block_size: {})",
-                            stream_size,
-                            evtx_header.header_block_size
-                        )
-                    )
-                );
-            }
+        self
+    }
+
+    /// Sets how records with more than one root element (top-level XML fragment) are serialized.
+    /// Defaults to `MultiFragmentMode::Concatenate`.
+    ///
+    /// Consumers which expect a single root object can use `WrapInArray` or `FirstOnly`.
+    /// Note that with `WrapInArray` the JSON of every record is an array, even if it has a single root.
+    pub fn multi_fragment(mut self, multi_fragment: MultiFragmentMode) -> Self {
+        self.multi_fragment = multi_fragment;
+
+        self
+    }
+
+    /// If enabled, identical template definitions will only be parsed once, and shared between chunks.
+    /// See `SharedTemplateCache` for the expected gains, and `EvtxParser::with_template_cache`
+    /// to reuse a cache between parsers.
+    pub fn shared_template_cache(mut self, shared: bool) -> Self {
+        self.shared_template_cache = if shared {
+            Some(SharedTemplateCache::new())
+        } else {
+            None
         };
-        let chunk_count = chunk_data_size / EVTX_CHUNK_SIZE as u64;
 
-        debug!("EVTX Header: {:#?}", evtx_header);
-        Ok(EvtxParser {
-            data: read_seek,
-            header: evtx_header,
-            config: Arc::new(ParserSettings::default()),
-            calculated_chunk_count: chunk_count
-        })
+        self
     }
 
-    pub fn with_configuration(mut self, configuration: ParserSettings) -> Self {
-        self.config = Arc::new(configuration);
+    /// If enabled, records are yielded in ascending `event_record_id` order, also when parsing
+    /// with multiple threads.
+    ///
+    /// Chunks are always yielded in the order they appear in the file, but evtx files are circular,
+    /// so once a log wraps around the oldest records are no longer at the start of the file.
+    /// When this is set, the headers of all the chunks are read before iteration starts,
+    /// and chunks are then read in the order of their first record id.
+    /// This costs an extra read of each chunk header (512 bytes per 64KB chunk),
+    /// but does not increase memory usage, since no records need to be buffered.
+    ///
+    /// Records inside a chunk are always written in ascending order.
+    pub fn ordered_output(mut self, ordered: bool) -> Self {
+        self.ordered_output = ordered;
+
         self
     }
 
-    /// Allocate a new chunk from the given data, at the offset expected by `chunk_number`.
-    /// If the read chunk contains valid data, an `Ok(Some(EvtxChunkData))` will be returned.
-    /// If the read chunk contains invalid data (bad magic, bad checksum when `validate_checksum` is set to true),
-    /// of if not enough data can be read (e.g. because we reached EOF), an `Err` is returned.
-    /// If the read chunk is empty, `Ok(None)` will be returned.
-    fn allocate_chunk(
-        data: &mut T,
-        chunk_number: u64,
-        validate_checksum: bool,
-    ) -> Result<Option<EvtxChunkData>> {
-        let mut chunk_data = Vec::with_capacity(EVTX_CHUNK_SIZE);
-        let chunk_offset = EVTX_FILE_HEADER_SIZE + chunk_number as usize * EVTX_CHUNK_SIZE;
+    /// Sets the encoding used to render binary values, in both XML and JSON output.
+    /// Defaults to `BinaryEncoding::HexUpper`.
+    pub fn binary_encoding(mut self, binary_encoding: BinaryEncoding) -> Self {
+        self.binary_encoding = binary_encoding;
 
-        trace!(
-            "Offset `0x{:08x} ({})` - Reading chunk number `{}`",
-            chunk_offset,
-            chunk_offset,
-            chunk_number
-        );
+        self
+    }
 
-        data.seek(SeekFrom::Start(chunk_offset as u64))
-            .map_err(|e| EvtxError::FailedToParseChunk {
-                chunk_id: chunk_number,
-                source: ChunkError::FailedToSeekToChunk(e),
-            })?;
+    /// Sets the format used to render GUID values, in both XML and JSON output.
+    /// Defaults to `GuidFormat::HyphenatedUpper`.
+    pub fn guid_format(mut self, guid_format: GuidFormat) -> Self {
+        self.guid_format = guid_format;
 
-        let amount_read = data
-            .take(EVTX_CHUNK_SIZE as u64)
-            .read_to_end(&mut chunk_data)
-            .map_err(|_| EvtxError::incomplete_chunk(chunk_number))?;
+        self
+    }
 
-        if amount_read != EVTX_CHUNK_SIZE {
-            return Err(EvtxError::incomplete_chunk(chunk_number));
-        }
+    /// If enabled, the file is assumed to be intact, and any sign of tampering or corruption is
+    /// a hard error: a bad file header checksum, a bad chunk checksum or a bad chunk magic
+    /// will be returned as the last item of the iteration, instead of skipping to the next chunk.
+    ///
+    /// Implies `validate_checksums(true)`.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
 
-        // There might be empty chunks in the middle of a dirty file.
-        if chunk_data.iter().all(|x| *x == 0) {
-            return Ok(None);
-        }
+        self
+    }
 
-        EvtxChunkData::new(chunk_data, validate_checksum)
-            .map(Some)
-            .map_err(|e| EvtxError::FailedToParseChunk {
-                chunk_id: chunk_number,
-                source: e,
-            })
+    /// If enabled, files with a format version which was not tested
+    /// (see `EvtxParser::format_version`) are not parsed: iterating over their records only
+    /// returns `EvtxError::UnsupportedFormatVersion`. Otherwise, a warning is logged.
+    /// Defaults to `false`.
+    pub fn reject_untested_versions(mut self, reject: bool) -> Self {
+        self.reject_untested_versions = reject;
+
+        self
     }
 
-    /// Find the next chunk, staring at `chunk_number` (inclusive).
-    /// If a chunk is found, returns the data of the chunk or the relevant error,
-    /// and the number of that chunk.
-    pub fn find_next_chunk(
-        &mut self,
-        mut chunk_number: u64,
-    ) -> Option<(Result<EvtxChunkData>, u64)> {
-        loop {
-            match EvtxParser::allocate_chunk(
-                &mut self.data,
-                chunk_number,
-                self.config.validate_checksums,
-            ) {
-                Err(err) => {
-                    // We try to read past the `chunk_count` to allow for dirty files.
-                    // But if we failed, it means we really are at the end of the file.
-                    if chunk_number >= self.calculated_chunk_count {
-                        return None;
-                    } else {
-                        return Some((Err(err), chunk_number));
-                    }
-                }
-                Ok(None) => {
-                    // We try to read past the `chunk_count` to allow for dirty files.
-                    // But if we get an empty chunk, we need to keep looking.
+    /// If enabled, chunks with a bad header (which cannot be parsed, or with a bad header checksum)
+    /// are not skipped, instead their data is scanned for anything which looks like a record,
+    /// and each candidate is parsed on its own.
+    ///
+    /// Carved records are marked with `SerializedEvtxRecord::carved`.
+    /// They are lower fidelity: the string and template tables in the header of the chunk are not used,
+    /// records which fail to parse are silently dropped (as they are likely false positives),
+    /// and the record ids are not guaranteed to be in order.
+    ///
+    /// Ignored in strict mode.
+    pub fn carve_records(mut self, carve_records: bool) -> Self {
+        self.carve_records = carve_records;
+
+        self
+    }
+
+    /// If enabled, SID values of well-known accounts and groups (ex. `S-1-5-18`)
+    /// are rendered as their names (ex. `Local System`), other SIDs are rendered as usual.
+    /// See `resolve_well_known_sid` for the lookup itself.
+    pub fn resolve_well_known_sids(mut self, resolve: bool) -> Self {
+        self.resolve_well_known_sids = resolve;
+
+        self
+    }
+
+    /// If enabled, 64-bit integers (`Int64` and `UInt64` values, and arrays of them) are written
+    /// as strings in JSON, since parsers which read numbers as doubles lose precision above 2^53.
+    /// `HexInt64` values are always strings. Smaller integers and XML output are unaffected.
+    pub fn large_ints_as_strings(mut self, as_strings: bool) -> Self {
+        self.large_ints_as_strings = as_strings;
+
+        self
+    }
+
+    /// If enabled, the `<Level>` element of `<System>` gets a `Name` attribute with the name
+    /// of the level, as shown by the Event Viewer (ex. `<Level Name="Warning">3</Level>`).
+    /// Levels without a standard name are left as is.
+    /// See `resolve_level_name` for the lookup itself.
+    pub fn render_level_names(mut self, render: bool) -> Self {
+        self.render_level_names = render;
+
+        self
+    }
+
+    /// If enabled, the `<Keywords>` element of `<System>` gets a `Names` attribute with the names
+    /// of the standard keyword bits which are set (ex. `<Keywords Names="AuditSuccess">0x8020000000000000</Keywords>`).
+    /// The names are an array in JSON, and are separated by spaces in XML.
+    /// See `resolve_keyword_names` for the lookup itself.
+    pub fn decode_keywords(mut self, decode: bool) -> Self {
+        self.decode_keywords = decode;
+
+        self
+    }
+
+    /// If enabled, a root `<Event>` element without an `xmlns` attribute gets the standard one
+    /// (`http://schemas.microsoft.com/win/2004/08/events/event`) in XML output,
+    /// so the output validates against the event schema. JSON output is unchanged.
+    ///
+    /// Records usually declare the namespace in their template, which is kept either way.
+    pub fn include_event_namespace(mut self, include: bool) -> Self {
+        self.include_event_namespace = include;
+
+        self
+    }
+
+    /// Renames elements and attributes in JSON output, for example `TimeCreated` to `@timestamp`.
+    /// Names which are not in `renames` are kept.
+    ///
+    /// XML output is not renamed, since the new names don't have to be valid XML names.
+    /// Only names are renamed, so the keys of `<Data Name="...">` elements in JSON
+    /// (which come from the value of the `Name` attribute) are kept.
+    pub fn field_renames(mut self, renames: HashMap<String, String>) -> Self {
+        self.field_renames = Arc::new(renames);
+
+        self
+    }
+
+    /// If enabled, strings with invalid UTF-16 (ex. unpaired surrogates) or invalid ansi sequences
+    /// are decoded with U+FFFD (`�`) in place of the invalid data, instead of failing the record.
+    ///
+    /// Strings inside template definitions are always decoded strictly.
+    pub fn lossy_string_decoding(mut self, lossy: bool) -> Self {
+        self.lossy_string_decoding = lossy;
+
+        self
+    }
+
+    /// If enabled, every serialized record has a copy of its raw bytes in `raw`
+    /// (from the record magic to the copy of its size at the end),
+    /// so it can be archived or parsed again by other tools.
+    ///
+    /// Disabled by default, since it roughly doubles the memory used by every record.
+    pub fn include_raw_bytes(mut self, include_raw_bytes: bool) -> Self {
+        self.include_raw_bytes = include_raw_bytes;
+
+        self
+    }
+
+    /// If enabled, every serialized record has the id of the template it was built from
+    /// in `template_id` (see `EvtxRecord::template_id`), so records can be grouped by template.
+    pub fn include_template_id(mut self, include_template_id: bool) -> Self {
+        self.include_template_id = include_template_id;
+
+        self
+    }
+
+    /// If enabled, records whose id is zero, or was already used by a previous record of the file
+    /// (which happens with carved or corrupt records), are given a synthetic `event_record_id`,
+    /// and have `synthetic_record_id` set, so keying records by id never overwrites a record.
+    ///
+    /// Synthetic ids start at `SYNTHETIC_RECORD_ID_BASE` and increase in file order.
+    /// The `<EventRecordID>` element of the data of the record keeps the original id.
+    /// Only the records read in the same iteration are considered, so records which were skipped
+    /// with `seek_record_id` are not known as duplicates.
+    pub fn synthesize_record_ids(mut self, synthesize_record_ids: bool) -> Self {
+        self.synthesize_record_ids = synthesize_record_ids;
+
+        self
+    }
+
+    /// If enabled, every serialized record has `RecordStats` in `stats`: the number of
+    /// template instances and substitution values it was expanded from, and the depth of its elements.
+    /// Useful for finding unusually complex records which slow down parsing.
+    pub fn collect_stats(mut self, collect_stats: bool) -> Self {
+        self.collect_stats = collect_stats;
+
+        self
+    }
+
+    /// If enabled, binxml tokens which are not implemented (currently, character references)
+    /// are skipped instead of failing the record, and an `<UnparsedToken name="..." offset="0x..."/>`
+    /// element is written in their place (where an element is allowed).
+    pub fn tolerate_unknown_tokens(mut self, tolerate: bool) -> Self {
+        self.tolerate_unknown_tokens = tolerate;
+
+        self
+    }
+
+    /// Sets the largest size (in bytes) a record header may declare.
+    /// Records which declare a larger size are returned as `EvtxError::RecordSizeExceeded`,
+    /// and since the offset of the next record cannot be trusted, the rest of the chunk is skipped.
+    ///
+    /// Defaults to 2 MiB.
+    pub fn max_record_size(mut self, max_record_size: usize) -> Self {
+        self.max_record_size = max_record_size;
+
+        self
+    }
+
+    /// Sets how deeply the templates (and nested binxml fragments) and the elements of a record
+    /// may be nested. Deeper records, which are usually crafted to exhaust the stack,
+    /// are returned as `EvtxError::MaxDepthExceeded`, and the next records are still parsed
+    /// (in strict mode too). Nested fragments are already rejected while the record is deserialized.
+    ///
+    /// Defaults to 256.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+
+        self
+    }
+
+    /// Records whose header declares more bytes than remain in the chunk (usually the last record
+    /// of a file which is still being written) are returned as `EvtxError::TruncatedRecord`.
+    /// If enabled, such a record is treated as the end of the chunk instead, without an error.
+    pub fn tolerate_truncation(mut self, tolerate: bool) -> Self {
+        self.tolerate_truncation = tolerate;
+
+        self
+    }
+
+    /// Gets the current ansi codec
+    pub fn get_ansi_codec(&self) -> EncodingRef {
+        self.ansi_codec
+    }
+
+    pub fn should_decode_strings_lossily(&self) -> bool {
+        self.lossy_string_decoding
+    }
+
+    pub fn should_separate_json_attributes(&self) -> bool {
+        self.separate_json_attributes
+    }
+
+    pub fn should_always_array_repeated(&self) -> bool {
+        self.always_array_repeated
+    }
+
+    pub fn should_key_event_data_by_name(&self) -> bool {
+        self.event_data_by_name
+    }
+
+    pub fn should_indent(&self) -> bool {
+        self.indent
+    }
+
+    pub fn get_indent_style(&self) -> IndentStyle {
+        self.indent_style
+    }
+
+    pub fn should_emit_xml_declaration(&self) -> bool {
+        self.emit_xml_declaration
+    }
+
+    pub fn is_xml_compact(&self) -> bool {
+        self.xml_compact
+    }
+
+    pub fn should_emit_bom(&self) -> bool {
+        self.emit_bom
+    }
+
+    pub fn should_self_close_empty_elements(&self) -> bool {
+        self.self_closing_empty_elements
+    }
+
+    pub fn should_omit_empty_elements(&self) -> bool {
+        self.omit_empty_elements
+    }
+
+    pub fn should_sort_event_data(&self) -> bool {
+        self.sort_event_data
+    }
+
+    pub fn should_render_raw_filetimes(&self) -> bool {
+        self.raw_filetimes
+    }
+
+    pub fn get_null_value(&self) -> NullRendering {
+        self.null_value
+    }
+
+    pub fn get_multi_fragment(&self) -> MultiFragmentMode {
+        self.multi_fragment
+    }
+
+    pub fn get_message_tables(&self) -> Option<&MessageTableProvider> {
+        self.message_tables.as_deref()
+    }
+
+    /// Reports a template which was not found in the template cache, see `on_template_miss`.
+    pub(crate) fn report_template_miss(&self, offset: ChunkOffset) {
+        if let Some(callback) = &self.template_miss_callback {
+            callback(offset);
+        }
+    }
+
+    pub fn should_include_raw_bytes(&self) -> bool {
+        self.include_raw_bytes
+    }
+
+    pub fn should_include_template_id(&self) -> bool {
+        self.include_template_id
+    }
+
+    pub fn should_synthesize_record_ids(&self) -> bool {
+        self.synthesize_record_ids
+    }
+
+    pub fn should_collect_stats(&self) -> bool {
+        self.collect_stats
+    }
+
+    pub fn should_tolerate_unknown_tokens(&self) -> bool {
+        self.tolerate_unknown_tokens
+    }
+
+    pub fn get_max_record_size(&self) -> usize {
+        self.max_record_size
+    }
+
+    pub fn get_max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub fn should_tolerate_truncation(&self) -> bool {
+        self.tolerate_truncation
+    }
+
+    pub fn should_validate_checksums(&self) -> bool {
+        (self.validate_checksums || self.strict) && self.compute_checksums
+    }
+
+    pub fn should_compute_checksums(&self) -> bool {
+        self.compute_checksums
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    pub fn should_reject_untested_versions(&self) -> bool {
+        self.reject_untested_versions
+    }
+
+    pub fn should_carve_records(&self) -> bool {
+        self.carve_records && !self.strict
+    }
+
+    pub fn get_num_threads(&self) -> &usize {
+        &self.num_threads
+    }
+
+    pub fn get_max_buffered_records(&self) -> Option<usize> {
+        self.max_buffered_records
+    }
+
+    #[cfg(feature = "multithreading")]
+    pub fn get_thread_pool(&self) -> Option<&Arc<rayon::ThreadPool>> {
+        self.thread_pool.as_ref()
+    }
+
+    #[cfg(feature = "multithreading")]
+    fn has_same_thread_pool(&self, other: &ParserSettings) -> bool {
+        match (&self.thread_pool, &other.thread_pool) {
+            (Some(pool), Some(other_pool)) => Arc::ptr_eq(pool, other_pool),
+            (pool, other_pool) => pool.is_none() && other_pool.is_none(),
+        }
+    }
+
+    #[cfg(not(feature = "multithreading"))]
+    fn has_same_thread_pool(&self, _other: &ParserSettings) -> bool {
+        true
+    }
+
+    pub fn get_binary_encoding(&self) -> BinaryEncoding {
+        self.binary_encoding
+    }
+
+    pub fn get_guid_format(&self) -> GuidFormat {
+        self.guid_format
+    }
+
+    pub fn should_resolve_well_known_sids(&self) -> bool {
+        self.resolve_well_known_sids
+    }
+
+    pub fn should_render_large_ints_as_strings(&self) -> bool {
+        self.large_ints_as_strings
+    }
+
+    pub fn should_render_level_names(&self) -> bool {
+        self.render_level_names
+    }
+
+    pub fn should_decode_keywords(&self) -> bool {
+        self.decode_keywords
+    }
+
+    pub fn should_include_event_namespace(&self) -> bool {
+        self.include_event_namespace
+    }
+
+    pub fn get_field_renames(&self) -> &HashMap<String, String> {
+        self.field_renames.as_ref()
+    }
+
+    /// The renames for `JsonOutput`, which shares them between records (`None` if there are none).
+    pub(crate) fn shared_field_renames(&self) -> Option<Arc<HashMap<String, String>>> {
+        Some(Arc::clone(&self.field_renames)).filter(|renames| !renames.is_empty())
+    }
+
+    pub fn should_order_output(&self) -> bool {
+        self.ordered_output
+    }
+
+    pub fn should_share_templates(&self) -> bool {
+        self.shared_template_cache.is_some()
+    }
+
+    pub fn get_shared_template_cache(&self) -> Option<&SharedTemplateCache> {
+        self.shared_template_cache.as_ref()
+    }
+}
+
+impl EvtxParser<File> {
+    /// Attempts to load an evtx file from a given path, will fail if the path does not exist,
+    /// or if evtx header is invalid.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path
+            .as_ref()
+            .canonicalize()
+            .map_err(|e| InputError::failed_to_open_file(e, &path))?;
+
+        let f = File::open(&path).map_err(|e| InputError::failed_to_open_file(e, &path))?;
+
+        let cursor = f;
+        Self::from_read_seek(cursor)
+    }
+
+    /// Opens several files (for example, a log and its rolled-over files) as a single stream
+    /// of records, ordered by record id. See `MultiFileParser`.
+    pub fn from_paths(paths: &[PathBuf]) -> Result<MultiFileParser<File>> {
+        let parsers = paths
+            .iter()
+            .map(EvtxParser::from_path)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(MultiFileParser::from_parsers(parsers))
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl EvtxParser<Cursor<memmap2::Mmap>> {
+    /// Attempts to load an evtx file from a given path by memory mapping it (read-only),
+    /// will fail if the path does not exist, or if evtx header is invalid.
+    ///
+    /// Chunks are paged in by the OS as they are read, which keeps memory usage low
+    /// for very large files.
+    ///
+    /// NOTE: The mapping is only valid as long as the file is not modified.
+    /// Truncating a file while it is mapped (for example, if it is still being written to by
+    /// the event log service) will crash the process, so prefer `from_path` for live logs.
+    pub fn from_path_mmap(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path
+            .as_ref()
+            .canonicalize()
+            .map_err(|e| InputError::failed_to_open_file(e, &path))?;
+
+        let f = File::open(&path).map_err(|e| InputError::failed_to_open_file(e, &path))?;
+
+        // Safety: the mapping is read-only, and is owned by the parser, so it lives as long as
+        // any chunk read from it. See the note above about files modified while mapped.
+        #[allow(unsafe_code)]
+        let mmap = unsafe { memmap2::Mmap::map(&f) }
+            .map_err(|e| InputError::failed_to_map_file(e, &path))?;
+
+        Self::from_read_seek(Cursor::new(mmap))
+    }
+}
+
+/// The number of parsed records which are buffered by `into_stream` before parsing waits for the stream.
+#[cfg(feature = "async")]
+const STREAM_BUFFER_SIZE: usize = 1024;
+
+#[cfg(feature = "async")]
+impl<T: ReadSeek + Send + 'static> EvtxParser<T> {
+    /// Consumes the parser, returning a stream of XML records (like `records`).
+    ///
+    /// Records are parsed on a blocking task (using the configured number of threads),
+    /// which waits when 1024 records are waiting to be polled, and stops once the stream is dropped.
+    /// Note that the records of the chunks which are being parsed are buffered as well
+    /// (see `ParserSettings::max_buffered_records`), so memory use is bounded by the sum of both.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn into_stream(mut self) -> RecordStream {
+        let (sender, receiver) = tokio::sync::mpsc::channel(STREAM_BUFFER_SIZE);
+
+        tokio::task::spawn_blocking(move || {
+            for record in self.records() {
+                if sender.blocking_send(record).is_err() {
+                    // The stream was dropped.
+                    break;
+                }
+            }
+        });
+
+        RecordStream::new(receiver)
+    }
+}
+
+impl<R: Read> EvtxParser<SequentialReader<R>> {
+    /// Attempts to load an evtx file from a `Read` source which cannot seek
+    /// (for example, a pipe or an entry of a tar stream), will fail if the evtx header is invalid.
+    ///
+    /// The chunks are read one after the other, so only iterating over the records (or chunks)
+    /// in file order is supported: `ParserSettings::ordered_output`, `seek_record_id`
+    /// and the methods which read the chunk headers in advance will fail to read the chunks.
+    /// Since the size of the file is unknown, the number of chunks is taken from the file header
+    /// (chunks after it are still read, like in dirty files).
+    pub fn from_sequential_read(mut read: R) -> Result<Self> {
+        let mut header_data = vec![0; EVTX_FILE_HEADER_SIZE];
+        read.read_exact(&mut header_data)?;
+
+        let evtx_header = EvtxFileHeader::from_stream(&mut Cursor::new(header_data))?;
+        let chunk_count = u64::from(evtx_header.chunk_count);
+
+        let data = SequentialReader::new(read, EVTX_FILE_HEADER_SIZE as u64);
+
+        let mut parser = EvtxParser::new(data, evtx_header, chunk_count);
+        parser.forward_only = true;
+
+        Ok(parser)
+    }
+}
+
+impl EvtxParser<Cursor<Vec<u8>>> {
+    /// Attempts to load an evtx file from a given path, will fail the evtx header is invalid.
+    pub fn from_buffer(buffer: Vec<u8>) -> Result<Self> {
+        let cursor = Cursor::new(buffer);
+        Self::from_read_seek(cursor)
+    }
+}
+
+#[cfg(feature = "flate2")]
+impl EvtxParser<Cursor<Vec<u8>>> {
+    /// Attempts to load a gzip-compressed evtx file (for example, `Security.evtx.gz`)
+    /// from a given path, by decompressing it into memory (like `from_buffer`).
+    /// Will fail if the path does not exist, if the file is not gzip-compressed,
+    /// if it decompresses to more than 16GB, or if evtx header is invalid.
+    pub fn from_gzip_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path
+            .as_ref()
+            .canonicalize()
+            .map_err(|e| InputError::failed_to_open_file(e, &path))?;
+
+        let data = std::fs::read(&path).map_err(|e| InputError::failed_to_open_file(e, &path))?;
+
+        if !is_gzip(&data) {
+            return Err(InputError::not_gzip_compressed(&path).into());
+        }
+
+        let buffer = decompress_gzip(&data, MAX_DECOMPRESSED_SIZE)
+            .map_err(|e| InputError::failed_to_decompress_file(e, &path))?;
+
+        Self::from_buffer(buffer)
+    }
+}
+
+impl EvtxParser<Cursor<Arc<[u8]>>> {
+    /// Like `from_buffer`, but the buffer can be shared with other parsers,
+    /// so the parser can be cheaply `reconfigured`.
+    pub fn from_shared_buffer(buffer: Arc<[u8]>) -> Result<Self> {
+        Self::from_read_seek(Cursor::new(buffer))
+    }
+
+    /// Returns an independent parser over the same buffer, with `configuration`
+    /// (for example, to write both XML and JSON from a file which was loaded once).
+    /// The buffer is shared, not copied.
+    ///
+    /// The filters, callbacks and chunk range of this parser are kept, and iterating over
+    /// one parser doesn't affect the other.
+    pub fn reconfigured(&self, configuration: ParserSettings) -> Self {
+        EvtxParser {
+            data: Cursor::new(Arc::clone(self.data.get_ref())),
+            header: self.header.clone(),
+            config: Arc::new(configuration),
+            calculated_chunk_count: self.calculated_chunk_count,
+            error_callback: self.error_callback.clone(),
+            seek_record_id: self.seek_record_id,
+            skip_records: self.skip_records,
+            take_records: self.take_records,
+            filter: Arc::clone(&self.filter),
+            json_transform: self.json_transform.clone(),
+            chunk_range: self.chunk_range.clone(),
+            zero_padding: None,
+            next_non_zeroed_chunk: None,
+            forward_only: self.forward_only,
+            template_cache: None,
+        }
+        .with_shared_template_cache(self.template_cache.clone())
+    }
+}
+
+impl<T: ReadSeek> EvtxParser<T> {
+    /// Attempts to load an evtx file from any `Read + Seek` source (for example, a decompressed
+    /// archive entry), will fail if the evtx header is invalid.
+    ///
+    /// The source must be seekable, since chunks are located by their offset in the file,
+    /// and the size of the file is used to calculate the number of chunks.
+    pub fn from_read_seek(mut read_seek: T) -> Result<Self> {
+        let evtx_header = EvtxFileHeader::from_stream(&mut read_seek)?;
+
+        // Because an event log can be larger than u16 MAX * EVTX_CHUNK_SIZE,
+        // We need to calculate the chunk count instead of using the header value
+        // this allows us to continue parsing events past the 4294901760 bytes of
+        // chunk data
+        let stream_size = ReadSeek::stream_len(&mut read_seek)?;
+        let chunk_data_size: u64 = match stream_size.checked_sub(
+            evtx_header.header_block_size.into()
+        ){
+            Some(c) => c,
+            None => {
+                return Err(
+                    EvtxError::calculation_error(
+                        format!(
+                            "Could not calculate valid chunk count because stream size is less \
+                            than evtx header block size. (stream_size: {}, header_block_size: {})",
+                            stream_size,
+                            evtx_header.header_block_size
+                        )
+                    )
+                );
+            }
+        };
+        let chunk_count = chunk_data_size / EVTX_CHUNK_SIZE as u64;
+
+        Ok(EvtxParser::new(read_seek, evtx_header, chunk_count))
+    }
+
+    fn new(data: T, header: EvtxFileHeader, calculated_chunk_count: u64) -> Self {
+        debug!("EVTX Header: {:#?}", header);
+
+        let (major_version, minor_version) = (header.major_version, header.minor_version);
+        if !TESTED_FORMAT_VERSIONS.contains(&(major_version, minor_version)) {
+            warn!(
+                "The file has format version {}.{}, which was not tested, records may be parsed incorrectly",
+                major_version, minor_version
+            );
+        }
+
+        EvtxParser {
+            data,
+            header,
+            config: Arc::new(ParserSettings::default()),
+            calculated_chunk_count,
+            error_callback: None,
+            seek_record_id: None,
+            skip_records: 0,
+            take_records: None,
+            filter: Arc::new(RecordFilter::default()),
+            json_transform: None,
+            chunk_range: None,
+            zero_padding: None,
+            next_non_zeroed_chunk: None,
+            forward_only: false,
+            template_cache: None,
+        }
+    }
+
+    pub fn with_configuration(mut self, configuration: ParserSettings) -> Self {
+        self.config = Arc::new(configuration);
+        let template_cache = self.template_cache.take();
+        self.with_shared_template_cache(template_cache)
+    }
+
+    /// Shares template definitions between chunks using `cache`, which may already contain
+    /// definitions from previous parses (ex. of other files in the same format).
+    /// Definitions which are not found in the cache are parsed as usual, and added to it.
+    /// The cache can be cloned before it is passed in, and reused for the next parser once it is warm.
+    ///
+    /// The cache is kept by the parser, so it is used regardless of `ParserSettings::shared_template_cache`,
+    /// also if the configuration is replaced later on.
+    /// See `SharedTemplateCache` for when a cached definition is used.
+    pub fn with_template_cache(self, cache: SharedTemplateCache) -> Self {
+        self.with_shared_template_cache(Some(cache))
+    }
+
+    /// Keeps `cache`, and puts it in the current configuration (if set).
+    fn with_shared_template_cache(mut self, cache: Option<SharedTemplateCache>) -> Self {
+        if let Some(cache) = &cache {
+            let mut config = (*self.config).clone();
+            config.shared_template_cache = Some(cache.clone());
+            self.config = Arc::new(config);
+        }
+        self.template_cache = cache;
+        self
+    }
+
+    /// Sets a callback which is invoked with every error encountered by `records_ok`,
+    /// before iteration continues to the next record.
+    ///
+    /// Record errors are `EvtxError::FailedToParseRecord`, which carries the record id,
+    /// and the offset of the failure (in the source error).
+    /// Errors which fail a whole chunk are reported as well.
+    pub fn on_error(mut self, callback: Box<dyn FnMut(&EvtxError) + Send>) -> Self {
+        self.error_callback = Some(Arc::new(Mutex::new(callback)));
+
+        self
+    }
+
+    /// Only yield records from one of the given providers, matched case-insensitively against
+    /// either the `Name` or the `Guid` attribute of the `<Provider>` element.
+    /// When combined with other filters, a record must match all of them.
+    pub fn filter_providers(mut self, providers: &[String]) -> Self {
+        self.filter = Arc::new((*self.filter).clone().providers(providers));
+
+        self
+    }
+
+    /// Only yield records from one of the given channels, matched case-insensitively against
+    /// the `<Channel>` element.
+    /// When combined with other filters, a record must match all of them.
+    pub fn filter_channels(mut self, channels: &[String]) -> Self {
+        self.filter = Arc::new((*self.filter).clone().channels(channels));
+
+        self
+    }
+
+    /// Only yield records for which `predicate` returns true, for conditions which are not covered
+    /// by the other filters. The predicate is given the JSON value of the record
+    /// (the same value as `records_json_value` yields, before `map_json`),
+    /// which is built in the same pass as the record is serialized.
+    /// When combined with other filters, a record must match all of them.
+    ///
+    /// With `MultiFragmentMode::WrapInArray`, the value is an array of the root elements
+    /// of the record, so the predicate must index it first (`record[0]["Event"]`).
+    ///
+    /// ```rust
+    /// # use evtx::EvtxParser;
+    /// # let fp = std::path::PathBuf::from(format!("{}/samples/security.evtx", std::env::var("CARGO_MANIFEST_DIR").unwrap()));
+    /// let mut parser = EvtxParser::from_path(fp)
+    ///     .unwrap()
+    ///     .retain(|record| record["Event"]["EventData"]["LogonType"] == 10);
+    ///
+    /// for record in parser.records() {
+    ///     println!("Remote logon {}", record.unwrap().event_record_id);
+    /// }
+    /// ```
+    pub fn retain(
+        mut self,
+        predicate: impl Fn(&serde_json::Value) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.filter = Arc::new((*self.filter).clone().retain(Arc::new(predicate)));
+
+        self
+    }
+
+    /// Passes the JSON value of every record to `transform` before it is serialized, so records can
+    /// be enriched (or have fields removed) in the same pass. Replaces any previous transform.
+    ///
+    /// This applies to `records_json`, `records_json_value`, `records_dual`,
+    /// `records_with_format`, `dump_jsonl` and `dump_parquet`. Filters see the original value,
+    /// and records which are filtered out are never transformed.
+    ///
+    /// With more than one thread (see `ParserSettings::num_threads`), the transform is called
+    /// concurrently, in no particular order.
+    ///
+    /// ```rust
+    /// # use evtx::EvtxParser;
+    /// # let fp = std::path::PathBuf::from(format!("{}/samples/security.evtx", std::env::var("CARGO_MANIFEST_DIR").unwrap()));
+    /// let mut parser = EvtxParser::from_path(fp)
+    ///     .unwrap()
+    ///     .map_json(Box::new(|record| {
+    ///         record["host"] = "workstation-1".into();
+    ///     }));
+    ///
+    /// let record = parser.records_json_value().next().unwrap().unwrap();
+    /// assert_eq!(record.data["host"], "workstation-1");
+    /// ```
+    pub fn map_json(
+        mut self,
+        transform: Box<dyn Fn(&mut serde_json::Value) + Send + Sync>,
+    ) -> Self {
+        self.json_transform = Some(Arc::from(transform));
+
+        self
+    }
+
+    /// Only read the chunks numbered `start` (inclusive) to `end` (exclusive), for example to split
+    /// a large file between several workers. Chunks outside of the range are never read.
+    ///
+    /// This applies to iteration over the chunks and the records, and to the methods which read
+    /// the chunk headers (ex. `time_span`), but not to `validate` and `parse_record_at_offset`.
+    pub fn chunk_range(mut self, start: u64, end: u64) -> Self {
+        self.chunk_range = Some(start..end);
+
+        self
+    }
+
+    /// The number of the first chunk to read.
+    fn first_chunk_number(&self) -> u64 {
+        self.chunk_range.as_ref().map_or(0, |range| range.start)
+    }
+
+    /// The numbers of the chunks which are expected to exist, limited to `chunk_range`.
+    fn chunk_numbers(&self) -> Range<u64> {
+        match &self.chunk_range {
+            Some(range) => range.start..range.end.min(self.calculated_chunk_count),
+            None => 0..self.calculated_chunk_count,
+        }
+    }
+
+    /// Returns the file header, as parsed when the parser was created.
+    pub fn file_header(&self) -> &EvtxFileHeader {
+        &self.header
+    }
+
+    /// The (major, minor) format version of the file, according to the file header
+    /// (`(3, 1)` or `(3, 2)` in files written by the supported versions of Windows).
+    ///
+    /// A warning is logged for other versions, and they are an error
+    /// with `ParserSettings::reject_untested_versions`.
+    pub fn format_version(&self) -> (u16, u16) {
+        (self.header.major_version, self.header.minor_version)
+    }
+
+    /// The number of chunks in the file, according to the file header.
+    /// Dirty files may contain more chunks, which are still read by `chunks`.
+    pub fn num_chunks(&self) -> u16 {
+        self.header.chunk_count
+    }
+
+    /// Allocate a new chunk from the given data, at the offset expected by `chunk_number`.
+    /// If the read chunk contains valid data, an `Ok(Some(EvtxChunkData))` will be returned.
+    /// If the read chunk contains invalid data (bad magic, bad checksum when `validate_checksums` is set to true),
+    /// of if not enough data can be read (e.g. because we reached EOF), an `Err` is returned.
+    /// When `carve_records` is set, a chunk with an invalid header is returned as a carved chunk instead.
+    /// If the read chunk is empty, `Ok(None)` will be returned.
+    fn allocate_chunk(
+        data: &mut T,
+        chunk_number: u64,
+        settings: &ParserSettings,
+    ) -> Result<Option<EvtxChunkData>> {
+        let mut chunk_data = Vec::with_capacity(EVTX_CHUNK_SIZE);
+        let chunk_offset = EVTX_FILE_HEADER_SIZE + chunk_number as usize * EVTX_CHUNK_SIZE;
+
+        trace!(
+            "Offset `0x{:08x} ({})` - Reading chunk number `{}`",
+            chunk_offset,
+            chunk_offset,
+            chunk_number
+        );
+
+        data.seek(SeekFrom::Start(chunk_offset as u64))
+            .map_err(|e| EvtxError::FailedToParseChunk {
+                chunk_id: chunk_number,
+                source: ChunkError::FailedToSeekToChunk(e),
+            })?;
+
+        let amount_read = data
+            .take(EVTX_CHUNK_SIZE as u64)
+            .read_to_end(&mut chunk_data)
+            .map_err(|_| EvtxError::incomplete_chunk(chunk_number))?;
+
+        if amount_read != EVTX_CHUNK_SIZE {
+            return Err(EvtxError::incomplete_chunk(chunk_number));
+        }
+
+        // There might be empty chunks in the middle of a dirty file.
+        if chunk_data.iter().all(|x| *x == 0) {
+            return Ok(None);
+        }
+
+        let validate_checksum = settings.should_validate_checksums();
+
+        if settings.should_carve_records() {
+            let chunk = EvtxChunkData::new_or_carved(
+                chunk_data,
+                validate_checksum,
+                settings.should_compute_checksums(),
+            );
+            return Ok(Some(chunk.with_chunk_number(chunk_number)));
+        }
+
+        EvtxChunkData::new_with_chunk_number(chunk_data, validate_checksum, Some(chunk_number))
+            .map(Some)
+            .map_err(|e| EvtxError::FailedToParseChunk {
+                chunk_id: chunk_number,
+                source: e,
+            })
+    }
+
+    /// In strict mode, returns an error if the file header checksum is invalid.
+    /// With `reject_untested_versions`, returns an error if the format version was not tested.
+    fn check_file_header_integrity(&self) -> Result<()> {
+        if self.config.strict && !self.header.validate_checksum() {
+            return Err(EvtxError::InvalidFileHeaderChecksum {
+                expected: self.header.checksum,
+                found: self.header.calculated_checksum(),
+            });
+        }
+
+        let (major_version, minor_version) = self.format_version();
+        if self.config.reject_untested_versions
+            && !TESTED_FORMAT_VERSIONS.contains(&(major_version, minor_version))
+        {
+            return Err(EvtxError::UnsupportedFormatVersion {
+                major_version,
+                minor_version,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// In strict mode, bad chunk checksums and magics stop the iteration.
+    fn is_fatal_chunk_error(&self, err: &EvtxError) -> bool {
+        if !self.config.strict {
+            return false;
+        }
+
+        matches!(
+            err,
+            EvtxError::FailedToParseChunk {
+                source: ChunkError::InvalidChunkChecksum { .. },
+                ..
+            } | EvtxError::FailedToParseChunk {
+                source: ChunkError::FailedToParseChunkHeader(
+                    DeserializationError::InvalidEvtxChunkMagic { .. }
+                ),
+                ..
+            }
+        )
+    }
+
+    /// Find the next chunk, staring at `chunk_number` (inclusive).
+    /// If a chunk is found, returns the data of the chunk or the relevant error,
+    /// and the number of that chunk.
+    pub fn find_next_chunk(
+        &mut self,
+        mut chunk_number: u64,
+    ) -> Option<(Result<EvtxChunkData>, u64)> {
+        loop {
+            if let Some(range) = &self.chunk_range {
+                if chunk_number >= range.end {
+                    return None;
+                }
+            }
+
+            match EvtxParser::allocate_chunk(&mut self.data, chunk_number, &self.config) {
+                Err(err) => {
+                    // We try to read past the `chunk_count` to allow for dirty files.
+                    // But if we failed, it means we really are at the end of the file.
+                    if chunk_number >= self.calculated_chunk_count {
+                        return None;
+                    } else {
+                        return Some((Err(err), chunk_number));
+                    }
+                }
+                Ok(None) if self.is_zero_padding(chunk_number) => {
+                    return None;
+                }
+                Ok(None) => {
+                    // We try to read past the `chunk_count` to allow for dirty files.
+                    // But if we get an empty chunk, we need to keep looking.
                     // Increment and try again.
                     chunk_number = match chunk_number.checked_add(1) {
                         None => return None,
                         Some(n) => n,
                     }
                 }
-                Ok(Some(chunk)) => {
-                    return Some((Ok(chunk), chunk_number));
+                Ok(Some(chunk)) => {
+                    return Some((Ok(chunk), chunk_number));
+                }
+            };
+        }
+    }
+
+    /// Returns true if the magic of this (all-zero) chunk and of all the chunks after it is zeroed,
+    /// recording the start of the padding.
+    /// Always false for data which can only be read forward, where the padding is read through.
+    fn is_zero_padding(&mut self, chunk_number: u64) -> bool {
+        if self.forward_only {
+            return false;
+        }
+
+        if let Some(next_chunk) = self.next_non_zeroed_chunk {
+            if chunk_number < next_chunk {
+                return false;
+            }
+        }
+
+        let mut magic = [0_u8; 8];
+
+        for n in chunk_number..self.calculated_chunk_count {
+            let chunk_offset = EVTX_FILE_HEADER_SIZE as u64 + n * EVTX_CHUNK_SIZE as u64;
+
+            let read = self
+                .data
+                .seek(SeekFrom::Start(chunk_offset))
+                .and_then(|_| self.data.read_exact(&mut magic));
+
+            if read.is_err() || magic.iter().any(|b| *b != 0) {
+                self.next_non_zeroed_chunk = Some(n);
+                return false;
+            }
+        }
+
+        debug!(
+            "Chunk {} and the chunks after it are zero padding, stopping",
+            chunk_number
+        );
+        self.zero_padding = Some(ZeroPaddedChunk { chunk_number });
+
+        true
+    }
+
+    /// The zero padding at which the last iteration over the chunks stopped, if any
+    /// (it is reset when a new iteration starts). See `ZeroPaddedChunk`.
+    pub fn zero_padding(&self) -> Option<ZeroPaddedChunk> {
+        self.zero_padding
+    }
+
+    /// The lowest first record id in the headers of the chunks, if any chunk header can be read.
+    pub(crate) fn first_record_id(&mut self) -> Option<u64> {
+        self.chunk_headers()
+            .into_iter()
+            .filter_map(|(_, header)| header.ok())
+            .map(|header| header.first_event_record_id)
+            .min()
+    }
+
+    /// Enables `ParserSettings::ordered_output`, keeping the rest of the configuration.
+    pub(crate) fn with_ordered_output(mut self) -> Self {
+        self.config = Arc::new((*self.config).clone().ordered_output(true));
+        self
+    }
+
+    /// Reads the header of every non-empty chunk, along with its chunk number,
+    /// without reading the rest of the chunk (so checksums are not validated).
+    /// Chunks with a header which cannot be read or parsed are returned with the error.
+    ///
+    /// Useful for reporting the metadata of every chunk, or for finding inconsistencies
+    /// (for example, a `free_space_offset` past the end of the chunk).
+    pub fn chunk_headers(
+        &mut self,
+    ) -> Vec<(u64, std::result::Result<EvtxChunkHeader, ChunkError>)> {
+        let mut headers = Vec::new();
+        let mut header_data = vec![0; EVTX_CHUNK_HEADER_SIZE];
+
+        for chunk_number in self.chunk_numbers() {
+            let chunk_offset = EVTX_FILE_HEADER_SIZE as u64 + chunk_number * EVTX_CHUNK_SIZE as u64;
+
+            if let Err(err) = self.data.seek(SeekFrom::Start(chunk_offset)) {
+                headers.push((chunk_number, Err(ChunkError::FailedToSeekToChunk(err))));
+                continue;
+            }
+
+            if self.data.read_exact(&mut header_data).is_err() {
+                headers.push((chunk_number, Err(ChunkError::IncompleteChunk)));
+                continue;
+            }
+
+            // Empty chunks are skipped anyway.
+            if header_data.iter().all(|x| *x == 0) {
+                continue;
+            }
+
+            let header = EvtxChunkHeader::from_reader(&mut Cursor::new(header_data.as_slice()))
+                .map_err(ChunkError::from);
+            headers.push((chunk_number, header));
+        }
+
+        headers
+    }
+
+    /// Returns the chunk numbers ordered by the first record id in each chunk.
+    /// Chunks with an invalid header are placed last (reading them will yield the error).
+    /// Chunks which only contain records before `min_record_id` are omitted.
+    fn chunk_numbers_by_record_id(&mut self, min_record_id: u64) -> Vec<u64> {
+        let mut chunks = Vec::new();
+
+        for (chunk_number, header) in self.chunk_headers() {
+            match header {
+                Ok(header) if header.last_event_record_id < min_record_id => continue,
+                Ok(header) => chunks.push((header.first_event_record_id, chunk_number)),
+                Err(_) => chunks.push((u64::MAX, chunk_number)),
+            }
+        }
+
+        // Sorting is stable, so bad chunks will keep their order.
+        chunks.sort_by_key(|(first_event_record_id, _)| *first_event_record_id);
+        chunks
+            .into_iter()
+            .map(|(_, chunk_number)| chunk_number)
+            .collect()
+    }
+
+    /// Parses the single record which starts at `offset` (from the start of the file),
+    /// using the string and template tables of the chunk containing it.
+    /// Records will be XML-formatted.
+    ///
+    /// Returns `InvalidEvtxRecordHeaderMagic` if `offset` does not point at the start of a record.
+    pub fn parse_record_at_offset(&mut self, offset: u64) -> Result<SerializedEvtxRecord<String>> {
+        let chunk_number = match offset.checked_sub(EVTX_FILE_HEADER_SIZE as u64) {
+            Some(offset_from_first_chunk) => offset_from_first_chunk / EVTX_CHUNK_SIZE as u64,
+            None => {
+                return Err(EvtxError::calculation_error(format!(
+                    "Offset {} is inside the file header",
+                    offset
+                )))
+            }
+        };
+
+        let mut magic = [0_u8; 4];
+        self.data.seek(SeekFrom::Start(offset))?;
+        self.data.read_exact(&mut magic)?;
+
+        if &magic != b"\x2a\x2a\x00\x00" {
+            return Err(DeserializationError::InvalidEvtxRecordHeaderMagic { magic }.into());
+        }
+
+        let mut chunk_data =
+            match EvtxParser::allocate_chunk(&mut self.data, chunk_number, &self.config)? {
+                Some(chunk_data) => chunk_data,
+                // Cannot happen, since we just read the magic.
+                None => return Err(EvtxError::incomplete_chunk(chunk_number)),
+            };
+
+        let offset_in_chunk = (offset - EVTX_FILE_HEADER_SIZE as u64) % EVTX_CHUNK_SIZE as u64;
+        let mut chunk = chunk_data.parse(Arc::clone(&self.config)).map_err(|err| {
+            EvtxError::FailedToParseChunk {
+                chunk_id: chunk_number,
+                source: err,
+            }
+        })?;
+
+        match chunk.iter_from_offset(offset_in_chunk).next() {
+            Some(record) => record?.into_xml(),
+            None => Err(EvtxError::calculation_error(format!(
+                "Offset {} is past the records of chunk {}",
+                offset, chunk_number
+            ))),
+        }
+    }
+
+    /// Checks the integrity of the whole file: the file header checksum,
+    /// and the magic, header checksum and data checksum of every non-empty chunk.
+    /// Records are not parsed.
+    ///
+    /// Unlike iteration (even with `ParserSettings::strict`), every chunk is checked,
+    /// and failed checks are reported rather than returned as errors.
+    /// This includes chunks which cannot be read at all (see `ChunkValidation::error`).
+    pub fn validate(&mut self) -> Result<ValidationReport> {
+        let settings = ParserSettings::default();
+        let mut chunks = vec![];
+
+        for chunk_number in 0..self.calculated_chunk_count {
+            let chunk = match EvtxParser::allocate_chunk(&mut self.data, chunk_number, &settings) {
+                Ok(Some(chunk)) => ChunkValidation {
+                    chunk_number,
+                    magic_valid: true,
+                    header_checksum_valid: chunk.validate_header_checksum(),
+                    data_checksum_valid: chunk.validate_data_checksum(),
+                    error: None,
+                },
+                Ok(None) => continue,
+                Err(EvtxError::FailedToParseChunk {
+                    source:
+                        ChunkError::FailedToParseChunkHeader(
+                            DeserializationError::InvalidEvtxChunkMagic { .. },
+                        ),
+                    ..
+                }) => ChunkValidation {
+                    chunk_number,
+                    magic_valid: false,
+                    header_checksum_valid: false,
+                    data_checksum_valid: false,
+                    error: None,
+                },
+                Err(err) => ChunkValidation {
+                    chunk_number,
+                    magic_valid: true,
+                    header_checksum_valid: false,
+                    data_checksum_valid: false,
+                    error: Some(err.to_string()),
+                },
+            };
+
+            chunks.push(chunk);
+        }
+
+        Ok(ValidationReport {
+            file_header_checksum_valid: self.header.validate_checksum(),
+            chunks,
+        })
+    }
+
+    /// Positions the next iteration over the records (`records`, `records_json`, etc.)
+    /// to start at `record_id`, or at the next higher record id if it doesn't exist
+    /// (for example, if the record was deleted).
+    ///
+    /// To resume after the last record consumed, seek to its `event_record_id + 1`.
+    ///
+    /// Returns an error if `record_id` is past the last record in the file.
+    ///
+    /// Only the chunk headers are read in order to find the chunk containing the record,
+    /// records are then yielded in ascending order (as with `ParserSettings::ordered_output`).
+    /// The position applies to a single iteration, following iterations start from the first record.
+    pub fn seek_to_record_id(&mut self, record_id: u64) -> Result<()> {
+        // The file header is not updated for dirty files, so the chunks might have later records.
+        let next_record_id = self
+            .chunk_headers()
+            .into_iter()
+            .filter_map(|(_, header)| header.ok().map(|header| header.last_event_record_id + 1))
+            .fold(self.header.next_record_id, max);
+
+        if record_id > next_record_id {
+            return Err(EvtxError::RecordIdOutOfRange {
+                record_id,
+                next_record_id,
+            });
+        }
+
+        self.seek_record_id = Some(record_id);
+
+        Ok(())
+    }
+
+    /// Skips the first `count` records of the next iteration over the records
+    /// (after `seek_to_record_id`, if it was called before), without serializing them.
+    /// Chunks which only contain skipped records are not parsed at all.
+    ///
+    /// Like `take_records`, records are counted after they are matched against the filters
+    /// (`filter_providers`, `filter_channels` and `retain`), so the two can be used to paginate
+    /// the matching records. Note that when records are filtered, every chunk has to be parsed
+    /// to find the skipped records.
+    ///
+    /// Errors are yielded as they are reached, and are not counted as records.
+    ///
+    /// Returns `count`, or the number of records in the file (after `seek_to_record_id`)
+    /// if it is lower. This counts all the records, as declared in the chunk headers:
+    /// it is not the number of matching records when records are filtered.
+    /// Like `seek_to_record_id`, this applies to a single iteration.
+    pub fn skip_records(&mut self, count: u64) -> u64 {
+        let min_record_id = self.seek_record_id.unwrap_or(0);
+        let available: u64 = self
+            .chunk_headers()
+            .into_iter()
+            .filter_map(|(_, header)| header.ok())
+            .map(|header| {
+                let first_record_id = max(header.first_event_record_id, min_record_id);
+
+                if header.last_event_record_id >= first_record_id {
+                    header.last_event_record_id - first_record_id + 1
+                } else {
+                    0
+                }
+            })
+            .sum();
+
+        self.skip_records = count;
+
+        count.min(available)
+    }
+
+    /// Limits the next iteration over the records to at most `count` records.
+    ///
+    /// Like `skip_records`, records are counted after they are matched against the filters
+    /// (`filter_providers`, `filter_channels` and `retain`), and errors are not counted.
+    /// Like `seek_to_record_id`, this applies to a single iteration.
+    pub fn take_records(&mut self, count: usize) {
+        self.take_records = Some(count);
+    }
+
+    /// Finds the ranges of record ids which are missing from the file (for example, because the
+    /// records were deleted), as inclusive `(first, last)` pairs in ascending order.
+    ///
+    /// Record ids are expected to be contiguous, from the lowest to the highest record id
+    /// seen either in a record or in the header of a chunk.
+    /// Only the chunk and record headers are read, records are not deserialized.
+    ///
+    /// Chunks which fail to load are skipped (so their records are reported as missing),
+    /// and the errors are reported like in `records_ok`, unless `ParserSettings::strict` is set.
+    pub fn record_id_gaps(&mut self) -> Result<Vec<(u64, u64)>> {
+        let strict = self.config.is_strict();
+        let error_callback = self.error_callback.clone();
+
+        let mut record_ids = vec![];
+        let mut expected_ids: Option<(u64, u64)> = None;
+        let mut expect = |first: u64, last: u64| {
+            expected_ids = Some(match expected_ids {
+                Some((min_id, max_id)) => (min_id.min(first), max_id.max(last)),
+                None => (first, last),
+            });
+        };
+
+        for chunk in self.chunks() {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(err) if !strict => {
+                    report_error(error_callback.as_ref(), &err);
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+
+            let header = &chunk.header;
+            if !chunk.is_carved() && header.first_event_record_id <= header.last_event_record_id {
+                expect(header.first_event_record_id, header.last_event_record_id);
+            }
+
+            for record_header in chunk.record_headers() {
+                expect(record_header.event_record_id, record_header.event_record_id);
+                record_ids.push(record_header.event_record_id);
+            }
+        }
+
+        let (first_id, last_id) = match expected_ids {
+            Some(expected_ids) => expected_ids,
+            None => return Ok(vec![]),
+        };
+
+        record_ids.sort_unstable();
+        record_ids.dedup();
+
+        let mut gaps = vec![];
+        let mut next_id = first_id;
+
+        for record_id in record_ids {
+            if record_id > next_id {
+                gaps.push((next_id, record_id - 1));
+            }
+
+            next_id = record_id + 1;
+        }
+
+        if next_id <= last_id {
+            gaps.push((next_id, last_id));
+        }
+
+        Ok(gaps)
+    }
+
+    /// Return an iterator over all the chunks.
+    /// Each chunk supports iterating over it's records in their un-serialized state
+    /// (before they are converted to XML or JSON).
+    ///
+    /// Chunks own their data, and know their position in the file (see `EvtxChunkData::chunk_number`),
+    /// so they can be distributed to other threads.
+    ///
+    /// Chunks are always at 64KB-aligned offsets, so a chunk with a bad header (for example,
+    /// a bad magic) is yielded as an error, and the iteration resumes with the next chunk
+    /// (except in strict mode). The records of such chunks can be recovered with
+    /// `ParserSettings::carve_records`.
+    pub fn chunks(&mut self) -> IterChunks<T> {
+        self.zero_padding = None;
+        self.next_non_zeroed_chunk = None;
+
+        IterChunks {
+            current_chunk_number: self.first_chunk_number(),
+            parser: self,
+            chunk_order: None,
+            failed: false,
+        }
+    }
+
+    /// Like `chunks`, but yields the chunks in the order of the record ids they contain
+    /// (see `ParserSettings::ordered_output`).
+    pub fn chunks_by_record_id(&mut self) -> IterChunks<T> {
+        self.chunks_from_record_id(0)
+    }
+
+    /// Like `chunks_by_record_id`, but skips chunks which only contain records before `record_id`.
+    fn chunks_from_record_id(&mut self, record_id: u64) -> IterChunks<T> {
+        let chunk_order = self.chunk_numbers_by_record_id(record_id);
+        self.zero_padding = None;
+        self.next_non_zeroed_chunk = None;
+
+        IterChunks {
+            parser: self,
+            current_chunk_number: 0,
+            chunk_order: Some(chunk_order.into_iter()),
+            failed: false,
+        }
+    }
+
+    /// Consumes the parser, returning an iterator over all the chunks.
+    /// Each chunk supports iterating over it's records in their un-serialized state
+    /// (before they are converted to XML or JSON).
+    pub fn into_chunks(mut self) -> IntoIterChunks<T> {
+        self.zero_padding = None;
+        self.next_non_zeroed_chunk = None;
+
+        IntoIterChunks {
+            current_chunk_number: self.first_chunk_number(),
+            parser: self,
+            failed: false,
+        }
+    }
+    /// Counts the records in the file, without deserializing or serializing them.
+    /// Chunks are handled the same way as while iterating (empty chunks are skipped,
+    /// and bad chunks are not counted), so this is a cheap estimate of what `records()` will yield.
+    pub fn count_records(&mut self) -> Result<usize> {
+        self.count_records_with_errors().map(|(count, _)| count)
+    }
+
+    /// Like `count_records`, but also returns the number of chunks which could not be read.
+    pub fn count_records_with_errors(&mut self) -> Result<(usize, usize)> {
+        let mut count = 0;
+        let mut chunk_errors = 0;
+
+        for chunk in self.chunks() {
+            match chunk {
+                Ok(chunk) => count += chunk.count_records(),
+                Err(err) => {
+                    debug!("Chunk could not be read while counting records: {}", err);
+                    chunk_errors += 1
+                }
+            }
+        }
+
+        Ok((count, chunk_errors))
+    }
+
+    /// Returns the number of records in each non-empty chunk along with its chunk number, in file order,
+    /// according to the first and last record numbers in the chunk header.
+    /// Empty (all-zero) chunks are left out, so the chunk numbers may have gaps.
+    /// Only the chunk headers are read, so this is cheaper than `count_records`.
+    ///
+    /// Chunks with a header which cannot be read are counted as empty,
+    /// and the errors are reported like in `records_ok`, unless `ParserSettings::strict` is set.
+    pub fn chunk_record_counts(&mut self) -> Result<Vec<(u64, u64)>> {
+        let strict = self.config.is_strict();
+        let error_callback = self.error_callback.clone();
+        let mut counts = vec![];
+
+        for (chunk_number, header) in self.chunk_headers() {
+            let count = match header {
+                Ok(header) => (header.last_event_record_number + 1)
+                    .saturating_sub(header.first_event_record_number),
+                Err(source) => {
+                    let err = EvtxError::FailedToParseChunk {
+                        chunk_id: chunk_number,
+                        source,
+                    };
+
+                    if strict {
+                        return Err(err);
+                    }
+
+                    report_error(error_callback.as_ref(), &err);
+                    0
+                }
+            };
+
+            counts.push((chunk_number, count));
+        }
+
+        Ok(counts)
+    }
+
+    /// Returns the timestamps of the first and the last records in the file,
+    /// or `None` if the file has no records.
+    ///
+    /// Only the chunk headers are read, followed by the header of the first record of the chunk
+    /// with the lowest record ids, and the header of the last record of the chunk with the highest
+    /// record ids, so this is much faster than iterating over the records.
+    ///
+    /// Chunks with an invalid header are skipped, and are reported like in `records_ok`
+    /// (in strict mode, the error is returned instead).
+    pub fn time_span(&mut self) -> Result<Option<(DateTime<Utc>, DateTime<Utc>)>> {
+        let strict = self.config.is_strict();
+        let error_callback = self.error_callback.clone();
+
+        // (record id, chunk number, offset of the record in the chunk)
+        let mut first: Option<(u64, u64, u64)> = None;
+        let mut last: Option<(u64, u64, u64)> = None;
+
+        for (chunk_number, header) in self.chunk_headers() {
+            let header = match header {
+                Ok(header) => header,
+                Err(source) => {
+                    let err = EvtxError::FailedToParseChunk {
+                        chunk_id: chunk_number,
+                        source,
+                    };
+
+                    if strict {
+                        return Err(err);
+                    }
+
+                    report_error(error_callback.as_ref(), &err);
+                    continue;
+                }
+            };
+
+            if header.first_event_record_id > header.last_event_record_id {
+                continue;
+            }
+
+            if first.is_none_or(|(id, _, _)| header.first_event_record_id < id) {
+                first = Some((
+                    header.first_event_record_id,
+                    chunk_number,
+                    EVTX_CHUNK_HEADER_SIZE as u64,
+                ));
+            }
+
+            if last.is_none_or(|(id, _, _)| header.last_event_record_id > id) {
+                last = Some((
+                    header.last_event_record_id,
+                    chunk_number,
+                    u64::from(header.last_event_record_data_offset),
+                ));
+            }
+        }
+
+        let ((_, first_chunk, first_offset), (_, last_chunk, last_offset)) = match (first, last) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Ok(None),
+        };
+
+        let first_record = self.read_record_header(first_chunk, first_offset)?;
+        let last_record = self.read_record_header(last_chunk, last_offset)?;
+
+        Ok(Some((first_record.timestamp, last_record.timestamp)))
+    }
+
+    /// Reads the header of the record at `offset` (from the start of the chunk).
+    fn read_record_header(&mut self, chunk_number: u64, offset: u64) -> Result<EvtxRecordHeader> {
+        let chunk_offset = EVTX_FILE_HEADER_SIZE as u64 + chunk_number * EVTX_CHUNK_SIZE as u64;
+
+        // Magic, size, id and timestamp.
+        let mut header_data = [0; 24];
+        self.data.seek(SeekFrom::Start(chunk_offset + offset))?;
+        self.data.read_exact(&mut header_data)?;
+
+        Ok(EvtxRecordHeader::from_reader(&mut Cursor::new(
+            &header_data[..],
+        ))?)
+    }
+
+    /// Return an iterator over all the records.
+    /// Records will be mapped `f`, which must produce owned data from the records.
+    pub fn serialized_records<'a, U: Send>(
+        &'a mut self,
+        mut f: impl FnMut(Result<EvtxRecord<'_>>) -> Result<U> + Send + Sync + Clone + 'a,
+    ) -> impl Iterator<Item = Result<U>> + '_ {
+        // `f` is opaque, so the records are visited once more to be filtered.
+        self.filtered_records(move |record, filter| match record {
+            Ok(record) => match record.matches(filter) {
+                Ok(true) => Some(f(Ok(record))),
+                Ok(false) => None,
+                Err(err) => Some(Err(err)),
+            },
+            Err(err) => Some(f(Err(err))),
+        })
+    }
+
+    /// Like `serialized_records`, but the records are given to `f` with the filters of the parser
+    /// (see `RecordFilter`), so they can be filtered in the same pass as they are serialized.
+    /// `f` returns `None` for records which don't match.
+    fn filtered_records<'a, U: Send>(
+        &'a mut self,
+        f: impl FnMut(Result<EvtxRecord<'_>>, &RecordFilter) -> Option<Result<U>>
+            + Send
+            + Sync
+            + Clone
+            + 'a,
+    ) -> impl Iterator<Item = Result<U>> + '_ {
+        // Retrieve parser settings here, while `self` is immutably borrowed.
+        #[cfg(feature = "multithreading")]
+        let thread_pool = self.config.thread_pool.clone();
+        #[cfg(feature = "multithreading")]
+        let num_threads = match &thread_pool {
+            Some(pool) => pool.current_num_threads(),
+            None => max(self.config.num_threads, 1),
+        };
+        #[cfg(not(feature = "multithreading"))]
+        let num_threads = max(self.config.num_threads, 1);
+        let chunk_settings = Arc::clone(&self.config);
+        let ordered_output = self.config.ordered_output;
+        let max_buffered_records = self.config.max_buffered_records.unwrap_or(usize::MAX);
+        let seek_record_id = self.seek_record_id.take();
+        let take_records = self.take_records.take().unwrap_or(usize::MAX);
+        let filter = Arc::clone(&self.filter);
+        // Without filters, whole chunks of skipped records can be dropped before they are parsed.
+        // Otherwise, the records are skipped after they are filtered, like they are taken.
+        let (mut skip_records, skip_matching_records) = if filter.is_empty() {
+            (std::mem::take(&mut self.skip_records), 0)
+        } else {
+            (0, std::mem::take(&mut self.skip_records) as usize)
+        };
+        let mut synthetic_record_ids = if self.config.synthesize_record_ids {
+            Some(SyntheticRecordIds::new())
+        } else {
+            None
+        };
+
+        // `self` is mutably borrowed from here on.
+        let mut chunks = match seek_record_id {
+            Some(record_id) => self.chunks_from_record_id(record_id),
+            None if ordered_output => self.chunks_by_record_id(),
+            None => self.chunks(),
+        };
+        let min_record_id = seek_record_id.unwrap_or(0);
+        // A chunk which didn't fit in the previous batch of `max_buffered_records`.
+        let mut next_chunk = None;
+
+        let records_per_chunk = std::iter::from_fn(move || {
+            // Allocate some chunks in advance, so they can be parsed in parallel.
+            let mut chunk_of_chunks = Vec::with_capacity(num_threads);
+            let mut buffered_records = 0;
+
+            while chunk_of_chunks.len() < num_threads {
+                let chunk = match next_chunk.take().or_else(|| chunks.next()) {
+                    Some(chunk) => chunk,
+                    None => break,
+                };
+
+                let chunk_records = match &chunk {
+                    Ok(chunk) => records_from(chunk, min_record_id) as usize,
+                    Err(_) => 1,
+                };
+                if !chunk_of_chunks.is_empty()
+                    && buffered_records + chunk_records > max_buffered_records
+                {
+                    next_chunk = Some(chunk);
+                    break;
+                }
+
+                // The ids are assigned in file order, so they don't depend on the number of threads.
+                let synthetic_ids = match (&chunk, &mut synthetic_record_ids) {
+                    (Ok(chunk), Some(synthetic_record_ids)) => synthetic_record_ids.assign(chunk),
+                    _ => HashMap::new(),
+                };
+
+                // Chunks with only skipped records are dropped before being parsed.
+                let mut records_to_skip = 0;
+                if let (Ok(chunk), true) = (&chunk, skip_records > 0) {
+                    let chunk_records = records_from(chunk, min_record_id);
+
+                    if chunk_records <= skip_records {
+                        skip_records -= chunk_records;
+                        continue;
+                    }
+
+                    records_to_skip = skip_records as usize;
+                    skip_records = 0;
+                }
+
+                buffered_records += chunk_records - records_to_skip;
+                chunk_of_chunks.push((chunk, records_to_skip, synthetic_ids));
+            }
+
+            // We only stop once no chunks can be allocated.
+            if chunk_of_chunks.is_empty() {
+                None
+            } else {
+                // Serialize the records in each chunk.
+                let serialize_chunk = |(chunk_res, records_to_skip, synthetic_ids): (
+                    Result<EvtxChunkData>,
+                    usize,
+                    HashMap<u64, RecordId>,
+                )| match chunk_res {
+                    Err(err) => vec![Err(err)],
+                    Ok(mut chunk) => {
+                        let chunk_id = chunk.chunk_number().unwrap_or_default();
+                        let chunk_records_res = chunk.parse(chunk_settings.clone());
+
+                        match chunk_records_res {
+                            Err(err) => vec![Err(EvtxError::FailedToParseChunk {
+                                chunk_id,
+                                source: err,
+                            })],
+                            Ok(mut chunk_records) => chunk_records
+                                .iter()
+                                .map(|record| {
+                                    record.map(|mut record| {
+                                        if let Some(&id) = synthetic_ids.get(&record.offset) {
+                                            record.event_record_id = id;
+                                            record.synthetic_record_id = true;
+                                        }
+
+                                        record
+                                    })
+                                })
+                                .filter(|record| match record {
+                                    Ok(record) => record.event_record_id >= min_record_id,
+                                    Err(_) => true,
+                                })
+                                .filter({
+                                    let mut skipped = 0;
+                                    move |record| skip_ok(record, &mut skipped, records_to_skip)
+                                })
+                                .filter_map({
+                                    let mut f = f.clone();
+                                    let filter = &filter;
+                                    move |record| f(record, filter)
+                                })
+                                .collect(),
+                        }
+                    }
+                };
+
+                #[cfg(feature = "multithreading")]
+                let iterators: Vec<Vec<Result<U>>> = match &thread_pool {
+                    Some(pool) => pool.install(|| {
+                        chunk_of_chunks
+                            .into_par_iter()
+                            .map(&serialize_chunk)
+                            .collect()
+                    }),
+                    None => chunk_of_chunks
+                        .into_par_iter()
+                        .map(&serialize_chunk)
+                        .collect(),
+                };
+
+                #[cfg(not(feature = "multithreading"))]
+                let iterators: Vec<Vec<Result<U>>> =
+                    chunk_of_chunks.into_iter().map(serialize_chunk).collect();
+
+                Some(iterators.into_iter().flatten())
+            }
+        });
+
+        let mut skipped = 0;
+        let mut taken = 0;
+
+        records_per_chunk
+            .flatten()
+            .filter(move |record| skip_ok(record, &mut skipped, skip_matching_records))
+            .take_while(move |record| {
+                if taken == take_records {
+                    return false;
+                }
+                if record.is_ok() {
+                    taken += 1;
+                }
+
+                true
+            })
+    }
+
+    /// Return an iterator over all the records.
+    /// Records will be XML-formatted.
+    pub fn records(&mut self) -> impl Iterator<Item = Result<SerializedEvtxRecord<String>>> + '_ {
+        // '_ is required in the signature because the iterator is bound to &self.
+        self.filtered_records(|record, filter| {
+            record
+                .and_then(|record| record.into_filtered_xml(filter))
+                .transpose()
+        })
+    }
+
+    /// Return an iterator over all the records which were parsed successfully.
+    /// Records will be XML-formatted.
+    ///
+    /// Errors are passed to the callback set with `on_error` (or discarded if none was set).
+    pub fn records_ok(&mut self) -> impl Iterator<Item = SerializedEvtxRecord<String>> + '_ {
+        let error_callback = self.error_callback.clone();
+
+        skip_errors(self.records(), error_callback)
+    }
+
+    /// Return an iterator over all the records, grouped into vectors of up to `n` records
+    /// (only the last one may be smaller), for example to insert them to a database in bulk.
+    /// Records will be XML-formatted.
+    ///
+    /// An error is yielded on its own, after a (smaller) batch of the records which preceded it,
+    /// and the iteration continues as in `records`.
+    pub fn records_batched(
+        &mut self,
+        n: NonZeroUsize,
+    ) -> impl Iterator<Item = Result<Vec<SerializedEvtxRecord<String>>>> + '_ {
+        batch_results(self.records(), n)
+    }
+
+    /// Writes all the records as a single XML document to `writer`, wrapped in an `<Events>` root
+    /// element (similar to the output of `wevtutil`).
+    /// Records are written as they are parsed, without buffering the whole document.
+    ///
+    /// Records which fail to parse are skipped, and are reported like in `records_ok`.
+    pub fn dump_xml_document<W: Write>(&mut self, mut writer: W) -> Result<()> {
+        // Records are nested under `<Events>`, so they must not have their own declaration.
+        let config = Arc::clone(&self.config);
+        self.config = Arc::new((*config).clone().emit_xml_declaration(false));
+
+        let result = self.write_xml_document(&mut writer);
+
+        self.config = config;
+        result
+    }
+
+    fn write_xml_document<W: Write>(&mut self, writer: &mut W) -> Result<()> {
+        let newline = if self.config.is_xml_compact() {
+            ""
+        } else {
+            "\n"
+        };
+
+        self.write_bom(writer)?;
+        write!(
+            writer,
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>{}",
+            newline
+        )?;
+        write!(writer, "<Events>{}", newline)?;
+
+        for record in self.records_ok() {
+            write!(writer, "{}{}", record.data, newline)?;
+        }
+
+        write!(writer, "</Events>{}", newline)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Writes a UTF-8 BOM to the start of a document, if `ParserSettings::emit_bom` is enabled.
+    fn write_bom<W: Write>(&self, writer: &mut W) -> Result<()> {
+        if self.config.should_emit_bom() {
+            writer.write_all(UTF8_BOM)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes all the records to `writer` as JSON lines: each record is written as compact JSON
+    /// (regardless of `ParserSettings::indent`), followed by a newline.
+    /// Records are serialized in parallel, and are written in order as they are parsed
+    /// (see `ParserSettings::ordered_output`).
+    ///
+    /// Records which fail to parse are skipped, and are reported like in `records_ok`.
+    pub fn dump_jsonl<W: Write>(&mut self, writer: W) -> Result<()> {
+        let mut writer = io::BufWriter::new(writer);
+        let error_callback = self.error_callback.clone();
+        self.write_bom(&mut writer)?;
+
+        let lines = self
+            .json_records(false)
+            .map(|record| record.map(|record| record.data));
+
+        for line in skip_errors(lines, error_callback) {
+            writeln!(writer, "{}", line)?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Collects the element paths of all the records, with the types of their values
+    /// and the number of times they appeared (see `SchemaSummary`).
+    /// Records are only traversed, and are not serialized to XML or JSON.
+    ///
+    /// Records which fail to parse are skipped, and the errors are reported like in `records_ok`,
+    /// unless `ParserSettings::strict` is set.
+    pub fn schema_summary(&mut self) -> Result<SchemaSummary> {
+        let strict = self.config.is_strict();
+        let error_callback = self.error_callback.clone();
+        let mut summary = SchemaSummary::default();
+
+        let record_summaries = self.filtered_records(|record, filter| {
+            let record = record
+                .and_then(|record| record.into_filtered_output(SchemaOutput::default(), filter))
+                .transpose()?;
+
+            Some(record.map(|record| record.data.into_summary()))
+        });
+
+        for record_summary in record_summaries {
+            match record_summary {
+                Ok(record_summary) => summary.merge(record_summary),
+                Err(err) if !strict => report_error(error_callback.as_ref(), &err),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Copies the chunks of the file to `writer` as a new evtx file (see `EvtxWriter`).
+    /// Chunks are copied whole: when the records are filtered (for example with `filter_providers`),
+    /// the chunks with at least one matching record are copied with all of their records,
+    /// and the others are left out.
+    ///
+    /// Carved chunks are not copied. Chunks which fail to load or to parse are skipped,
+    /// and are reported like in `records_ok`.
+    pub fn copy_chunks_to<W: Write + Seek>(&mut self, writer: W) -> Result<W> {
+        let settings = Arc::clone(&self.config);
+        let filter = Arc::clone(&self.filter);
+        let error_callback = self.error_callback.clone();
+        let mut evtx_writer = EvtxWriter::new(writer)?;
+
+        for mut chunk in skip_errors(self.chunks(), error_callback.clone()) {
+            if !filter.is_empty() {
+                let chunk_id = chunk.chunk_number().unwrap_or_default();
+
+                let has_matches = match chunk.parse(Arc::clone(&settings)) {
+                    Ok(mut chunk_records) => chunk_records.iter().any(|record| match record {
+                        Ok(record) => record.matches(&filter).unwrap_or(false),
+                        Err(_) => false,
+                    }),
+                    Err(err) => {
+                        let err = EvtxError::FailedToParseChunk {
+                            chunk_id,
+                            source: err,
+                        };
+                        report_error(error_callback.as_ref(), &err);
+
+                        false
+                    }
+                };
+
+                if !has_matches {
+                    continue;
+                }
+            }
+
+            evtx_writer.write_chunk(&chunk)?;
+        }
+
+        evtx_writer.finish()
+    }
+
+    /// Writes all the records to `writer` as a parquet file, with the columns described by `schema`.
+    /// Each chunk is written as a separate row group.
+    ///
+    /// Records which fail to parse are skipped, and are reported like in `records_ok`.
+    #[cfg(feature = "parquet")]
+    pub fn dump_parquet<W: Write + Send>(
+        &mut self,
+        schema: &ParquetSchema,
+        writer: W,
+    ) -> Result<()> {
+        let error_callback = self.error_callback.clone();
+        let records = skip_errors(self.records_json_value(), error_callback);
+
+        write_parquet(records, schema, writer)
+    }
+
+    /// Return an iterator over all the records.
+    /// Records will be JSON-formatted.
+    pub fn records_json(
+        &mut self,
+    ) -> impl Iterator<Item = Result<SerializedEvtxRecord<String>>> + '_ {
+        let indent = self.config.should_indent();
+
+        self.json_records(indent)
+    }
+
+    /// Serializes the records to JSON, after passing them to the transform set with `map_json`.
+    fn json_records(
+        &mut self,
+        indent: bool,
+    ) -> impl Iterator<Item = Result<SerializedEvtxRecord<String>>> + '_ {
+        let json_transform = self.json_transform.clone();
+
+        self.filtered_records(move |record, filter| {
+            record
+                .and_then(|record| {
+                    record.into_filtered_json(
+                        indent,
+                        |value| transform_json(json_transform.as_ref(), value),
+                        filter,
+                    )
+                })
+                .transpose()
+        })
+    }
+
+    /// Return an iterator over all the records.
+    /// Every record is serialized to both XML and JSON in a single pass,
+    /// which is cheaper than iterating over `records` and `records_json` separately.
+    pub fn records_dual(
+        &mut self,
+    ) -> impl Iterator<Item = Result<SerializedEvtxRecord<DualOutput>>> + '_ {
+        let json_transform = self.json_transform.clone();
+
+        self.filtered_records(move |record, filter| {
+            record
+                .and_then(|record| {
+                    record.into_filtered_dual(
+                        |value| transform_json(json_transform.as_ref(), value),
+                        filter,
+                    )
+                })
+                .transpose()
+        })
+    }
+
+    /// Return an iterator over all the records.
+    /// Every record is both collected into a tree of its elements (its JSON value,
+    /// as in `records_json_value`) and serialized to XML in a single pass,
+    /// for tools which inspect and render records.
+    pub fn records_with_raw_xml_and_model(
+        &mut self,
+    ) -> impl Iterator<Item = Result<SerializedEvtxRecord<(serde_json::Value, String)>>> + '_ {
+        self.filtered_records(|record, filter| {
+            record
+                .and_then(|record| record.into_filtered_xml_and_model(filter))
+                .transpose()
+        })
+    }
+
+    /// Return an iterator over all the records.
+    /// Records will be deserialized from their JSON value into `U`
+    /// (the same value yielded by `records_json_value`).
+    pub fn records_into_struct<U: DeserializeOwned + Send>(
+        &mut self,
+    ) -> impl Iterator<Item = Result<SerializedEvtxRecord<U>>> + '_ {
+        self.filtered_records(|record, filter| {
+            let record = record
+                .and_then(|record| record.into_filtered_json_value(filter))
+                .transpose()?;
+
+            Some(record.and_then(|record| record.deserialize_data()))
+        })
+    }
+
+    /// Return an iterator over all the records.
+    /// Every record is processed by a new `BinXmlOutput` created with `factory`,
+    /// which is then returned as the data of the record.
+    pub fn records_to_output<'a, O: BinXmlOutput + Send>(
+        &'a mut self,
+        factory: impl Fn() -> O + Send + Sync + 'a,
+    ) -> impl Iterator<Item = Result<SerializedEvtxRecord<O>>> + 'a {
+        let factory = Arc::new(factory);
+
+        self.filtered_records(move |record, filter| {
+            record
+                .and_then(|record| record.into_filtered_output(factory(), filter))
+                .transpose()
+        })
+    }
+
+    /// Return an iterator over all the records.
+    /// Records will have a `serde_json::Value` data attribute.
+    pub fn records_json_value(
+        &mut self,
+    ) -> impl Iterator<Item = Result<SerializedEvtxRecord<serde_json::Value>>> + '_ {
+        let json_transform = self.json_transform.clone();
+
+        self.filtered_records(move |record, filter| {
+            let record = record
+                .and_then(|record| record.into_filtered_json_value(filter))
+                .transpose()?;
+
+            Some(record.map(|mut record| {
+                transform_json(json_transform.as_ref(), &mut record.data);
+
+                record
+            }))
+        })
+    }
+
+    /// Return an iterator over all the records, serialized to `format`
+    /// (for example, one which was parsed from a `--format` flag).
+    pub fn records_with_format(
+        &mut self,
+        format: OutputFormat,
+    ) -> Box<dyn Iterator<Item = Result<SerializedEvtxRecord<String>>> + '_> {
+        match format {
+            OutputFormat::Xml => Box::new(self.records()),
+            OutputFormat::Json => Box::new(self.records_json()),
+            OutputFormat::JsonLines => Box::new(self.json_records(false)),
+        }
+    }
+}
+
+pub struct IterChunks<'c, T: ReadSeek> {
+    parser: &'c mut EvtxParser<T>,
+    current_chunk_number: u64,
+    // If set, chunks are read in this order (instead of sequentially).
+    chunk_order: Option<std::vec::IntoIter<u64>>,
+    // Set after a fatal error in strict mode.
+    failed: bool,
+}
+
+impl<'c, T: ReadSeek> Iterator for IterChunks<'c, T> {
+    type Item = Result<EvtxChunkData>;
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        if self.failed {
+            return None;
+        }
+
+        if let Err(err) = self.parser.check_file_header_integrity() {
+            self.failed = true;
+            return Some(Err(err));
+        }
+
+        let chunk = self.next_chunk();
+
+        if let Some(Err(err)) = &chunk {
+            self.failed = self.parser.is_fatal_chunk_error(err);
+        }
+
+        chunk
+    }
+}
+
+impl<'c, T: ReadSeek> IterChunks<'c, T> {
+    fn next_chunk(&mut self) -> Option<Result<EvtxChunkData>> {
+        if let Some(chunk_order) = self.chunk_order.as_mut() {
+            for chunk_number in chunk_order {
+                match EvtxParser::allocate_chunk(
+                    &mut self.parser.data,
+                    chunk_number,
+                    &self.parser.config,
+                ) {
+                    Err(err) => return Some(Err(err)),
+                    Ok(None) => continue,
+                    Ok(Some(chunk)) => return Some(Ok(chunk)),
+                }
+            }
+
+            return None;
+        }
+
+        match self.parser.find_next_chunk(self.current_chunk_number) {
+            None => None,
+            Some((chunk, chunk_number)) => {
+                self.current_chunk_number = match chunk_number.checked_add(1) {
+                    None => return None,
+                    Some(n) => n,
+                };
+
+                Some(chunk)
+            }
+        }
+    }
+}
+
+pub struct IntoIterChunks<T: ReadSeek> {
+    parser: EvtxParser<T>,
+    current_chunk_number: u64,
+    // Set after a fatal error in strict mode.
+    failed: bool,
+}
+
+impl<T: ReadSeek> Iterator for IntoIterChunks<T> {
+    type Item = Result<EvtxChunkData>;
+    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
+        if self.failed {
+            return None;
+        }
+
+        if let Err(err) = self.parser.check_file_header_integrity() {
+            self.failed = true;
+            return Some(Err(err));
+        }
+
+        let chunk = self.next_chunk();
+
+        if let Some(Err(err)) = &chunk {
+            self.failed = self.parser.is_fatal_chunk_error(err);
+        }
+
+        chunk
+    }
+}
+
+impl<T: ReadSeek> IntoIterChunks<T> {
+    fn next_chunk(&mut self) -> Option<Result<EvtxChunkData>> {
+        info!("Chunk {}", self.current_chunk_number);
+        match self.parser.find_next_chunk(self.current_chunk_number) {
+            None => None,
+            Some((chunk, chunk_number)) => {
+                self.current_chunk_number = match chunk_number.checked_add(1) {
+                    None => return None,
+                    Some(n) => n,
+                };
+
+                Some(chunk)
+            }
+        }
+    }
+}
+
+/// A filter which drops the first `count` records like `Iterator::skip`, but keeps errors
+/// without counting them. `skipped` is the number of records which were dropped so far.
+fn skip_ok<T>(record: &Result<T>, skipped: &mut usize, count: usize) -> bool {
+    if record.is_ok() && *skipped < count {
+        *skipped += 1;
+        return false;
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(unused_variables)]
+
+    use super::*;
+    use crate::binxml::value_variant::BinXmlValueType;
+    use crate::ensure_env_logger_initialized;
+    use anyhow::anyhow;
+
+    fn process_90_records(buffer: &'static [u8]) -> anyhow::Result<()> {
+        let mut parser = EvtxParser::from_buffer(buffer.to_vec())?;
+
+        for (i, record) in parser.records().take(90).enumerate() {
+            match record {
+                Ok(r) => {
+                    assert_eq!(r.event_record_id, i as u64 + 1);
+                }
+                Err(e) => return Err(anyhow!("Error while reading record {}, {:?}", i, e)),
+            }
+        }
+
+        Ok(())
+    }
+
+    // For clion profiler
+    #[test]
+    fn test_process_single_chunk() -> anyhow::Result<()> {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        process_90_records(evtx_file)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sample_2() {
+        let evtx_file = include_bytes!("../samples/system.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        let records: Vec<_> = parser.records().take(10).collect();
+
+        for (i, record) in records.iter().enumerate() {
+            match record {
+                Ok(r) => {
+                    assert_eq!(
+                        r.event_record_id,
+                        i as u64 + 1,
+                        "Parser is skipping records!"
+                    );
+                }
+                Err(e) => panic!("Error while reading record {}, {:?}", i, e),
+            }
+        }
+
+        // It should be empty, and not a [].
+        assert!(records[0]
+            .as_ref()
+            .unwrap()
+            .data
+            .contains("<Binary></Binary>"));
+        assert!(records[1]
+            .as_ref()
+            .unwrap()
+            .data
+            .contains("<Binary>E107070003000C00110010001C00D6000000000000000000</Binary>"));
+    }
+
+    #[test]
+    fn test_parses_first_10_records() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        for (i, record) in parser.records().take(10).enumerate() {
+            match record {
+                Ok(r) => {
+                    assert_eq!(
+                        r.event_record_id,
+                        i as u64 + 1,
+                        "Parser is skipping records!"
+                    );
+                }
+                Err(e) => panic!("Error while reading record {}, {:?}", i, e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parses_records_from_different_chunks() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        for (i, record) in parser.records().take(1000).enumerate() {
+            match record {
+                Ok(r) => {
+                    assert_eq!(r.event_record_id, i as u64 + 1);
+                }
+                Err(e) => println!("Error while reading record {}, {:?}", i, e),
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "multithreading")]
+    fn test_multithreading() {
+        use std::collections::HashSet;
+
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        let mut record_ids = HashSet::new();
+        for record in parser.records().take(1000) {
+            match record {
+                Ok(r) => {
+                    record_ids.insert(r.event_record_id);
+                }
+                Err(e) => panic!("Error while reading record {:?}", e),
+            }
+        }
+
+        assert_eq!(record_ids.len(), 1000);
+    }
+
+    #[test]
+    #[cfg(feature = "multithreading")]
+    fn test_chunks_are_parsed_on_the_given_thread_pool() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .thread_name(|i| format!("evtx-test-pool-{}", i))
+            .build()
+            .unwrap();
+
+        let settings = ParserSettings::new()
+            .num_threads(8)
+            .thread_pool(Arc::new(pool));
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(settings);
+
+        let thread_names: Vec<String> = parser
+            .serialized_records(|record| {
+                record.map(|_| std::thread::current().name().unwrap_or_default().to_owned())
+            })
+            .map(|name| name.unwrap())
+            .collect();
+
+        assert_eq!(thread_names.len(), 2261);
+        assert!(thread_names
+            .iter()
+            .all(|name| name.starts_with("evtx-test-pool-")));
+    }
+
+    #[test]
+    #[cfg(feature = "multithreading")]
+    fn test_settings_with_different_thread_pools_are_not_equal() {
+        let pool = || Arc::new(rayon::ThreadPoolBuilder::new().build().unwrap());
+        let settings = ParserSettings::new().thread_pool(pool());
+
+        assert_eq!(settings, settings.clone());
+        assert_ne!(settings, ParserSettings::new().thread_pool(pool()));
+        assert_ne!(settings, ParserSettings::new());
+    }
+
+    #[test]
+    fn test_file_with_only_a_single_chunk() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/new-user-security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        let records: Vec<_> = parser.records().collect();
+        assert_eq!(records.len(), 4);
+    }
+
+    #[test]
+    fn test_parses_chunk2() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        let mut chunk = EvtxChunkData::new(
+            evtx_file[EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_SIZE
+                ..EVTX_FILE_HEADER_SIZE + 2 * EVTX_CHUNK_SIZE]
+                .to_vec(),
+            false,
+        )
+        .unwrap();
+
+        assert!(chunk.validate_checksum());
+
+        for record in chunk
+            .parse(Arc::new(ParserSettings::default()))
+            .unwrap()
+            .iter()
+        {
+            record.unwrap();
+        }
+    }
+
+    #[test]
+    fn test_count_records() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        let count = parser.count_records().unwrap();
+        assert_eq!(count, parser.records().count());
+        assert_eq!(parser.count_records_with_errors().unwrap(), (count, 0));
+    }
+
+    #[test]
+    #[cfg(feature = "mmap")]
+    fn test_from_path_mmap() {
+        ensure_env_logger_initialized();
+        let path =
+            std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("samples/security.evtx");
+
+        let mut mapped = EvtxParser::from_path_mmap(&path)
+            .unwrap()
+            .with_configuration(ParserSettings::new().num_threads(0));
+        let mut parser = EvtxParser::from_path(&path).unwrap();
+
+        assert_eq!(mapped.records().count(), parser.records().count());
+    }
+
+    #[test]
+    #[cfg(feature = "flate2")]
+    fn test_from_gzip_path() {
+        ensure_env_logger_initialized();
+        let samples = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("samples");
+
+        let mut decompressed =
+            EvtxParser::from_gzip_path(samples.join("new-user-security.evtx.gz")).unwrap();
+        let mut parser = EvtxParser::from_path(samples.join("new-user-security.evtx")).unwrap();
+
+        let records = parser
+            .records()
+            .map(|r| r.unwrap().data)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            decompressed
+                .records()
+                .map(|r| r.unwrap().data)
+                .collect::<Vec<_>>(),
+            records
+        );
+
+        let not_gzipped = EvtxParser::from_gzip_path(samples.join("new-user-security.evtx"));
+        assert!(matches!(
+            not_gzipped,
+            Err(EvtxError::InputError(InputError::NotGzipCompressed { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_ansi_codepage() {
+        use crate::utils::read_ansi_encoded_string;
+
+        let settings = ParserSettings::new();
+        assert_eq!(settings.get_ansi_codec().name(), "windows-1252");
+
+        let settings = settings.ansi_codepage(1251);
+        assert_eq!(settings.get_ansi_codec().name(), "windows-1251");
+
+        // "Привет" in windows-1251.
+        let mut cursor = Cursor::new(vec![0xCF, 0xF0, 0xE8, 0xE2, 0xE5, 0xF2, 0x00]);
+        let decoded =
+            read_ansi_encoded_string(&mut cursor, 7, settings.get_ansi_codec(), false).unwrap();
+        assert_eq!(decoded.as_deref(), Some("Привет"));
+
+        // Unknown code pages keep the current codec.
+        let settings = settings.ansi_codepage(12345);
+        assert_eq!(settings.get_ansi_codec().name(), "windows-1251");
+    }
+
+    #[test]
+    fn test_include_raw_bytes() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let record = parser.records().next().unwrap().unwrap();
+        assert_eq!(record.raw, None);
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(ParserSettings::new().include_raw_bytes(true));
+
+        for record in parser.records().take(10) {
+            let record = record.unwrap();
+            let raw = record.raw.expect("raw bytes to be included");
+
+            assert_eq!(&raw[..4], b"\x2a\x2a\x00\x00");
+            let size = u32::from_le_bytes([raw[4], raw[5], raw[6], raw[7]]);
+            assert_eq!(raw.len(), size as usize);
+            assert_eq!(&raw[raw.len() - 4..], &raw[4..8]);
+
+            let mut event_record_id = [0; 8];
+            event_record_id.copy_from_slice(&raw[8..16]);
+            assert_eq!(u64::from_le_bytes(event_record_id), record.event_record_id);
+        }
+    }
+
+    #[test]
+    fn test_truncated_record() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let last_in_first_chunk = parser
+            .records()
+            .map(|r| r.unwrap())
+            .take_while(|r| r.chunk_number == Some(0))
+            .last()
+            .unwrap();
+
+        // The last record of the first chunk declares a size past the end of the chunk.
+        let mut data = evtx_file.to_vec();
+        let size_offset = last_in_first_chunk.file_offset.unwrap() as usize + 4;
+        data[size_offset..size_offset + 4].copy_from_slice(&0x20000_u32.to_le_bytes());
+
+        let mut parser = EvtxParser::from_buffer(data.clone()).unwrap();
+        let errors: Vec<EvtxError> = parser.records().filter_map(|r| r.err()).collect();
+
+        assert_eq!(errors.len(), 1);
+        match &errors[0] {
+            EvtxError::TruncatedRecord {
+                record_id,
+                available,
+                expected,
+            } => {
+                assert_eq!(*record_id, last_in_first_chunk.event_record_id);
+                assert!(*available < 0x10000);
+                assert_eq!(*expected, 0x20000);
+            }
+            other => panic!("Expected a `TruncatedRecord` error, got {:?}", other),
+        }
+
+        let mut parser = EvtxParser::from_buffer(data)
+            .unwrap()
+            .with_configuration(ParserSettings::new().tolerate_truncation(true));
+        let results: Vec<_> = parser.records().collect();
+
+        assert_eq!(results.len(), 2260);
+        assert!(results.iter().all(|r| r.is_ok()));
+    }
+
+    #[test]
+    fn test_max_depth() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        // Every record has at least `<Event><System><Provider>`.
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(ParserSettings::new().max_depth(2).strict(true));
+
+        let results: Vec<_> = parser.records_json().collect();
+        assert_eq!(results.len(), 2261);
+
+        match &results[0] {
+            Err(EvtxError::MaxDepthExceeded { record_id, depth }) => {
+                assert_eq!(*record_id, 1);
+                assert_eq!(*depth, 3);
+            }
+            other => panic!("Expected a `MaxDepthExceeded` error, got {:?}", other),
+        }
+        assert!(results
+            .iter()
+            .all(|r| matches!(r, Err(EvtxError::MaxDepthExceeded { .. }))));
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(ParserSettings::new().max_depth(3));
+        assert_eq!(parser.records().filter(|r| r.is_ok()).count(), 2260);
+    }
+
+    #[test]
+    fn test_max_record_size() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(ParserSettings::new().max_record_size(64));
+
+        let results: Vec<_> = parser.records().collect();
+
+        // Only the first record of every chunk is read, the rest of the chunk is skipped.
+        assert_eq!(results.len(), parser.chunks().filter(|c| c.is_ok()).count());
+
+        match &results[0] {
+            Err(EvtxError::RecordSizeExceeded { record_id, size }) => {
+                assert_eq!(*record_id, 1);
+                assert!(*size > 64);
+            }
+            other => panic!("Expected a `RecordSizeExceeded` error, got {:?}", other),
+        }
+        assert!(results.iter().all(|r| r.is_err()));
+
+        // A corrupt size in the first record header.
+        let mut data = evtx_file.to_vec();
+        let size_offset = EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_HEADER_SIZE + 4;
+        data[size_offset..size_offset + 4].copy_from_slice(&0xffff_fff0_u32.to_le_bytes());
+
+        let mut parser = EvtxParser::from_buffer(data).unwrap();
+        let mut records = parser.records();
+
+        assert!(matches!(
+            records.next(),
+            Some(Err(EvtxError::RecordSizeExceeded {
+                record_id: 1,
+                size: 0xffff_fff0
+            }))
+        ));
+        // Parsing continues with the next chunk.
+        assert!(records.next().unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_record_file_offset() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        let records: Vec<SerializedEvtxRecord<String>> =
+            parser.records().take(150).map(|r| r.unwrap()).collect();
+
+        // The first record is right after the header of the first chunk.
+        assert_eq!(
+            records[0].file_offset,
+            Some((EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_HEADER_SIZE) as u64)
+        );
+
+        for record in &records {
+            let offset = record.file_offset.unwrap() as usize;
+            let size = record.size as usize;
+
+            assert_eq!(&evtx_file[offset..offset + 4], b"\x2a\x2a\x00\x00");
+            assert_eq!(
+                &evtx_file[offset + 4..offset + 8],
+                &record.size.to_le_bytes()
+            );
+            assert_eq!(
+                &evtx_file[offset + size - 4..offset + size],
+                &record.size.to_le_bytes()
+            );
+
+            let reparsed = parser.parse_record_at_offset(offset as u64).unwrap();
+            assert_eq!(reparsed.event_record_id, record.event_record_id);
+        }
+    }
+
+    #[test]
+    fn test_lossy_string_decoding() {
+        use crate::utils::read_utf16_by_size;
+
+        let settings = ParserSettings::new();
+        assert!(!settings.should_decode_strings_lossily());
+
+        let settings = settings.lossy_string_decoding(true);
+        assert!(settings.should_decode_strings_lossily());
+
+        // "a", an unpaired high surrogate, "b".
+        let data = vec![0x61, 0x00, 0x00, 0xD8, 0x62, 0x00];
+
+        let mut cursor = Cursor::new(data.clone());
+        assert!(read_utf16_by_size(&mut cursor, 6, false).is_err());
+
+        let mut cursor = Cursor::new(data);
+        let decoded = read_utf16_by_size(&mut cursor, 6, true).unwrap();
+        assert_eq!(decoded.as_deref(), Some("a\u{FFFD}b"));
+    }
+
+    #[test]
+    fn test_shared_template_cache() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let expected: Vec<_> = parser.records().map(|r| r.unwrap().data).collect();
+
+        let settings = ParserSettings::new().shared_template_cache(true);
+        let mut parser = parser.with_configuration(settings.clone());
+        let records: Vec<_> = parser.records().map(|r| r.unwrap().data).collect();
+
+        assert_eq!(records, expected);
+        assert!(!settings.get_shared_template_cache().unwrap().is_empty());
+
+        assert_eq!(settings, settings.clone());
+        assert_ne!(settings, ParserSettings::new().shared_template_cache(true));
+    }
+
+    #[test]
+    fn test_with_template_cache() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let expected: Vec<_> = parser.records().map(|r| r.unwrap().data).collect();
+
+        let cache = SharedTemplateCache::new();
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_template_cache(cache.clone());
+        let records: Vec<_> = parser.records().map(|r| r.unwrap().data).collect();
+        assert_eq!(records, expected);
+
+        // The cache is kept when the configuration is replaced afterwards.
+        let parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_template_cache(cache.clone())
+            .with_configuration(ParserSettings::new().num_threads(1));
+        assert_eq!(parser.config.get_shared_template_cache(), Some(&cache));
+
+        let warm_len = cache.len();
+        let warm_hits = cache.hits();
+        assert!(warm_len > 0);
+
+        // A second parse only uses the definitions of the warm cache.
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_template_cache(cache.clone());
+        let records: Vec<_> = parser.records().map(|r| r.unwrap().data).collect();
+        assert_eq!(records, expected);
+        assert_eq!(cache.len(), warm_len);
+        assert!(cache.hits() >= warm_hits + warm_len);
+
+        // Other files may use the cache too, with the names of their own chunks.
+        let evtx_file = include_bytes!("../samples/system.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let expected: Vec<_> = parser.records().map(|r| r.unwrap().data).collect();
+
+        let mut parser = parser.with_template_cache(cache.clone());
+        let records: Vec<_> = parser.records().map(|r| r.unwrap().data).collect();
+        assert_eq!(records, expected);
+    }
+
+    #[test]
+    fn test_seek_to_record_id() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(ParserSettings::new().num_threads(1));
+
+        let record_ids: Vec<_> = parser
+            .records()
+            .map(|record| record.unwrap().event_record_id)
+            .collect();
+        let middle = record_ids[record_ids.len() / 2];
+
+        parser.seek_to_record_id(middle).unwrap();
+        let after_seek: Vec<_> = parser
+            .records()
+            .map(|record| record.unwrap().event_record_id)
+            .collect();
+
+        assert_eq!(after_seek[0], middle);
+        assert_eq!(after_seek.len(), record_ids.len() - record_ids.len() / 2);
+
+        // Seeking only affects a single iteration.
+        assert_eq!(parser.records().count(), record_ids.len());
+    }
+
+    #[test]
+    fn test_seek_to_record_id_past_the_end() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let next_record_id = parser.records().last().unwrap().unwrap().event_record_id + 1;
+
+        // The file header is out of date.
+        assert!(parser.file_header().next_record_id < next_record_id);
+
+        parser.seek_to_record_id(next_record_id).unwrap();
+        assert_eq!(parser.records().count(), 0);
+
+        assert!(parser.seek_to_record_id(next_record_id + 1).is_err());
+    }
+
+    #[test]
+    fn test_strict_mode_stops_on_bad_file_header_checksum() {
+        ensure_env_logger_initialized();
+        let mut evtx_file = include_bytes!("../samples/security.evtx").to_vec();
+        // Corrupt the (unused) first chunk number, which is covered by the checksum.
+        evtx_file[8] ^= 0xff;
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.clone()).unwrap();
+        assert_eq!(parser.records().filter(|r| r.is_err()).count(), 0);
+
+        let mut parser = EvtxParser::from_buffer(evtx_file)
+            .unwrap()
+            .with_configuration(ParserSettings::new().strict(true));
+        let results: Vec<_> = parser.records().collect();
+
+        assert_eq!(results.len(), 1);
+        assert!(matches!(
+            results[0],
+            Err(EvtxError::InvalidFileHeaderChecksum { .. })
+        ));
+    }
+
+    #[test]
+    fn test_format_version() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx").to_vec();
+
+        let parser = EvtxParser::from_buffer(evtx_file.clone()).unwrap();
+        assert_eq!(parser.format_version(), (3, 1));
+
+        // Version 4.0, with a valid checksum.
+        let mut header = EvtxFileHeader::from_stream(&mut Cursor::new(&evtx_file)).unwrap();
+        header.major_version = 4;
+        header.minor_version = 0;
+        let mut evtx_file = evtx_file;
+        evtx_file[..EVTX_FILE_HEADER_SIZE].copy_from_slice(&header.to_bytes());
+        let checksum = crc::crc32::checksum_ieee(&evtx_file[..120]);
+        evtx_file[124..128].copy_from_slice(&checksum.to_le_bytes());
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.clone()).unwrap();
+        assert_eq!(parser.format_version(), (4, 0));
+        assert_eq!(parser.records().filter(|r| r.is_ok()).count(), 2261);
+
+        // Strict mode doesn't reject the version on its own.
+        let mut parser = EvtxParser::from_buffer(evtx_file.clone())
+            .unwrap()
+            .with_configuration(ParserSettings::new().strict(true));
+        assert_eq!(parser.records().filter(|r| r.is_ok()).count(), 2261);
+
+        let mut parser = EvtxParser::from_buffer(evtx_file)
+            .unwrap()
+            .with_configuration(ParserSettings::new().reject_untested_versions(true));
+        let results: Vec<_> = parser.records().collect();
+
+        assert_eq!(results.len(), 1);
+        assert!(matches!(
+            results[0],
+            Err(EvtxError::UnsupportedFormatVersion {
+                major_version: 4,
+                minor_version: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn test_record_id_gaps() {
+        ensure_env_logger_initialized();
+        let mut evtx_file = include_bytes!("../samples/security.evtx").to_vec();
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.clone()).unwrap();
+        assert_eq!(parser.record_id_gaps().unwrap(), vec![]);
+
+        // Remove the second chunk, as if its records were deleted.
+        let second_chunk =
+            EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_SIZE..EVTX_FILE_HEADER_SIZE + 2 * EVTX_CHUNK_SIZE;
+        let header = EvtxChunkData::new(evtx_file[second_chunk.clone()].to_vec(), false)
+            .unwrap()
+            .header;
+        evtx_file[second_chunk].iter_mut().for_each(|b| *b = 0);
+
+        let mut parser = EvtxParser::from_buffer(evtx_file).unwrap();
+        assert_eq!(
+            parser.record_id_gaps().unwrap(),
+            vec![(header.first_event_record_id, header.last_event_record_id)]
+        );
+    }
+
+    #[test]
+    fn test_carve_records_from_chunk_with_bad_magic() {
+        ensure_env_logger_initialized();
+        let mut evtx_file = include_bytes!("../samples/security.evtx").to_vec();
+        let first_chunk_records = EvtxChunkData::new(
+            evtx_file[EVTX_FILE_HEADER_SIZE..EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_SIZE].to_vec(),
+            false,
+        )
+        .unwrap()
+        .count_records();
+        let original_records: Vec<_> = EvtxParser::from_buffer(evtx_file.clone())
+            .unwrap()
+            .records()
+            .map(|r| r.unwrap())
+            .collect();
+        let total_records = original_records.len();
+
+        // Damage the magic of the first chunk.
+        evtx_file[EVTX_FILE_HEADER_SIZE] = b'X';
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.clone()).unwrap();
+        assert_eq!(
+            parser.records().filter(|r| r.is_ok()).count(),
+            total_records - first_chunk_records
+        );
+
+        let mut parser = EvtxParser::from_buffer(evtx_file)
+            .unwrap()
+            .with_configuration(ParserSettings::new().carve_records(true));
+        let records: Vec<_> = parser.records().map(|r| r.unwrap()).collect();
+
+        assert_eq!(records.len(), total_records);
+        assert_eq!(
+            records.iter().filter(|r| r.carved).count(),
+            first_chunk_records
+        );
+        // The records in this chunk don't need anything from the header tables.
+        for (record, original) in records.iter().zip(original_records.iter()) {
+            assert_eq!(record.data, original.data);
+        }
+    }
+
+    #[test]
+    fn test_parse_record_at_offset() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        let second_chunk_offset = (EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_SIZE) as u64;
+        let mut second_chunk = parser.chunks().nth(1).unwrap().unwrap();
+        let expected = second_chunk
+            .parse(Arc::new(ParserSettings::default()))
+            .unwrap()
+            .iter()
+            .next()
+            .unwrap()
+            .unwrap()
+            .into_xml()
+            .unwrap();
+
+        let record = parser
+            .parse_record_at_offset(second_chunk_offset + EVTX_CHUNK_HEADER_SIZE as u64)
+            .unwrap();
+        assert_eq!(record, expected);
+
+        let err = parser
+            .parse_record_at_offset(second_chunk_offset + EVTX_CHUNK_HEADER_SIZE as u64 + 1)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            EvtxError::DeserializationError(
+                DeserializationError::InvalidEvtxRecordHeaderMagic { .. }
+            )
+        ));
+    }
+
+    #[test]
+    fn test_skip_and_take_records() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let record_ids = |parser: &mut EvtxParser<Cursor<Vec<u8>>>| -> Vec<u64> {
+            parser
+                .records()
+                .map(|r| r.unwrap().event_record_id)
+                .collect()
+        };
+
+        let all = record_ids(&mut parser);
+        assert_eq!(all.len(), 2261);
+
+        assert_eq!(parser.skip_records(1000), 1000);
+        parser.take_records(50);
+        assert_eq!(record_ids(&mut parser), &all[1000..1050]);
+
+        // Skipping and taking only apply to a single iteration.
+        assert_eq!(record_ids(&mut parser), all);
+
+        // Skipping past the end yields nothing.
+        assert_eq!(parser.skip_records(5000), 2261);
+        assert!(record_ids(&mut parser).is_empty());
+
+        // Records are skipped from the position of the seek.
+        parser.seek_to_record_id(100).unwrap();
+        assert_eq!(parser.skip_records(10), 10);
+        parser.take_records(3);
+        assert_eq!(record_ids(&mut parser), vec![110, 111, 112]);
+    }
+
+    #[test]
+    fn test_skip_and_take_filtered_records() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .retain(|record| record["Event"]["System"]["EventID"] == 4624);
+        let record_ids = |parser: &mut EvtxParser<Cursor<Vec<u8>>>| -> Vec<u64> {
+            parser
+                .records()
+                .map(|r| r.unwrap().event_record_id)
+                .collect()
+        };
+
+        let all = record_ids(&mut parser);
+        assert!(all.len() > 20);
+
+        // Pages of the matching records.
+        parser.skip_records(10);
+        parser.take_records(5);
+        assert_eq!(record_ids(&mut parser), &all[10..15]);
+
+        parser.skip_records(15);
+        parser.take_records(5);
+        assert_eq!(record_ids(&mut parser), &all[15..20]);
+    }
+
+    #[test]
+    fn test_skip_and_take_records_dont_count_errors() {
+        ensure_env_logger_initialized();
+        let mut evtx_file = include_bytes!("../samples/security.evtx").to_vec();
+
+        // The second chunk is an error.
+        let second_chunk = EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_SIZE;
+        evtx_file[second_chunk..second_chunk + 8].copy_from_slice(b"NotChunk");
+
+        let records: Vec<_> = EvtxParser::from_buffer(evtx_file.clone())
+            .unwrap()
+            .records()
+            .collect();
+        let error = records.iter().position(|r| r.is_err()).unwrap();
+        let all: Vec<u64> = records
+            .into_iter()
+            .filter_map(|r| r.ok())
+            .map(|r| r.event_record_id)
+            .collect();
+
+        for filtered in &[false, true] {
+            let mut parser = EvtxParser::from_buffer(evtx_file.clone()).unwrap();
+            if *filtered {
+                parser = parser.retain(|_| true);
+            }
+
+            // A page of 4 records around the error.
+            parser.skip_records(error as u64 - 2);
+            parser.take_records(4);
+            let records: Vec<_> = parser.records().collect();
+
+            assert_eq!(records.len(), 5);
+            assert!(records[2].is_err());
+            let record_ids: Vec<u64> = records
+                .into_iter()
+                .filter_map(|r| r.ok())
+                .map(|r| r.event_record_id)
+                .collect();
+            assert_eq!(record_ids, &all[error - 2..error + 2]);
+        }
+    }
+
+    #[test]
+    fn test_emit_bom() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        let mut document = Vec::new();
+        parser.dump_xml_document(&mut document).unwrap();
+        assert!(document.starts_with(b"<?xml"));
+
+        let mut parser = parser.with_configuration(ParserSettings::new().emit_bom(true));
+
+        let mut document = Vec::new();
+        parser.dump_xml_document(&mut document).unwrap();
+        assert!(document.starts_with(b"\xEF\xBB\xBF<?xml"));
+
+        let mut jsonl = Vec::new();
+        parser.dump_jsonl(&mut jsonl).unwrap();
+        assert!(jsonl.starts_with(b"\xEF\xBB\xBF{"));
+        assert_eq!(jsonl.windows(3).filter(|w| *w == UTF8_BOM).count(), 1);
+
+        // Records are not affected.
+        let record = parser.records().next().unwrap().unwrap();
+        assert!(record.data.starts_with("<?xml"));
+    }
+
+    #[test]
+    fn test_records_with_format() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        assert_eq!(
+            "JSONL".parse::<OutputFormat>().unwrap(),
+            OutputFormat::JsonLines
+        );
+        assert!(matches!(
+            "csv".parse::<OutputFormat>(),
+            Err(EvtxError::UnknownOutputFormat { .. })
+        ));
+
+        let xml = parser
+            .records_with_format("xml".parse().unwrap())
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(xml.data, parser.records().next().unwrap().unwrap().data);
+
+        let json = parser
+            .records_with_format(OutputFormat::Json)
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            json.data,
+            parser.records_json().next().unwrap().unwrap().data
+        );
+        assert!(json.data.contains('\n'));
+
+        let jsonl: Vec<_> = parser
+            .records_with_format(OutputFormat::JsonLines)
+            .map(|record| record.unwrap())
+            .collect();
+        assert_eq!(jsonl.len(), 2261);
+        assert!(!jsonl[0].data.contains('\n'));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&jsonl[0].data).unwrap(),
+            serde_json::from_str::<serde_json::Value>(&json.data).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_reconfigured() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_shared_buffer(Arc::from(&evtx_file[..]))
+            .unwrap()
+            .with_configuration(ParserSettings::new().num_threads(1));
+
+        let mut json_parser =
+            parser.reconfigured(ParserSettings::new().separate_json_attributes(true));
+        assert!(json_parser.config.should_separate_json_attributes());
+        assert!(!parser.config.should_separate_json_attributes());
+        assert!(Arc::ptr_eq(
+            json_parser.data.get_ref(),
+            parser.data.get_ref()
+        ));
+
+        let json: Vec<_> = json_parser
+            .records_json_value()
+            .map(|r| r.unwrap())
+            .collect();
+        let xml: Vec<_> = parser.records().map(|r| r.unwrap()).collect();
+        assert_eq!(json.len(), 2261);
+        assert_eq!(xml.len(), 2261);
+        assert!(json[0].data["Event"]["System"]["Provider_attributes"].is_object());
+    }
+
+    #[test]
+    fn test_max_buffered_records() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let record_ids = |settings: ParserSettings| {
+            EvtxParser::from_buffer(evtx_file.to_vec())
+                .unwrap()
+                .with_configuration(settings)
+                .records()
+                .map(|r| r.unwrap().event_record_id)
+                .collect::<Vec<_>>()
+        };
+
+        let expected = record_ids(ParserSettings::new().num_threads(1));
+        assert_eq!(expected.len(), 2261);
+
+        for max_buffered_records in [1, 100, 500] {
+            let settings = ParserSettings::new()
+                .num_threads(8)
+                .max_buffered_records(max_buffered_records);
+            assert_eq!(record_ids(settings), expected);
+        }
+    }
+
+    #[test]
+    fn test_max_buffered_records_bounds_batches() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let max_chunk_records = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .chunks()
+            .map(|chunk| chunk.unwrap().count_records())
+            .max()
+            .unwrap();
+
+        // The sizes of the batches of records parsed ahead of the consumer.
+        let batch_sizes = |settings: ParserSettings| {
+            let parsed = Arc::new(AtomicUsize::new(0));
+            let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+                .unwrap()
+                .with_configuration(settings);
+            let mut records = parser.serialized_records({
+                let parsed = Arc::clone(&parsed);
+                move |record| {
+                    parsed.fetch_add(1, Ordering::SeqCst);
+                    record.map(|record| record.event_record_id)
+                }
+            });
+
+            let mut batch_sizes = vec![];
+            loop {
+                let parsed_before = parsed.load(Ordering::SeqCst);
+                if records.next().is_none() {
+                    break;
                 }
-            };
+                let batch_size = parsed.load(Ordering::SeqCst) - parsed_before;
+                if batch_size > 0 {
+                    batch_sizes.push(batch_size);
+                }
+            }
+
+            assert_eq!(batch_sizes.iter().sum::<usize>(), 2261);
+            batch_sizes
+        };
+
+        let unbounded = batch_sizes(ParserSettings::new().num_threads(8));
+        assert!(unbounded.iter().any(|&size| size > 500));
+
+        for max_buffered_records in [1, 100, 500] {
+            let settings = ParserSettings::new()
+                .num_threads(8)
+                .max_buffered_records(max_buffered_records);
+
+            // A single chunk with more records than the limit is parsed as a whole.
+            let limit = max(max_buffered_records, max_chunk_records);
+            for size in batch_sizes(settings) {
+                assert!(size <= limit, "{} > {}", size, limit);
+            }
         }
     }
 
-    /// Return an iterator over all the chunks.
-    /// Each chunk supports iterating over it's records in their un-serialized state
-    /// (before they are converted to XML or JSON).
-    pub fn chunks(&mut self) -> IterChunks<T> {
-        IterChunks {
-            parser: self,
-            current_chunk_number: 0,
+    #[test]
+    fn test_synthesize_record_ids() {
+        ensure_env_logger_initialized();
+        let mut evtx_file = include_bytes!("../samples/security.evtx").to_vec();
+
+        // Zero the id of the second record of the first chunk, and copy the id of the first record
+        // to the third one (and to a record of another chunk).
+        let first_record = EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_HEADER_SIZE;
+        let record_offsets: Vec<_> = EvtxParser::from_buffer(evtx_file.clone())
+            .unwrap()
+            .records()
+            .map(|r| r.unwrap().file_offset.unwrap() as usize)
+            .collect();
+        assert_eq!(record_offsets[0], first_record);
+
+        let set_record_id = |evtx_file: &mut Vec<u8>, offset: usize, record_id: u64| {
+            evtx_file[offset + 8..offset + 16].copy_from_slice(&record_id.to_le_bytes());
+        };
+        set_record_id(&mut evtx_file, record_offsets[1], 0);
+        set_record_id(&mut evtx_file, record_offsets[2], 1);
+        set_record_id(&mut evtx_file, record_offsets[200], 1);
+
+        let records = |settings: ParserSettings| {
+            EvtxParser::from_buffer(evtx_file.clone())
+                .unwrap()
+                .with_configuration(settings)
+                .records()
+                .map(|r| r.unwrap())
+                .map(|r| (r.event_record_id, r.synthetic_record_id))
+                .collect::<Vec<_>>()
+        };
+
+        let real_ids = records(ParserSettings::new());
+        assert_eq!(real_ids[..3], [(1, false), (0, false), (1, false)]);
+
+        let settings = ParserSettings::new().synthesize_record_ids(true);
+        let expected = records(settings.clone().num_threads(1));
+        assert_eq!(
+            expected[..4],
+            [
+                (1, false),
+                (SYNTHETIC_RECORD_ID_BASE, true),
+                (SYNTHETIC_RECORD_ID_BASE + 1, true),
+                (4, false)
+            ]
+        );
+        assert_eq!(expected[200], (SYNTHETIC_RECORD_ID_BASE + 2, true));
+        assert_eq!(expected.iter().filter(|(_, s)| *s).count(), 3);
+        assert_eq!(records(settings.num_threads(8)), expected);
+    }
+
+    #[test]
+    fn test_records_with_raw_xml_and_model() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        let records: Vec<_> = parser
+            .records_with_raw_xml_and_model()
+            .take(10)
+            .map(|r| r.unwrap())
+            .collect();
+        let xml: Vec<_> = parser.records().take(10).map(|r| r.unwrap()).collect();
+        let json: Vec<_> = parser
+            .records_json_value()
+            .take(10)
+            .map(|r| r.unwrap())
+            .collect();
+
+        for ((record, xml), json) in records.into_iter().zip(xml).zip(json) {
+            let (model, record_xml) = record.data;
+            assert_eq!(record_xml, xml.data);
+            assert_eq!(model, json.data);
         }
     }
 
-    /// Consumes the parser, returning an iterator over all the chunks.
-    /// Each chunk supports iterating over it's records in their un-serialized state
-    /// (before they are converted to XML or JSON).
-    pub fn into_chunks(self) -> IntoIterChunks<T> {
-        IntoIterChunks {
-            parser: self,
-            current_chunk_number: 0,
+    #[test]
+    fn test_records_batched() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        let batches: Vec<_> = parser
+            .records_batched(NonZeroUsize::new(1000).unwrap())
+            .map(|b| b.unwrap())
+            .collect();
+        assert_eq!(
+            batches.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![1000, 1000, 261]
+        );
+
+        let record_ids: Vec<_> = parser
+            .records()
+            .map(|r| r.unwrap().event_record_id)
+            .collect();
+        assert_eq!(
+            batches
+                .into_iter()
+                .flatten()
+                .map(|r| r.event_record_id)
+                .collect::<Vec<_>>(),
+            record_ids
+        );
+    }
+
+    #[test]
+    fn test_batch_results_yields_errors_after_preceding_items() {
+        let error = || EvtxError::UnknownOutputFormat {
+            name: "bad".to_string(),
+        };
+        let results = vec![Ok(1), Ok(2), Ok(3), Err(error()), Err(error()), Ok(4)];
+
+        let batches: Vec<_> = batch_results(results.into_iter(), NonZeroUsize::new(2).unwrap())
+            .map(|batch| batch.ok())
+            .collect();
+        assert_eq!(
+            batches,
+            vec![Some(vec![1, 2]), Some(vec![3]), None, None, Some(vec![4])]
+        );
+    }
+
+    #[test]
+    fn test_map_json() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let transformed = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&transformed);
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(ParserSettings::new().num_threads(4))
+            .retain(|record| record["Event"]["System"]["EventID"] == 4624)
+            .map_json(Box::new(move |record| {
+                record["host"] = (counter.fetch_add(1, Ordering::SeqCst) + 1).into();
+                if let Some(event) = record["Event"].as_object_mut() {
+                    event.remove("EventData");
+                }
+            }));
+
+        let records: Vec<_> = parser.records_json_value().map(|r| r.unwrap()).collect();
+        assert!(!records.is_empty());
+        assert_eq!(transformed.load(Ordering::SeqCst), records.len());
+
+        // The records are transformed concurrently, so only the set of values is known.
+        let mut hosts: Vec<u64> = records
+            .iter()
+            .map(|record| record.data["host"].as_u64().unwrap())
+            .collect();
+        hosts.sort_unstable();
+        assert_eq!(hosts, (1..=records.len() as u64).collect::<Vec<_>>());
+
+        for record in records.iter() {
+            // The filter sees the original value.
+            assert_eq!(record.data["Event"]["System"]["EventID"], 4624);
+            assert_eq!(record.data["Event"]["EventData"], serde_json::Value::Null);
         }
+
+        let json = parser.records_json().next().unwrap().unwrap();
+        assert!(json.data.contains("\"host\""));
+        assert!(!json.data.contains("EventData"));
+
+        let dual = parser.records_dual().next().unwrap().unwrap();
+        assert!(dual.data.json.contains("\"host\""));
+        assert!(dual.data.xml.contains("<EventData>"));
     }
-    /// Return an iterator over all the records.
-    /// Records will be mapped `f`, which must produce owned data from the records.
-    pub fn serialized_records<'a, U: Send>(
-        &'a mut self,
-        f: impl FnMut(Result<EvtxRecord<'_>>) -> Result<U> + Send + Sync + Clone + 'a,
-    ) -> impl Iterator<Item = Result<U>> + '_ {
-        // Retrieve parser settings here, while `self` is immutably borrowed.
-        let num_threads = max(self.config.num_threads, 1);
-        let chunk_settings = Arc::clone(&self.config);
 
-        // `self` is mutably borrowed from here on.
-        let mut chunks = self.chunks();
+    #[test]
+    fn test_dump_jsonl() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(ParserSettings::new().ordered_output(true));
 
-        let records_per_chunk = std::iter::from_fn(move || {
-            // Allocate some chunks in advance, so they can be parsed in parallel.
-            let mut chunk_of_chunks = Vec::with_capacity(num_threads);
+        let mut jsonl = Vec::new();
+        parser.dump_jsonl(&mut jsonl).unwrap();
+        let jsonl = String::from_utf8(jsonl).unwrap();
 
-            for _ in 0..num_threads {
-                if let Some(chunk) = chunks.next() {
-                    chunk_of_chunks.push(chunk);
-                };
+        let expected: Vec<serde_json::Value> = parser
+            .records_json_value()
+            .map(|r| r.unwrap().data)
+            .collect();
+        let lines: Vec<serde_json::Value> = jsonl
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert!(jsonl.ends_with("}\n"));
+        assert_eq!(lines.len(), 2261);
+        assert_eq!(lines, expected);
+    }
+
+    #[test]
+    fn test_dump_xml_document() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        let mut document = Vec::new();
+        parser.dump_xml_document(&mut document).unwrap();
+        let document = String::from_utf8(document).unwrap();
+
+        assert!(
+            document.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<Events>\n<Event ")
+        );
+        assert!(document.ends_with("</Event>\n</Events>\n"));
+        assert_eq!(document.matches("<?xml").count(), 1);
+        assert_eq!(document.matches("<Event ").count(), 2261);
+
+        // The whole document should be well formed.
+        let mut reader = quick_xml::Reader::from_str(&document);
+        let mut buf = Vec::new();
+        let mut depth = 0;
+        loop {
+            match reader.read_event(&mut buf).unwrap() {
+                quick_xml::events::Event::Start(_) => depth += 1,
+                quick_xml::events::Event::End(_) => depth -= 1,
+                quick_xml::events::Event::Eof => break,
+                _ => {}
             }
+            buf.clear();
+        }
+        assert_eq!(depth, 0);
 
-            // We only stop once no chunks can be allocated.
-            if chunk_of_chunks.is_empty() {
-                None
-            } else {
-                #[cfg(feature = "multithreading")]
-                let chunk_iter = chunk_of_chunks.into_par_iter();
+        // The parser's own settings are left as they were.
+        let first_record = parser.records().next().unwrap().unwrap();
+        assert!(first_record.data.starts_with("<?xml"));
+    }
+
+    #[test]
+    fn test_xml_compact() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(ParserSettings::new().indent(true).xml_compact(true));
+
+        for record in parser.records().take(100) {
+            let record = record.unwrap();
+            assert!(!record.data.contains(">\n"));
+            assert!(!record.data.ends_with('\n'));
+        }
+
+        // Text nodes keep their internal whitespace.
+        let privileges = parser
+            .records()
+            .map(|record| record.unwrap().data)
+            .find(|data| data.contains("SeTcbPrivilege"))
+            .unwrap();
+        assert!(privileges.contains("\r\n\t\t\tSeTcbPrivilege"));
+
+        let mut document = Vec::new();
+        parser.dump_xml_document(&mut document).unwrap();
+        let document = String::from_utf8(document).unwrap();
+
+        assert!(document.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?><Events><Event "));
+        assert!(document.ends_with("</Event></Events>"));
+    }
+
+    #[test]
+    fn test_filter_providers_and_channels() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let count = |parser: EvtxParser<Cursor<Vec<u8>>>| {
+            let mut parser = parser;
+            parser.records().collect::<Result<Vec<_>>>().unwrap().len()
+        };
+        let parser = || EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        let by_name = parser().filter_providers(&["microsoft-windows-eventlog".to_string()]);
+        assert_eq!(count(by_name), 37);
+
+        // Guids may be given with or without braces.
+        let by_guid = parser().filter_providers(&[
+            "{54849625-5478-4994-a5ba-3e3b0328c30d}".to_string(),
+            "FC65DDD8-D6EF-4962-83D5-6E5CFE9CE148".to_string(),
+        ]);
+        assert_eq!(count(by_guid), 2261);
+
+        let by_channel = parser().filter_channels(&["SECURITY".to_string()]);
+        assert_eq!(count(by_channel), 2261);
+
+        let both = parser()
+            .filter_channels(&["Security".to_string()])
+            .filter_providers(&["Microsoft-Windows-Eventlog".to_string()]);
+        assert_eq!(count(both), 37);
+
+        let none = parser()
+            .filter_channels(&["System".to_string()])
+            .filter_providers(&["Microsoft-Windows-Eventlog".to_string()]);
+        assert_eq!(count(none), 0);
+    }
+
+    #[test]
+    fn test_retain() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
 
-                #[cfg(not(feature = "multithreading"))]
-                let chunk_iter = chunk_of_chunks.into_iter();
+        let expected: Vec<u64> = parser
+            .records_json_value()
+            .map(|r| r.unwrap())
+            .filter(|r| r.data["Event"]["EventData"]["LogonType"] == 5)
+            .map(|r| r.event_record_id)
+            .collect();
+        assert!(!expected.is_empty());
 
-                // Serialize the records in each chunk.
-                let iterators: Vec<Vec<Result<U>>> = chunk_iter
-                    .enumerate()
-                    .map(|(i, chunk_res)| match chunk_res {
-                        Err(err) => vec![Err(err)],
-                        Ok(mut chunk) => {
-                            let chunk_records_res = chunk.parse(chunk_settings.clone());
-
-                            match chunk_records_res {
-                                Err(err) => vec![Err(EvtxError::FailedToParseChunk {
-                                    chunk_id: i as u64,
-                                    source: err,
-                                })],
-                                Ok(mut chunk_records) => {
-                                    chunk_records.iter().map(f.clone()).collect()
-                                }
-                            }
-                        }
-                    })
-                    .collect();
+        let mut parser = parser.retain(|record| record["Event"]["EventData"]["LogonType"] == 5);
+        let record_ids: Vec<u64> = parser
+            .records()
+            .map(|r| r.unwrap().event_record_id)
+            .collect();
+        assert_eq!(record_ids, expected);
 
-                Some(iterators.into_iter().flatten())
-            }
-        });
+        // Combined with the other filters.
+        let mut parser = parser.filter_channels(&["System".to_string()]);
+        assert_eq!(parser.records().count(), 0);
 
-        records_per_chunk.flatten()
+        // The value given to the predicate is an array of the root elements.
+        let settings = ParserSettings::new().multi_fragment(MultiFragmentMode::WrapInArray);
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(settings)
+            .retain(|record| record[0]["Event"]["EventData"]["LogonType"] == 5);
+        let record_ids: Vec<u64> = parser
+            .records()
+            .map(|r| r.unwrap().event_record_id)
+            .collect();
+        assert_eq!(record_ids, expected);
     }
 
-    /// Return an iterator over all the records.
-    /// Records will be XML-formatted.
-    pub fn records(&mut self) -> impl Iterator<Item = Result<SerializedEvtxRecord<String>>> + '_ {
-        // '_ is required in the signature because the iterator is bound to &self.
-        self.serialized_records(|record| record.and_then(|record| record.into_xml()))
-    }
+    #[test]
+    fn test_retain_with_every_output() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
 
-    /// Return an iterator over all the records.
-    /// Records will be JSON-formatted.
-    pub fn records_json(
-        &mut self,
-    ) -> impl Iterator<Item = Result<SerializedEvtxRecord<String>>> + '_ {
-        self.serialized_records(|record| record.and_then(|record| record.into_json()))
-    }
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let calls = Arc::new(AtomicUsize::new(0));
+        let parser = || {
+            let calls = Arc::clone(&calls);
 
-    /// Return an iterator over all the records.
-    /// Records will have a `serde_json::Value` data attribute.
-    pub fn records_json_value(
-        &mut self,
-    ) -> impl Iterator<Item = Result<SerializedEvtxRecord<serde_json::Value>>> + '_ {
-        self.serialized_records(|record| record.and_then(|record| record.into_json_value()))
-    }
-}
+            EvtxParser::from_buffer(evtx_file.to_vec())
+                .unwrap()
+                .retain(move |record| {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    record["Event"]["EventData"]["LogonType"] == 5
+                })
+        };
+        let record_ids = |ids: Vec<Result<u64>>| -> Vec<u64> {
+            let ids = ids.into_iter().map(|id| id.unwrap()).collect();
+            // The predicate is called once for every record, in the same pass as it is serialized.
+            assert_eq!(calls.swap(0, Ordering::SeqCst), 2261);
 
-pub struct IterChunks<'c, T: ReadSeek> {
-    parser: &'c mut EvtxParser<T>,
-    current_chunk_number: u64,
-}
+            ids
+        };
 
-impl<'c, T: ReadSeek> Iterator for IterChunks<'c, T> {
-    type Item = Result<EvtxChunkData>;
-    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
-        match self.parser.find_next_chunk(self.current_chunk_number) {
-            None => None,
-            Some((chunk, chunk_number)) => {
-                self.current_chunk_number = match chunk_number.checked_add(1) {
-                    None => return None,
-                    Some(n) => n,
-                };
+        let expected = record_ids(
+            parser()
+                .records()
+                .map(|r| r.map(|r| r.event_record_id))
+                .collect(),
+        );
+        assert!(!expected.is_empty());
 
-                Some(chunk)
-            }
+        let outputs = vec![
+            record_ids(
+                parser()
+                    .records_json()
+                    .map(|r| r.map(|r| r.event_record_id))
+                    .collect(),
+            ),
+            record_ids(
+                parser()
+                    .records_json_value()
+                    .map(|r| r.map(|r| r.event_record_id))
+                    .collect(),
+            ),
+            record_ids(
+                parser()
+                    .records_dual()
+                    .map(|r| r.map(|r| r.event_record_id))
+                    .collect(),
+            ),
+            record_ids(
+                parser()
+                    .records_with_raw_xml_and_model()
+                    .map(|r| r.map(|r| r.event_record_id))
+                    .collect(),
+            ),
+            record_ids(
+                parser()
+                    .records_into_struct::<serde_json::Value>()
+                    .map(|r| r.map(|r| r.event_record_id))
+                    .collect(),
+            ),
+            record_ids(
+                parser()
+                    .serialized_records(|record| record.map(|record| record.event_record_id))
+                    .collect(),
+            ),
+        ];
+
+        for record_ids in outputs {
+            assert_eq!(record_ids, expected);
         }
     }
-}
 
-pub struct IntoIterChunks<T: ReadSeek> {
-    parser: EvtxParser<T>,
-    current_chunk_number: u64,
-}
+    #[test]
+    fn test_schema_summary() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
 
-impl<T: ReadSeek> Iterator for IntoIterChunks<T> {
-    type Item = Result<EvtxChunkData>;
-    fn next(&mut self) -> Option<<Self as Iterator>::Item> {
-        info!("Chunk {}", self.current_chunk_number);
-        match self.parser.find_next_chunk(self.current_chunk_number) {
-            None => None,
-            Some((chunk, chunk_number)) => {
-                self.current_chunk_number = match chunk_number.checked_add(1) {
-                    None => return None,
-                    Some(n) => n,
-                };
+        let summary = parser.schema_summary().unwrap();
+        assert_eq!(summary.records, 2261);
 
-                Some(chunk)
-            }
-        }
+        let event_id = &summary.paths["Event/System/EventID"];
+        assert_eq!(event_id.occurrences, 2261);
+        assert_eq!(
+            event_id.value_types.get(&BinXmlValueType::UInt16Type),
+            Some(&2261)
+        );
+
+        let provider_name = &summary.paths["Event/System/Provider/@Name"];
+        assert_eq!(provider_name.occurrences, 2261);
+        assert!(summary.paths["Event/System"].value_types.is_empty());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    #![allow(unused_variables)]
+    #[test]
+    fn test_from_sequential_read() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
 
-    use super::*;
-    use crate::ensure_env_logger_initialized;
-    use anyhow::anyhow;
+        // A slice can only be read forward.
+        let mut parser = EvtxParser::from_sequential_read(&evtx_file[..]).unwrap();
+        let record_ids: Vec<u64> = parser
+            .records()
+            .map(|record| record.unwrap().event_record_id)
+            .collect();
 
-    fn process_90_records(buffer: &'static [u8]) -> anyhow::Result<()> {
-        let mut parser = EvtxParser::from_buffer(buffer.to_vec())?;
+        let mut expected = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let expected_ids: Vec<u64> = expected
+            .records()
+            .map(|record| record.unwrap().event_record_id)
+            .collect();
 
-        for (i, record) in parser.records().take(90).enumerate() {
-            match record {
-                Ok(r) => {
-                    assert_eq!(r.event_record_id, i as u64 + 1);
-                }
-                Err(e) => return Err(anyhow!("Error while reading record {}, {:?}", i, e)),
-            }
-        }
+        assert_eq!(record_ids, expected_ids);
+    }
 
-        Ok(())
+    #[test]
+    fn test_ordered_output() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/2-system-Security-dirty.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(ParserSettings::new().num_threads(8).ordered_output(true));
+
+        let record_ids: Vec<_> = parser
+            .records()
+            .map(|record| record.unwrap().event_record_id)
+            .collect();
+
+        let mut sorted = record_ids.clone();
+        sorted.sort_unstable();
+
+        assert_eq!(record_ids.len(), 14621);
+        assert_eq!(record_ids, sorted);
     }
 
-    // For clion profiler
     #[test]
-    fn test_process_single_chunk() -> anyhow::Result<()> {
+    fn test_file_header() {
         ensure_env_logger_initialized();
         let evtx_file = include_bytes!("../samples/security.evtx");
-        process_90_records(evtx_file)?;
+        let parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
 
-        Ok(())
+        let header = parser.file_header();
+        assert_eq!(header.next_record_id, 2226);
+        assert_eq!(header.chunk_count, 26);
+        assert_eq!((header.major_version, header.minor_version), (3, 1));
+        assert!(header.validate_checksum());
     }
 
     #[test]
-    fn test_sample_2() {
-        let evtx_file = include_bytes!("../samples/system.evtx");
+    fn test_chunk_record_counts() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
         let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
 
-        let records: Vec<_> = parser.records().take(10).collect();
+        let counts = parser.chunk_record_counts().unwrap();
+        let chunks: Vec<EvtxChunkData> = parser.chunks().map(|c| c.unwrap()).collect();
 
-        for (i, record) in records.iter().enumerate() {
-            match record {
-                Ok(r) => {
-                    assert_eq!(
-                        r.event_record_id,
-                        i as u64 + 1,
-                        "Parser is skipping records!"
-                    );
-                }
-                Err(e) => panic!("Error while reading record {}, {:?}", i, e),
-            }
+        assert_eq!(parser.num_chunks(), 26);
+        assert_eq!(counts.len(), chunks.len());
+        for (&(chunk_number, count), chunk) in counts.iter().zip(chunks.iter()) {
+            assert_eq!(Some(chunk_number), chunk.chunk_number());
+            assert_eq!(count, chunk.count_records() as u64);
         }
+        assert_eq!(counts.iter().map(|(_, count)| count).sum::<u64>(), 2261);
 
-        // It should be empty, and not a [].
-        assert!(records[0]
-            .as_ref()
-            .unwrap()
-            .data
-            .contains("<Binary></Binary>"));
-        assert!(records[1]
-            .as_ref()
+        // Empty chunks are left out, the following chunks keep their numbers.
+        let mut evtx_file = evtx_file.to_vec();
+        let second_chunk = EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_SIZE;
+        evtx_file[second_chunk..second_chunk + EVTX_CHUNK_SIZE].fill(0);
+
+        let mut parser = EvtxParser::from_buffer(evtx_file).unwrap();
+        let chunk_numbers: Vec<u64> = parser
+            .chunk_record_counts()
             .unwrap()
-            .data
-            .contains("<Binary>E107070003000C00110010001C00D6000000000000000000</Binary>"));
+            .into_iter()
+            .map(|(chunk_number, _)| chunk_number)
+            .collect();
+        assert_eq!(chunk_numbers[..3], [0, 2, 3]);
     }
 
     #[test]
-    fn test_parses_first_10_records() {
+    fn test_time_span() {
         ensure_env_logger_initialized();
         let evtx_file = include_bytes!("../samples/security.evtx");
         let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
 
-        for (i, record) in parser.records().take(10).enumerate() {
-            match record {
-                Ok(r) => {
-                    assert_eq!(
-                        r.event_record_id,
-                        i as u64 + 1,
-                        "Parser is skipping records!"
-                    );
-                }
-                Err(e) => panic!("Error while reading record {}, {:?}", i, e),
-            }
-        }
+        let (first, last) = parser.time_span().unwrap().unwrap();
+
+        let mut records: Vec<_> = parser.records().map(|r| r.unwrap()).collect();
+        records.sort_by_key(|r| r.event_record_id);
+        assert_eq!(first, records.first().unwrap().timestamp);
+        assert_eq!(last, records.last().unwrap().timestamp);
+        assert!(first <= last);
+
+        // Only the file header.
+        let mut parser =
+            EvtxParser::from_buffer(evtx_file[..EVTX_FILE_HEADER_SIZE].to_vec()).unwrap();
+        assert_eq!(parser.time_span().unwrap(), None);
     }
 
     #[test]
-    fn test_parses_records_from_different_chunks() {
+    fn test_chunk_headers() {
         ensure_env_logger_initialized();
         let evtx_file = include_bytes!("../samples/security.evtx");
         let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
 
-        for (i, record) in parser.records().take(1000).enumerate() {
-            match record {
-                Ok(r) => {
-                    assert_eq!(r.event_record_id, i as u64 + 1);
-                }
-                Err(e) => println!("Error while reading record {}, {:?}", i, e),
-            }
+        let headers: Vec<(u64, EvtxChunkHeader)> = parser
+            .chunk_headers()
+            .into_iter()
+            .map(|(chunk_number, header)| (chunk_number, header.unwrap()))
+            .collect();
+
+        let chunks: Vec<EvtxChunkData> = parser.chunks().map(|c| c.unwrap()).collect();
+        assert_eq!(headers.len(), chunks.len());
+
+        for ((chunk_number, header), chunk) in headers.iter().zip(&chunks) {
+            assert_eq!(Some(*chunk_number), chunk.chunk_number());
+            assert_eq!(
+                header.first_event_record_id,
+                chunk.header.first_event_record_id
+            );
+            assert_eq!(
+                header.last_event_record_id,
+                chunk.header.last_event_record_id
+            );
+            assert_eq!(header.events_checksum, chunk.header.events_checksum);
+            assert!(header.free_space_offset as usize <= EVTX_CHUNK_SIZE);
         }
+
+        assert_eq!(headers[0].1.first_event_record_id, 1);
     }
 
     #[test]
-    #[cfg(feature = "multithreading")]
-    fn test_multithreading() {
-        use std::collections::HashSet;
+    fn test_validate() {
+        ensure_env_logger_initialized();
+        let mut evtx_file = include_bytes!("../samples/security.evtx").to_vec();
+
+        let mut parser = EvtxParser::from_buffer(evtx_file.clone()).unwrap();
+        let report = parser.validate().unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.chunks.len(), parser.chunks().count());
+
+        // Corrupt the file header, a record in the second chunk and the magic of the third chunk.
+        evtx_file[8] ^= 0xff;
+        let second_chunk = EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_SIZE;
+        evtx_file[second_chunk + EVTX_CHUNK_HEADER_SIZE + 100] ^= 0xff;
+        let third_chunk = EVTX_FILE_HEADER_SIZE + 2 * EVTX_CHUNK_SIZE;
+        evtx_file[third_chunk] = b'X';
+
+        let mut parser = EvtxParser::from_buffer(evtx_file).unwrap();
+        let report = parser.validate().unwrap();
+
+        assert!(!report.is_valid());
+        assert!(!report.file_header_checksum_valid);
+
+        let invalid_chunks: Vec<&ChunkValidation> = report.invalid_chunks().collect();
+        assert_eq!(
+            invalid_chunks,
+            vec![
+                &ChunkValidation {
+                    chunk_number: 1,
+                    magic_valid: true,
+                    header_checksum_valid: true,
+                    data_checksum_valid: false,
+                    error: None,
+                },
+                &ChunkValidation {
+                    chunk_number: 2,
+                    magic_valid: false,
+                    header_checksum_valid: false,
+                    data_checksum_valid: false,
+                    error: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_unreadable_chunks() {
+        ensure_env_logger_initialized();
+        let mut evtx_file = include_bytes!("../samples/security.evtx").to_vec();
 
+        // An unknown value of the flags of the second chunk's header.
+        let second_chunk = EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_SIZE;
+        evtx_file[second_chunk + 120..second_chunk + 124].copy_from_slice(&[0xff; 4]);
+
+        let mut parser = EvtxParser::from_buffer(evtx_file).unwrap();
+        let report = parser.validate().unwrap();
+        assert!(report.chunks.len() > 2);
+
+        let invalid_chunks: Vec<&ChunkValidation> = report.invalid_chunks().collect();
+        assert_eq!(invalid_chunks.len(), 1);
+        assert_eq!(invalid_chunks[0].chunk_number, 1);
+        assert!(invalid_chunks[0].magic_valid);
+        assert!(invalid_chunks[0].error.is_some());
+    }
+
+    #[test]
+    fn test_chunk_range() {
         ensure_env_logger_initialized();
         let evtx_file = include_bytes!("../samples/security.evtx");
+
         let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let all_chunks: Vec<EvtxChunkData> = parser.chunks().map(|c| c.unwrap()).collect();
+        let expected_records: usize = all_chunks[2..5].iter().map(|c| c.count_records()).sum();
 
-        let mut record_ids = HashSet::new();
-        for record in parser.records().take(1000) {
-            match record {
-                Ok(r) => {
-                    record_ids.insert(r.event_record_id);
-                }
-                Err(e) => panic!("Error while reading record {:?}", e),
-            }
-        }
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .chunk_range(2, 5);
 
-        assert_eq!(record_ids.len(), 1000);
+        let chunk_numbers: Vec<Option<u64>> =
+            parser.chunks().map(|c| c.unwrap().chunk_number()).collect();
+        assert_eq!(chunk_numbers, vec![Some(2), Some(3), Some(4)]);
+        assert_eq!(parser.chunk_headers().len(), 3);
+
+        let record_ids: Vec<u64> = parser
+            .records()
+            .map(|r| r.unwrap().event_record_id)
+            .collect();
+        assert_eq!(record_ids.len(), expected_records);
+        assert_eq!(record_ids[0], all_chunks[2].header.first_event_record_id);
+
+        let mut parser = parser.with_configuration(ParserSettings::new().ordered_output(true));
+        assert_eq!(parser.records().count(), expected_records);
+
+        // Past the end of the file.
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .chunk_range(1000, 2000);
+        assert_eq!(parser.records().count(), 0);
     }
 
     #[test]
-    fn test_file_with_only_a_single_chunk() {
+    fn test_records_dual() {
         ensure_env_logger_initialized();
-        let evtx_file = include_bytes!("../samples/new-user-security.evtx");
+        let evtx_file = include_bytes!("../samples/security.evtx");
         let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
 
-        let records: Vec<_> = parser.records().collect();
-        assert_eq!(records.len(), 4);
+        let xml: Vec<String> = parser.records().map(|r| r.unwrap().data).collect();
+        let json: Vec<String> = parser.records_json().map(|r| r.unwrap().data).collect();
+        let dual: Vec<DualOutput> = parser.records_dual().map(|r| r.unwrap().data).collect();
+
+        assert_eq!(dual.len(), 2261);
+        for (i, record) in dual.into_iter().enumerate() {
+            assert_eq!(record.xml, xml[i]);
+            assert_eq!(record.json, json[i]);
+        }
     }
 
     #[test]
-    fn test_parses_chunk2() {
+    fn test_zero_padding() {
         ensure_env_logger_initialized();
         let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
 
-        let mut chunk = EvtxChunkData::new(
-            evtx_file[EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_SIZE
-                ..EVTX_FILE_HEADER_SIZE + 2 * EVTX_CHUNK_SIZE]
-                .to_vec(),
-            false,
-        )
-        .unwrap();
+        assert_eq!(parser.zero_padding(), None);
+        assert_eq!(parser.chunks().count(), 26);
+        assert_eq!(
+            parser.zero_padding(),
+            Some(ZeroPaddedChunk { chunk_number: 26 })
+        );
 
-        assert!(chunk.validate_checksum());
+        // A zeroed chunk followed by a real chunk is not padding.
+        let mut data = evtx_file[..EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_SIZE].to_vec();
+        data.extend(vec![0; EVTX_CHUNK_SIZE]);
+        data.extend_from_slice(
+            &evtx_file[EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_SIZE
+                ..EVTX_FILE_HEADER_SIZE + 2 * EVTX_CHUNK_SIZE],
+        );
+        let mut parser = EvtxParser::from_buffer(data).unwrap();
 
-        for record in chunk
-            .parse(Arc::new(ParserSettings::default()))
-            .unwrap()
-            .iter()
-        {
-            record.unwrap();
+        let chunk_numbers: Vec<Option<u64>> =
+            parser.chunks().map(|c| c.unwrap().chunk_number()).collect();
+        assert_eq!(chunk_numbers, vec![Some(0), Some(2)]);
+        assert_eq!(parser.zero_padding(), None);
+
+        // The padding is only reported for the iteration which reached it.
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        assert_eq!(parser.chunks().count(), 26);
+        assert!(parser.zero_padding().is_some());
+        assert!(parser.chunks().next().is_some());
+        assert_eq!(parser.zero_padding(), None);
+    }
+
+    #[test]
+    fn test_zeroed_chunks_are_scanned_once() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        struct CountSeeks(Cursor<Vec<u8>>, Arc<AtomicUsize>);
+
+        impl Read for CountSeeks {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.0.read(buf)
+            }
+        }
+
+        impl Seek for CountSeeks {
+            fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+                self.1.fetch_add(1, Ordering::SeqCst);
+                self.0.seek(pos)
+            }
         }
+
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let zeroed_chunks = 100;
+
+        // Many zeroed chunks followed by a real chunk.
+        let mut data = evtx_file[..EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_SIZE].to_vec();
+        data.extend(vec![0; zeroed_chunks * EVTX_CHUNK_SIZE]);
+        data.extend_from_slice(
+            &evtx_file[EVTX_FILE_HEADER_SIZE..EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_SIZE],
+        );
+
+        let seeks = Arc::new(AtomicUsize::new(0));
+        let mut parser =
+            EvtxParser::from_read_seek(CountSeeks(Cursor::new(data), Arc::clone(&seeks))).unwrap();
+        seeks.store(0, Ordering::SeqCst);
+
+        assert_eq!(parser.chunks().count(), 2);
+        assert_eq!(parser.zero_padding(), None);
+        // Every chunk is read once, and checked for padding at most once.
+        assert!(seeks.load(Ordering::SeqCst) <= 3 * (zeroed_chunks + 3));
+    }
+
+    #[test]
+    fn test_sequential_read_skips_zeroed_chunks() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut data = evtx_file.to_vec();
+        let zeroed_chunk = EVTX_FILE_HEADER_SIZE + 5 * EVTX_CHUNK_SIZE;
+        data[zeroed_chunk..zeroed_chunk + EVTX_CHUNK_SIZE].fill(0);
+
+        let mut parser = EvtxParser::from_sequential_read(&data[..]).unwrap();
+        let chunk_numbers: Vec<u64> = parser
+            .chunks()
+            .map(|chunk| chunk.unwrap().chunk_number().unwrap())
+            .collect();
+
+        let expected: Vec<u64> = (0..26).filter(|n| *n != 5).collect();
+        assert_eq!(chunk_numbers, expected);
     }
 
     #[test]
@@ -715,6 +4891,23 @@ mod tests {
         assert_eq!(records.len(), 1);
     }
 
+    #[test]
+    fn test_chunks_know_their_position() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        for (i, chunk) in parser.chunks().enumerate() {
+            let chunk = chunk.unwrap();
+
+            assert_eq!(chunk.chunk_number(), Some(i as u64));
+            assert_eq!(
+                chunk.offset(),
+                Some((EVTX_FILE_HEADER_SIZE + i * EVTX_CHUNK_SIZE) as u64)
+            );
+        }
+    }
+
     #[test]
     fn test_into_json_value_records() {
         ensure_env_logger_initialized();
@@ -1,40 +1,189 @@
-use crate::binxml::assemble::parse_tokens;
+use crate::binxml::assemble::{parse_record, peek_event_id};
+use crate::binxml::value_variant::BinXmlValue;
 use crate::err::{
     DeserializationError, DeserializationResult, EvtxError, Result, SerializationError,
+    SerializationResult,
 };
+use crate::evtx_parser::{EVTX_CHUNK_SIZE, EVTX_FILE_HEADER_SIZE};
 use crate::json_output::JsonOutput;
+use crate::message_table::MessageParams;
 use crate::model::deserialized::BinXMLDeserializedTokens;
+use crate::record_filter::{FilterOutput, RecordFilter};
 use crate::xml_output::{BinXmlOutput, XmlOutput};
-use crate::{EvtxChunk, ParserSettings};
+use crate::{EvtxChunk, FileOffset, ParserSettings};
 
 use byteorder::ReadBytesExt;
 use chrono::prelude::*;
-use std::io::{Cursor, Read};
+use serde::de::DeserializeOwned;
+use std::convert::{Infallible, TryFrom};
+use std::io::{Cursor, Read, Write};
 use std::sync::Arc;
+use winstructs::timestamp::WinTimestamp;
 
 pub type RecordId = u64;
 
+/// Magic, size, id and timestamp, followed by a copy of the size at the end of the record.
+pub(crate) const EVTX_RECORD_HEADER_SIZE: usize = 24 + 4;
+
 #[derive(Debug, Clone)]
 pub struct EvtxRecord<'a> {
     pub chunk: &'a EvtxChunk<'a>,
     pub event_record_id: RecordId,
     pub timestamp: DateTime<Utc>,
+    /// The raw FILETIME of `timestamp` (see `EvtxRecordHeader::filetime`).
+    pub filetime: u64,
     pub tokens: Vec<BinXMLDeserializedTokens<'a>>,
     pub settings: Arc<ParserSettings>,
+    /// The bytes of the record, from the `**\0\0` magic to the copy of its size at the end.
+    pub raw: &'a [u8],
+    /// The offset of the record from the start of its chunk.
+    pub offset: u64,
+    /// The size of the record, as declared in its header.
+    pub size: u32,
+    /// True if `event_record_id` is synthetic (see `ParserSettings::synthesize_record_ids`).
+    pub synthetic_record_id: bool,
 }
 
+/// The header of a record. Unlike the file and chunk headers, it has no flags:
+/// the position of a record in its chunk is known from the chunk header instead
+/// (see `EvtxRecord::is_last_in_chunk`), and records never span chunks.
 #[derive(Debug, Clone, PartialEq)]
 pub struct EvtxRecordHeader {
     pub data_size: u32,
     pub event_record_id: RecordId,
     pub timestamp: DateTime<Utc>,
+    /// The timestamp as it is stored in the header, a FILETIME (the number of 100-nanosecond
+    /// intervals since 1601-01-01 UTC). `timestamp` is truncated to microseconds.
+    /// It is the same as the `SystemTime` of the `<TimeCreated>` element of the record.
+    pub filetime: u64,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct SerializedEvtxRecord<T> {
     pub event_record_id: RecordId,
     pub timestamp: DateTime<Utc>,
+    /// The raw FILETIME of `timestamp`, without its truncation to microseconds
+    /// (see `EvtxRecordHeader::filetime`), for ordering records precisely.
+    pub filetime: u64,
     pub data: T,
+    /// True if the record was carved from a chunk with a bad header (see `ParserSettings::carve_records`).
+    pub carved: bool,
+    /// The number of the chunk the record was read from, if it is known
+    /// (it isn't for chunks constructed from raw bytes).
+    /// Like other chunk numbers, it is a `u64`, since files can have more chunks than a `u16` can count.
+    pub chunk_number: Option<u64>,
+    /// Whether the checksums of the chunk the record was read from are valid,
+    /// `None` if they were not validated (see `EvtxChunkData::checksum_valid`).
+    pub chunk_checksum_valid: Option<bool>,
+    /// The bytes of the record, only kept if `ParserSettings::include_raw_bytes` is enabled.
+    pub raw: Option<Vec<u8>>,
+    /// The offset of the record from the start of the file, if the chunk number is known
+    /// (it can be passed to `EvtxParser::parse_record_at_offset`).
+    pub file_offset: Option<FileOffset>,
+    /// The size of the record, as declared in its header.
+    pub size: u32,
+    /// True if `event_record_id` is synthetic, and not the id of the record in the file
+    /// (see `ParserSettings::synthesize_record_ids`).
+    pub synthetic_record_id: bool,
+    /// Only collected if `ParserSettings::collect_stats` is enabled.
+    pub stats: Option<RecordStats>,
+    /// Only kept if `ParserSettings::include_template_id` is enabled.
+    pub template_id: Option<u32>,
+    /// The message of the event, only rendered if `ParserSettings::message_tables` is set
+    /// and has a format string for the provider and event id of the record.
+    pub rendered_message: Option<String>,
+    /// The `UserID` attribute of the `<Security>` element of the record, the SID of the user
+    /// which logged the event (ex. `S-1-5-18`), or `None` if it is absent.
+    /// It is read from the record itself, so it is always a SID
+    /// (even with `ParserSettings::resolve_well_known_sids`), in every output.
+    pub security_user_id: Option<String>,
+}
+
+/// What is collected from a record while it is serialized, besides its output.
+struct RecordExtras {
+    stats: Option<RecordStats>,
+    rendered_message: Option<String>,
+    security_user_id: Option<String>,
+}
+
+/// A record serialized to both XML and JSON, see `EvtxParser::records_dual`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DualOutput {
+    /// The same as the data of `EvtxRecord::into_xml`.
+    pub xml: String,
+    /// The same as the data of `EvtxRecord::into_json`.
+    pub json: String,
+}
+
+/// The process and thread which logged a record,
+/// from the `<Execution ProcessID="..." ThreadID="..."/>` element of its `<System>`.
+/// See `SerializedEvtxRecord::execution`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Execution {
+    pub process_id: u32,
+    pub thread_id: u32,
+}
+
+/// The fields of the `<System>` element of a record, see `SerializedEvtxRecord::system`.
+/// Fields which are absent (or aren't valid) are `None`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SystemData {
+    pub provider_name: Option<String>,
+    pub provider_guid: Option<String>,
+    pub event_id: Option<u32>,
+    /// The `Qualifiers` attribute of `<EventID>`, set by providers which log legacy event ids.
+    pub event_id_qualifiers: Option<u16>,
+    pub version: Option<u8>,
+    pub level: Option<u8>,
+    pub task: Option<u16>,
+    pub opcode: Option<u8>,
+    pub keywords: Option<u64>,
+    pub time_created: Option<DateTime<Utc>>,
+    pub event_record_id: Option<RecordId>,
+    pub correlation_activity_id: Option<String>,
+    pub correlation_related_activity_id: Option<String>,
+    pub execution: Option<Execution>,
+    pub channel: Option<String>,
+    pub computer: Option<String>,
+    /// See `SerializedEvtxRecord::security_user_id`.
+    pub security_user_id: Option<String>,
+}
+
+/// Measures of the complexity of a record, see `ParserSettings::collect_stats`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RecordStats {
+    /// The number of template instances, including templates nested in substitution values.
+    pub template_instances: usize,
+    /// The number of substitution values, in all the template instances.
+    pub substitutions: usize,
+    /// The maximum depth of nested elements (the root element has a depth of 1).
+    pub depth: usize,
+}
+
+impl RecordStats {
+    /// Counts the template instances and substitutions in `tokens` (the depth is left as 0).
+    fn count_templates(tokens: &[BinXMLDeserializedTokens]) -> RecordStats {
+        let mut stats = RecordStats::default();
+
+        for token in tokens {
+            if let BinXMLDeserializedTokens::TemplateInstance(template) = token {
+                stats.template_instances += 1;
+                stats.substitutions += template.substitution_array.len();
+
+                for substitution in &template.substitution_array {
+                    if let BinXMLDeserializedTokens::Value(BinXmlValue::BinXmlType(nested)) =
+                        substitution
+                    {
+                        let nested = RecordStats::count_templates(nested);
+                        stats.template_instances += nested.template_instances;
+                        stats.substitutions += nested.substitutions;
+                    }
+                }
+            }
+        }
+
+        stats
+    }
 }
 
 impl EvtxRecordHeader {
@@ -48,85 +197,1278 @@ impl EvtxRecordHeader {
 
         let size = try_read!(input, u32)?;
         let record_id = try_read!(input, u64)?;
-        let timestamp = try_read!(input, filetime)?;
+        let filetime = try_read!(input, u64)?;
+        let timestamp = WinTimestamp::new(&filetime.to_le_bytes())
+            .expect("A FILETIME is read from 8 bytes")
+            .to_datetime();
 
         Ok(EvtxRecordHeader {
             data_size: size,
             event_record_id: record_id,
             timestamp,
+            filetime,
         })
     }
 
     pub fn record_data_size(&self) -> u32 {
         // 24 - record header size
         // 4 - copy of size record size
-        self.data_size - 24 - 4
+        self.data_size - EVTX_RECORD_HEADER_SIZE as u32
     }
 }
 
 impl<'a> EvtxRecord<'a> {
+    /// A copy of the raw bytes of the record, if `ParserSettings::include_raw_bytes` is enabled.
+    fn raw_bytes(&self) -> Option<Vec<u8>> {
+        if self.settings.should_include_raw_bytes() {
+            Some(self.raw.to_vec())
+        } else {
+            None
+        }
+    }
+
+    /// The id of the record's template, if `ParserSettings::include_template_id` is enabled.
+    fn included_template_id(&self) -> Option<u32> {
+        if self.settings.should_include_template_id() {
+            self.template_id()
+        } else {
+            None
+        }
+    }
+
+    /// The id of the template the record was built from (records of the same kind of event share
+    /// a template, even across chunks and files), or `None` if the record has no template.
+    pub fn template_id(&self) -> Option<u32> {
+        self.tokens.iter().find_map(|token| match token {
+            BinXMLDeserializedTokens::TemplateInstance(template) => Some(template.template_id),
+            _ => None,
+        })
+    }
+
+    /// The `<EventID>` of the record, for example to route records before paying for
+    /// their serialization. It is read from the substitution values of the record's template
+    /// (and the definition of the template), without expanding the template into elements.
+    /// Returns `None` if the record has no numeric `<EventID>`, or if its template can't be read.
+    pub fn peek_event_id(&self) -> Option<u64> {
+        peek_event_id(&self.tokens, self.chunk, self.event_record_id)
+            .ok()
+            .flatten()
+    }
+
+    /// True if this is the last record of its chunk, according to the chunk header.
+    /// Always false for records carved from a chunk with a bad header.
+    pub fn is_last_in_chunk(&self) -> bool {
+        !self.chunk.carved
+            && self.offset == u64::from(self.chunk.header.last_event_record_data_offset)
+    }
+
+    /// The offset of the record from the start of the file, if the number of its chunk is known
+    /// (it isn't for chunks constructed from raw bytes).
+    pub fn file_offset(&self) -> Option<FileOffset> {
+        self.chunk.chunk_number.map(|chunk_number| {
+            EVTX_FILE_HEADER_SIZE as FileOffset
+                + chunk_number * EVTX_CHUNK_SIZE as FileOffset
+                + self.offset
+        })
+    }
+
+    /// The values which fill the placeholders of the record's template (its substitution array),
+    /// in order, as they are before being merged into the elements of the template.
+    ///
+    /// Values of nested templates are returned as a single `BinXmlValue::BinXmlType`.
+    pub fn substitutions(&self) -> Vec<&BinXmlValue<'a>> {
+        self.tokens
+            .iter()
+            .filter_map(|token| match token {
+                BinXMLDeserializedTokens::TemplateInstance(template) => Some(template),
+                _ => None,
+            })
+            .flat_map(|template| template.substitution_array.iter())
+            .filter_map(|token| match token {
+                BinXMLDeserializedTokens::Value(value) => Some(value),
+                _ => None,
+            })
+            .collect()
+    }
+
     /// Consumes the record, processing it using the given `output_builder`.
     pub fn into_output<T: BinXmlOutput>(self, output_builder: &mut T) -> Result<()> {
+        self.into_output_with_stats(output_builder)?;
+
+        Ok(())
+    }
+
+    /// Like `into_output`, but also returns what is collected from the record along the way.
+    fn into_output_with_stats<T: BinXmlOutput>(
+        self,
+        output_builder: &mut T,
+    ) -> Result<RecordExtras> {
+        let settings = Arc::clone(&self.settings);
+
+        match settings.get_message_tables() {
+            Some(message_tables) => {
+                let mut message_params = MessageParams::default();
+                let extras =
+                    self.into_output_with_record_stats(output_builder, Some(&mut message_params))?;
+
+                Ok(RecordExtras {
+                    rendered_message: message_params.render(message_tables),
+                    ..extras
+                })
+            }
+            None => self.into_output_with_record_stats(output_builder, None),
+        }
+    }
+
+    fn into_output_with_record_stats<T: BinXmlOutput>(
+        self,
+        output_builder: &mut T,
+        message_params: Option<&mut MessageParams>,
+    ) -> Result<RecordExtras> {
+        let stats = if self.settings.should_collect_stats() {
+            Some(RecordStats::count_templates(&self.tokens))
+        } else {
+            None
+        };
+
         let event_record_id = self.event_record_id;
-        parse_tokens(self.tokens, &self.chunk, output_builder).map_err(|e| {
-            EvtxError::FailedToParseRecord {
+        output_builder
+            .visit_start_of_record(event_record_id, self.timestamp)
+            .map_err(|e| EvtxError::FailedToParseRecord {
+                record_id: event_record_id,
+                source: Box::new(e.into()),
+            })?;
+
+        let (depth, security_user_id) = parse_record(
+            self.tokens,
+            &self.chunk,
+            event_record_id,
+            output_builder,
+            message_params,
+        )
+        .map_err(|e| match e {
+            EvtxError::MaxDepthExceeded { .. } => e,
+            e => EvtxError::FailedToParseRecord {
                 record_id: event_record_id,
                 source: Box::new(e),
-            }
+            },
         })?;
 
-        Ok(())
+        Ok(RecordExtras {
+            stats: stats.map(|stats| RecordStats { depth, ..stats }),
+            rendered_message: None,
+            security_user_id,
+        })
     }
 
-    /// Consumes the record, returning a `EvtxRecordWithJsonValue` with the `serde_json::Value` data.
-    pub fn into_json_value(self) -> Result<SerializedEvtxRecord<serde_json::Value>> {
-        let mut output_builder = JsonOutput::new(&self.settings);
-
+    /// Consumes the record, processing it using `output_builder`,
+    /// and returns the builder as the data of the serialized record.
+    pub fn into_serialized_output<T: BinXmlOutput>(
+        self,
+        mut output_builder: T,
+    ) -> Result<SerializedEvtxRecord<T>> {
         let event_record_id = self.event_record_id;
         let timestamp = self.timestamp;
-        self.into_output(&mut output_builder)?;
+        let filetime = self.filetime;
+        let carved = self.chunk.carved;
+        let chunk_number = self.chunk.chunk_number;
+        let chunk_checksum_valid = self.chunk.checksum_valid;
+        let raw = self.raw_bytes();
+        let file_offset = self.file_offset();
+        let size = self.size;
+        let synthetic_record_id = self.synthetic_record_id;
+        let template_id = self.included_template_id();
+        let extras = self.into_output_with_stats(&mut output_builder)?;
 
         Ok(SerializedEvtxRecord {
             event_record_id,
             timestamp,
-            data: output_builder.into_value()?,
+            filetime,
+            data: output_builder,
+            carved,
+            chunk_number,
+            chunk_checksum_valid,
+            raw,
+            file_offset,
+            size,
+            synthetic_record_id,
+            stats: extras.stats,
+            template_id,
+            rendered_message: extras.rendered_message,
+            security_user_id: extras.security_user_id,
         })
     }
 
+    /// Like `into_serialized_output`, but the record is only returned if it matches `filter`,
+    /// which is checked in the same pass over the record.
+    pub(crate) fn into_filtered_output<T: BinXmlOutput>(
+        self,
+        output_builder: T,
+        filter: &RecordFilter,
+    ) -> Result<Option<SerializedEvtxRecord<T>>> {
+        let (record, filter_output) = self.into_output_for_filter(output_builder, filter, false)?;
+
+        if filter.matches_output(filter_output, None)? {
+            Ok(Some(record))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Returns true if the record matches `filter`, for records which are not serialized
+    /// (the record is visited once, only for the filter).
+    pub(crate) fn matches(&self, filter: &RecordFilter) -> Result<bool> {
+        let mut filter_output = filter.output(&self.settings, false);
+
+        if filter_output.is_none() {
+            return Ok(true);
+        }
+
+        self.clone().into_output(&mut filter_output)?;
+
+        Ok(filter.matches_output(filter_output, None)?)
+    }
+
+    /// Like `into_serialized_output`, but also visits the record with the output of `filter`
+    /// (see `RecordFilter::output`), in the same pass.
+    fn into_output_for_filter<T: BinXmlOutput>(
+        self,
+        output_builder: T,
+        filter: &RecordFilter,
+        has_json_value: bool,
+    ) -> Result<(SerializedEvtxRecord<T>, FilterOutput)> {
+        let mut filter_output = filter.output(&self.settings, has_json_value);
+        let record = self
+            .into_serialized_output((output_builder, &mut filter_output))?
+            .map_data(|(output_builder, _)| output_builder);
+
+        Ok((record, filter_output))
+    }
+
+    /// Consumes the record, returning a `EvtxRecordWithJsonValue` with the `serde_json::Value` data.
+    pub fn into_json_value(self) -> Result<SerializedEvtxRecord<serde_json::Value>> {
+        unfiltered(self.into_filtered_json_value(&RecordFilter::default()))
+    }
+
+    /// Like `into_json_value`, but only if the record matches `filter` (see `into_filtered_output`).
+    pub(crate) fn into_filtered_json_value(
+        self,
+        filter: &RecordFilter,
+    ) -> Result<Option<SerializedEvtxRecord<serde_json::Value>>> {
+        let output_builder = JsonOutput::new(&self.settings);
+
+        // The predicates of the filter are given the JSON value of the record.
+        let (record, filter_output) = self.into_output_for_filter(output_builder, filter, true)?;
+        let record = record.try_map_data(JsonOutput::into_value)?;
+
+        if filter.matches_output(filter_output, Some(&record.data))? {
+            Ok(Some(record))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Consumes the record, deserializing its JSON value into `T`.
+    /// Deserialization errors are reported as `EvtxError::FailedToParseRecord`,
+    /// wrapping a `SerializationError::JsonError`.
+    pub fn into_struct<T: DeserializeOwned>(self) -> Result<SerializedEvtxRecord<T>> {
+        self.into_json_value()?.deserialize_data()
+    }
+
     /// Consumes the record and parse it, producing a JSON serialized record.
     pub fn into_json(self) -> Result<SerializedEvtxRecord<String>> {
         let indent = self.settings.should_indent();
+
+        unfiltered(self.into_filtered_json(indent, |_| {}, &RecordFilter::default()))
+    }
+
+    /// Like `into_json`, but ignores `ParserSettings::indent`,
+    /// passes the JSON value to `transform` before it is serialized,
+    /// and only returns the record if it matches `filter` (see `into_filtered_output`).
+    pub(crate) fn into_filtered_json(
+        self,
+        indent: bool,
+        transform: impl FnOnce(&mut serde_json::Value),
+        filter: &RecordFilter,
+    ) -> Result<Option<SerializedEvtxRecord<String>>> {
+        let record = match self.into_filtered_json_value(filter)? {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+
+        Ok(Some(record.try_map_data(|mut data| {
+            transform(&mut data);
+
+            json_to_string(&data, indent)
+        })?))
+    }
+
+    /// Consumes the record and writes it to `writer` as JSON (indented if `ParserSettings::indent` is set).
+    /// Unlike `into_json`, the text is written to `writer` instead of a `String`,
+    /// so a single (buffered) writer can be reused for many records.
+    ///
+    /// Note that the record is still built as a `serde_json::Value` before it is written
+    /// (unlike `write_xml`, which streams the record).
+    pub fn write_json<W: Write>(self, writer: &mut W) -> Result<()> {
+        let indent = self.settings.should_indent();
         let record_with_json_value = self.into_json_value()?;
 
-        let data = if indent {
-            serde_json::to_string_pretty(&record_with_json_value.data)
-                .map_err(SerializationError::from)?
+        if indent {
+            serde_json::to_writer_pretty(writer, &record_with_json_value.data)
+                .map_err(SerializationError::from)?;
         } else {
-            serde_json::to_string(&record_with_json_value.data).map_err(SerializationError::from)?
-        };
+            serde_json::to_writer(writer, &record_with_json_value.data)
+                .map_err(SerializationError::from)?;
+        }
 
-        Ok(SerializedEvtxRecord {
-            event_record_id: record_with_json_value.event_record_id,
-            timestamp: record_with_json_value.timestamp,
-            data,
-        })
+        Ok(())
+    }
+
+    /// Consumes the record and writes it to `writer` as XML.
+    /// Unlike `into_xml`, no intermediate `String` is allocated,
+    /// so a single (buffered) writer can be reused for many records.
+    pub fn write_xml<W: Write>(self, writer: &mut W) -> Result<()> {
+        let mut output_builder = XmlOutput::with_writer(writer, &self.settings);
+
+        self.into_output(&mut output_builder)
     }
 
     /// Consumes the record and parse it, producing an XML serialized record.
     pub fn into_xml(self) -> Result<SerializedEvtxRecord<String>> {
-        let mut output_builder = XmlOutput::with_writer(Vec::new(), &self.settings);
+        unfiltered(self.into_filtered_xml(&RecordFilter::default()))
+    }
 
-        let event_record_id = self.event_record_id;
-        let timestamp = self.timestamp;
-        self.into_output(&mut output_builder)?;
+    /// Like `into_xml`, but only if the record matches `filter` (see `into_filtered_output`).
+    pub(crate) fn into_filtered_xml(
+        self,
+        filter: &RecordFilter,
+    ) -> Result<Option<SerializedEvtxRecord<String>>> {
+        let output_builder = XmlOutput::with_writer(Vec::new(), &self.settings);
+
+        match self.into_filtered_output(output_builder, filter)? {
+            Some(record) => Ok(Some(
+                record
+                    .try_map_data(|xml_output| String::from_utf8(xml_output.into_writer()))
+                    .map_err(SerializationError::from)?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Consumes the record and parse it, producing both the tree of its elements
+    /// (as its JSON value, see `into_json_value`) and its XML from a single pass over its tokens.
+    pub fn into_xml_and_model(self) -> Result<SerializedEvtxRecord<(serde_json::Value, String)>> {
+        unfiltered(self.into_filtered_xml_and_model(&RecordFilter::default()))
+    }
+
+    /// Like `into_xml_and_model`, but only if the record matches `filter` (see `into_filtered_output`).
+    pub(crate) fn into_filtered_xml_and_model(
+        self,
+        filter: &RecordFilter,
+    ) -> Result<Option<SerializedEvtxRecord<(serde_json::Value, String)>>> {
+        let output_builder = (
+            JsonOutput::new(&self.settings),
+            XmlOutput::with_writer(Vec::new(), &self.settings),
+        );
+
+        let (record, filter_output) = self.into_output_for_filter(output_builder, filter, true)?;
+        let record = record.try_map_data(|(json_output, xml_output)| {
+            let xml = String::from_utf8(xml_output.into_writer())?;
+
+            SerializationResult::Ok((json_output.into_value()?, xml))
+        })?;
+
+        if filter.matches_output(filter_output, Some(&record.data.0))? {
+            Ok(Some(record))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Consumes the record and parse it, producing both XML and JSON from a single pass
+    /// over its tokens.
+    pub fn into_dual(self) -> Result<SerializedEvtxRecord<DualOutput>> {
+        unfiltered(self.into_filtered_dual(|_| {}, &RecordFilter::default()))
+    }
+
+    /// Like `into_dual`, but passes the JSON value to `transform` before it is serialized,
+    /// and only returns the record if it matches `filter` (see `into_filtered_output`).
+    pub(crate) fn into_filtered_dual(
+        self,
+        transform: impl FnOnce(&mut serde_json::Value),
+        filter: &RecordFilter,
+    ) -> Result<Option<SerializedEvtxRecord<DualOutput>>> {
+        let output_builder = (
+            XmlOutput::with_writer(Vec::new(), &self.settings),
+            JsonOutput::new(&self.settings),
+        );
+        let indent = self.settings.should_indent();
+
+        let (record, filter_output) = self.into_output_for_filter(output_builder, filter, true)?;
+        let record = record.try_map_data(|(xml_output, json_output)| {
+            let xml = String::from_utf8(xml_output.into_writer())?;
+
+            SerializationResult::Ok((xml, json_output.into_value()?))
+        })?;
+
+        if !filter.matches_output(filter_output, Some(&record.data.1))? {
+            return Ok(None);
+        }
+
+        Ok(Some(record.try_map_data(|(xml, mut json_value)| {
+            transform(&mut json_value);
+            let json = json_to_string(&json_value, indent)?;
+
+            SerializationResult::Ok(DualOutput { xml, json })
+        })?))
+    }
+}
 
-        let data =
-            String::from_utf8(output_builder.into_writer()).map_err(SerializationError::from)?;
+/// Unwraps a record which was filtered with an empty filter, which matches every record.
+fn unfiltered<T>(record: Result<Option<T>>) -> Result<T> {
+    Ok(record?.expect("An empty filter matches every record"))
+}
 
+impl<T> SerializedEvtxRecord<T> {
+    /// Converts the data of the record with `f`, keeping the rest of the record.
+    pub fn map_data<U>(self, f: impl FnOnce(T) -> U) -> SerializedEvtxRecord<U> {
+        match self.try_map_data(|data| Ok::<U, Infallible>(f(data))) {
+            Ok(record) => record,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Like `map_data`, but `f` may fail.
+    pub fn try_map_data<U, E>(
+        self,
+        f: impl FnOnce(T) -> std::result::Result<U, E>,
+    ) -> std::result::Result<SerializedEvtxRecord<U>, E> {
         Ok(SerializedEvtxRecord {
-            event_record_id,
-            timestamp,
-            data,
+            event_record_id: self.event_record_id,
+            timestamp: self.timestamp,
+            filetime: self.filetime,
+            data: f(self.data)?,
+            carved: self.carved,
+            chunk_number: self.chunk_number,
+            chunk_checksum_valid: self.chunk_checksum_valid,
+            raw: self.raw,
+            file_offset: self.file_offset,
+            size: self.size,
+            synthetic_record_id: self.synthetic_record_id,
+            stats: self.stats,
+            template_id: self.template_id,
+            rendered_message: self.rendered_message,
+            security_user_id: self.security_user_id,
         })
     }
 }
+
+impl SerializedEvtxRecord<serde_json::Value> {
+    /// Deserializes the JSON value of the record into `T` (see `EvtxRecord::into_struct`).
+    pub(crate) fn deserialize_data<T: DeserializeOwned>(self) -> Result<SerializedEvtxRecord<T>> {
+        let record_id = self.event_record_id;
+
+        self.try_map_data(serde_json::from_value)
+            .map_err(|e| EvtxError::FailedToParseRecord {
+                record_id,
+                source: Box::new(EvtxError::SerializationError(SerializationError::from(e))),
+            })
+    }
+
+    /// The text of the `<Message>` element of the record, if it has one (for example,
+    /// forwarded events keep the message rendered by their source in `<RenderingInfo>`).
+    /// Otherwise, the first `<Message>` element found in the record is used.
+    ///
+    /// This is only the message embedded in the record, messages are never resolved
+    /// from the manifest of the provider.
+    pub fn message(&self) -> Option<&str> {
+        let event = self.event()?;
+
+        event
+            .get("RenderingInfo")
+            .and_then(|rendering_info| rendering_info.get("Message"))
+            .and_then(element_text)
+            .or_else(|| find_message(event))
+    }
+
+    /// The process and thread ids of the `<Execution>` element of the record,
+    /// or `None` if it is absent (or either id is missing or isn't a valid number).
+    /// Works with and without `ParserSettings::separate_json_attributes`.
+    pub fn execution(&self) -> Option<Execution> {
+        let attributes = self.system_attributes("Execution")?;
+
+        let id = |name: &str| match attributes.get(name)? {
+            serde_json::Value::Number(id) => u32::try_from(id.as_u64()?).ok(),
+            serde_json::Value::String(id) => id.parse().ok(),
+            _ => None,
+        };
+
+        Some(Execution {
+            process_id: id("ProcessID")?,
+            thread_id: id("ThreadID")?,
+        })
+    }
+
+    /// The fields of the `<System>` element of the record, or `None` if it has no `<System>`.
+    /// Works with and without `ParserSettings::separate_json_attributes`,
+    /// and with the annotations of `ParserSettings::render_level_names` and `decode_keywords`.
+    pub fn system(&self) -> Option<SystemData> {
+        let system = self.system_element()?;
+
+        let text = |name: &str| element_value(system.get(name)?).and_then(json_string);
+        let number = |name: &str| element_value(system.get(name)?).and_then(json_number);
+        let attribute = |element: &str, name: &str| {
+            self.system_attributes(element)?
+                .get(name)
+                .and_then(json_string)
+        };
+
+        Some(SystemData {
+            provider_name: attribute("Provider", "Name"),
+            provider_guid: attribute("Provider", "Guid"),
+            event_id: number("EventID").and_then(|id| u32::try_from(id).ok()),
+            event_id_qualifiers: self
+                .system_attributes("EventID")
+                .and_then(|attributes| json_number(attributes.get("Qualifiers")?))
+                .and_then(|qualifiers| u16::try_from(qualifiers).ok()),
+            version: number("Version").and_then(|version| u8::try_from(version).ok()),
+            level: number("Level").and_then(|level| u8::try_from(level).ok()),
+            task: number("Task").and_then(|task| u16::try_from(task).ok()),
+            opcode: number("Opcode").and_then(|opcode| u8::try_from(opcode).ok()),
+            keywords: number("Keywords"),
+            time_created: attribute("TimeCreated", "SystemTime").and_then(|time| {
+                DateTime::parse_from_rfc3339(&time)
+                    .ok()
+                    .map(|time| time.with_timezone(&Utc))
+            }),
+            event_record_id: number("EventRecordID"),
+            correlation_activity_id: attribute("Correlation", "ActivityID"),
+            correlation_related_activity_id: attribute("Correlation", "RelatedActivityID"),
+            execution: self.execution(),
+            channel: text("Channel"),
+            computer: text("Computer"),
+            security_user_id: self.security_user_id.clone(),
+        })
+    }
+
+    /// The `Event` root element of the record. With `MultiFragmentMode::WrapInArray`,
+    /// the JSON of the record is an array of its root elements, and the first `Event` is used.
+    fn event(&self) -> Option<&serde_json::Value> {
+        match &self.data {
+            serde_json::Value::Array(fragments) => {
+                fragments.iter().find_map(|fragment| fragment.get("Event"))
+            }
+            data => data.get("Event"),
+        }
+    }
+
+    fn system_element(&self) -> Option<&serde_json::Value> {
+        let event = first_occurrence(self.event()?);
+
+        Some(first_occurrence(event.get("System")?))
+    }
+
+    /// The attributes of an element of `<System>`, in either JSON layout.
+    fn system_attributes(&self, name: &str) -> Option<&serde_json::Value> {
+        let system = self.system_element()?;
+
+        let attributes = match system.get(format!("{}_attributes", name).as_str()) {
+            Some(attributes) => attributes,
+            None => first_occurrence(system.get(name)?).get("#attributes")?,
+        };
+
+        Some(first_occurrence(attributes))
+    }
+}
+
+/// The first occurrence of an element, which is an array if it is repeated
+/// (or with `ParserSettings::always_array_repeated`).
+fn first_occurrence(element: &serde_json::Value) -> &serde_json::Value {
+    match element {
+        serde_json::Value::Array(occurrences) => occurrences.first().unwrap_or(element),
+        _ => element,
+    }
+}
+
+/// Depth-first search for a `Message` element with text.
+fn find_message(value: &serde_json::Value) -> Option<&str> {
+    match value {
+        serde_json::Value::Object(map) => map.iter().find_map(|(key, value)| match key.as_str() {
+            "Message" => element_text(value).or_else(|| find_message(value)),
+            _ => find_message(value),
+        }),
+        serde_json::Value::Array(values) => values.iter().find_map(find_message),
+        _ => None,
+    }
+}
+
+/// The text of an element, with or without attributes.
+fn element_text(element: &serde_json::Value) -> Option<&str> {
+    match element {
+        serde_json::Value::String(text) => Some(text),
+        serde_json::Value::Object(map) => map.get("#text").and_then(serde_json::Value::as_str),
+        _ => None,
+    }
+}
+
+/// The value of an element, which is an object if it has attributes.
+fn element_value(element: &serde_json::Value) -> Option<&serde_json::Value> {
+    match first_occurrence(element) {
+        serde_json::Value::Object(map) => map.get("#text"),
+        serde_json::Value::Null => None,
+        value => Some(value),
+    }
+}
+
+/// A non-empty string value.
+fn json_string(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) if !s.is_empty() => Some(s.clone()),
+        _ => None,
+    }
+}
+
+/// A number, or a string with a decimal or a hex (`0x` prefixed) number.
+fn json_number(value: &serde_json::Value) -> Option<u64> {
+    match value {
+        serde_json::Value::Number(n) => n.as_u64(),
+        serde_json::Value::String(s) => match s.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16).ok(),
+            None => s.parse().ok(),
+        },
+        _ => None,
+    }
+}
+
+fn json_to_string(value: &serde_json::Value, indent: bool) -> SerializationResult<String> {
+    let json = if indent {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    };
+
+    Ok(json?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binxml::name::BinXmlName;
+    use crate::ensure_env_logger_initialized;
+    use crate::err::SerializationResult;
+    use crate::model::xml::{BinXmlPI, XmlElement};
+    use crate::{EvtxParser, MessageTableProvider, MultiFragmentMode, NullRendering};
+    use std::borrow::Cow;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_output_is_told_about_the_record_before_it_starts() {
+        #[derive(Default)]
+        struct ElementsPerRecord(Vec<(u64, DateTime<Utc>, usize)>);
+
+        impl BinXmlOutput for ElementsPerRecord {
+            fn visit_start_of_record(
+                &mut self,
+                event_record_id: u64,
+                timestamp: DateTime<Utc>,
+            ) -> SerializationResult<()> {
+                self.0.push((event_record_id, timestamp, 0));
+                Ok(())
+            }
+            fn visit_open_start_element(&mut self, _: &XmlElement) -> SerializationResult<()> {
+                self.0.last_mut().expect("record to start first").2 += 1;
+                Ok(())
+            }
+            fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
+                Ok(())
+            }
+            fn visit_close_element(&mut self, _: &XmlElement) -> SerializationResult<()> {
+                Ok(())
+            }
+            fn visit_characters(&mut self, _: &BinXmlValue) -> SerializationResult<()> {
+                Ok(())
+            }
+            fn visit_cdata_section(&mut self, _: Cow<'_, str>) -> SerializationResult<()> {
+                Ok(())
+            }
+            fn visit_entity_reference(&mut self, _: &BinXmlName) -> SerializationResult<()> {
+                Ok(())
+            }
+            fn visit_character_reference(&mut self, _: Cow<'_, str>) -> SerializationResult<()> {
+                Ok(())
+            }
+            fn visit_processing_instruction(&mut self, _: &BinXmlPI) -> SerializationResult<()> {
+                Ok(())
+            }
+            fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
+                Ok(())
+            }
+        }
+
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let settings = std::sync::Arc::new(ParserSettings::default());
+
+        let mut output = ElementsPerRecord::default();
+        let mut chunk = parser.chunks().next().unwrap().unwrap();
+        let mut chunk = chunk.parse(settings).unwrap();
+        let records: Vec<_> = chunk.iter().map(|r| r.unwrap()).collect();
+        let expected: Vec<_> = records
+            .iter()
+            .map(|r| (r.event_record_id, r.timestamp))
+            .collect();
+
+        for record in records {
+            record.into_output(&mut output).unwrap();
+        }
+
+        assert_eq!(
+            output
+                .0
+                .iter()
+                .map(|(id, timestamp, _)| (*id, *timestamp))
+                .collect::<Vec<_>>(),
+            expected
+        );
+        assert!(output.0.iter().all(|(_, _, elements)| *elements > 0));
+    }
+
+    #[test]
+    fn test_template_id() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let settings = std::sync::Arc::new(ParserSettings::new().include_template_id(true));
+
+        let mut chunk = parser.chunks().next().unwrap().unwrap();
+        let mut chunk = chunk.parse(settings).unwrap();
+
+        for record in chunk.iter() {
+            let record = record.unwrap();
+            let template_def_offset = record
+                .tokens
+                .iter()
+                .find_map(|token| match token {
+                    BinXMLDeserializedTokens::TemplateInstance(template) => {
+                        Some(template.template_def_offset)
+                    }
+                    _ => None,
+                })
+                .unwrap();
+            let template = record
+                .chunk
+                .template_table
+                .get_template(template_def_offset)
+                .unwrap();
+
+            // The id is the first part of the template's GUID.
+            let template_id = record.template_id().unwrap();
+            assert!(template
+                .header
+                .guid
+                .to_string()
+                .starts_with(&format!("{:08X}", template_id)));
+
+            assert_eq!(record.into_xml().unwrap().template_id, Some(template_id));
+        }
+
+        let record = parser.records().next().unwrap().unwrap();
+        assert_eq!(record.template_id, None);
+    }
+
+    #[test]
+    fn test_substitutions() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let settings = std::sync::Arc::new(ParserSettings::default());
+
+        let mut chunk = parser.chunks().next().unwrap().unwrap();
+        let mut chunk = chunk.parse(settings).unwrap();
+        let records: Vec<_> = chunk.iter().map(|r| r.unwrap()).collect();
+
+        for record in &records {
+            let substitutions = record.substitutions();
+
+            assert!(!substitutions.is_empty());
+            // `<EventRecordID>` is filled from the substitutions.
+            assert!(substitutions
+                .iter()
+                .any(|value| **value == BinXmlValue::UInt64Type(record.event_record_id)));
+        }
+    }
+
+    #[test]
+    fn test_peek_event_id() {
+        ensure_env_logger_initialized();
+        let settings = std::sync::Arc::new(ParserSettings::default());
+
+        // Records of `system.evtx` have `Qualifiers`, and the records of the second sample
+        // are built from nested templates.
+        let evtx_files: [&[u8]; 3] = [
+            include_bytes!("../samples/security.evtx"),
+            include_bytes!("../samples/system.evtx"),
+            include_bytes!(
+                "../samples/E_Windows_system32_winevt_logs_Microsoft-Windows-Shell-Core%4Operational.evtx"
+            ),
+        ];
+
+        for evtx_file in evtx_files.iter() {
+            let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+            for chunk in parser.chunks() {
+                let mut chunk = chunk.unwrap();
+                let mut chunk = chunk.parse(std::sync::Arc::clone(&settings)).unwrap();
+
+                for record in chunk.iter().map(|r| r.unwrap()) {
+                    let event_id = record.peek_event_id();
+                    let json = record.into_json_value().unwrap().data;
+                    let expected = &json["Event"]["System"]["EventID"];
+
+                    assert!(event_id.is_some());
+                    assert_eq!(
+                        expected.as_u64().or_else(|| expected["#text"].as_u64()),
+                        event_id,
+                        "{}",
+                        json
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_collect_stats() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let settings = std::sync::Arc::new(ParserSettings::new().collect_stats(true));
+
+        let mut chunk = parser.chunks().next().unwrap().unwrap();
+        let mut chunk = chunk.parse(settings).unwrap();
+
+        for record in chunk.iter().map(|r| r.unwrap()) {
+            let substitutions = record.substitutions().len();
+            let stats = record.into_json_value().unwrap().stats.unwrap();
+
+            assert!(stats.template_instances >= 1);
+            assert!(stats.substitutions >= substitutions);
+            // `<Event>`, `<System>` and the elements of `<System>`.
+            assert!(stats.depth >= 3);
+        }
+
+        let record = parser.records().next().unwrap().unwrap();
+        assert_eq!(record.stats, None);
+    }
+
+    #[test]
+    fn test_write_records_to_a_shared_writer() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let settings = std::sync::Arc::new(ParserSettings::default());
+
+        let mut chunk = parser.chunks().next().unwrap().unwrap();
+        let mut chunk = chunk.parse(settings).unwrap();
+
+        let mut expected_xml = String::new();
+        let mut expected_json = String::new();
+        for record in chunk.iter() {
+            expected_xml.push_str(&record.unwrap().into_xml().unwrap().data);
+        }
+        for record in chunk.iter() {
+            expected_json.push_str(&record.unwrap().into_json().unwrap().data);
+        }
+
+        let mut xml = Vec::new();
+        let mut json = Vec::new();
+        for record in chunk.iter() {
+            record.unwrap().write_xml(&mut xml).unwrap();
+        }
+        for record in chunk.iter() {
+            record.unwrap().write_json(&mut json).unwrap();
+        }
+
+        assert_eq!(String::from_utf8(xml).unwrap(), expected_xml);
+        assert_eq!(String::from_utf8(json).unwrap(), expected_json);
+    }
+
+    #[test]
+    fn test_is_last_in_chunk() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        for chunk in parser.chunks().take(3) {
+            let mut chunk = chunk.unwrap();
+            let last_event_record_id = chunk.header.last_event_record_id;
+            let mut chunk = chunk.parse(Arc::new(ParserSettings::new())).unwrap();
+
+            let last_records: Vec<_> = chunk
+                .iter()
+                .map(|record| record.unwrap())
+                .filter(|record| record.is_last_in_chunk())
+                .map(|record| record.event_record_id)
+                .collect();
+            assert_eq!(last_records, vec![last_event_record_id]);
+        }
+    }
+
+    #[test]
+    fn test_message() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/Archive-ForwardedEvents-test.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        let record = parser.records_json_value().next().unwrap().unwrap();
+        assert!(record
+            .message()
+            .unwrap()
+            .starts_with("An account failed to log on."));
+
+        let settings = ParserSettings::new().multi_fragment(MultiFragmentMode::WrapInArray);
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(settings);
+        let wrapped_record = parser.records_json_value().next().unwrap().unwrap();
+        assert!(wrapped_record.data.is_array());
+        assert_eq!(wrapped_record.message(), record.message());
+
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        let record = parser.records_json_value().next().unwrap().unwrap();
+        assert_eq!(record.message(), None);
+    }
+
+    #[test]
+    fn test_execution() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let expected = Some(Execution {
+            process_id: 456,
+            thread_id: 460,
+        });
+
+        for settings in [
+            ParserSettings::new(),
+            ParserSettings::new().separate_json_attributes(true),
+            ParserSettings::new().always_array_repeated(true),
+            ParserSettings::new().multi_fragment(MultiFragmentMode::WrapInArray),
+        ] {
+            let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+                .unwrap()
+                .with_configuration(settings);
+
+            let record = parser.records_json_value().next().unwrap().unwrap();
+            assert_eq!(record.execution(), expected);
+        }
+
+        let mut record = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .records_json_value()
+            .next()
+            .unwrap()
+            .unwrap();
+        record.data["Event"]["System"]
+            .as_object_mut()
+            .unwrap()
+            .remove("Execution");
+        assert_eq!(record.execution(), None);
+    }
+
+    #[test]
+    fn test_filetime() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let record = parser.records_json_value().next().unwrap().unwrap();
+
+        // `2016-07-08T18:12:51.6816408Z`, the last digit is lost in `timestamp`.
+        assert_eq!(record.filetime, 131_124_751_716_816_408);
+        assert_eq!(record.timestamp.timestamp_subsec_nanos(), 681_640_000);
+        assert_eq!(
+            record.data["Event"]["System"]["TimeCreated"]["#attributes"]["SystemTime"],
+            "2016-07-08T18:12:51.681640Z"
+        );
+    }
+
+    #[test]
+    fn test_raw_filetimes() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let settings = ParserSettings::new().raw_filetimes(true);
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(settings);
+
+        let record = parser.records_json_value().next().unwrap().unwrap();
+        assert_eq!(
+            record.data["Event"]["System"]["TimeCreated"]["#attributes"]["SystemTime"],
+            131_124_751_716_816_408_u64
+        );
+
+        let record = parser.records().next().unwrap().unwrap();
+        assert!(record
+            .data
+            .contains(r#"<TimeCreated SystemTime="131124751716816408">"#));
+    }
+
+    #[test]
+    fn test_every_output_keeps_the_record_metadata() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let settings = std::sync::Arc::new(
+            ParserSettings::new()
+                .include_raw_bytes(true)
+                .include_template_id(true)
+                .collect_stats(true),
+        );
+
+        let mut chunk = parser.chunks().next().unwrap().unwrap();
+        let mut chunk = chunk.parse(settings).unwrap();
+        let record = chunk.iter().nth(1).unwrap().unwrap();
+
+        let metadata = record
+            .clone()
+            .into_serialized_output(JsonOutput::new(&record.settings))
+            .unwrap()
+            .map_data(|_| ());
+        assert_eq!(metadata.filetime, record.filetime);
+        assert_eq!(metadata.raw.as_deref(), Some(record.raw));
+        assert!(metadata.stats.is_some());
+
+        let outputs = vec![
+            record.clone().into_json_value().unwrap().map_data(|_| ()),
+            record.clone().into_json().unwrap().map_data(|_| ()),
+            record.clone().into_xml().unwrap().map_data(|_| ()),
+            record.clone().into_dual().unwrap().map_data(|_| ()),
+            record.into_xml_and_model().unwrap().map_data(|_| ()),
+        ];
+
+        for output in outputs {
+            assert_eq!(output, metadata);
+        }
+    }
+
+    #[test]
+    fn test_system() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/system.evtx");
+        let system = |settings: ParserSettings| {
+            let records: Vec<_> = EvtxParser::from_buffer(evtx_file.to_vec())
+                .unwrap()
+                .with_configuration(settings)
+                .records_json_value()
+                .take(28)
+                .map(|r| r.unwrap().system().unwrap())
+                .collect();
+
+            // A legacy event, an event of a manifest provider, and an event with a correlation id.
+            vec![records[0].clone(), records[2].clone(), records[27].clone()]
+        };
+
+        let time = |time: &str| Some(time.parse::<DateTime<Utc>>().unwrap());
+        let expected = vec![
+            SystemData {
+                provider_name: Some("EventLog".to_string()),
+                event_id: Some(6009),
+                event_id_qualifiers: Some(32768),
+                level: Some(4),
+                task: Some(0),
+                keywords: Some(0x0080_0000_0000_0000),
+                time_created: time("2017-07-12T17:16:28.214161Z"),
+                event_record_id: Some(1),
+                channel: Some("System".to_string()),
+                computer: Some("WIN-P4SIAA0SQCO".to_string()),
+                ..SystemData::default()
+            },
+            SystemData {
+                provider_name: Some("Microsoft-Windows-Kernel-General".to_string()),
+                provider_guid: Some("A68CA8B7-004F-D7B6-A698-07E2DE0F1F5D".to_string()),
+                event_id: Some(12),
+                version: Some(0),
+                level: Some(4),
+                task: Some(1),
+                opcode: Some(0),
+                keywords: Some(0x8000_0000_0000_0080),
+                time_created: time("2017-07-12T17:16:05.539450Z"),
+                event_record_id: Some(3),
+                execution: Some(Execution {
+                    process_id: 4,
+                    thread_id: 8,
+                }),
+                channel: Some("System".to_string()),
+                computer: Some("WIN-P4SIAA0SQCO".to_string()),
+                security_user_id: Some("S-1-5-18".to_string()),
+                ..SystemData::default()
+            },
+            SystemData {
+                provider_name: Some("Microsoft-Windows-Directory-Services-SAM".to_string()),
+                provider_guid: Some("0D4FDC09-8C27-494A-BDA0-505E4FD8ADAE".to_string()),
+                event_id: Some(16962),
+                version: Some(0),
+                level: Some(4),
+                task: Some(0),
+                opcode: Some(0),
+                keywords: Some(0x8000_0000_0000_0000),
+                time_created: time("2017-07-12T17:16:19.649346Z"),
+                event_record_id: Some(28),
+                correlation_activity_id: Some("8B8726D7-FB32-0002-7C27-878B32FBD201".to_string()),
+                execution: Some(Execution {
+                    process_id: 636,
+                    thread_id: 640,
+                }),
+                channel: Some("System".to_string()),
+                computer: Some("WIN-P4SIAA0SQCO".to_string()),
+                security_user_id: Some("S-1-5-18".to_string()),
+                ..SystemData::default()
+            },
+        ];
+
+        assert_eq!(system(ParserSettings::new()), expected);
+        assert_eq!(
+            system(ParserSettings::new().separate_json_attributes(true)),
+            expected
+        );
+        assert_eq!(
+            system(ParserSettings::new().always_array_repeated(true)),
+            expected
+        );
+        assert_eq!(
+            system(ParserSettings::new().multi_fragment(MultiFragmentMode::WrapInArray)),
+            expected
+        );
+        assert_eq!(
+            system(
+                ParserSettings::new()
+                    .render_level_names(true)
+                    .decode_keywords(true)
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_security_user_id() {
+        ensure_env_logger_initialized();
+        // The first record has a null `UserID`, the third one is logged by `S-1-5-18`.
+        let evtx_file = include_bytes!("../samples/system.evtx");
+        let user_ids = |settings: ParserSettings| {
+            let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+                .unwrap()
+                .with_configuration(settings);
+            let json_user_ids = parser
+                .records_json_value()
+                .take(3)
+                .map(|r| r.unwrap().security_user_id)
+                .collect::<Vec<_>>();
+            let xml_user_ids = parser
+                .records()
+                .take(3)
+                .map(|r| r.unwrap().security_user_id)
+                .collect::<Vec<_>>();
+
+            assert_eq!(json_user_ids, xml_user_ids);
+            json_user_ids
+        };
+
+        let expected = vec![None, None, Some("S-1-5-18".to_string())];
+        assert_eq!(user_ids(ParserSettings::new()), expected);
+        assert_eq!(
+            user_ids(ParserSettings::new().separate_json_attributes(true)),
+            expected
+        );
+        // The SID is kept even if it is rendered as its name.
+        let mut renames = HashMap::new();
+        renames.insert("UserID".to_string(), "User".to_string());
+        assert_eq!(
+            user_ids(
+                ParserSettings::new()
+                    .resolve_well_known_sids(true)
+                    .field_renames(renames)
+            ),
+            expected
+        );
+    }
+
+    fn security_message_tables() -> MessageTableProvider {
+        let mut message_tables = MessageTableProvider::new();
+        message_tables.insert(
+            "Microsoft-Windows-Security-Auditing",
+            4624,
+            "An account was successfully logged on.%n%nAccount Name:%t%6%nLogon Type:%t%9",
+        );
+
+        message_tables
+    }
+
+    #[test]
+    fn test_rendered_message() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        let settings = ParserSettings::new().message_tables(security_message_tables());
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(settings);
+
+        let records: Vec<_> = parser.records().take(2).map(|r| r.unwrap()).collect();
+
+        // Record 1 is a 4608 event, which has no format string.
+        assert_eq!(records[0].rendered_message, None);
+        assert_eq!(
+            records[1].rendered_message.as_deref(),
+            Some("An account was successfully logged on.\r\n\r\nAccount Name:\tSYSTEM\r\nLogon Type:\t0")
+        );
+
+        let record = parser.records_json_value().nth(1).unwrap().unwrap();
+        assert_eq!(record.rendered_message, records[1].rendered_message);
+    }
+
+    #[test]
+    fn test_rendered_message_ignores_output_settings() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        let rendered_message = |settings: ParserSettings| {
+            let settings = settings.message_tables(security_message_tables());
+            let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+                .unwrap()
+                .with_configuration(settings);
+
+            let record = parser.records_json_value().nth(1).unwrap().unwrap();
+
+            record.rendered_message
+        };
+
+        let expected = rendered_message(ParserSettings::new());
+        assert_eq!(
+            expected.as_deref(),
+            Some("An account was successfully logged on.\r\n\r\nAccount Name:\tSYSTEM\r\nLogon Type:\t0")
+        );
+
+        let mut renames = HashMap::new();
+        renames.insert("Data".to_owned(), "Field".to_owned());
+        renames.insert("Provider".to_owned(), "Source".to_owned());
+
+        for settings in &[
+            ParserSettings::new().sort_event_data(true),
+            ParserSettings::new()
+                .sort_event_data(true)
+                .omit_empty_elements(true),
+            ParserSettings::new().omit_empty_elements(true),
+            ParserSettings::new().null_value(NullRendering::OmitKey),
+            ParserSettings::new().field_renames(renames),
+        ] {
+            assert_eq!(
+                rendered_message(settings.clone()),
+                expected,
+                "{:?}",
+                settings
+            );
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use crate::err::Result;
+use crate::evtx_file_header::EvtxFileHeader;
+use crate::evtx_parser::{EVTX_CHUNK_SIZE, EVTX_FILE_HEADER_SIZE};
+use crate::EvtxChunkData;
+
+use std::io::{Seek, SeekFrom, Write};
+
+/// Writes chunks into a new evtx file, with a file header which matches them.
+///
+/// Records reference the templates and strings of their chunk by offset, so they cannot be moved
+/// to another chunk. Instead, whole chunks are copied as they are (keeping their checksums),
+/// which is enough to produce a smaller file with only the chunks of interest.
+/// Carved chunks are not copied, since their headers are invalid.
+/// See `EvtxParser::copy_chunks_to`.
+///
+/// ```rust
+/// # use evtx::{EvtxParser, EvtxWriter};
+/// # let fp = std::path::PathBuf::from(format!("{}/samples/security.evtx", std::env::var("CARGO_MANIFEST_DIR").unwrap()));
+/// let mut parser = EvtxParser::from_path(fp).unwrap();
+/// let mut writer = EvtxWriter::new(std::io::Cursor::new(vec![])).unwrap();
+///
+/// for chunk in parser.chunks().take(2) {
+///     assert!(writer.write_chunk(&chunk.unwrap()).unwrap());
+/// }
+///
+/// let evtx_file = writer.finish().unwrap().into_inner();
+/// assert_eq!(EvtxParser::from_buffer(evtx_file).unwrap().num_chunks(), 2);
+/// ```
+pub struct EvtxWriter<W: Write + Seek> {
+    writer: W,
+    chunk_count: u64,
+    next_record_id: u64,
+}
+
+impl<W: Write + Seek> EvtxWriter<W> {
+    /// Starts a new file at the current position of `writer`.
+    /// The file header is only written by `finish`.
+    pub fn new(mut writer: W) -> Result<Self> {
+        writer.write_all(&[0; EVTX_FILE_HEADER_SIZE])?;
+
+        Ok(EvtxWriter {
+            writer,
+            chunk_count: 0,
+            next_record_id: 1,
+        })
+    }
+
+    /// Appends a copy of the chunk to the file.
+    /// Returns false (and writes nothing) if the chunk is carved.
+    pub fn write_chunk(&mut self, chunk: &EvtxChunkData) -> Result<bool> {
+        if chunk.is_carved() {
+            return Ok(false);
+        }
+
+        let mut data = chunk.data.clone();
+        data.resize(EVTX_CHUNK_SIZE, 0);
+
+        self.writer.write_all(&data)?;
+
+        self.chunk_count += 1;
+        self.next_record_id = self
+            .next_record_id
+            .max(chunk.header.last_event_record_id + 1);
+
+        Ok(true)
+    }
+
+    /// The number of chunks written so far.
+    pub fn chunk_count(&self) -> u64 {
+        self.chunk_count
+    }
+
+    /// Writes the file header, and returns the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        // Files with more chunks are still readable, since the chunk count is not relied upon.
+        let chunk_count = self.chunk_count.min(u64::from(u16::MAX)) as u16;
+        let header = EvtxFileHeader::new(chunk_count, self.next_record_id);
+
+        let end = self.writer.stream_position()?;
+        let start = end - EVTX_FILE_HEADER_SIZE as u64 - self.chunk_count * EVTX_CHUNK_SIZE as u64;
+
+        self.writer.seek(SeekFrom::Start(start))?;
+        self.writer.write_all(&header.to_bytes())?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ensure_env_logger_initialized;
+    use crate::evtx_chunk::EVTX_CHUNK_HEADER_SIZE;
+    use crate::EvtxParser;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_round_trip() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        let evtx_file = parser
+            .copy_chunks_to(Cursor::new(vec![]))
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            evtx_file.len(),
+            EVTX_FILE_HEADER_SIZE + 26 * EVTX_CHUNK_SIZE
+        );
+
+        let header = EvtxFileHeader::from_stream(&mut Cursor::new(&evtx_file)).unwrap();
+        assert_eq!(header.chunk_count, 26);
+        assert_eq!(header.next_record_id, 2262);
+        assert!(header.validate_checksum());
+
+        let mut parser = EvtxParser::from_buffer(evtx_file).unwrap();
+        assert_eq!(parser.records().filter(|r| r.is_ok()).count(), 2261);
+    }
+
+    #[test]
+    fn test_copy_filtered_chunks() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let providers = ["Microsoft-Windows-Eventlog".to_string()];
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .filter_providers(&providers);
+
+        let expected = parser.records().count();
+        let evtx_file = parser
+            .copy_chunks_to(Cursor::new(vec![]))
+            .unwrap()
+            .into_inner();
+
+        let mut parser = EvtxParser::from_buffer(evtx_file)
+            .unwrap()
+            .filter_providers(&providers);
+        assert!(parser.num_chunks() < 26);
+        assert_eq!(parser.records().count(), expected);
+    }
+
+    #[test]
+    fn test_carved_chunks_are_not_copied() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut chunk_data =
+            evtx_file[EVTX_FILE_HEADER_SIZE..EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_SIZE].to_vec();
+        chunk_data[EVTX_CHUNK_HEADER_SIZE + 100] ^= 0xff;
+        let chunk = EvtxChunkData::new_or_carved(chunk_data, true, true);
+        assert!(chunk.is_carved());
+
+        let mut writer = EvtxWriter::new(Cursor::new(vec![])).unwrap();
+        assert!(!writer.write_chunk(&chunk).unwrap());
+        assert_eq!(writer.chunk_count(), 0);
+
+        let evtx_file = writer.finish().unwrap().into_inner();
+        assert_eq!(evtx_file.len(), EVTX_FILE_HEADER_SIZE);
+    }
+}
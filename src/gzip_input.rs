@@ -0,0 +1,89 @@
+use flate2::read::MultiGzDecoder;
+use std::io::{self, Read};
+
+/// The magic bytes at the start of a gzip file (RFC 1952).
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+pub(crate) fn is_gzip(data: &[u8]) -> bool {
+    data.starts_with(&GZIP_MAGIC)
+}
+
+/// The largest decompressed file which is read into memory, so a small file which decompresses
+/// to a huge one (a "gzip bomb") cannot exhaust the memory.
+/// Evtx files are rarely larger than a few GBs.
+pub(crate) const MAX_DECOMPRESSED_SIZE: u64 = 16 * 1024 * 1024 * 1024;
+
+/// Decompresses a gzip file, including files with several members
+/// (as written by `pigz`, `bgzip`, or by concatenating gzip files).
+/// Fails if the decompressed data is larger than `max_size` bytes.
+pub(crate) fn decompress_gzip(data: &[u8], max_size: u64) -> io::Result<Vec<u8>> {
+    let mut buffer = vec![];
+    MultiGzDecoder::new(data)
+        .take(max_size.saturating_add(1))
+        .read_to_end(&mut buffer)?;
+
+    if buffer.len() as u64 > max_size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Decompressed data is larger than {} bytes", max_size),
+        ));
+    }
+
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    #[test]
+    fn test_decompress_gzip() {
+        let data = include_bytes!("../samples/new-user-security.evtx.gz");
+        let expected = include_bytes!("../samples/new-user-security.evtx");
+
+        assert!(is_gzip(data));
+        assert_eq!(
+            decompress_gzip(data, MAX_DECOMPRESSED_SIZE).unwrap(),
+            expected.to_vec()
+        );
+
+        let mut corrupted = data.to_vec();
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 0xff;
+        assert!(decompress_gzip(&corrupted, MAX_DECOMPRESSED_SIZE).is_err());
+
+        assert!(!is_gzip(expected));
+        assert!(decompress_gzip(expected, MAX_DECOMPRESSED_SIZE).is_err());
+        assert!(decompress_gzip(&data[..20], MAX_DECOMPRESSED_SIZE).is_err());
+    }
+
+    #[test]
+    fn test_decompress_multiple_members() {
+        let expected = include_bytes!("../samples/new-user-security.evtx");
+
+        let mut data = vec![];
+        for part in expected.chunks(expected.len() / 2 + 1) {
+            let mut encoder = GzEncoder::new(vec![], Compression::default());
+            encoder.write_all(part).unwrap();
+            data.extend(encoder.finish().unwrap());
+        }
+
+        assert_eq!(
+            decompress_gzip(&data, MAX_DECOMPRESSED_SIZE).unwrap(),
+            expected.to_vec()
+        );
+    }
+
+    #[test]
+    fn test_decompressed_size_is_capped() {
+        let data = include_bytes!("../samples/new-user-security.evtx.gz");
+        let size = include_bytes!("../samples/new-user-security.evtx").len() as u64;
+
+        assert!(decompress_gzip(data, size).is_ok());
+        let err = decompress_gzip(data, size - 1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
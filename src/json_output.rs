@@ -1,24 +1,105 @@
 use crate::err::{SerializationError, SerializationResult};
 
-use crate::binxml::value_variant::BinXmlValue;
-use crate::model::xml::{BinXmlPI, XmlElement};
-use crate::xml_output::BinXmlOutput;
-use crate::ParserSettings;
+use crate::binxml::value_variant::{BinXmlValue, ValueFormat};
+use crate::model::xml::{BinXmlPI, XmlAttribute, XmlElement};
+use crate::xml_output::{entity_text, BinXmlOutput};
+use crate::{MultiFragmentMode, NullRendering, ParserSettings};
 
 use core::borrow::BorrowMut;
 use log::trace;
 use serde_json::{Map, Value};
 use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
 
 use crate::binxml::name::BinXmlName;
 use crate::err::SerializationError::JsonStructureError;
-use quick_xml::events::BytesText;
-use std::mem;
 
+/// Builds a `serde_json::Value` from the binxml of a record, by implementing `BinXmlOutput`
+/// (honoring `ParserSettings::separate_json_attributes`).
+///
+/// This is what `EvtxRecord::into_json_value` (and so `EvtxParser::records_json`) uses,
+/// and it can also be driven directly, without serializing the value to a string:
+///
+/// ```rust
+/// # use evtx::{EvtxParser, JsonOutput, ParserSettings};
+/// # use std::sync::Arc;
+/// # let fp = std::path::PathBuf::from(format!("{}/samples/security.evtx", std::env::var("CARGO_MANIFEST_DIR").unwrap()));
+/// let settings = ParserSettings::new().separate_json_attributes(true);
+/// let mut parser = EvtxParser::from_path(fp).unwrap();
+///
+/// for chunk in parser.chunks() {
+///     let mut chunk = chunk.unwrap();
+///     let mut chunk = chunk.parse(Arc::new(settings.clone())).unwrap();
+///
+///     for record in chunk.iter() {
+///         let mut output = JsonOutput::new(&settings);
+///         record.unwrap().into_output(&mut output).unwrap();
+///
+///         let value = output.into_value().unwrap();
+///         assert!(value["Event"]["System"]["Provider_attributes"].is_object());
+///     }
+/// }
+/// ```
 pub struct JsonOutput {
     map: Value,
-    stack: Vec<String>,
+    stack: Vec<PathNode>,
+    /// The number of times each child element of the root was seen so far.
+    root_children: HashMap<String, usize>,
+    /// See `current_occurrences`.
+    root_occurrences: HashMap<String, usize>,
     separate_json_attributes: bool,
+    always_array_repeated: bool,
+    event_data_by_name: bool,
+    value_format: ValueFormat,
+    /// If set, attributes with a null value are empty strings (see `NullRendering::EmptyString`),
+    /// otherwise they are left out.
+    empty_attributes: bool,
+    /// If enabled, every root element is built into a separate value (see `MultiFragmentMode`).
+    wrap_fragments: bool,
+    /// Elements and attributes with these names are renamed (see `ParserSettings::field_renames`).
+    field_renames: Option<Arc<HashMap<String, String>>>,
+    /// The values of the root elements which were closed so far, if `wrap_fragments` is enabled.
+    fragments: Vec<Value>,
+    /// The offset of the last element which was visited, reported in `JsonStructureError`s.
+    offset: u64,
+}
+
+/// An element on the path to the current element.
+#[derive(Debug)]
+struct PathNode {
+    key: String,
+    /// Set if the element is stored in an array (because it has siblings with the same name).
+    index: Option<usize>,
+    /// The number of times each child element was seen so far.
+    children: HashMap<String, usize>,
+    /// See `JsonOutput::current_occurrences`.
+    occurrences: HashMap<String, usize>,
+    /// Set if the element is only kept if it has content, to the value it was inserted with.
+    lazy_placeholder: Option<Value>,
+    /// If set, the element is only removed if it is the first of its siblings with the same name,
+    /// later occurrences keep their placeholder (see `insert_node_with_attributes`).
+    keep_repeated: bool,
+}
+
+impl PathNode {
+    fn new(key: &str, index: Option<usize>) -> Self {
+        PathNode {
+            key: key.to_owned(),
+            index,
+            children: HashMap::new(),
+            occurrences: HashMap::new(),
+            lazy_placeholder: None,
+            keep_repeated: false,
+        }
+    }
+
+    fn lazy(key: &str, index: Option<usize>, placeholder: Value) -> Self {
+        PathNode {
+            lazy_placeholder: Some(placeholder),
+            ..PathNode::new(key, index)
+        }
+    }
 }
 
 impl JsonOutput {
@@ -26,7 +107,17 @@ impl JsonOutput {
         JsonOutput {
             map: Value::Object(Map::new()),
             stack: vec![],
+            root_children: HashMap::new(),
+            root_occurrences: HashMap::new(),
             separate_json_attributes: settings.should_separate_json_attributes(),
+            always_array_repeated: settings.should_always_array_repeated(),
+            event_data_by_name: settings.should_key_event_data_by_name(),
+            value_format: ValueFormat::from_settings(settings),
+            empty_attributes: settings.get_null_value() == NullRendering::EmptyString,
+            wrap_fragments: settings.get_multi_fragment() == MultiFragmentMode::WrapInArray,
+            field_renames: settings.shared_field_renames(),
+            fragments: vec![],
+            offset: 0,
         }
     }
 
@@ -34,7 +125,9 @@ impl JsonOutput {
     fn get_or_create_current_path(&mut self) -> &mut Value {
         let mut v_temp = self.map.borrow_mut();
 
-        for key in self.stack.iter() {
+        for node in self.stack.iter() {
+            let key = &node.key;
+
             // Current path does not exist yet, we need to create it.
             if v_temp.get(key).is_none() {
                 // Can happen if we have
@@ -78,25 +171,176 @@ impl JsonOutput {
                 }
             }
 
-            v_temp = v_temp.get_mut(key).expect("Loop above inserted this node.")
+            v_temp = v_temp.get_mut(key).expect("Loop above inserted this node.");
+
+            if let Some(index) = node.index {
+                v_temp = array_element(v_temp, index);
+            }
         }
 
         v_temp
     }
 
-    fn get_current_parent(&mut self) -> &mut Value {
-        // Make sure we are operating on created nodes.
-        self.get_or_create_current_path();
+    /// Inserts `placeholder` as the value of the child element `name` of the current element,
+    /// and returns the index of the child if it is stored in an array.
+    ///
+    /// Repeated siblings are collected into an array, like so:
+    ///    <HTTPResponseHeadersInfo>
+    ///        <Header>HTTP/1.1 200 OK</Header>
+    ///        <Header>x-ms-version: 2009-09-19</Header>
+    ///    </HTTPResponseHeadersInfo>
+    ///
+    /// Insertions should look like:
+    ///
+    ///    {"Header": Null}
+    ///    {"Header": String("HTTP/1.1 200 OK")}
+    ///    {"Header": Array([String("HTTP/1.1 200 OK"), Null])}
+    ///    {"Header": Array([String("HTTP/1.1 200 OK"), String("x-ms-version: 2009-09-19")])}
+    ///
+    /// If `lazy` is set, the child is removed when it is closed if it has no content
+    /// (see `remove_if_empty`), so empty placeholders are never left in arrays.
+    fn insert_child(
+        &mut self,
+        name: &str,
+        placeholder: Value,
+        lazy: bool,
+    ) -> SerializationResult<Option<usize>> {
+        let always_array = self.always_array_repeated;
+        let count = *self.current_children().get(name).unwrap_or(&0);
+
+        let current = self.get_or_create_current_path();
+        if current.is_null() {
+            // An element without attributes is a `Null` placeholder until it has children.
+            *current = Value::Object(Map::new());
+        } else if !current.is_object() {
+            // Characters are shifted into the child, see `get_or_create_current_path`.
+            let mut map = Map::new();
+            map.insert(name.to_owned(), current.take());
+
+            *current = Value::Object(map);
+        }
+
+        let container = current
+            .as_object_mut()
+            .expect("current was converted to an object above.");
 
-        let mut v_temp = self.map.borrow_mut();
+        let (index, seen) = match (container.remove(name), count) {
+            (None, _) if !always_array => {
+                container.insert(name.to_owned(), placeholder);
+                (None, 1)
+            }
+            (None, _) => {
+                container.insert(name.to_owned(), Value::Array(vec![placeholder]));
+                (Some(0), 1)
+            }
+            // Characters which were shifted into the child (see `get_or_create_current_path`).
+            (Some(existing), 0) if lazy && !always_array => {
+                container.insert(name.to_owned(), existing);
+                (None, 1)
+            }
+            (Some(Value::Array(mut array)), count) if count > 1 || always_array => {
+                array.push(placeholder);
+                let index = array.len() - 1;
+                container.insert(name.to_owned(), Value::Array(array));
+                (Some(index), count + 1)
+            }
+            // The first occurrence is moved into an array.
+            (Some(first), _) => {
+                container.insert(name.to_owned(), Value::Array(vec![first, placeholder]));
+                (Some(1), 2)
+            }
+        };
+
+        self.current_children().insert(name.to_owned(), seen);
+
+        Ok(index)
+    }
+
+    /// Removes `node`, a lazy child of the current element which was just closed,
+    /// if it still has its placeholder value (an array which is left with a single element
+    /// is replaced by the element, unless `always_array_repeated` is set).
+    fn remove_if_empty(&mut self, node: &PathNode, placeholder: &Value) {
+        let always_array = self.always_array_repeated;
+        let key = node.key.as_str();
+
+        let removed = match self.get_or_create_current_path().as_object_mut() {
+            Some(container) => match (container.get_mut(key), node.index) {
+                (Some(Value::Array(_)), Some(index)) if node.keep_repeated && index > 0 => false,
+                (Some(Value::Array(array)), Some(index))
+                    if index + 1 == array.len() && array[index] == *placeholder =>
+                {
+                    array.pop();
+
+                    if array.is_empty() {
+                        container.remove(key);
+                    } else if array.len() == 1 && !always_array {
+                        let first = array.pop().expect("The array has a single element.");
+                        container.insert(key.to_owned(), first);
+                    }
 
-        for key in self.stack.iter().take(self.stack.len() - 1) {
-            v_temp = v_temp
-                .get_mut(key)
-                .expect("Calling `get_or_create_current_path` ensures that the node was created")
+                    true
+                }
+                (Some(value), None) if value == placeholder => {
+                    container.remove(key);
+                    true
+                }
+                _ => false,
+            },
+            None => false,
+        };
+
+        if removed {
+            if let Some(count) = self.current_children().get_mut(key) {
+                *count -= 1;
+            }
         }
+    }
 
-        v_temp
+    /// With `separate_json_attributes`, the value of an element with attributes is dropped if it
+    /// is empty. Once the element is repeated, this inserts a `null` for each of its occurrences
+    /// which were dropped, so its values stay aligned with the array of its attributes,
+    /// and returns true if later empty occurrences must be kept as `null`
+    /// (see `PathNode::keep_repeated`).
+    ///
+    /// This is only done if every occurrence of the element has attributes, otherwise the values
+    /// and the attributes can't be aligned anyway.
+    fn pad_values(&mut self, name: &str) -> SerializationResult<bool> {
+        let occurrences = self
+            .current_occurrences()
+            .entry(name.to_owned())
+            .or_insert(0);
+        let previous_occurrences = *occurrences;
+        *occurrences += 1;
+
+        let attributes_name = format!("{}_attributes", name);
+        let attribute_maps = *self.current_children().get(&attributes_name).unwrap_or(&0);
+        if attribute_maps != previous_occurrences {
+            return Ok(false);
+        }
+
+        let values = *self.current_children().get(name).unwrap_or(&0);
+        for _ in values..previous_occurrences {
+            self.insert_child(name, Value::Null, false)?;
+        }
+
+        Ok(true)
+    }
+
+    /// The number of times each child of the current element was seen so far,
+    /// including children which were dropped (used by `pad_values`).
+    fn current_occurrences(&mut self) -> &mut HashMap<String, usize> {
+        match self.stack.last_mut() {
+            Some(node) => &mut node.occurrences,
+            None => &mut self.root_occurrences,
+        }
+    }
+
+    /// The number of times each child of the current element was seen so far.
+    fn current_children(&mut self) -> &mut HashMap<String, usize> {
+        match self.stack.last_mut() {
+            Some(node) => &mut node.children,
+            None => &mut self.root_children,
+        }
     }
 
     /// Like a regular node, but uses it's "Name" attribute.
@@ -112,9 +356,11 @@ impl JsonOutput {
 
                 self.insert_node_without_attributes(element, &data_key)
             }
-            // Ignore this node
+            // Ignore the attributes of this node
             None => {
-                self.stack.push("Data".to_owned());
+                let placeholder = Value::Object(Map::new());
+                let index = self.insert_child("Data", placeholder.clone(), true)?;
+                self.stack.push(PathNode::lazy("Data", index, placeholder));
                 Ok(())
             }
         }
@@ -126,57 +372,15 @@ impl JsonOutput {
         name: &str,
     ) -> SerializationResult<()> {
         trace!("insert_node_without_attributes");
-        self.stack.push(name.to_owned());
-
-        let container = self.get_current_parent().as_object_mut().ok_or_else(|| {
-            SerializationError::JsonStructureError {
-                message:
-                    "This is a bug - expected parent container to exist, and to be an object type.\
-                     Check that the referencing parent is not `Value::null`"
-                        .to_string(),
-            }
-        })?;
-
-        // We do a linear probe in case XML contains duplicate keys like so:
-        //    <HTTPResponseHeadersInfo>
-        //        <Header>HTTP/1.1 200 OK</Header>
-        //        <Header>x-ms-version: 2009-09-19</Header>
-        //        <Header>x-ms-lease-status: unlocked</Header>
-        //        <Header>x-ms-blob-type: BlockBlob</Header>
-        //    </HTTPResponseHeadersInfo>
-        //
-        // Insertions should look like:
-        //
-        //    {"Header": Object({})}
-        //    {"Header": String("HTTP/1.1 200 OK")}
-        //    {"Header": String("x-ms-version: 2009-09-19"), "Header_1": String("HTTP/1.1 200 OK")}
-        //   ....
-        //
-        if let Some(old_value) = container.insert(name.to_string(), Value::Null) {
-            // Value should move to next slot, key should remain free to allow for next value.
-
-            // If old value is a placeholder, we don't yet move it, to avoid creating empty placeholers.
-            // A placeholder can be either a `Null` or an empty Map.
-            if old_value.is_null() {
-                return Ok(());
-            }
-
-            if let Some(map) = old_value.as_object() {
-                if map.is_empty() {
-                    return Ok(());
-                }
-            }
-
-            let mut free_slot = 1;
-
-            // If it is a concrete value, we look for another slot.
-            while container.get(&format!("{}_{}", name, free_slot)).is_some() {
-                // Value is an empty object - we can override it's value.
-                free_slot += 1
-            }
+        if self.separate_json_attributes {
+            *self
+                .current_occurrences()
+                .entry(name.to_owned())
+                .or_insert(0) += 1;
+        }
 
-            container.insert(format!("{}_{}", name, free_slot), old_value);
-        };
+        let index = self.insert_child(name, Value::Null, false)?;
+        self.stack.push(PathNode::new(name, index));
 
         Ok(())
     }
@@ -187,87 +391,133 @@ impl JsonOutput {
         name: &str,
     ) -> SerializationResult<()> {
         trace!("insert_node_with_attributes");
-        self.stack.push(name.to_owned());
-
         let mut attributes = Map::new();
 
         for attribute in element.attributes.iter() {
-            let value = attribute.value.clone().into_owned();
-            let value: Value = value.into();
+            let value: Value = attribute.value.to_json_value_with_format(self.value_format);
 
             if !value.is_null() {
                 let name: &str = attribute.name.as_str();
                 attributes.insert(name.to_owned(), value);
+            } else if self.empty_attributes {
+                let name: &str = attribute.name.as_str();
+                attributes.insert(name.to_owned(), Value::String(String::new()));
             }
         }
 
         // If we have attributes, create a map as usual.
-        if !attributes.is_empty() {
+        let mut lazy = false;
+        let mut keep_repeated = false;
+        let index = if !attributes.is_empty() {
             if self.separate_json_attributes {
                 // If we are separating the attributes we want
                 // to insert the object for the attributes
                 // into the parent.
-                let value = self.get_current_parent().as_object_mut().ok_or_else(|| {
-                    SerializationError::JsonStructureError {
-                    message:
-                        "This is a bug - expected current value to exist, and to be an object type.
-                        Check that the value is not `Value::null`"
-                            .to_string(),
-                }
-                })?;
-
-                value.insert(format!("{}_attributes", name), Value::Object(attributes));
-
-                // If the element's main value is empty, we want to remove it because we
-                // do not want the value to represent an empty object.
-                if value[name] == Value::Object(Map::new()) {
-                    value.remove(name);
-                }
+                // The element's main value is only kept if it has content,
+                // because we do not want the value to represent an empty object.
+                // Empty occurrences of repeated elements are kept as `null` (see `pad_values`).
+                lazy = true;
+                keep_repeated = self.pad_values(name)?;
+                let index = self.insert_child(name, Value::Null, true)?;
+                self.insert_child(
+                    &format!("{}_attributes", name),
+                    Value::Object(attributes),
+                    false,
+                )?;
+
+                index
             } else {
-                let value = self
-                    .get_or_create_current_path()
-                    .as_object_mut()
-                    .ok_or_else(|| {
-                        SerializationError::JsonStructureError {
-                    message:
-                        "This is a bug - expected current value to exist, and to be an object type.
-                            Check that the value is not `Value::null`"
-                            .to_string(),
-                }
-                    })?;
-
+                let mut value = Map::new();
                 value.insert("#attributes".to_owned(), Value::Object(attributes));
+
+                self.insert_child(name, Value::Object(value), false)?
             }
         } else {
             // If the object does not have attributes, replace it with a null placeholder,
             // so it will be printed as a key-value pair
-            let value =
-                self.get_current_parent()
-                    .as_object_mut()
-                    .ok_or(SerializationError::JsonStructureError {
-                    message:
-                        "This is a bug - expected current value to exist, and to be an object type.
-                         Check that the value is not `Value::null`"
-                            .to_string(),
-                })?;
-
-            value.insert(name.to_string(), Value::Null);
+            self.insert_child(name, Value::Null, false)?
+        };
+
+        if lazy {
+            self.stack.push(PathNode {
+                keep_repeated,
+                ..PathNode::lazy(name, index, Value::Null)
+            });
+        } else {
+            self.stack.push(PathNode::new(name, index));
         }
 
         Ok(())
     }
 
+    /// Returns the value built so far, fails if there are elements which were not closed.
+    ///
+    /// With `MultiFragmentMode::WrapInArray`, this is an array with the value of every root element.
     pub fn into_value(self) -> SerializationResult<Value> {
         if !self.stack.is_empty() {
             return Err(SerializationError::JsonStructureError {
                 message: "Invalid stream, EOF reached before closing all attributes".to_string(),
+                offset: self.offset,
             });
         }
 
+        if self.wrap_fragments {
+            let mut fragments = self.fragments;
+            // Characters (or processing instructions) after the last root element.
+            if !matches!(&self.map, Value::Object(map) if map.is_empty()) {
+                fragments.push(self.map);
+            }
+
+            return Ok(Value::Array(fragments));
+        }
+
         Ok(self.map)
     }
 }
 
+/// Returns the element at `index` of an array of repeated elements,
+/// converting `value` to an array if a non-standard structure replaced it.
+fn array_element(value: &mut Value, index: usize) -> &mut Value {
+    if !value.is_array() {
+        *value = Value::Array(vec![value.take()]);
+    }
+
+    let array = value
+        .as_array_mut()
+        .expect("Value was converted to an array.");
+
+    while array.len() <= index {
+        array.push(Value::Null);
+    }
+
+    &mut array[index]
+}
+
+/// A copy of `element`, with the element and attribute names which are mapped in `renames`
+/// renamed, other names are kept.
+fn rename_element<'a>(
+    element: &XmlElement<'a>,
+    renames: &HashMap<String, String>,
+) -> XmlElement<'a> {
+    let rename = |name: &Cow<'a, BinXmlName>| match renames.get(name.as_str()) {
+        Some(new_name) => Cow::Owned(BinXmlName::from_string(new_name.clone())),
+        None => name.clone(),
+    };
+
+    XmlElement {
+        name: rename(&element.name),
+        attributes: element
+            .attributes
+            .iter()
+            .map(|attribute| XmlAttribute {
+                name: rename(&attribute.name),
+                value: attribute.value.clone(),
+            })
+            .collect(),
+        offset: element.offset,
+    }
+}
+
 impl BinXmlOutput for JsonOutput {
     fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
         trace!("visit_end_of_stream");
@@ -276,9 +526,19 @@ impl BinXmlOutput for JsonOutput {
 
     fn visit_open_start_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
         trace!("visit_open_start_element: {:?}", element.name);
+        self.offset = element.offset;
+
+        let renamed;
+        let element = match &self.field_renames {
+            Some(renames) => {
+                renamed = rename_element(element, renames);
+                &renamed
+            }
+            None => element,
+        };
         let element_name = element.name.as_str();
 
-        if element_name == "Data" {
+        if element_name == "Data" && self.event_data_by_name {
             return self.insert_data_node(element);
         }
 
@@ -290,9 +550,24 @@ impl BinXmlOutput for JsonOutput {
         self.insert_node_with_attributes(element, element_name)
     }
 
-    fn visit_close_element(&mut self, _element: &XmlElement) -> SerializationResult<()> {
+    fn visit_close_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        self.offset = element.offset;
         let p = self.stack.pop();
         trace!("visit_close_element: {:?}", p);
+
+        if let Some(node) = p {
+            if let Some(placeholder) = &node.lazy_placeholder {
+                self.remove_if_empty(&node, placeholder);
+            }
+        }
+
+        if self.wrap_fragments && self.stack.is_empty() {
+            self.fragments
+                .push(std::mem::replace(&mut self.map, Value::Object(Map::new())));
+            self.root_children.clear();
+            self.root_occurrences.clear();
+        }
+
         Ok(())
     }
 
@@ -300,13 +575,22 @@ impl BinXmlOutput for JsonOutput {
         trace!("visit_chars {:?}", &self.stack);
         // We need to clone this bool since the next statement will borrow self as mutable.
         let separate_json_attributes = self.separate_json_attributes;
+        let value_format = self.value_format;
+        let offset = self.offset;
         let current_value = self.get_or_create_current_path();
 
         // If our parent is an element without any attributes,
         // we simply swap the null with the string value.
         // This is also true for the case when the attributes were inserted as our siblings.
-        if current_value.is_null() || separate_json_attributes {
-            *current_value = value.clone().into();
+        if current_value.is_null() {
+            *current_value = value.to_json_value_with_format(value_format);
+        } else if let Some(s) = current_value.as_str() {
+            // If we already have a string (because we got two consecutive `Character` events,
+            // for example around an entity reference), concat them.
+            let new_string = s.to_string() + &value.as_cow_str_with_format(value_format);
+            *current_value = Value::String(new_string);
+        } else if separate_json_attributes {
+            *current_value = value.to_json_value_with_format(value_format);
         } else {
             // Otherwise,
             // Should look like:
@@ -317,54 +601,43 @@ impl BinXmlOutput for JsonOutput {
             //    },
             //    "#text": "4902"
             //  },
-            if let Some(object) = current_value.as_object_mut() {
-                object.insert("#text".to_owned(), value.clone().into());
-                return Ok(());
+            let object = match current_value.as_object_mut() {
+                Some(object) => object,
+                None => {
+                    return Err(SerializationError::JsonStructureError {
+                        message: format!(
+                            "expected current value to be an object type or a String, found {:?}, value is {:?}",
+                            current_value, value
+                        ),
+                        offset,
+                    })
+                }
             };
 
-            // If we already have a string (because we got two consecutive `Character` events,
-            // Concat them.
-            if let Some(s) = current_value.as_str() {
-                let new_string = s.to_string();
-                mem::replace(
-                    current_value,
-                    Value::String(new_string + &value.as_cow_str()),
-                )
-            } else {
-                return Err(SerializationError::JsonStructureError {
-                    message: format!(
-                        "expected current value to be an object type or a String, found {:?}, value is {:?}",
-                        current_value, value
-                    ),
-                });
+            // Consecutive `Character` events are concatenated, like in elements without attributes.
+            let text = match object.remove("#text") {
+                Some(Value::String(s)) => {
+                    Value::String(s + &value.as_cow_str_with_format(value_format))
+                }
+                _ => value.to_json_value_with_format(value_format),
             };
+            object.insert("#text".to_owned(), text);
         }
 
         Ok(())
     }
 
-    fn visit_cdata_section(&mut self) -> SerializationResult<()> {
-        Err(SerializationError::Unimplemented {
-            message: format!("`{}`: visit_cdata_section", file!()),
-        })
+    fn visit_cdata_section(&mut self, cdata: Cow<'_, str>) -> SerializationResult<()> {
+        // CDATA is plain text in JSON.
+        self.visit_characters(&BinXmlValue::StringType(cdata.into_owned()))
     }
 
     fn visit_entity_reference(&mut self, entity: &BinXmlName) -> Result<(), SerializationError> {
-        // We need to create a BytesText event to access quick-xml's unescape functionality (which is private).
-        // We also terminate the entity.
-        let entity_ref = "&".to_string() + entity.as_str() + ";";
-
-        let xml_event = BytesText::from_escaped_str(&entity_ref);
-        match xml_event.unescaped() {
-            Ok(escaped) => {
-                let as_string = String::from_utf8(escaped.to_vec())
-                    .expect("This cannot fail, since it was a valid string beforehand");
-
-                self.visit_characters(&BinXmlValue::StringType(as_string))?;
-                Ok(())
-            }
-            Err(_) => Err(JsonStructureError {
-                message: format!("Unterminated XML Entity {}", entity_ref),
+        match entity_text(entity) {
+            Some(text) => self.visit_characters(&BinXmlValue::StringType(text)),
+            None => Err(JsonStructureError {
+                message: format!("Unterminated XML Entity &{};", entity.as_str()),
+                offset: self.offset,
             }),
         }
     }
@@ -378,10 +651,24 @@ impl BinXmlOutput for JsonOutput {
         })
     }
 
-    fn visit_processing_instruction(&mut self, _pi: &BinXmlPI) -> Result<(), SerializationError> {
-        Err(SerializationError::Unimplemented {
-            message: format!("`{}`: visit_processing_instruction_data", file!()),
-        })
+    /// Processing instructions are kept as a `#pi` child of their element, like so:
+    ///    <Event><?xml-stylesheet href="style.xsl"?></Event>
+    ///
+    ///    {"Event": {"#pi": {"target": "xml-stylesheet", "data": "href=\"style.xsl\""}}}
+    ///
+    /// (multiple processing instructions in an element are collected into an array).
+    fn visit_processing_instruction(&mut self, pi: &BinXmlPI) -> Result<(), SerializationError> {
+        trace!("visit_processing_instruction: {:?}", pi);
+        let mut value = Map::new();
+        value.insert(
+            "target".to_owned(),
+            Value::String(pi.name.as_str().to_owned()),
+        );
+        value.insert("data".to_owned(), Value::String(pi.data.to_string()));
+
+        self.insert_child("#pi", Value::Object(value), false)?;
+
+        Ok(())
     }
 
     fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
@@ -394,11 +681,13 @@ impl BinXmlOutput for JsonOutput {
 mod tests {
     use crate::binxml::name::BinXmlName;
     use crate::binxml::value_variant::BinXmlValue;
-    use crate::model::xml::{XmlAttribute, XmlElement};
-    use crate::{BinXmlOutput, JsonOutput, ParserSettings};
+    use crate::err::SerializationError;
+    use crate::model::xml::{BinXmlPI, XmlAttribute, XmlElement};
+    use crate::{BinXmlOutput, JsonOutput, MultiFragmentMode, ParserSettings};
     use pretty_assertions::assert_eq;
     use quick_xml::events::{BytesStart, Event};
     use quick_xml::Reader;
+    use serde_json::json;
     use std::borrow::Cow;
 
     fn bytes_to_string(bytes: &[u8]) -> String {
@@ -409,6 +698,7 @@ mod tests {
         XmlElement {
             name: Cow::Owned(BinXmlName::from_str("Dummy")),
             attributes: vec![],
+            offset: 0,
         }
     }
 
@@ -427,6 +717,7 @@ mod tests {
         XmlElement {
             name: Cow::Owned(BinXmlName::from_string(bytes_to_string(event.name()))),
             attributes: attrs,
+            offset: 0,
         }
     }
 
@@ -464,9 +755,21 @@ mod tests {
                         .visit_characters(&BinXmlValue::StringType(bytes_to_string(text.as_ref())))
                         .expect("Text element"),
                     Event::Comment(_) => {}
-                    Event::CData(_) => unimplemented!(),
+                    Event::CData(cdata) => output
+                        .visit_cdata_section(Cow::Owned(bytes_to_string(cdata.as_ref())))
+                        .expect("CDATA section"),
                     Event::Decl(_) => {}
-                    Event::PI(_) => unimplemented!(),
+                    Event::PI(pi) => {
+                        let pi = bytes_to_string(pi.as_ref());
+                        let (target, data) = pi.split_once(' ').unwrap_or((&pi, ""));
+
+                        output
+                            .visit_processing_instruction(&BinXmlPI {
+                                name: Cow::Owned(BinXmlName::from_str(target)),
+                                data: Cow::Borrowed(data),
+                            })
+                            .expect("Processing instruction")
+                    }
                     Event::DocType(_) => {}
                     Event::Eof => {
                         output.visit_end_of_stream().expect("End of stream");
@@ -510,4 +813,349 @@ mod tests {
 
         assert_eq!(xml_to_json(s1, &settings), s2)
     }
+
+    #[test]
+    fn test_separate_attributes_of_empty_repeated_elements_are_aligned() {
+        let to_json = |xml: &str| {
+            let settings = ParserSettings::new()
+                .num_threads(1)
+                .separate_json_attributes(true);
+            serde_json::from_str::<serde_json::Value>(&xml_to_json(xml, &settings)).unwrap()
+        };
+
+        assert_eq!(
+            to_json(r#"<Headers><H a="1">x</H><H a="2"></H><H a="3">z</H><H a="4"></H></Headers>"#),
+            json!({"Headers": {
+                "H": ["x", null, "z", null],
+                "H_attributes": [{"a": "1"}, {"a": "2"}, {"a": "3"}, {"a": "4"}]
+            }})
+        );
+        assert_eq!(
+            to_json(r#"<Headers><H a="1"></H><H a="2">y</H></Headers>"#),
+            json!({"Headers": {"H": [null, "y"], "H_attributes": [{"a": "1"}, {"a": "2"}]}})
+        );
+        // A single empty element only has its attributes.
+        assert_eq!(
+            to_json(r#"<Headers><H a="1"></H></Headers>"#),
+            json!({"Headers": {"H_attributes": {"a": "1"}}})
+        );
+    }
+
+    #[test]
+    fn test_empty_repeated_elements_are_not_collected_into_arrays() {
+        let s1 = r#"
+<Event>
+    <EventData>
+        <Data></Data>
+        <Data>first</Data>
+        <Data></Data>
+    </EventData>
+    <Headers>
+        <Header attribute1="NoProxy"></Header>
+        <Header>HTTP/1.1 200 OK</Header>
+        <Header attribute1="Proxy"></Header>
+        <Header>x-ms-version: 2009-09-19</Header>
+    </Headers>
+</Event>
+"#
+        .trim();
+        let s2 = r##"
+{
+  "Event": {
+    "EventData": {
+      "Data": "first"
+    },
+    "Headers": {
+      "Header": [
+        "HTTP/1.1 200 OK",
+        "x-ms-version: 2009-09-19"
+      ],
+      "Header_attributes": [
+        {
+          "attribute1": "NoProxy"
+        },
+        {
+          "attribute1": "Proxy"
+        }
+      ]
+    }
+  }
+}
+"##
+        .trim();
+
+        let settings = ParserSettings::new()
+            .num_threads(1)
+            .separate_json_attributes(true);
+
+        assert_eq!(xml_to_json(s1, &settings), s2)
+    }
+
+    #[test]
+    fn test_repeated_elements_are_collected_into_arrays() {
+        let s1 = r#"
+<EventData>
+    <Data>first</Data>
+    <Data>second</Data>
+    <Data Name="Param">1</Data>
+    <Data Name="Param">2</Data>
+    <Data Name="Param">3</Data>
+    <Binary>00</Binary>
+</EventData>
+"#
+        .trim();
+        let s2 = r##"
+{
+  "EventData": {
+    "Binary": "00",
+    "Data": [
+      {
+        "#text": "first"
+      },
+      {
+        "#text": "second"
+      }
+    ],
+    "Param": [
+      "1",
+      "2",
+      "3"
+    ]
+  }
+}
+"##
+        .trim();
+
+        let settings = ParserSettings::new().num_threads(1);
+
+        assert_eq!(xml_to_json(s1, &settings), s2)
+    }
+
+    #[test]
+    fn test_event_data_not_keyed_by_name() {
+        let s1 = r#"
+<EventData>
+    <Data Name="TargetUserName">SYSTEM</Data>
+    <Data Name="TargetDomainName">NT AUTHORITY</Data>
+    <Data>unnamed</Data>
+</EventData>
+"#
+        .trim();
+        let s2 = r##"
+{
+  "EventData": {
+    "Data": [
+      {
+        "#attributes": {
+          "Name": "TargetUserName"
+        },
+        "#text": "SYSTEM"
+      },
+      {
+        "#attributes": {
+          "Name": "TargetDomainName"
+        },
+        "#text": "NT AUTHORITY"
+      },
+      "unnamed"
+    ]
+  }
+}
+"##
+        .trim();
+
+        let settings = ParserSettings::new()
+            .num_threads(1)
+            .event_data_by_name(false);
+
+        assert_eq!(xml_to_json(s1, &settings), s2)
+    }
+
+    #[test]
+    fn test_always_array_repeated() {
+        let s1 = r#"
+<Event>
+    <EventID>4624</EventID>
+    <Data Name="Param">1</Data>
+</Event>
+"#
+        .trim();
+        let s2 = r#"
+{
+  "Event": [
+    {
+      "EventID": [
+        "4624"
+      ],
+      "Param": [
+        "1"
+      ]
+    }
+  ]
+}
+"#
+        .trim();
+
+        let settings = ParserSettings::new()
+            .num_threads(1)
+            .always_array_repeated(true);
+
+        assert_eq!(xml_to_json(s1, &settings), s2)
+    }
+
+    #[test]
+    fn test_multiple_fragments() {
+        let xml = r#"<Event><EventID>1</EventID></Event><Event><EventID>2</EventID></Event>"#;
+        let to_json = |mode: MultiFragmentMode| {
+            let settings = ParserSettings::new().num_threads(1).multi_fragment(mode);
+            serde_json::from_str::<serde_json::Value>(&xml_to_json(xml, &settings)).unwrap()
+        };
+
+        assert_eq!(
+            to_json(MultiFragmentMode::Concatenate),
+            json!({"Event": [{"EventID": "1"}, {"EventID": "2"}]})
+        );
+        assert_eq!(
+            to_json(MultiFragmentMode::WrapInArray),
+            json!([{"Event": {"EventID": "1"}}, {"Event": {"EventID": "2"}}])
+        );
+    }
+
+    #[test]
+    fn test_processing_instructions_are_kept() {
+        let s1 = r#"
+<Event>
+    <?xml-stylesheet href="style.xsl"?>
+    <EventID>4624</EventID>
+    <?app first?>
+    <?app second?>
+</Event>
+"#
+        .trim();
+        let s2 = r##"
+{
+  "Event": {
+    "#pi": [
+      {
+        "data": "href=\"style.xsl\"",
+        "target": "xml-stylesheet"
+      },
+      {
+        "data": "first",
+        "target": "app"
+      },
+      {
+        "data": "second",
+        "target": "app"
+      }
+    ],
+    "EventID": "4624"
+  }
+}
+"##
+        .trim();
+
+        let settings = ParserSettings::new().num_threads(1);
+
+        assert_eq!(xml_to_json(s1, &settings), s2)
+    }
+
+    #[test]
+    fn test_structure_errors_report_the_offset_of_the_element() {
+        let settings = ParserSettings::new();
+        let mut output = JsonOutput::new(&settings);
+        let element = XmlElement {
+            offset: 0x1234,
+            ..dummy_event()
+        };
+
+        output.visit_start_of_stream().expect("Start of stream");
+        output
+            .visit_open_start_element(&element)
+            .expect("Open element");
+
+        match output.into_value() {
+            Err(SerializationError::JsonStructureError { offset, .. }) => {
+                assert_eq!(offset, 0x1234)
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    /// Builds the JSON of
+    ///    <A a="1"><B b="2"><C c="3">x &amp; y</C></B></A>
+    /// where the text of `<C>` is visited in three parts (as it is around an entity reference).
+    fn nested_elements_to_json(settings: &ParserSettings) -> serde_json::Value {
+        let mut output = JsonOutput::new(settings);
+        output.visit_start_of_stream().expect("Start of stream");
+
+        for (name, attribute, value) in &[("A", "a", "1"), ("B", "b", "2"), ("C", "c", "3")] {
+            let element = XmlElement {
+                name: Cow::Owned(BinXmlName::from_str(name)),
+                attributes: vec![XmlAttribute {
+                    name: Cow::Owned(BinXmlName::from_str(attribute)),
+                    value: Cow::Owned(BinXmlValue::StringType(value.to_string())),
+                }],
+                offset: 0,
+            };
+            output
+                .visit_open_start_element(&element)
+                .expect("Open element");
+        }
+
+        output
+            .visit_characters(&BinXmlValue::StringType("x ".to_string()))
+            .expect("Text");
+        output
+            .visit_entity_reference(&BinXmlName::from_str("amp"))
+            .expect("Entity reference");
+        output
+            .visit_characters(&BinXmlValue::StringType(" y".to_string()))
+            .expect("Text");
+
+        for _ in 0..3 {
+            output
+                .visit_close_element(&dummy_event())
+                .expect("Close element");
+        }
+
+        output.into_value().expect("Output")
+    }
+
+    #[test]
+    fn test_nested_attributes() {
+        let settings = ParserSettings::new().separate_json_attributes(true);
+
+        assert_eq!(
+            nested_elements_to_json(&settings),
+            json!({
+                "A": {
+                    "B": {
+                        "C": "x & y",
+                        "C_attributes": {"c": "3"}
+                    },
+                    "B_attributes": {"b": "2"}
+                },
+                "A_attributes": {"a": "1"}
+            })
+        );
+
+        let settings = ParserSettings::new().separate_json_attributes(false);
+
+        assert_eq!(
+            nested_elements_to_json(&settings),
+            json!({
+                "A": {
+                    "#attributes": {"a": "1"},
+                    "B": {
+                        "#attributes": {"b": "2"},
+                        "C": {
+                            "#attributes": {"c": "3"},
+                            "#text": "x & y"
+                        }
+                    }
+                }
+            })
+        );
+    }
 }
@@ -1,7 +1,11 @@
 #![deny(unused_must_use)]
 #![cfg_attr(backtraces, feature(backtrace))]
-#![forbid(unsafe_code)]
+// Mapping a file is inherently unsafe, so it is only allowed (locally) when `mmap` is enabled.
+#![cfg_attr(not(feature = "mmap"), forbid(unsafe_code))]
+#![cfg_attr(feature = "mmap", deny(unsafe_code))]
 #![allow(clippy::upper_case_acronyms)]
+// Defaults of enums are implemented by hand, like those which cannot be derived (ex. `IndentStyle`).
+#![allow(clippy::derivable_impls)]
 // Don't allow dbg! prints in release.
 #![cfg_attr(not(debug_assertions), deny(clippy::dbg_macro))]
 // This needs to come first!
@@ -11,11 +15,31 @@ mod macros;
 #[macro_use]
 extern crate bitflags;
 
+pub use binxml::value_variant::{BinaryEncoding, GuidFormat, NullRendering, ValueFormat};
 pub use evtx_chunk::{EvtxChunk, EvtxChunkData, EvtxChunkHeader, IterChunkRecords};
-pub use evtx_parser::{EvtxParser, IntoIterChunks, IterChunks, ParserSettings};
-pub use evtx_record::{EvtxRecord, EvtxRecordHeader, SerializedEvtxRecord};
+pub use evtx_file_header::{EvtxFileHeader, HeaderFlags};
+pub use evtx_parser::{
+    EvtxParser, IntoIterChunks, IterChunks, MultiFragmentMode, OutputFormat, ParserSettings,
+    ReadSeek, ZeroPaddedChunk, SYNTHETIC_RECORD_ID_BASE,
+};
+pub use evtx_record::{
+    DualOutput, EvtxRecord, EvtxRecordHeader, Execution, RecordStats, SerializedEvtxRecord,
+    SystemData,
+};
+pub use evtx_writer::EvtxWriter;
 pub use json_output::JsonOutput;
-pub use xml_output::{BinXmlOutput, XmlOutput};
+pub use message_table::MessageTableProvider;
+pub use multi_file_parser::MultiFileParser;
+#[cfg(feature = "parquet")]
+pub use parquet_output::ParquetSchema;
+#[cfg(feature = "async")]
+pub use record_stream::RecordStream;
+pub use schema_summary::{PathSummary, SchemaSummary};
+pub use sequential_reader::SequentialReader;
+pub use template_cache::SharedTemplateCache;
+pub use utils::{resolve_keyword_names, resolve_level_name, resolve_well_known_sid};
+pub use validation::{ChunkValidation, ValidationReport};
+pub use xml_output::{BinXmlOutput, IndentStyle, XmlOutput};
 
 pub mod binxml;
 pub mod err;
@@ -25,11 +49,24 @@ mod evtx_chunk;
 mod evtx_file_header;
 mod evtx_parser;
 mod evtx_record;
+mod evtx_writer;
+#[cfg(feature = "flate2")]
+mod gzip_input;
+mod message_table;
+mod multi_file_parser;
+mod record_filter;
+mod schema_summary;
+mod sequential_reader;
 mod string_cache;
 mod template_cache;
+mod validation;
 mod utils;
 
 mod json_output;
+#[cfg(feature = "parquet")]
+mod parquet_output;
+#[cfg(feature = "async")]
+mod record_stream;
 mod xml_output;
 
 pub type ChunkOffset = u32;
@@ -152,7 +152,11 @@ macro_rules! try_read {
     }};
 
     ($cursor: ident, len_prefixed_utf_16_str, $name: expr) => {
-        read_len_prefixed_utf16_string($cursor, false)
+        try_read!($cursor, len_prefixed_utf_16_str, $name, false)
+    };
+
+    ($cursor: ident, len_prefixed_utf_16_str, $name: expr, $lossy: expr) => {
+        read_len_prefixed_utf16_string($cursor, false, $lossy)
             .map_err(|e| capture_context!($cursor, e, "len_prefixed_utf_16_str", $name))
     };
 
@@ -161,7 +165,16 @@ macro_rules! try_read {
     }};
 
     ($cursor: ident, len_prefixed_utf_16_str_nul_terminated, $name: expr) => {
-        read_len_prefixed_utf16_string($cursor, true).map_err(|e| {
+        try_read!(
+            $cursor,
+            len_prefixed_utf_16_str_nul_terminated,
+            $name,
+            false
+        )
+    };
+
+    ($cursor: ident, len_prefixed_utf_16_str_nul_terminated, $name: expr, $lossy: expr) => {
+        read_len_prefixed_utf16_string($cursor, true, $lossy).map_err(|e| {
             capture_context!($cursor, e, "len_prefixed_utf_16_str_nul_terminated", $name)
         })
     };
@@ -171,7 +184,11 @@ macro_rules! try_read {
     }};
 
     ($cursor: ident, null_terminated_utf_16_str, $name: expr) => {
-        read_null_terminated_utf16_string($cursor)
+        try_read!($cursor, null_terminated_utf_16_str, $name, false)
+    };
+
+    ($cursor: ident, null_terminated_utf_16_str, $name: expr, $lossy: expr) => {
+        read_null_terminated_utf16_string($cursor, $lossy)
             .map_err(|e| capture_context!($cursor, e, "null_terminated_utf_16_str", $name))
     };
 
@@ -196,9 +213,10 @@ macro_rules! try_read {
     };
 
     ($cursor: ident, filetime, $name: expr) => {
-        winstructs::timestamp::WinTimestamp::from_reader($cursor)
+        $cursor
+            .read_u64::<byteorder::LittleEndian>()
             .map_err(|e| capture_context!($cursor, e, "filetime", $name))
-            .map(|t| t.to_datetime())
+            .map($crate::utils::filetime_to_datetime)
     };
 
     ($cursor: ident, systime) => {
@@ -208,6 +226,14 @@ macro_rules! try_read {
 
 macro_rules! try_read_sized_array {
     ($cursor: ident, $unit: ident, $size: ident) => {{
+        try_read_sized_array!(@read $cursor, $size, try_read!($cursor, $unit))
+    }};
+
+    ($cursor: ident, $unit: ident, $size: ident, $lossy: expr) => {{
+        try_read_sized_array!(@read $cursor, $size, try_read!($cursor, $unit, "<Unknown>", $lossy))
+    }};
+
+    (@read $cursor: ident, $size: ident, $read: expr) => {{
         let mut array = vec![];
         let start_pos = $cursor.position();
 
@@ -216,7 +242,7 @@ macro_rules! try_read_sized_array {
                 break;
             }
 
-            let val = try_read!($cursor, $unit)?;
+            let val = $read?;
             array.push(val);
         }
 
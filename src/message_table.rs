@@ -0,0 +1,289 @@
+use crate::binxml::name::BinXmlName;
+use crate::binxml::value_variant::BinXmlValue;
+use crate::err::{InputError, Result, SerializationError, SerializationResult};
+use crate::model::xml::{BinXmlPI, XmlElement};
+use crate::xml_output::{entity_text, BinXmlOutput};
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+/// Message format strings of event providers, used to render the messages of records
+/// (see `ParserSettings::message_tables`).
+///
+/// Format strings are the ones found in the message tables of the providers' DLLs,
+/// with `%1`, `%2`, ... placeholders for the `<Data>` values of the record's `<EventData>`, in order.
+/// Provider names are matched case-insensitively.
+///
+/// Tables are usually loaded from JSON, mapping each provider to the format strings of its event ids:
+///
+/// ```json
+/// {
+///   "Microsoft-Windows-Security-Auditing": {
+///     "4688": "A new process has been created.\r\n\r\nNew Process Name:\t%6"
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MessageTableProvider {
+    messages: HashMap<String, HashMap<u32, String>>,
+}
+
+impl MessageTableProvider {
+    pub fn new() -> Self {
+        MessageTableProvider::default()
+    }
+
+    /// Reads a JSON mapping of provider names to event ids to format strings (see above).
+    pub fn from_json<R: Read>(reader: R) -> Result<Self> {
+        let messages: HashMap<String, HashMap<u32, String>> =
+            serde_json::from_reader(reader).map_err(SerializationError::from)?;
+
+        let mut tables = MessageTableProvider::new();
+        for (provider, formats) in messages {
+            for (event_id, format) in formats {
+                tables.insert(&provider, event_id, format);
+            }
+        }
+
+        Ok(tables)
+    }
+
+    /// Reads all the `.json` files in `path` (for example, one file for each provider),
+    /// each with a mapping like the one read by `from_json`.
+    pub fn from_dir(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut tables = MessageTableProvider::new();
+
+        let entries = path
+            .read_dir()
+            .map_err(|e| InputError::failed_to_open_file(e, path))?;
+
+        for entry in entries {
+            let entry_path = entry
+                .map_err(|e| InputError::failed_to_open_file(e, path))?
+                .path();
+
+            if entry_path.extension() != Some(OsStr::new("json")) {
+                continue;
+            }
+
+            let file = File::open(&entry_path)
+                .map_err(|e| InputError::failed_to_open_file(e, &entry_path))?;
+
+            tables.extend(MessageTableProvider::from_json(BufReader::new(file))?);
+        }
+
+        Ok(tables)
+    }
+
+    /// Adds (or replaces) the format string of an event of `provider`.
+    pub fn insert(&mut self, provider: &str, event_id: u32, format: impl Into<String>) {
+        self.messages
+            .entry(provider.to_lowercase())
+            .or_default()
+            .insert(event_id, format.into());
+    }
+
+    /// Adds all the format strings of `other`, replacing existing ones.
+    pub fn extend(&mut self, other: MessageTableProvider) {
+        for (provider, formats) in other.messages {
+            self.messages.entry(provider).or_default().extend(formats);
+        }
+    }
+
+    /// The format string of an event of `provider`, if it is known.
+    pub fn get(&self, provider: &str, event_id: u32) -> Option<&str> {
+        self.messages
+            .get(&provider.to_lowercase())?
+            .get(&event_id)
+            .map(String::as_str)
+    }
+
+    /// Renders the message of an event of `provider`, substituting `params` into its format string.
+    pub fn render<S: AsRef<str>>(
+        &self,
+        provider: &str,
+        event_id: u32,
+        params: &[S],
+    ) -> Option<String> {
+        self.get(provider, event_id)
+            .map(|format| format_message(format, params))
+    }
+}
+
+/// Substitutes the inserts of a message table format string, as `FormatMessage` does:
+/// `%1` to `%99` are replaced by the params (with their optional `!printf format!` ignored),
+/// `%n`, `%t`, `%r` and `%b` are a newline, a tab, a carriage return and a space,
+/// `%0` ends the message, and `%` escapes any other character.
+/// Placeholders without a matching param are kept as they are.
+fn format_message<S: AsRef<str>>(format: &str, params: &[S]) -> String {
+    let mut message = String::with_capacity(format.len());
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            message.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('0') => break,
+            Some(digit @ '1'..='9') => {
+                let mut placeholder = String::from(digit);
+                if let Some(second_digit @ '0'..='9') = chars.peek().copied() {
+                    placeholder.push(second_digit);
+                    chars.next();
+                }
+
+                // A printf format, such as `%1!s!`.
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                    for c in chars.by_ref() {
+                        if c == '!' {
+                            break;
+                        }
+                    }
+                }
+
+                let index: usize = placeholder.parse().expect("Only digits were collected");
+                match params.get(index - 1) {
+                    Some(param) => message.push_str(param.as_ref()),
+                    None => {
+                        message.push('%');
+                        message.push_str(&placeholder);
+                    }
+                }
+            }
+            Some('n') => message.push_str("\r\n"),
+            Some('t') => message.push('\t'),
+            Some('r') => message.push('\r'),
+            Some('b') => message.push(' '),
+            Some(other) => message.push(other),
+            None => message.push('%'),
+        }
+    }
+
+    message
+}
+
+/// Collects the provider name, event id and `<Data>` values of a record,
+/// to render its message.
+#[derive(Debug, Default)]
+pub(crate) struct MessageParams {
+    path: Vec<String>,
+    provider: Option<String>,
+    event_id: String,
+    data: Vec<String>,
+}
+
+impl MessageParams {
+    pub fn render(&self, tables: &MessageTableProvider) -> Option<String> {
+        let provider = self.provider.as_ref()?;
+        let event_id = self.event_id.trim().parse().ok()?;
+
+        tables.render(provider, event_id, &self.data)
+    }
+
+    fn is_at(&self, path: &[&str]) -> bool {
+        self.path
+            .iter()
+            .map(String::as_str)
+            .eq(path.iter().copied())
+    }
+}
+
+impl BinXmlOutput for MessageParams {
+    fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
+        Ok(())
+    }
+
+    fn visit_open_start_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        self.path.push(element.name.as_str().to_owned());
+
+        if self.is_at(&["Event", "System", "Provider"]) {
+            self.provider = element
+                .attributes
+                .iter()
+                .find(|attribute| attribute.name.as_str() == "Name")
+                .map(|attribute| attribute.value.as_cow_str().into_owned());
+        } else if self.is_at(&["Event", "EventData", "Data"]) {
+            self.data.push(String::new());
+        }
+
+        Ok(())
+    }
+
+    fn visit_close_element(&mut self, _element: &XmlElement) -> SerializationResult<()> {
+        self.path.pop();
+
+        Ok(())
+    }
+
+    fn visit_characters(&mut self, value: &BinXmlValue) -> SerializationResult<()> {
+        if self.is_at(&["Event", "System", "EventID"]) {
+            self.event_id.push_str(&value.as_cow_str());
+        } else if self.is_at(&["Event", "EventData", "Data"]) {
+            if let Some(data) = self.data.last_mut() {
+                data.push_str(&value.as_cow_str());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn visit_cdata_section(&mut self, cdata: Cow<'_, str>) -> SerializationResult<()> {
+        self.visit_characters(&BinXmlValue::StringType(cdata.into_owned()))
+    }
+
+    fn visit_entity_reference(&mut self, entity: &BinXmlName) -> SerializationResult<()> {
+        match entity_text(entity) {
+            Some(text) => self.visit_characters(&BinXmlValue::StringType(text)),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_character_reference(&mut self, _char_ref: Cow<'_, str>) -> SerializationResult<()> {
+        Ok(())
+    }
+
+    fn visit_processing_instruction(&mut self, _pi: &BinXmlPI) -> SerializationResult<()> {
+        Ok(())
+    }
+
+    fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_message() {
+        let params = ["S-1-5-18", "SYSTEM"];
+
+        assert_eq!(
+            format_message("Account:%t%2 (%1)%n100%% done.%0 ignored", &params),
+            "Account:\tSYSTEM (S-1-5-18)\r\n100% done."
+        );
+        assert_eq!(format_message("%1!s! and %3", &params), "S-1-5-18 and %3");
+    }
+
+    #[test]
+    fn test_from_json() {
+        let json =
+            r#"{"Microsoft-Windows-Eventlog": {"1102": "The audit log was cleared by %2."}}"#;
+        let tables = MessageTableProvider::from_json(json.as_bytes()).unwrap();
+
+        assert_eq!(
+            tables.render("microsoft-windows-eventlog", 1102, &["S-1-5-18", "SYSTEM"]),
+            Some("The audit log was cleared by SYSTEM.".to_string())
+        );
+        assert_eq!(tables.get("Microsoft-Windows-Eventlog", 1100), None);
+    }
+}
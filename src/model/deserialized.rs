@@ -16,7 +16,7 @@ pub enum BinXMLDeserializedTokens<'a> {
     CloseEmptyElement,
     CloseElement,
     Value(BinXmlValue<'a>),
-    CDATASection,
+    CDATASection(String),
     CharRef,
     EntityRef(BinXmlEntityReference),
     PITarget(BinXMLProcessingInstructionTarget),
@@ -24,6 +24,60 @@ pub enum BinXMLDeserializedTokens<'a> {
     Substitution(TemplateSubstitutionDescriptor),
     EndOfStream,
     StartOfStream,
+    /// A token which could not be parsed, kept in place of it when
+    /// `ParserSettings::tolerate_unknown_tokens` is enabled.
+    Unparsed {
+        name: &'static str,
+        offset: u64,
+    },
+}
+
+impl<'a> BinXMLDeserializedTokens<'a> {
+    /// Converts the token to one which does not borrow from the chunk, see `BinXmlValue::into_static`.
+    pub(crate) fn into_static(self) -> BinXMLDeserializedTokens<'static> {
+        match self {
+            BinXMLDeserializedTokens::FragmentHeader(h) => {
+                BinXMLDeserializedTokens::FragmentHeader(h)
+            }
+            BinXMLDeserializedTokens::TemplateInstance(template) => {
+                BinXMLDeserializedTokens::TemplateInstance(BinXmlTemplateRef {
+                    template_id: template.template_id,
+                    template_def_offset: template.template_def_offset,
+                    substitution_array: template
+                        .substitution_array
+                        .into_iter()
+                        .map(BinXMLDeserializedTokens::into_static)
+                        .collect(),
+                })
+            }
+            BinXMLDeserializedTokens::OpenStartElement(e) => {
+                BinXMLDeserializedTokens::OpenStartElement(e)
+            }
+            BinXMLDeserializedTokens::AttributeList => BinXMLDeserializedTokens::AttributeList,
+            BinXMLDeserializedTokens::Attribute(a) => BinXMLDeserializedTokens::Attribute(a),
+            BinXMLDeserializedTokens::CloseStartElement => {
+                BinXMLDeserializedTokens::CloseStartElement
+            }
+            BinXMLDeserializedTokens::CloseEmptyElement => {
+                BinXMLDeserializedTokens::CloseEmptyElement
+            }
+            BinXMLDeserializedTokens::CloseElement => BinXMLDeserializedTokens::CloseElement,
+            BinXMLDeserializedTokens::Value(value) => {
+                BinXMLDeserializedTokens::Value(value.into_static())
+            }
+            BinXMLDeserializedTokens::CDATASection(c) => BinXMLDeserializedTokens::CDATASection(c),
+            BinXMLDeserializedTokens::CharRef => BinXMLDeserializedTokens::CharRef,
+            BinXMLDeserializedTokens::EntityRef(e) => BinXMLDeserializedTokens::EntityRef(e),
+            BinXMLDeserializedTokens::PITarget(t) => BinXMLDeserializedTokens::PITarget(t),
+            BinXMLDeserializedTokens::PIData(d) => BinXMLDeserializedTokens::PIData(d),
+            BinXMLDeserializedTokens::Substitution(s) => BinXMLDeserializedTokens::Substitution(s),
+            BinXMLDeserializedTokens::EndOfStream => BinXMLDeserializedTokens::EndOfStream,
+            BinXMLDeserializedTokens::StartOfStream => BinXMLDeserializedTokens::StartOfStream,
+            BinXMLDeserializedTokens::Unparsed { name, offset } => {
+                BinXMLDeserializedTokens::Unparsed { name, offset }
+            }
+        }
+    }
 }
 
 #[derive(Debug, PartialOrd, PartialEq, Clone)]
@@ -35,6 +89,8 @@ pub struct BinXMLProcessingInstructionTarget {
 pub struct BinXMLOpenStartElement {
     pub data_size: u32,
     pub name: BinXmlNameRef,
+    /// The offset of the token in the chunk.
+    pub offset: u64,
 }
 
 #[derive(Debug, PartialOrd, PartialEq, Clone)]
@@ -69,6 +125,9 @@ pub struct BinXmlEntityReference {
 
 #[derive(Debug, PartialOrd, PartialEq, Clone)]
 pub struct BinXmlTemplateRef<'a> {
+    /// The id of the template, which is the same in every chunk
+    /// (the first 4 bytes of the template definition's GUID).
+    pub template_id: u32,
     pub template_def_offset: ChunkOffset,
     pub substitution_array: Vec<BinXMLDeserializedTokens<'a>>,
 }
@@ -1,3 +1,3 @@
 pub mod deserialized;
 pub(crate) mod raw;
-pub(crate) mod xml;
+pub mod xml;
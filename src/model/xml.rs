@@ -11,6 +11,7 @@ pub enum XmlModel<'a> {
     CloseElement,
     PI(BinXmlPI<'a>),
     EntityRef(Cow<'a, BinXmlName>),
+    CData(Cow<'a, str>),
     Value(Cow<'a, BinXmlValue<'a>>),
     EndOfStream,
     StartOfStream,
@@ -19,6 +20,7 @@ pub enum XmlModel<'a> {
 #[derive(Debug)]
 pub(crate) struct XmlElementBuilder<'a> {
     name: Option<Cow<'a, BinXmlName>>,
+    offset: u64,
     attributes: Vec<XmlAttribute<'a>>,
     current_attribute_name: Option<Cow<'a, BinXmlName>>,
     current_attribute_value: Option<Cow<'a, BinXmlValue<'a>>>,
@@ -28,6 +30,7 @@ impl<'a> XmlElementBuilder<'a> {
     pub fn new() -> Self {
         XmlElementBuilder {
             name: None,
+            offset: 0,
             attributes: Vec::new(),
             current_attribute_name: None,
             current_attribute_value: None,
@@ -37,6 +40,10 @@ impl<'a> XmlElementBuilder<'a> {
         self.name = Some(name);
     }
 
+    pub fn offset(&mut self, offset: u64) {
+        self.offset = offset;
+    }
+
     pub fn attribute_name(&mut self, name: Cow<'a, BinXmlName>) {
         match self.current_attribute_name {
             None => self.current_attribute_name = Some(name),
@@ -77,6 +84,7 @@ impl<'a> XmlElementBuilder<'a> {
                 "Element name should be set",
             ))?,
             attributes: self.attributes,
+            offset: self.offset,
         })
     }
 }
@@ -119,6 +127,8 @@ pub struct XmlAttribute<'a> {
 pub struct XmlElement<'a> {
     pub name: Cow<'a, BinXmlName>,
     pub attributes: Vec<XmlAttribute<'a>>,
+    /// The offset of the `OpenStartElement` token of the element in the chunk.
+    pub offset: u64,
 }
 
 #[derive(Debug, PartialOrd, PartialEq, Clone)]
@@ -0,0 +1,252 @@
+use crate::err::Result;
+use crate::evtx_parser::ReadSeek;
+use crate::{EvtxParser, ParserSettings, SerializedEvtxRecord};
+
+use std::iter::Peekable;
+
+/// Parses several evtx files as a single stream of records, for example a log and its
+/// rolled-over files (`Security.evtx`, `Security.evtx.1`, ...).
+///
+/// Files are read in the order of their first record id, and records are yielded in ascending
+/// record id order (every file is read with `ParserSettings::ordered_output`).
+/// Records which appear in more than one file (at the boundaries of a rollover) are only yielded once,
+/// any other record is yielded, even if its id is out of order (as in dirty files)
+/// or is repeated within its file (as with carved records).
+///
+/// Errors have no record id, so they are yielded as soon as they are reached.
+///
+/// ```rust
+/// # use evtx::EvtxParser;
+/// # let fp = std::path::PathBuf::from(format!("{}/samples/security.evtx", std::env::var("CARGO_MANIFEST_DIR").unwrap()));
+/// let mut parser = EvtxParser::from_paths(&[fp]).unwrap();
+///
+/// for record in parser.records() {
+///     println!("Record {}", record.unwrap().event_record_id);
+/// }
+/// ```
+pub struct MultiFileParser<T: ReadSeek> {
+    parsers: Vec<EvtxParser<T>>,
+}
+
+impl<T: ReadSeek> MultiFileParser<T> {
+    /// Combines the given parsers, which are reordered by the first record id of their files.
+    pub fn from_parsers(parsers: Vec<EvtxParser<T>>) -> Self {
+        let mut parsers: Vec<(Option<u64>, EvtxParser<T>)> = parsers
+            .into_iter()
+            .map(|mut parser| (parser.first_record_id(), parser.with_ordered_output()))
+            .collect();
+
+        // Files without any records are read last.
+        parsers.sort_by_key(|(first_record_id, _)| first_record_id.unwrap_or(u64::MAX));
+
+        MultiFileParser {
+            parsers: parsers.into_iter().map(|(_, parser)| parser).collect(),
+        }
+    }
+
+    /// Sets the configuration of all the parsers (`ordered_output` is always enabled).
+    pub fn with_configuration(mut self, configuration: ParserSettings) -> Self {
+        let configuration = configuration.ordered_output(true);
+
+        self.parsers = self
+            .parsers
+            .into_iter()
+            .map(|parser| parser.with_configuration(configuration.clone()))
+            .collect();
+
+        self
+    }
+
+    /// The parsers of the files, in the order they are read.
+    pub fn parsers(&self) -> &[EvtxParser<T>] {
+        &self.parsers
+    }
+
+    /// Return an iterator over all the records of all the files.
+    /// Records will be XML-formatted.
+    pub fn records(&mut self) -> impl Iterator<Item = Result<SerializedEvtxRecord<String>>> + '_ {
+        merge_by_record_id(
+            self.parsers
+                .iter_mut()
+                .map(|parser| parser.records().peekable())
+                .collect(),
+        )
+    }
+
+    /// Return an iterator over all the records of all the files.
+    /// Records will be JSON-formatted.
+    pub fn records_json(
+        &mut self,
+    ) -> impl Iterator<Item = Result<SerializedEvtxRecord<String>>> + '_ {
+        merge_by_record_id(
+            self.parsers
+                .iter_mut()
+                .map(|parser| parser.records_json().peekable())
+                .collect(),
+        )
+    }
+
+    /// Return an iterator over all the records of all the files.
+    /// Records will have a `serde_json::Value` data attribute.
+    pub fn records_json_value(
+        &mut self,
+    ) -> impl Iterator<Item = Result<SerializedEvtxRecord<serde_json::Value>>> + '_ {
+        merge_by_record_id(
+            self.parsers
+                .iter_mut()
+                .map(|parser| parser.records_json_value().peekable())
+                .collect(),
+        )
+    }
+}
+
+/// Merges iterators of records which are each ordered by record id,
+/// skipping records with the same id as the record which was just yielded from another iterator.
+///
+/// Since the iterators are ordered, records which are in several files are reached one after
+/// the other, so only the last yielded record has to be kept.
+fn merge_by_record_id<U, I: Iterator<Item = Result<SerializedEvtxRecord<U>>>>(
+    mut iterators: Vec<Peekable<I>>,
+) -> impl Iterator<Item = Result<SerializedEvtxRecord<U>>> {
+    // The id of the last yielded record, and the iterator it was taken from.
+    let mut last_yielded: Option<(u64, usize)> = None;
+
+    std::iter::from_fn(move || loop {
+        // Ties are taken from the earlier file, errors are taken first.
+        let (_, next) = iterators
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(i, iterator)| match iterator.peek()? {
+                Ok(record) => Some((Some(record.event_record_id), i)),
+                Err(_) => Some((None, i)),
+            })
+            .min()?;
+
+        let record = iterators[next].next()?;
+
+        if let Ok(record) = &record {
+            match last_yielded {
+                Some((id, source)) if id == record.event_record_id && source != next => continue,
+                _ => last_yielded = Some((record.event_record_id, next)),
+            }
+        }
+
+        return Some(record);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ensure_env_logger_initialized;
+    use crate::evtx_parser::{EVTX_CHUNK_SIZE, EVTX_FILE_HEADER_SIZE};
+    use byteorder::{ByteOrder, LittleEndian};
+    use std::io::Write;
+
+    /// A file with the header and the given chunks of `evtx_file`.
+    fn with_chunks(evtx_file: &[u8], chunks: std::ops::Range<usize>) -> Vec<u8> {
+        let mut data = evtx_file[..EVTX_FILE_HEADER_SIZE].to_vec();
+        data.extend_from_slice(
+            &evtx_file[EVTX_FILE_HEADER_SIZE + chunks.start * EVTX_CHUNK_SIZE
+                ..EVTX_FILE_HEADER_SIZE + chunks.end * EVTX_CHUNK_SIZE],
+        );
+
+        data
+    }
+
+    #[test]
+    fn test_overlapping_files_are_merged() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        // The rolled-over file is passed first, and both files have chunks 12 and 13.
+        let parsers = vec![
+            EvtxParser::from_buffer(with_chunks(evtx_file, 12..26)).unwrap(),
+            EvtxParser::from_buffer(with_chunks(evtx_file, 0..14)).unwrap(),
+        ];
+        let mut parser = MultiFileParser::from_parsers(parsers);
+
+        let record_ids: Vec<u64> = parser
+            .records()
+            .map(|record| record.unwrap().event_record_id)
+            .collect();
+
+        assert_eq!(record_ids.len(), 2261);
+        assert_eq!(record_ids[0], 1);
+        assert!(record_ids.windows(2).all(|ids| ids[0] < ids[1]));
+    }
+
+    #[test]
+    fn test_out_of_order_records_are_kept() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut data = with_chunks(evtx_file, 0..1);
+
+        // Give the second record of the chunk a higher id than the records after it.
+        let first_record = EVTX_FILE_HEADER_SIZE + 512;
+        let size = LittleEndian::read_u32(&data[first_record + 4..]);
+        let second_record = first_record + size as usize;
+        data[second_record + 8..second_record + 16].copy_from_slice(&1000_u64.to_le_bytes());
+
+        let parsers = vec![EvtxParser::from_buffer(data).unwrap()];
+        let mut parser = MultiFileParser::from_parsers(parsers);
+
+        let record_ids: Vec<u64> = parser
+            .records()
+            .map(|record| record.unwrap().event_record_id)
+            .collect();
+
+        assert_eq!(record_ids.len(), 91);
+        assert_eq!(&record_ids[..3], &[1, 1000, 3]);
+    }
+
+    #[test]
+    fn test_repeated_records_of_a_file_are_kept() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut data = with_chunks(evtx_file, 0..1);
+
+        // Give the second record of the chunk the id of the first one.
+        let first_record = EVTX_FILE_HEADER_SIZE + 512;
+        let size = LittleEndian::read_u32(&data[first_record + 4..]);
+        let second_record = first_record + size as usize;
+        data[second_record + 8..second_record + 16].copy_from_slice(&1_u64.to_le_bytes());
+
+        // The second file has the original records of the first one, and more.
+        let mut second_file = EvtxParser::from_buffer(with_chunks(evtx_file, 0..2)).unwrap();
+        let second_file_records = second_file.records().count();
+        let parsers = vec![EvtxParser::from_buffer(data).unwrap(), second_file];
+        let mut parser = MultiFileParser::from_parsers(parsers);
+
+        let record_ids: Vec<u64> = parser
+            .records()
+            .map(|record| record.unwrap().event_record_id)
+            .collect();
+
+        // Record 2 is only in the second file.
+        assert_eq!(&record_ids[..4], &[1, 1, 2, 3]);
+        assert_eq!(record_ids.len(), second_file_records + 1);
+    }
+
+    #[test]
+    fn test_from_paths() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut paths = vec![];
+        for (name, chunks) in &[("Security.evtx", 10..26), ("Security.evtx.1", 0..10)] {
+            let path = dir.path().join(name);
+            let mut file = std::fs::File::create(&path).unwrap();
+            file.write_all(&with_chunks(evtx_file, chunks.clone()))
+                .unwrap();
+            paths.push(path);
+        }
+
+        let mut parser = EvtxParser::from_paths(&paths).unwrap();
+
+        let first = parser.records_json_value().next().unwrap().unwrap();
+        assert_eq!(first.event_record_id, 1);
+        assert_eq!(parser.records_json().count(), 2261);
+    }
+}
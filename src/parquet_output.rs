@@ -0,0 +1,326 @@
+use crate::err::{Result, SerializationError};
+use crate::evtx_parser::ReadSeek;
+use crate::{EvtxParser, SerializedEvtxRecord};
+
+use parquet::basic::{LogicalType, Repetition, TimeUnit, Type as PhysicalType};
+use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+use parquet::errors::{ParquetError, Result as ParquetResult};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::types::{Type, TypePtr};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Arc;
+
+const EVENT_RECORD_ID_COLUMN: &str = "event_record_id";
+const TIMESTAMP_COLUMN: &str = "timestamp";
+const DATA_COLUMN: &str = "data";
+
+/// The columns of the parquet file written by `EvtxParser::dump_parquet`.
+///
+/// Every file has an `event_record_id` column, a `timestamp` column,
+/// a (string) column for each of the given fields of the `<System>` element,
+/// and a `data` column with the whole record as JSON.
+///
+/// Fields of `<System>` are named by joining the names of nested elements and attributes with `_`,
+/// for example `EventID`, `Provider_Name` or `TimeCreated_SystemTime`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ParquetSchema {
+    system_columns: Vec<String>,
+}
+
+impl ParquetSchema {
+    pub fn new(system_columns: Vec<String>) -> Self {
+        ParquetSchema { system_columns }
+    }
+
+    /// Infers the fields of `<System>` from the first `num_records` records of the parser.
+    /// Columns are ordered by their first appearance. Records which fail to parse are ignored.
+    pub fn infer<T: ReadSeek>(parser: &mut EvtxParser<T>, num_records: usize) -> Self {
+        let mut system_columns: Vec<String> = vec![];
+
+        for record in parser
+            .records_json_value()
+            .take(num_records)
+            .filter_map(|r| r.ok())
+        {
+            for (name, _) in flatten_system(&record.data) {
+                if !system_columns.contains(&name) {
+                    system_columns.push(name);
+                }
+            }
+        }
+
+        ParquetSchema { system_columns }
+    }
+
+    pub fn system_columns(&self) -> &[String] {
+        &self.system_columns
+    }
+
+    fn to_parquet_type(&self) -> ParquetResult<TypePtr> {
+        let mut fields = vec![
+            Arc::new(
+                Type::primitive_type_builder(EVENT_RECORD_ID_COLUMN, PhysicalType::INT64)
+                    .with_repetition(Repetition::REQUIRED)
+                    .build()?,
+            ),
+            Arc::new(
+                Type::primitive_type_builder(TIMESTAMP_COLUMN, PhysicalType::INT64)
+                    .with_repetition(Repetition::REQUIRED)
+                    .with_logical_type(Some(LogicalType::timestamp(true, TimeUnit::MICROS)))
+                    .build()?,
+            ),
+        ];
+
+        for name in &self.system_columns {
+            fields.push(Arc::new(string_column(name, Repetition::OPTIONAL)?));
+        }
+
+        fields.push(Arc::new(string_column(DATA_COLUMN, Repetition::REQUIRED)?));
+
+        Ok(Arc::new(
+            Type::group_type_builder("evtx_record")
+                .with_fields(fields)
+                .build()?,
+        ))
+    }
+
+    fn write_row_group<W: Write + Send>(
+        &self,
+        writer: &mut SerializedFileWriter<W>,
+        records: &[SerializedEvtxRecord<Value>],
+    ) -> ParquetResult<()> {
+        let event_record_ids: Vec<i64> = records.iter().map(|r| r.event_record_id as i64).collect();
+        let timestamps: Vec<i64> = records
+            .iter()
+            .map(|r| r.timestamp.timestamp_micros())
+            .collect();
+        let system_fields: Vec<HashMap<String, String>> = records
+            .iter()
+            .map(|r| {
+                flatten_system(&r.data)
+                    .into_iter()
+                    .filter_map(|(name, value)| value.map(|value| (name, value)))
+                    .collect()
+            })
+            .collect();
+        let data: Vec<ByteArray> = records
+            .iter()
+            .map(|r| ByteArray::from(r.data.to_string().into_bytes()))
+            .collect();
+
+        let mut row_group = writer.next_row_group()?;
+
+        let mut column = next_column(row_group.next_column()?, EVENT_RECORD_ID_COLUMN)?;
+        column
+            .typed::<Int64Type>()
+            .write_batch(&event_record_ids, None, None)?;
+        column.close()?;
+
+        let mut column = next_column(row_group.next_column()?, TIMESTAMP_COLUMN)?;
+        column
+            .typed::<Int64Type>()
+            .write_batch(&timestamps, None, None)?;
+        column.close()?;
+
+        for name in &self.system_columns {
+            let mut values = vec![];
+            let mut definition_levels = vec![];
+
+            for fields in &system_fields {
+                match fields.get(name) {
+                    Some(value) => {
+                        values.push(ByteArray::from(value.as_str()));
+                        definition_levels.push(1);
+                    }
+                    None => definition_levels.push(0),
+                }
+            }
+
+            let mut column = next_column(row_group.next_column()?, name)?;
+            column
+                .typed::<ByteArrayType>()
+                .write_batch(&values, Some(&definition_levels), None)?;
+            column.close()?;
+        }
+
+        let mut column = next_column(row_group.next_column()?, DATA_COLUMN)?;
+        column
+            .typed::<ByteArrayType>()
+            .write_batch(&data, None, None)?;
+        column.close()?;
+
+        row_group.close()?;
+
+        Ok(())
+    }
+}
+
+/// Fails if the row group has no more columns, if it was written with a different schema.
+fn next_column<C>(column: Option<C>, name: &str) -> ParquetResult<C> {
+    column.ok_or_else(|| ParquetError::General(format!("Missing the {} column", name)))
+}
+
+fn string_column(name: &str, repetition: Repetition) -> ParquetResult<Type> {
+    Type::primitive_type_builder(name, PhysicalType::BYTE_ARRAY)
+        .with_repetition(repetition)
+        .with_logical_type(Some(LogicalType::String))
+        .build()
+}
+
+/// Writes the records to `writer` as a parquet file, with a row group for every chunk.
+pub(crate) fn write_parquet<W: Write + Send>(
+    records: impl Iterator<Item = SerializedEvtxRecord<Value>>,
+    schema: &ParquetSchema,
+    writer: W,
+) -> Result<()> {
+    write_row_groups(records, schema, writer).map_err(SerializationError::from)?;
+
+    Ok(())
+}
+
+fn write_row_groups<W: Write + Send>(
+    records: impl Iterator<Item = SerializedEvtxRecord<Value>>,
+    schema: &ParquetSchema,
+    writer: W,
+) -> ParquetResult<()> {
+    let properties = Arc::new(WriterProperties::builder().build());
+    let mut writer = SerializedFileWriter::new(writer, schema.to_parquet_type()?, properties)?;
+
+    // Records of a chunk are always yielded together.
+    let mut row_group: Vec<SerializedEvtxRecord<Value>> = vec![];
+
+    for record in records {
+        if let Some(last) = row_group.last() {
+            if last.chunk_number != record.chunk_number {
+                schema.write_row_group(&mut writer, &row_group)?;
+                row_group.clear();
+            }
+        }
+
+        row_group.push(record);
+    }
+
+    if !row_group.is_empty() {
+        schema.write_row_group(&mut writer, &row_group)?;
+    }
+
+    writer.close()?;
+
+    Ok(())
+}
+
+/// Flattens the `<System>` element of a record, in document order.
+/// Empty elements are `None`.
+fn flatten_system(record: &Value) -> Vec<(String, Option<String>)> {
+    let mut fields = vec![];
+
+    if let Some(system) = record.get("Event").and_then(|event| event.get("System")) {
+        flatten("", system, &mut fields);
+    }
+
+    fields
+}
+
+fn flatten(name: &str, value: &Value, fields: &mut Vec<(String, Option<String>)>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let key = match key.as_str() {
+                    // Attributes and text are flattened into their element.
+                    "#attributes" | "#text" => name.to_owned(),
+                    _ if name.is_empty() => key.to_owned(),
+                    _ => format!("{}_{}", name, key),
+                };
+
+                flatten(&key, value, fields);
+            }
+        }
+        Value::Null => fields.push((name.to_owned(), None)),
+        Value::String(s) => fields.push((name.to_owned(), Some(s.to_owned()))),
+        other => fields.push((name.to_owned(), Some(other.to_string()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ensure_env_logger_initialized;
+    use parquet::file::reader::FileReader;
+    use parquet::file::serialized_reader::SerializedFileReader;
+    use parquet::record::Field;
+
+    #[test]
+    fn test_infer_schema() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+        let schema = ParquetSchema::infer(&mut parser, 10);
+
+        assert_eq!(
+            &schema.system_columns()[..5],
+            &[
+                "Channel",
+                "Computer",
+                "Correlation",
+                "EventID",
+                "EventRecordID"
+            ]
+        );
+        assert!(schema
+            .system_columns()
+            .contains(&"Provider_Name".to_string()));
+        assert!(schema
+            .system_columns()
+            .contains(&"TimeCreated_SystemTime".to_string()));
+    }
+
+    #[test]
+    fn test_dump_parquet() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let schema = ParquetSchema::new(vec!["EventID".to_string(), "Provider_Name".to_string()]);
+
+        let mut file = tempfile::tempfile().unwrap();
+        parser.dump_parquet(&schema, &mut file).unwrap();
+
+        let reader = SerializedFileReader::new(file).unwrap();
+        let metadata = reader.metadata();
+        assert_eq!(metadata.file_metadata().num_rows(), 2261);
+        assert_eq!(
+            metadata.num_row_groups(),
+            parser.chunks().filter(|c| c.is_ok()).count()
+        );
+
+        let first_row = reader.get_row_iter(None).unwrap().next().unwrap().unwrap();
+        let columns: Vec<(&String, &Field)> = first_row.get_column_iter().collect();
+
+        assert_eq!(columns.len(), 5);
+        assert_eq!(
+            columns[0],
+            (&"event_record_id".to_string(), &Field::Long(1))
+        );
+        assert_eq!(
+            columns[2],
+            (&"EventID".to_string(), &Field::Str("4608".to_string()))
+        );
+        assert_eq!(
+            columns[3],
+            (
+                &"Provider_Name".to_string(),
+                &Field::Str("Microsoft-Windows-Security-Auditing".to_string())
+            )
+        );
+        match columns[4] {
+            (name, Field::Str(data)) => {
+                assert_eq!(name, "data");
+                assert!(data.starts_with("{\"Event\":"));
+            }
+            other => panic!("Unexpected column {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use crate::binxml::name::BinXmlName;
+use crate::binxml::value_variant::BinXmlValue;
+use crate::err::SerializationResult;
+use crate::json_output::JsonOutput;
+use crate::model::xml::{BinXmlPI, XmlElement};
+use crate::xml_output::{entity_text, BinXmlOutput};
+use crate::ParserSettings;
+
+use serde_json::Value;
+use std::borrow::Cow;
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+
+type Predicate = Arc<dyn Fn(&Value) -> bool + Send + Sync>;
+
+/// Collects what a `RecordFilter` needs from a record, while the record is visited
+/// by another output (see `RecordFilter::output`).
+/// `None` if the filter is empty, otherwise the fields of the `System` element,
+/// and the JSON value of the record if the filter has predicates
+/// (unless the other output builds the JSON value of the record itself).
+pub(crate) type FilterOutput = Option<(SystemFields, Option<JsonOutput>)>;
+
+/// Filters records by the values of their `System` element.
+/// Each list of values is OR'ed, and the lists are AND'ed together (an empty list matches everything).
+/// Values are compared case-insensitively.
+///
+/// Records must also match all the predicates, which are given the JSON value of the record.
+#[derive(Default, Clone)]
+pub(crate) struct RecordFilter {
+    /// Matches either the `Name` or the `Guid` attribute of `<Provider>`.
+    providers: Vec<String>,
+    /// Matches the text of `<Channel>`.
+    channels: Vec<String>,
+    predicates: Vec<Predicate>,
+}
+
+impl Debug for RecordFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RecordFilter")
+            .field("providers", &self.providers)
+            .field("channels", &self.channels)
+            .field("predicates", &self.predicates.len())
+            .finish()
+    }
+}
+
+impl RecordFilter {
+    pub fn providers(mut self, providers: &[String]) -> Self {
+        self.providers = providers.iter().map(|p| normalize(p)).collect();
+
+        self
+    }
+
+    pub fn channels(mut self, channels: &[String]) -> Self {
+        self.channels = channels.iter().map(|c| normalize(c)).collect();
+
+        self
+    }
+
+    pub fn retain(mut self, predicate: Predicate) -> Self {
+        self.predicates.push(predicate);
+
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty() && self.channels.is_empty() && self.predicates.is_empty()
+    }
+
+    /// An output which collects what the filter needs from a record, so the record can be filtered
+    /// in the same pass as it is serialized (see `matches_output`).
+    /// If `has_json_value` is set, the JSON value of the record is not collected,
+    /// and must be passed to `matches_output` instead.
+    pub fn output(&self, settings: &ParserSettings, has_json_value: bool) -> FilterOutput {
+        if self.is_empty() {
+            return None;
+        }
+
+        let json_output = if self.predicates.is_empty() || has_json_value {
+            None
+        } else {
+            Some(JsonOutput::new(settings))
+        };
+
+        Some((SystemFields::default(), json_output))
+    }
+
+    /// Returns true if the record which was visited by `output` should be yielded.
+    /// `json_value` is the JSON value of the record, if `output` was created with `has_json_value`.
+    pub fn matches_output(
+        &self,
+        output: FilterOutput,
+        json_value: Option<&Value>,
+    ) -> SerializationResult<bool> {
+        let (fields, json_output) = match output {
+            Some(output) => output,
+            None => return Ok(true),
+        };
+
+        if !self.matches_fields(&fields) {
+            return Ok(false);
+        }
+
+        match (json_value, json_output) {
+            (Some(json_value), _) => Ok(self.matches_value(json_value)),
+            (None, Some(json_output)) => Ok(self.matches_value(&json_output.into_value()?)),
+            // There are no predicates.
+            (None, None) => Ok(true),
+        }
+    }
+
+    fn matches_fields(&self, fields: &SystemFields) -> bool {
+        let provider_matches = self.providers.is_empty()
+            || self.providers.iter().any(|provider| {
+                Some(provider) == fields.provider_name.as_ref()
+                    || Some(provider) == fields.provider_guid.as_ref()
+            });
+
+        let channel_matches = self.channels.is_empty()
+            || self
+                .channels
+                .iter()
+                .any(|channel| Some(channel) == fields.channel.as_ref());
+
+        provider_matches && channel_matches
+    }
+
+    fn matches_value(&self, value: &Value) -> bool {
+        self.predicates.iter().all(|predicate| predicate(value))
+    }
+}
+
+/// GUIDs may or may not be surrounded by braces, depending on the provider.
+fn normalize(value: &str) -> String {
+    value
+        .trim()
+        .trim_start_matches('{')
+        .trim_end_matches('}')
+        .to_lowercase()
+}
+
+/// Collects the (normalized) fields of the `System` element needed for filtering.
+#[derive(Debug, Default)]
+pub(crate) struct SystemFields {
+    stack: Vec<String>,
+    provider_name: Option<String>,
+    provider_guid: Option<String>,
+    channel: Option<String>,
+}
+
+impl SystemFields {
+    fn in_system_element(&self, name: &str) -> bool {
+        self.stack.len() == 3 && self.stack[1] == "System" && self.stack[2] == name
+    }
+}
+
+impl BinXmlOutput for SystemFields {
+    fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
+        Ok(())
+    }
+
+    fn visit_open_start_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        self.stack.push(element.name.as_str().to_owned());
+
+        if self.in_system_element("Provider") {
+            for attribute in element.attributes.iter() {
+                let value = normalize(&attribute.value.as_cow_str());
+
+                match attribute.name.as_str() {
+                    "Name" => self.provider_name = Some(value),
+                    "Guid" => self.provider_guid = Some(value),
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn visit_close_element(&mut self, _element: &XmlElement) -> SerializationResult<()> {
+        self.stack.pop();
+        Ok(())
+    }
+
+    fn visit_characters(&mut self, value: &BinXmlValue) -> SerializationResult<()> {
+        if self.in_system_element("Channel") {
+            let channel = self.channel.get_or_insert_with(String::new);
+            channel.push_str(&normalize(&value.as_cow_str()));
+        }
+
+        Ok(())
+    }
+
+    fn visit_cdata_section(&mut self, cdata: Cow<'_, str>) -> SerializationResult<()> {
+        self.visit_characters(&BinXmlValue::StringType(cdata.into_owned()))
+    }
+
+    fn visit_entity_reference(&mut self, entity: &BinXmlName) -> SerializationResult<()> {
+        match entity_text(entity) {
+            Some(text) => self.visit_characters(&BinXmlValue::StringType(text)),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_character_reference(&mut self, _char_ref: Cow<'_, str>) -> SerializationResult<()> {
+        Ok(())
+    }
+
+    fn visit_processing_instruction(&mut self, _pi: &BinXmlPI) -> SerializationResult<()> {
+        Ok(())
+    }
+
+    fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
+        Ok(())
+    }
+}
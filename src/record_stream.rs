@@ -0,0 +1,89 @@
+use crate::err::Result;
+use crate::SerializedEvtxRecord;
+
+use futures_core::Stream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+
+/// A stream of XML records, parsed on a blocking task by `EvtxParser::into_stream`.
+///
+/// Records are yielded in the same order as `EvtxParser::records`.
+pub struct RecordStream {
+    receiver: mpsc::Receiver<Result<SerializedEvtxRecord<String>>>,
+}
+
+impl RecordStream {
+    pub(crate) fn new(receiver: mpsc::Receiver<Result<SerializedEvtxRecord<String>>>) -> Self {
+        RecordStream { receiver }
+    }
+}
+
+impl Stream for RecordStream {
+    type Item = Result<SerializedEvtxRecord<String>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ensure_env_logger_initialized;
+    use crate::EvtxParser;
+    use std::future::poll_fn;
+
+    async fn next(stream: &mut RecordStream) -> Option<Result<SerializedEvtxRecord<String>>> {
+        poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    #[test]
+    fn test_into_stream() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let expected: Vec<String> = parser.records().map(|r| r.unwrap().data).collect();
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let records = runtime.block_on(async {
+            let parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+            let mut stream = parser.into_stream();
+            let mut records = vec![];
+
+            while let Some(record) = next(&mut stream).await {
+                records.push(record.unwrap().data);
+            }
+
+            records
+        });
+
+        assert_eq!(records.len(), 2261);
+        assert_eq!(records, expected);
+    }
+
+    #[test]
+    fn test_dropping_the_stream_stops_parsing() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let first = runtime.block_on(async {
+            let parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+            let mut stream = parser.into_stream();
+
+            next(&mut stream).await
+        });
+
+        assert_eq!(first.unwrap().unwrap().event_record_id, 1);
+
+        // Waits for the parsing task, which stops once the stream is dropped.
+        drop(runtime);
+    }
+}
@@ -0,0 +1,141 @@
+use crate::binxml::name::BinXmlName;
+use crate::binxml::value_variant::{BinXmlValue, BinXmlValueType};
+use crate::err::SerializationResult;
+use crate::model::xml::{BinXmlPI, XmlElement};
+use crate::xml_output::BinXmlOutput;
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+/// The element paths of the records of a file, as collected by `EvtxParser::schema_summary`.
+///
+/// Paths join the names of nested elements with `/` (for example `Event/System/EventID`),
+/// attributes are appended to the path of their element with `/@` (`Event/System/Provider/@Name`).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SchemaSummary {
+    /// The number of records which were summarized.
+    pub records: u64,
+    pub paths: BTreeMap<String, PathSummary>,
+}
+
+/// The occurrences of a single element or attribute.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PathSummary {
+    /// The number of times the element (or attribute) appeared, over all records.
+    pub occurrences: u64,
+    /// The number of values of every type which appeared in the element (or attribute).
+    /// Empty for elements which only contain other elements.
+    pub value_types: BTreeMap<BinXmlValueType, u64>,
+}
+
+impl SchemaSummary {
+    /// Adds the counts of `other` to this summary.
+    pub fn merge(&mut self, other: SchemaSummary) {
+        self.records += other.records;
+
+        for (path, other) in other.paths {
+            let summary = self.paths.entry(path).or_default();
+            summary.occurrences += other.occurrences;
+
+            for (value_type, count) in other.value_types {
+                *summary.value_types.entry(value_type).or_default() += count;
+            }
+        }
+    }
+
+    fn visit_path(&mut self, path: String) -> &mut PathSummary {
+        let summary = self.paths.entry(path).or_default();
+        summary.occurrences += 1;
+
+        summary
+    }
+
+    fn visit_value(&mut self, path: &str, value_type: BinXmlValueType) {
+        if let Some(summary) = self.paths.get_mut(path) {
+            *summary.value_types.entry(value_type).or_default() += 1;
+        }
+    }
+}
+
+/// Collects the `SchemaSummary` of a record.
+#[derive(Debug, Default)]
+pub(crate) struct SchemaOutput {
+    summary: SchemaSummary,
+    path: Vec<String>,
+}
+
+impl SchemaOutput {
+    pub fn into_summary(self) -> SchemaSummary {
+        self.summary
+    }
+
+    fn current_path(&self) -> String {
+        self.path.join("/")
+    }
+}
+
+impl BinXmlOutput for SchemaOutput {
+    fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
+        Ok(())
+    }
+
+    fn visit_open_start_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        self.path.push(element.name.as_str().to_owned());
+
+        let path = self.current_path();
+        self.summary.visit_path(path.clone());
+
+        for attribute in &element.attributes {
+            let summary = self
+                .summary
+                .visit_path(format!("{}/@{}", path, attribute.name.as_str()));
+            *summary
+                .value_types
+                .entry(attribute.value.value_type())
+                .or_default() += 1;
+        }
+
+        Ok(())
+    }
+
+    fn visit_close_element(&mut self, _element: &XmlElement) -> SerializationResult<()> {
+        self.path.pop();
+
+        Ok(())
+    }
+
+    fn visit_characters(&mut self, value: &BinXmlValue) -> SerializationResult<()> {
+        let path = self.current_path();
+        self.summary.visit_value(&path, value.value_type());
+
+        Ok(())
+    }
+
+    fn visit_cdata_section(&mut self, _cdata: Cow<'_, str>) -> SerializationResult<()> {
+        let path = self.current_path();
+        self.summary.visit_value(&path, BinXmlValueType::StringType);
+
+        Ok(())
+    }
+
+    fn visit_entity_reference(&mut self, _entity: &BinXmlName) -> SerializationResult<()> {
+        let path = self.current_path();
+        self.summary.visit_value(&path, BinXmlValueType::StringType);
+
+        Ok(())
+    }
+
+    fn visit_character_reference(&mut self, _char_ref: Cow<'_, str>) -> SerializationResult<()> {
+        Ok(())
+    }
+
+    fn visit_processing_instruction(&mut self, _pi: &BinXmlPI) -> SerializationResult<()> {
+        Ok(())
+    }
+
+    fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
+        self.summary.records += 1;
+
+        Ok(())
+    }
+}
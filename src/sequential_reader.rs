@@ -0,0 +1,84 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Adapts a `Read` source which cannot seek (a pipe, a socket, an entry of a tar stream)
+/// to the `Read + Seek` interface of the parser, by only allowing it to seek forward.
+///
+/// Seeking forward discards the skipped bytes, seeking backward (or from the end) fails.
+/// See `EvtxParser::from_sequential_read`.
+#[derive(Debug)]
+pub struct SequentialReader<R: Read> {
+    inner: R,
+    position: u64,
+}
+
+impl<R: Read> SequentialReader<R> {
+    /// Wraps `inner`, which was already read up to `position`.
+    pub(crate) fn new(inner: R, position: u64) -> Self {
+        SequentialReader { inner, position }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for SequentialReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let amount_read = self.inner.read(buf)?;
+        self.position += amount_read as u64;
+
+        Ok(amount_read)
+    }
+}
+
+impl<R: Read> Seek for SequentialReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+            SeekFrom::End(_) => None,
+        };
+
+        let target = match target {
+            Some(target) if target >= self.position => target,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "a sequential reader can only seek forward",
+                ))
+            }
+        };
+
+        let to_skip = target - self.position;
+        let skipped = io::copy(&mut self.by_ref().take(to_skip), &mut io::sink())?;
+
+        if skipped != to_skip {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "reached the end of the stream while seeking",
+            ));
+        }
+
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeks_only_forward() {
+        let mut reader = SequentialReader::new(&[0_u8, 1, 2, 3, 4, 5][..], 0);
+
+        assert_eq!(reader.seek(SeekFrom::Start(2)).unwrap(), 2);
+        let mut buf = [0_u8; 2];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [2, 3]);
+
+        assert_eq!(reader.stream_position().unwrap(), 4);
+        assert!(reader.seek(SeekFrom::Start(1)).is_err());
+        assert!(reader.seek(SeekFrom::End(0)).is_err());
+        assert!(reader.seek(SeekFrom::Start(10)).is_err());
+    }
+}
@@ -11,7 +11,11 @@ use std::io::{Cursor, Seek, SeekFrom};
 pub struct StringCache(HashMap<ChunkOffset, BinXmlName>);
 
 impl StringCache {
-    pub fn populate(data: &[u8], offsets: &[ChunkOffset]) -> DeserializationResult<Self> {
+    pub fn populate(
+        data: &[u8],
+        offsets: &[ChunkOffset],
+        lossy: bool,
+    ) -> DeserializationResult<Self> {
         let mut cache = HashMap::new();
         let mut cursor = Cursor::new(data);
         let cursor_ref = cursor.borrow_mut();
@@ -22,7 +26,7 @@ impl StringCache {
             loop {
                 let string_position = cursor_ref.position() as ChunkOffset;
                 let link = BinXmlNameLink::from_stream(cursor_ref)?;
-                let name = BinXmlName::from_stream(cursor_ref)?;
+                let name = BinXmlName::from_stream_with_decoding(cursor_ref, lossy)?;
 
                 cache.insert(string_position, name);
 
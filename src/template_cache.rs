@@ -1,20 +1,183 @@
-use crate::binxml::tokens::read_template_definition;
+use crate::binxml::tokens::{read_template_definition, read_template_definition_header};
 use crate::err::DeserializationResult;
 
-use crate::model::deserialized::BinXMLTemplateDefinition;
+use crate::binxml::value_variant::BinXmlValue;
+use crate::model::deserialized::{BinXMLDeserializedTokens, BinXMLTemplateDefinition};
 use crate::ChunkOffset;
 pub use byteorder::{LittleEndian, ReadBytesExt};
 
 use encoding::EncodingRef;
 use log::trace;
 use std::borrow::BorrowMut;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 use std::io::{Cursor, Seek, SeekFrom};
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
 pub type CachedTemplate<'chunk> = BinXMLTemplateDefinition<'chunk>;
+type SharedTemplates = HashMap<(ChunkOffset, u64), SharedTemplate>;
+
+/// A definition in the shared cache, with the names it refers to which are stored
+/// outside of its own data (in the chunk it was read from), as (offset, utf16 bytes) pairs.
+#[derive(Debug)]
+struct SharedTemplate {
+    definition: Arc<CachedTemplate<'static>>,
+    external_names: Vec<(ChunkOffset, Vec<u8>)>,
+}
+
+impl SharedTemplate {
+    /// True if the external names of the definition are the same in the chunk `data`.
+    fn names_match(&self, data: &[u8]) -> bool {
+        self.external_names
+            .iter()
+            .all(|(offset, name)| name_bytes(data, *offset) == Some(name.as_slice()))
+    }
+}
+
+/// Size of the template definition header (next template offset, guid and data size).
+const TEMPLATE_DEFINITION_HEADER_SIZE: usize = 4 + 16 + 4;
 
 #[derive(Debug, Default)]
-pub struct TemplateCache<'chunk>(HashMap<ChunkOffset, CachedTemplate<'chunk>>);
+pub struct TemplateCache<'chunk>(HashMap<ChunkOffset, Arc<CachedTemplate<'chunk>>>);
+
+/// A cache of template definitions which is shared between chunks.
+///
+/// Templates are keyed by their offset in the chunk, and a hash of their data,
+/// so two chunks which have different definitions at the same offset will not collide.
+/// Definitions which point directly into their chunk's data (binary values) are never shared.
+///
+/// The cache is cheap to clone (clones share the same definitions), and may be reused between parsers
+/// (see `EvtxParser::with_template_cache`), also for other files.
+/// Since definitions are only reused for identical bytes at the same offset, a cache warmed
+/// on an unrelated file is safe (it just won't be hit). Definitions may also refer to names of
+/// elements and attributes which are stored outside of them (in an earlier template of the chunk),
+/// a cached definition is only used if those names are the same in the current chunk.
+///
+/// Building the template caches is usually a small part of the parsing time (most of the time
+/// is spent on the records themselves), so files with many large templates per chunk benefit the most.
+#[derive(Debug, Default, Clone)]
+pub struct SharedTemplateCache {
+    templates: Arc<Mutex<SharedTemplates>>,
+    hits: Arc<AtomicUsize>,
+}
+
+impl SharedTemplateCache {
+    pub fn new() -> Self {
+        SharedTemplateCache::default()
+    }
+
+    /// Returns the definition with `key`, if the names it refers to are the same in the chunk `data`.
+    fn get(&self, key: &(ChunkOffset, u64), data: &[u8]) -> Option<Arc<CachedTemplate<'static>>> {
+        // Templates are never modified while the lock is held, so a poisoned lock is still usable.
+        let cache = self.templates.lock().unwrap_or_else(|e| e.into_inner());
+        let template = cache
+            .get(key)
+            .filter(|template| template.names_match(data))?;
+        self.hits.fetch_add(1, Ordering::Relaxed);
+
+        Some(Arc::clone(&template.definition))
+    }
+
+    fn insert(&self, key: (ChunkOffset, u64), template: SharedTemplate) {
+        let mut cache = self.templates.lock().unwrap_or_else(|e| e.into_inner());
+        cache.insert(key, template);
+    }
+
+    pub fn len(&self) -> usize {
+        self.templates
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of times a definition was found in the cache (instead of being parsed) so far.
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+}
+
+/// Caches are equal if they are the same cache (clones of each other), not if they hold the same definitions.
+impl PartialEq for SharedTemplateCache {
+    fn eq(&self, other: &SharedTemplateCache) -> bool {
+        Arc::ptr_eq(&self.templates, &other.templates)
+    }
+}
+
+/// The utf16 bytes of the name at `offset` of the chunk `data` (after its `BinXmlNameLink`).
+fn name_bytes(data: &[u8], offset: ChunkOffset) -> Option<&[u8]> {
+    let start = offset as usize + 6;
+    let len = u16::from_le_bytes([*data.get(start)?, *data.get(start + 1)?]) as usize;
+
+    data.get(start + 2..start + 2 + len * 2)
+}
+
+/// Collects the names referred to by `tokens` which are stored outside of `own_data`
+/// (the range of the definition in the chunk).
+fn collect_external_names(
+    data: &[u8],
+    tokens: &[BinXMLDeserializedTokens],
+    own_data: &Range<usize>,
+    names: &mut Vec<(ChunkOffset, Vec<u8>)>,
+) -> Option<()> {
+    for token in tokens {
+        let offset = match token {
+            BinXMLDeserializedTokens::OpenStartElement(element) => element.name.offset,
+            BinXMLDeserializedTokens::Attribute(attribute) => attribute.name.offset,
+            BinXMLDeserializedTokens::EntityRef(entity) => entity.name.offset,
+            BinXMLDeserializedTokens::PITarget(target) => target.name.offset,
+            BinXMLDeserializedTokens::Value(BinXmlValue::BinXmlType(tokens)) => {
+                collect_external_names(data, tokens, own_data, names)?;
+                continue;
+            }
+            _ => continue,
+        };
+
+        if !own_data.contains(&(offset as usize)) && !names.iter().any(|(o, _)| *o == offset) {
+            names.push((offset, name_bytes(data, offset)?.to_vec()));
+        }
+    }
+
+    Some(())
+}
+
+/// Hashes the data of the template definition at `offset`,
+/// skipping the offset of the next template (which is not a part of the definition itself).
+fn hash_template_data(
+    data: &[u8],
+    offset: ChunkOffset,
+    data_size: u32,
+    ansi_codec: EncodingRef,
+) -> Option<u64> {
+    let start = offset as usize + 4;
+    let end =
+        (offset as usize + TEMPLATE_DEFINITION_HEADER_SIZE).checked_add(data_size as usize)?;
+
+    let mut hasher = DefaultHasher::new();
+    data.get(start..end)?.hash(&mut hasher);
+    // Ansi strings are decoded while reading the definition.
+    ansi_codec.name().hash(&mut hasher);
+
+    Some(hasher.finish())
+}
+
+fn into_static_definition(definition: &CachedTemplate<'_>) -> CachedTemplate<'static> {
+    BinXMLTemplateDefinition {
+        header: definition.header.clone(),
+        tokens: definition
+            .tokens
+            .iter()
+            .cloned()
+            .map(BinXMLDeserializedTokens::into_static)
+            .collect(),
+    }
+}
 
 impl<'chunk> TemplateCache<'chunk> {
     pub fn new() -> Self {
@@ -25,6 +188,7 @@ impl<'chunk> TemplateCache<'chunk> {
         data: &'chunk [u8],
         offsets: &[ChunkOffset],
         ansi_codec: EncodingRef,
+        shared_cache: Option<&SharedTemplateCache>,
     ) -> DeserializationResult<Self> {
         let mut cache = HashMap::new();
         let mut cursor = Cursor::new(data);
@@ -35,10 +199,59 @@ impl<'chunk> TemplateCache<'chunk> {
 
             loop {
                 let table_offset = cursor_ref.position() as ChunkOffset;
-                let definition = read_template_definition(cursor_ref, None, ansi_codec)?;
-                let next_template_offset = definition.header.next_template_offset;
 
-                cache.insert(table_offset, definition);
+                let next_template_offset = match shared_cache {
+                    Some(shared_cache) => {
+                        let header = read_template_definition_header(cursor_ref)?;
+                        try_seek!(cursor_ref, table_offset, "template")?;
+
+                        let key =
+                            hash_template_data(data, table_offset, header.data_size, ansi_codec)
+                                .map(|hash| (table_offset, hash));
+
+                        match key.as_ref().and_then(|key| shared_cache.get(key, data)) {
+                            Some(template) => {
+                                trace!("Template at {} found in shared cache", table_offset);
+                                cache.insert(table_offset, template);
+                            }
+                            None => {
+                                let definition =
+                                    read_template_definition(cursor_ref, None, ansi_codec)?;
+
+                                let own_data =
+                                    table_offset as usize..cursor_ref.position() as usize;
+                                let mut external_names = vec![];
+                                let names = collect_external_names(
+                                    data,
+                                    &definition.tokens,
+                                    &own_data,
+                                    &mut external_names,
+                                );
+
+                                // Definitions which refer to names that cannot be read are not shared.
+                                if let (Some(key), Some(())) = (key, names) {
+                                    let shared_definition = SharedTemplate {
+                                        definition: Arc::new(into_static_definition(&definition)),
+                                        external_names,
+                                    };
+                                    shared_cache.insert(key, shared_definition);
+                                }
+
+                                cache.insert(table_offset, Arc::new(definition));
+                            }
+                        }
+
+                        header.next_template_offset
+                    }
+                    None => {
+                        let definition = read_template_definition(cursor_ref, None, ansi_codec)?;
+                        let next_template_offset = definition.header.next_template_offset;
+
+                        cache.insert(table_offset, Arc::new(definition));
+
+                        next_template_offset
+                    }
+                };
 
                 trace!("Next template will be at {}", next_template_offset);
 
@@ -54,10 +267,53 @@ impl<'chunk> TemplateCache<'chunk> {
     }
 
     pub fn get_template(&self, offset: ChunkOffset) -> Option<&CachedTemplate<'chunk>> {
-        self.0.get(&offset)
+        self.0.get(&offset).map(|template| template.as_ref())
     }
 
     pub fn len(&self) -> usize {
         self.0.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ensure_env_logger_initialized;
+    use crate::evtx_parser::{EVTX_CHUNK_SIZE, EVTX_FILE_HEADER_SIZE};
+    use crate::{EvtxChunkData, ParserSettings};
+
+    #[test]
+    fn test_cached_definitions_require_the_same_external_names() {
+        ensure_env_logger_initialized();
+        let evtx_file = include_bytes!("../samples/security.evtx");
+        let mut chunk_data =
+            evtx_file[EVTX_FILE_HEADER_SIZE..EVTX_FILE_HEADER_SIZE + EVTX_CHUNK_SIZE].to_vec();
+
+        let settings = Arc::new(ParserSettings::new().shared_template_cache(true));
+        let cache = settings.get_shared_template_cache().unwrap().clone();
+        let parse = |data: &[u8]| {
+            let mut chunk = EvtxChunkData::new(data.to_vec(), false).unwrap();
+            chunk.parse(Arc::clone(&settings)).unwrap();
+        };
+
+        parse(&chunk_data);
+        assert_eq!(cache.hits(), 0);
+
+        // The same chunk finds all of its definitions.
+        parse(&chunk_data);
+        assert_eq!(cache.hits(), cache.len());
+
+        let external_name = cache
+            .templates
+            .lock()
+            .unwrap()
+            .values()
+            .find_map(|template| template.external_names.first().cloned());
+        let (offset, _) = external_name.expect("a definition which refers to an earlier name");
+
+        // A chunk where that name is different does not use the definition.
+        chunk_data[offset as usize + 8] ^= 1;
+        parse(&chunk_data);
+        assert!(cache.hits() < 2 * cache.len());
+    }
+}
@@ -7,7 +7,7 @@ use byteorder::{LittleEndian, ReadBytesExt};
 
 use encoding::{decode, DecoderTrap, EncodingRef};
 use log::trace;
-use std::char::decode_utf16;
+use std::char::{decode_utf16, REPLACEMENT_CHARACTER};
 use std::error::Error as StdErr;
 use std::io::{self, Error, ErrorKind};
 
@@ -17,9 +17,12 @@ pub enum FailedToReadString {
     IoError(#[from] io::Error),
 }
 
+/// Reads a utf16 string prefixed by its number of characters.
+/// If `lossy` is set, invalid utf16 is replaced with U+FFFD instead of returning an error.
 pub fn read_len_prefixed_utf16_string<T: ReadSeek>(
     stream: &mut T,
     is_null_terminated: bool,
+    lossy: bool,
 ) -> Result<Option<String>, FailedToReadString> {
     let expected_number_of_characters = stream.read_u16::<LittleEndian>()?;
     let needed_bytes = u64::from(expected_number_of_characters * 2);
@@ -35,7 +38,7 @@ pub fn read_len_prefixed_utf16_string<T: ReadSeek>(
         len = expected_number_of_characters
     );
 
-    let s = read_utf16_by_size(stream, needed_bytes)?;
+    let s = read_utf16_by_size(stream, needed_bytes, lossy)?;
 
     if is_null_terminated {
         stream.read_u16::<LittleEndian>()?;
@@ -50,10 +53,14 @@ pub fn read_len_prefixed_utf16_string<T: ReadSeek>(
 
 /// Reads a utf16 string from the given stream.
 /// size is the actual byte representation of the string (not the number of characters).
-pub fn read_utf16_by_size<T: ReadSeek>(stream: &mut T, size: u64) -> io::Result<Option<String>> {
+pub fn read_utf16_by_size<T: ReadSeek>(
+    stream: &mut T,
+    size: u64,
+    lossy: bool,
+) -> io::Result<Option<String>> {
     match size {
         0 => Ok(None),
-        _ => read_utf16_string(stream, Some(size as usize / 2)).map(|mut s| {
+        _ => read_utf16_string(stream, Some(size as usize / 2), lossy).map(|mut s| {
             // Strip nul terminator if needed
             if let Some('\0') = s.chars().last() {
                 s.pop();
@@ -64,10 +71,12 @@ pub fn read_utf16_by_size<T: ReadSeek>(stream: &mut T, size: u64) -> io::Result<
 }
 
 /// Reads an ansi encoded string from the given stream using `ansi_codec`.
+/// If `lossy` is set, invalid sequences are replaced with U+FFFD instead of returning an error.
 pub fn read_ansi_encoded_string<T: ReadSeek>(
     stream: &mut T,
     size: u64,
     ansi_codec: EncodingRef,
+    lossy: bool,
 ) -> DeserializationResult<Option<String>> {
     match size {
         0 => Ok(None),
@@ -78,7 +87,13 @@ pub fn read_ansi_encoded_string<T: ReadSeek>(
             // There may be multiple NULs in the string, prune them.
             bytes.retain(|&b| b != 0);
 
-            let s = match decode(&bytes, DecoderTrap::Strict, ansi_codec).0 {
+            let trap = if lossy {
+                DecoderTrap::Replace
+            } else {
+                DecoderTrap::Strict
+            };
+
+            let s = match decode(&bytes, trap, ansi_codec).0 {
                 Ok(s) => s,
                 Err(message) => {
                     let as_boxed_err = Box::<dyn StdErr + Send + Sync>::from(message.to_string());
@@ -96,14 +111,22 @@ pub fn read_ansi_encoded_string<T: ReadSeek>(
     }
 }
 
-pub fn read_null_terminated_utf16_string<T: ReadSeek>(stream: &mut T) -> io::Result<String> {
-    read_utf16_string(stream, None)
+pub fn read_null_terminated_utf16_string<T: ReadSeek>(
+    stream: &mut T,
+    lossy: bool,
+) -> io::Result<String> {
+    read_utf16_string(stream, None, lossy)
 }
 
 /// Reads a utf16 string from the given stream.
 /// If `len` is given, exactly `len` u16 values are read from the stream.
 /// If `len` is None, the string is assumed to be null terminated and the stream will be read to the first null (0).
-fn read_utf16_string<T: ReadSeek>(stream: &mut T, len: Option<usize>) -> io::Result<String> {
+/// If `lossy` is set, unpaired surrogates are replaced with U+FFFD.
+fn read_utf16_string<T: ReadSeek>(
+    stream: &mut T,
+    len: Option<usize>,
+    lossy: bool,
+) -> io::Result<String> {
     let mut buffer = match len {
         Some(len) => Vec::with_capacity(len),
         None => Vec::new(),
@@ -128,7 +151,13 @@ fn read_utf16_string<T: ReadSeek>(stream: &mut T, len: Option<usize>) -> io::Res
     }
 
     // We need to stop if we see a NUL byte, even if asked for more bytes.
-    decode_utf16(buffer.into_iter().take_while(|&byte| byte != 0x00))
-        .map(|r| r.map_err(|_e| Error::from(ErrorKind::InvalidData)))
-        .collect()
+    let chars = decode_utf16(buffer.into_iter().take_while(|&byte| byte != 0x00));
+
+    if lossy {
+        Ok(chars.map(|r| r.unwrap_or(REPLACEMENT_CHARACTER)).collect())
+    } else {
+        chars
+            .map(|r| r.map_err(|_e| Error::from(ErrorKind::InvalidData)))
+            .collect()
+    }
 }
@@ -0,0 +1,30 @@
+/// The standard keyword bits, as defined in `winmeta.xml`.
+/// The lower 48 bits are defined by each provider, and the upper 8 bits are reserved for channels.
+const STANDARD_KEYWORDS: &[(u64, &str)] = &[
+    (0x0001_0000_0000_0000, "ResponseTime"),
+    (0x0002_0000_0000_0000, "WDIContext"),
+    (0x0004_0000_0000_0000, "WDIDiag"),
+    (0x0008_0000_0000_0000, "SQM"),
+    (0x0010_0000_0000_0000, "AuditFailure"),
+    (0x0020_0000_0000_0000, "AuditSuccess"),
+    (0x0040_0000_0000_0000, "CorrelationHint2"),
+    (0x0080_0000_0000_0000, "EventlogClassic"),
+];
+
+/// Resolves the standard keyword bits set in the `Keywords` bitmask of an event to their names.
+///
+/// Provider defined bits and channel bits are not resolved.
+///
+/// ```
+/// use evtx::resolve_keyword_names;
+///
+/// assert_eq!(resolve_keyword_names(0x8020000000000000), vec!["AuditSuccess"]);
+/// assert!(resolve_keyword_names(0x8000000000000018).is_empty());
+/// ```
+pub fn resolve_keyword_names(keywords: u64) -> Vec<&'static str> {
+    STANDARD_KEYWORDS
+        .iter()
+        .filter(|(mask, _)| keywords & mask != 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
@@ -0,0 +1,20 @@
+/// Resolves the numeric `Level` of an event to the name shown by the Windows Event Viewer.
+///
+/// Only the standard levels (1 to 5) are resolved.
+///
+/// ```
+/// use evtx::resolve_level_name;
+///
+/// assert_eq!(resolve_level_name(2), Some("Error"));
+/// assert_eq!(resolve_level_name(16), None);
+/// ```
+pub fn resolve_level_name(level: u64) -> Option<&'static str> {
+    match level {
+        1 => Some("Critical"),
+        2 => Some("Error"),
+        3 => Some("Warning"),
+        4 => Some("Information"),
+        5 => Some("Verbose"),
+        _ => None,
+    }
+}
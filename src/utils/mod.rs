@@ -1,10 +1,18 @@
 mod binxml_utils;
 pub(super) mod hexdump;
+mod keyword_names;
+mod level_names;
 mod time;
+mod well_known_sids;
 
 pub use self::binxml_utils::{
     read_ansi_encoded_string, read_len_prefixed_utf16_string, read_null_terminated_utf16_string,
     read_utf16_by_size,
 };
 pub use self::hexdump::{dump_stream, hexdump};
-pub use self::time::read_systemtime;
+pub use self::keyword_names::resolve_keyword_names;
+pub use self::level_names::resolve_level_name;
+pub use self::time::{
+    datetime_to_filetime, filetime_to_datetime, read_systemtime, truncate_to_microseconds,
+};
+pub use self::well_known_sids::resolve_well_known_sid;
@@ -3,6 +3,8 @@ use crate::err::DeserializationResult;
 use crate::evtx_parser::ReadSeek;
 use byteorder::ReadBytesExt;
 use chrono::prelude::*;
+use chrono::Duration;
+use std::convert::TryFrom;
 
 pub fn read_systemtime<R: ReadSeek>(r: &mut R) -> DeserializationResult<DateTime<Utc>> {
     let year = try_read!(r, u16)?;
@@ -19,8 +21,60 @@ pub fn read_systemtime<R: ReadSeek>(r: &mut R) -> DeserializationResult<DateTime
             u32::from(hour),
             u32::from(minute),
             u32::from(second),
-            u32::from(milliseconds),
+            u32::from(milliseconds) * 1_000_000,
         ),
         Utc,
     ))
 }
+
+/// The number of seconds between 1601-01-01 (the epoch of FILETIME) and 1970-01-01.
+const FILETIME_UNIX_EPOCH_SECONDS: i64 = 11_644_473_600;
+
+/// Converts a FILETIME (the number of 100-nanosecond intervals since 1601-01-01 UTC)
+/// to a datetime, without losing any precision.
+pub fn filetime_to_datetime(filetime: u64) -> DateTime<Utc> {
+    // `u64::MAX / 10^7` seconds is well within the range of a datetime.
+    let seconds = (filetime / 10_000_000) as i64 - FILETIME_UNIX_EPOCH_SECONDS;
+    let nanoseconds = (filetime % 10_000_000) as u32 * 100;
+
+    Utc.timestamp_opt(seconds, nanoseconds)
+        .single()
+        .expect("A FILETIME is within the range of a datetime")
+}
+
+/// Converts a datetime to a FILETIME, the inverse of `filetime_to_datetime`.
+/// Returns `None` for datetimes before 1601 or too far in the future.
+pub fn datetime_to_filetime(datetime: &DateTime<Utc>) -> Option<u64> {
+    let seconds = u64::try_from(datetime.timestamp() + FILETIME_UNIX_EPOCH_SECONDS).ok()?;
+
+    seconds
+        .checked_mul(10_000_000)?
+        .checked_add(u64::from(datetime.timestamp_subsec_nanos() / 100))
+}
+
+/// Truncates a datetime to microseconds, the precision in which FILETIMEs are rendered.
+pub fn truncate_to_microseconds(datetime: &DateTime<Utc>) -> DateTime<Utc> {
+    *datetime - Duration::nanoseconds(i64::from(datetime.timestamp_subsec_nanos() % 1000))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filetime_roundtrip() {
+        let filetime = 131_124_751_716_816_408;
+        let datetime = filetime_to_datetime(filetime);
+
+        assert_eq!(datetime.to_string(), "2016-07-08 18:12:51.681640800 UTC");
+        assert_eq!(datetime_to_filetime(&datetime), Some(filetime));
+        assert_eq!(
+            truncate_to_microseconds(&datetime).to_string(),
+            "2016-07-08 18:12:51.681640 UTC"
+        );
+        assert_eq!(
+            datetime_to_filetime(&(filetime_to_datetime(0) - Duration::seconds(1))),
+            None
+        );
+    }
+}
@@ -0,0 +1,81 @@
+/// Resolves a well-known SID (in its canonical `S-1-...` string form) to a friendly name.
+///
+/// Only SIDs which are the same on every machine are resolved
+/// (built-in accounts, built-in groups and integrity levels),
+/// domain relative SIDs (ex. `S-1-5-21-...-500`) are not.
+///
+/// ```
+/// use evtx::resolve_well_known_sid;
+///
+/// assert_eq!(resolve_well_known_sid("S-1-5-18"), Some("Local System"));
+/// assert_eq!(resolve_well_known_sid("S-1-5-21-1-2-3-500"), None);
+/// ```
+pub fn resolve_well_known_sid(sid: &str) -> Option<&'static str> {
+    let name = match sid {
+        // Universal well-known SIDs.
+        "S-1-0-0" => "Nobody",
+        "S-1-1-0" => "Everyone",
+        "S-1-2-0" => "Local",
+        "S-1-2-1" => "Console Logon",
+        "S-1-3-0" => "Creator Owner",
+        "S-1-3-1" => "Creator Group",
+        "S-1-3-4" => "Owner Rights",
+        // NT Authority.
+        "S-1-5-1" => "Dialup",
+        "S-1-5-2" => "Network",
+        "S-1-5-3" => "Batch",
+        "S-1-5-4" => "Interactive",
+        "S-1-5-6" => "Service",
+        "S-1-5-7" => "Anonymous Logon",
+        "S-1-5-8" => "Proxy",
+        "S-1-5-9" => "Enterprise Domain Controllers",
+        "S-1-5-10" => "Self",
+        "S-1-5-11" => "Authenticated Users",
+        "S-1-5-12" => "Restricted Code",
+        "S-1-5-13" => "Terminal Server User",
+        "S-1-5-14" => "Remote Interactive Logon",
+        "S-1-5-15" => "This Organization",
+        "S-1-5-17" => "IUSR",
+        "S-1-5-18" => "Local System",
+        "S-1-5-19" => "Local Service",
+        "S-1-5-20" => "Network Service",
+        "S-1-5-64-10" => "NTLM Authentication",
+        "S-1-5-64-14" => "SChannel Authentication",
+        "S-1-5-64-21" => "Digest Authentication",
+        "S-1-5-80-0" => "All Services",
+        "S-1-5-113" => "Local Account",
+        "S-1-5-114" => "Local Account and member of Administrators group",
+        // Built-in groups.
+        "S-1-5-32-544" => "Administrators",
+        "S-1-5-32-545" => "Users",
+        "S-1-5-32-546" => "Guests",
+        "S-1-5-32-547" => "Power Users",
+        "S-1-5-32-548" => "Account Operators",
+        "S-1-5-32-549" => "Server Operators",
+        "S-1-5-32-550" => "Print Operators",
+        "S-1-5-32-551" => "Backup Operators",
+        "S-1-5-32-552" => "Replicators",
+        "S-1-5-32-554" => "Pre-Windows 2000 Compatible Access",
+        "S-1-5-32-555" => "Remote Desktop Users",
+        "S-1-5-32-556" => "Network Configuration Operators",
+        "S-1-5-32-558" => "Performance Monitor Users",
+        "S-1-5-32-559" => "Performance Log Users",
+        "S-1-5-32-562" => "Distributed COM Users",
+        "S-1-5-32-568" => "IIS_IUSRS",
+        "S-1-5-32-569" => "Cryptographic Operators",
+        "S-1-5-32-573" => "Event Log Readers",
+        "S-1-5-32-578" => "Hyper-V Administrators",
+        "S-1-5-32-580" => "Remote Management Users",
+        // Integrity levels.
+        "S-1-16-0" => "Untrusted Mandatory Level",
+        "S-1-16-4096" => "Low Mandatory Level",
+        "S-1-16-8192" => "Medium Mandatory Level",
+        "S-1-16-8448" => "Medium Plus Mandatory Level",
+        "S-1-16-12288" => "High Mandatory Level",
+        "S-1-16-16384" => "System Mandatory Level",
+        "S-1-16-20480" => "Protected Process Mandatory Level",
+        _ => return None,
+    };
+
+    Some(name)
+}
@@ -0,0 +1,45 @@
+/// The integrity of a file, as checked by `EvtxParser::validate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// True if the checksum stored in the file header matches its data.
+    pub file_header_checksum_valid: bool,
+    /// Every non-empty chunk of the file, in file order.
+    pub chunks: Vec<ChunkValidation>,
+}
+
+/// The integrity of a single chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkValidation {
+    pub chunk_number: u64,
+    /// False if the chunk does not start with the `ElfChnk` magic
+    /// (in which case its checksums are not checked, and are reported as invalid).
+    pub magic_valid: bool,
+    pub header_checksum_valid: bool,
+    /// True if the CRC32 of the records (stored in the chunk header) matches their data.
+    pub data_checksum_valid: bool,
+    /// The error which prevented the chunk from being read, other than a bad magic
+    /// (ex. an unknown header flag, or a truncated chunk).
+    /// Its checksums are then not checked, and are reported as invalid.
+    pub error: Option<String>,
+}
+
+impl ChunkValidation {
+    pub fn is_valid(&self) -> bool {
+        self.magic_valid
+            && self.header_checksum_valid
+            && self.data_checksum_valid
+            && self.error.is_none()
+    }
+}
+
+impl ValidationReport {
+    /// True if the file header and all the chunks are valid.
+    pub fn is_valid(&self) -> bool {
+        self.file_header_checksum_valid && self.chunks.iter().all(ChunkValidation::is_valid)
+    }
+
+    /// The chunks which failed any of the checks.
+    pub fn invalid_chunks(&self) -> impl Iterator<Item = &ChunkValidation> {
+        self.chunks.iter().filter(|chunk| !chunk.is_valid())
+    }
+}
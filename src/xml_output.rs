@@ -1,5 +1,6 @@
-use crate::binxml::value_variant::BinXmlValue;
+use crate::binxml::value_variant::{BinXmlValue, NullRendering, ValueFormat};
 use crate::err::{SerializationError, SerializationResult};
+use crate::evtx_record::RecordId;
 use crate::model::xml::{BinXmlPI, XmlElement};
 use crate::ParserSettings;
 
@@ -11,6 +12,7 @@ use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
 use quick_xml::Writer;
 
 use crate::binxml::name::BinXmlName;
+use chrono::{DateTime, Utc};
 use std::borrow::Cow;
 
 pub trait BinXmlOutput {
@@ -18,6 +20,8 @@ pub trait BinXmlOutput {
     fn visit_end_of_stream(&mut self) -> SerializationResult<()>;
 
     /// Called on <Tag attr="value" another_attr="value">.
+    /// Attribute values are passed as `BinXmlValue`s, so their original type (ex. an integer or a GUID)
+    /// is available to implementors, and can be rendered with `BinXmlValue::as_cow_str` if needed.
     fn visit_open_start_element(
         &mut self,
         open_start_element: &XmlElement,
@@ -31,35 +35,323 @@ pub trait BinXmlOutput {
     ///                                                     ~~~~~~~~~~~~~~~
     fn visit_characters(&mut self, value: &BinXmlValue) -> SerializationResult<()>;
 
-    /// Unimplemented
-    fn visit_cdata_section(&mut self) -> SerializationResult<()>;
+    /// Called with the contents of a CDATA section, (ex. <![CDATA[text]]>)
+    ///                                                           ~~~~
+    fn visit_cdata_section(&mut self, cdata: Cow<'_, str>) -> SerializationResult<()>;
 
-    /// Emit the character "&" and the text.
+    /// Emit the character "&", the text and the character ";".
     fn visit_entity_reference(&mut self, entity: &BinXmlName) -> SerializationResult<()>;
 
     /// Emit the characters "&" and "#" and the decimal string representation of the value.
     fn visit_character_reference(&mut self, char_ref: Cow<'_, str>) -> SerializationResult<()>;
 
-    /// Unimplemented
+    /// Emit the text "<?", the target, a space, the data and then the text "?>".
     fn visit_processing_instruction(&mut self, pi: &BinXmlPI) -> SerializationResult<()>;
 
     /// Called once on beginning of parsing.
     fn visit_start_of_stream(&mut self) -> SerializationResult<()>;
+
+    /// Called once before parsing a record (before `visit_start_of_stream`),
+    /// so implementors can key their state by the record from the start.
+    fn visit_start_of_record(
+        &mut self,
+        _event_record_id: RecordId,
+        _timestamp: DateTime<Utc>,
+    ) -> SerializationResult<()> {
+        Ok(())
+    }
+}
+
+/// Passes every visit to both outputs, so a record can be serialized to both in a single pass.
+/// The text of an entity reference (ex. `&amp;` is `&`),
+/// or `None` if it isn't one of the entities predefined by XML.
+pub(crate) fn entity_text(entity: &BinXmlName) -> Option<String> {
+    // We need to create a BytesText event to access quick-xml's unescape functionality (which is private).
+    // We also terminate the entity.
+    let entity_ref = "&".to_string() + entity.as_str() + ";";
+
+    let xml_event = BytesText::from_escaped_str(&entity_ref);
+    let unescaped = xml_event.unescaped().ok()?;
+
+    Some(
+        String::from_utf8(unescaped.to_vec())
+            .expect("This cannot fail, since it was a valid string beforehand"),
+    )
+}
+
+impl<A: BinXmlOutput, B: BinXmlOutput> BinXmlOutput for (A, B) {
+    fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
+        self.0.visit_end_of_stream()?;
+        self.1.visit_end_of_stream()
+    }
+
+    fn visit_open_start_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        self.0.visit_open_start_element(element)?;
+        self.1.visit_open_start_element(element)
+    }
+
+    fn visit_close_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        self.0.visit_close_element(element)?;
+        self.1.visit_close_element(element)
+    }
+
+    fn visit_characters(&mut self, value: &BinXmlValue) -> SerializationResult<()> {
+        self.0.visit_characters(value)?;
+        self.1.visit_characters(value)
+    }
+
+    fn visit_cdata_section(&mut self, cdata: Cow<'_, str>) -> SerializationResult<()> {
+        self.0.visit_cdata_section(cdata.clone())?;
+        self.1.visit_cdata_section(cdata)
+    }
+
+    fn visit_entity_reference(&mut self, entity: &BinXmlName) -> SerializationResult<()> {
+        self.0.visit_entity_reference(entity)?;
+        self.1.visit_entity_reference(entity)
+    }
+
+    fn visit_character_reference(&mut self, char_ref: Cow<'_, str>) -> SerializationResult<()> {
+        self.0.visit_character_reference(char_ref.clone())?;
+        self.1.visit_character_reference(char_ref)
+    }
+
+    fn visit_processing_instruction(&mut self, pi: &BinXmlPI) -> SerializationResult<()> {
+        self.0.visit_processing_instruction(pi)?;
+        self.1.visit_processing_instruction(pi)
+    }
+
+    fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
+        self.0.visit_start_of_stream()?;
+        self.1.visit_start_of_stream()
+    }
+
+    fn visit_start_of_record(
+        &mut self,
+        event_record_id: RecordId,
+        timestamp: DateTime<Utc>,
+    ) -> SerializationResult<()> {
+        self.0.visit_start_of_record(event_record_id, timestamp)?;
+        self.1.visit_start_of_record(event_record_id, timestamp)
+    }
+}
+
+/// Passes every visit to the borrowed output, so a borrowed output can be paired with another one.
+impl<T: BinXmlOutput + ?Sized> BinXmlOutput for &mut T {
+    fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
+        (**self).visit_end_of_stream()
+    }
+
+    fn visit_open_start_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        (**self).visit_open_start_element(element)
+    }
+
+    fn visit_close_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        (**self).visit_close_element(element)
+    }
+
+    fn visit_characters(&mut self, value: &BinXmlValue) -> SerializationResult<()> {
+        (**self).visit_characters(value)
+    }
+
+    fn visit_cdata_section(&mut self, cdata: Cow<'_, str>) -> SerializationResult<()> {
+        (**self).visit_cdata_section(cdata)
+    }
+
+    fn visit_entity_reference(&mut self, entity: &BinXmlName) -> SerializationResult<()> {
+        (**self).visit_entity_reference(entity)
+    }
+
+    fn visit_character_reference(&mut self, char_ref: Cow<'_, str>) -> SerializationResult<()> {
+        (**self).visit_character_reference(char_ref)
+    }
+
+    fn visit_processing_instruction(&mut self, pi: &BinXmlPI) -> SerializationResult<()> {
+        (**self).visit_processing_instruction(pi)
+    }
+
+    fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
+        (**self).visit_start_of_stream()
+    }
+
+    fn visit_start_of_record(
+        &mut self,
+        event_record_id: RecordId,
+        timestamp: DateTime<Utc>,
+    ) -> SerializationResult<()> {
+        (**self).visit_start_of_record(event_record_id, timestamp)
+    }
+}
+
+/// Passes every visit to the output if there is one, so an output can be paired with another one
+/// only when it is needed.
+impl<T: BinXmlOutput> BinXmlOutput for Option<T> {
+    fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
+        match self {
+            Some(output) => output.visit_end_of_stream(),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_open_start_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        match self {
+            Some(output) => output.visit_open_start_element(element),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_close_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+        match self {
+            Some(output) => output.visit_close_element(element),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_characters(&mut self, value: &BinXmlValue) -> SerializationResult<()> {
+        match self {
+            Some(output) => output.visit_characters(value),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_cdata_section(&mut self, cdata: Cow<'_, str>) -> SerializationResult<()> {
+        match self {
+            Some(output) => output.visit_cdata_section(cdata),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_entity_reference(&mut self, entity: &BinXmlName) -> SerializationResult<()> {
+        match self {
+            Some(output) => output.visit_entity_reference(entity),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_character_reference(&mut self, char_ref: Cow<'_, str>) -> SerializationResult<()> {
+        match self {
+            Some(output) => output.visit_character_reference(char_ref),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_processing_instruction(&mut self, pi: &BinXmlPI) -> SerializationResult<()> {
+        match self {
+            Some(output) => output.visit_processing_instruction(pi),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
+        match self {
+            Some(output) => output.visit_start_of_stream(),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_start_of_record(
+        &mut self,
+        event_record_id: RecordId,
+        timestamp: DateTime<Utc>,
+    ) -> SerializationResult<()> {
+        match self {
+            Some(output) => output.visit_start_of_record(event_record_id, timestamp),
+            None => Ok(()),
+        }
+    }
 }
 
+/// Controls the indentation of XML output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    /// Indent each level with the given number of spaces.
+    Spaces(usize),
+    /// Indent each level with a single tab.
+    Tab,
+}
+
+impl Default for IndentStyle {
+    fn default() -> Self {
+        IndentStyle::Spaces(2)
+    }
+}
+
+impl IndentStyle {
+    fn char_and_size(self) -> (u8, usize) {
+        match self {
+            IndentStyle::Spaces(n) => (b' ', n),
+            IndentStyle::Tab => (b'\t', 1),
+        }
+    }
+}
+
+/// The namespace of the `<Event>` element, as declared by Windows.
+const EVENT_NAMESPACE: &str = "http://schemas.microsoft.com/win/2004/08/events/event";
+
 pub struct XmlOutput<W: Write> {
     writer: Writer<W>,
+    value_format: ValueFormat,
+    emit_xml_declaration: bool,
+    self_closing_empty_elements: bool,
+    /// If set, attributes with a null value are written as empty strings
+    /// (see `NullRendering::EmptyString`). Other empty attributes are always left out.
+    empty_attributes: bool,
+    /// When writing self-closing tags, the start of an element is held back until
+    /// it is known whether the element has any content.
+    pending_start: Option<BytesStart<'static>>,
+    compact: bool,
+    /// In compact mode, whitespace-only text is held back until it is known whether it is
+    /// between elements (and dropped), or is the content of an element.
+    pending_whitespace: Option<String>,
+    /// True if the last thing written was the end of an element.
+    after_end_tag: bool,
+    /// If set, a root `<Event>` element without an `xmlns` attribute gets `EVENT_NAMESPACE`.
+    include_event_namespace: bool,
+    /// The number of currently open elements.
+    depth: usize,
 }
 
 impl<W: Write> XmlOutput<W> {
     pub fn with_writer(target: W, settings: &ParserSettings) -> Self {
-        let writer = if settings.should_indent() {
-            Writer::new_with_indent(target, b' ', 2)
+        let writer = if settings.should_indent() && !settings.is_xml_compact() {
+            let (indent_char, indent_size) = settings.get_indent_style().char_and_size();
+            Writer::new_with_indent(target, indent_char, indent_size)
         } else {
             Writer::new(target)
         };
 
-        XmlOutput { writer }
+        XmlOutput {
+            writer,
+            value_format: ValueFormat::from_settings(settings),
+            emit_xml_declaration: settings.should_emit_xml_declaration(),
+            self_closing_empty_elements: settings.should_self_close_empty_elements(),
+            empty_attributes: settings.get_null_value() == NullRendering::EmptyString,
+            pending_start: None,
+            compact: settings.is_xml_compact(),
+            pending_whitespace: None,
+            after_end_tag: false,
+            include_event_namespace: settings.should_include_event_namespace(),
+            depth: 0,
+        }
+    }
+
+    /// Writes the held back whitespace, since it is adjacent to other text.
+    fn write_pending_whitespace(&mut self) -> SerializationResult<()> {
+        if let Some(whitespace) = self.pending_whitespace.take() {
+            self.write_pending_start()?;
+            let event = BytesText::from_plain_str(&whitespace);
+            self.writer.write_event(Event::Text(event))?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the start of the last opened element, since it has content.
+    fn write_pending_start(&mut self) -> SerializationResult<()> {
+        if let Some(start) = self.pending_start.take() {
+            self.writer.write_event(Event::Start(start))?;
+        }
+
+        Ok(())
     }
 
     pub fn into_writer(self) -> W {
@@ -71,6 +363,8 @@ impl<W: Write> XmlOutput<W> {
 impl<W: Write> BinXmlOutput for XmlOutput<W> {
     fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
         trace!("visit_end_of_stream");
+        self.pending_whitespace = None;
+        self.write_pending_start()?;
         self.writer.write_event(Event::Eof)?;
 
         Ok(())
@@ -82,23 +376,64 @@ impl<W: Write> BinXmlOutput for XmlOutput<W> {
         let mut event_builder =
             BytesStart::borrowed_name(element.name.as_ref().as_str().as_bytes());
 
+        if self.include_event_namespace
+            && self.depth == 0
+            && element.name.as_str() == "Event"
+            && !element
+                .attributes
+                .iter()
+                .any(|attribute| attribute.name.as_str() == "xmlns")
+        {
+            event_builder.push_attribute(("xmlns", EVENT_NAMESPACE));
+        }
+        self.depth += 1;
+
         for attr in element.attributes.iter() {
-            let value_cow: Cow<'_, str> = attr.value.as_ref().as_cow_str();
+            let value_cow: Cow<'_, str> = match attr.value.as_ref() {
+                // Lists in attributes are space separated (like `xs:list`).
+                BinXmlValue::StringArrayType(items) => Cow::Owned(items.join(" ")),
+                value => value.as_cow_str_with_format(self.value_format),
+            };
 
-            if value_cow.len() > 0 {
+            let is_null = matches!(attr.value.as_ref(), BinXmlValue::NullType);
+
+            if value_cow.len() > 0 || (is_null && self.empty_attributes) {
                 let name_as_str = attr.name.as_str();
                 let attr = Attribute::from((name_as_str, value_cow.as_ref()));
                 event_builder.push_attribute(attr);
             }
         }
 
-        self.writer.write_event(Event::Start(event_builder))?;
+        // Whitespace before a child element is structural.
+        self.pending_whitespace = None;
+        self.after_end_tag = false;
+
+        if self.self_closing_empty_elements {
+            self.write_pending_start()?;
+            self.pending_start = Some(event_builder.into_owned());
+        } else {
+            self.writer.write_event(Event::Start(event_builder))?;
+        }
 
         Ok(())
     }
 
     fn visit_close_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
         trace!("visit_close_element");
+        self.depth = self.depth.saturating_sub(1);
+        // Whitespace after a child element is structural, otherwise it is the element's text.
+        if self.after_end_tag {
+            self.pending_whitespace = None;
+        } else {
+            self.write_pending_whitespace()?;
+        }
+        self.after_end_tag = true;
+
+        if let Some(start) = self.pending_start.take() {
+            self.writer.write_event(Event::Empty(start))?;
+            return Ok(());
+        }
+
         let event = BytesEnd::borrowed(element.name.as_ref().as_str().as_bytes());
 
         self.writer.write_event(Event::End(event))?;
@@ -108,23 +443,50 @@ impl<W: Write> BinXmlOutput for XmlOutput<W> {
 
     fn visit_characters(&mut self, value: &BinXmlValue) -> SerializationResult<()> {
         trace!("visit_chars");
-        let cow: Cow<str> = value.as_cow_str();
+        let cow: Cow<str> = value.as_cow_str_with_format(self.value_format);
+        if cow.is_empty() && self.pending_start.is_some() {
+            return Ok(());
+        }
+
+        if self.compact && cow.trim().is_empty() {
+            let whitespace = self.pending_whitespace.get_or_insert_with(String::new);
+            whitespace.push_str(&cow);
+            return Ok(());
+        }
+
+        self.write_pending_whitespace()?;
+        self.after_end_tag = false;
+        self.write_pending_start()?;
         let event = BytesText::from_plain_str(&cow);
         self.writer.write_event(Event::Text(event))?;
 
         Ok(())
     }
 
-    fn visit_cdata_section(&mut self) -> SerializationResult<()> {
-        Err(SerializationError::Unimplemented {
-            message: format!("`{}`: visit_cdata_section", file!()),
-        })
+    fn visit_cdata_section(&mut self, cdata: Cow<'_, str>) -> SerializationResult<()> {
+        self.write_pending_whitespace()?;
+        self.after_end_tag = false;
+        self.write_pending_start()?;
+        // CDATA contents are written as is, except for the `]]>` terminator,
+        // which is split between two sections.
+        let cdata = if cdata.contains("]]>") {
+            Cow::Owned(cdata.replace("]]>", "]]]]><![CDATA[>"))
+        } else {
+            cdata
+        };
+        let event = Event::CData(BytesText::from_escaped_str(cdata));
+        self.writer.write_event(event)?;
+
+        Ok(())
     }
 
     fn visit_entity_reference(&mut self, entity: &BinXmlName) -> Result<(), SerializationError> {
-        let xml_ref = "&".to_string() + entity.as_str();
-        // This will yield stuff like `&quot`, which should be escaped.
-        let event = Event::Text(BytesText::from_plain_str(&xml_ref));
+        self.write_pending_whitespace()?;
+        self.after_end_tag = false;
+        self.write_pending_start()?;
+        let xml_ref = format!("&{};", entity.as_str());
+        // The reference itself must not be escaped, otherwise `&quot;` would become `&amp;quot;`.
+        let event = Event::Text(BytesText::from_escaped_str(&xml_ref));
         self.writer.write_event(event)?;
 
         Ok(())
@@ -142,8 +504,15 @@ impl<W: Write> BinXmlOutput for XmlOutput<W> {
     fn visit_processing_instruction(&mut self, pi: &BinXmlPI) -> SerializationResult<()> {
         // PITARGET - Emit the text "<?", the text (as specified by the Name rule in 2.2.12), and then the space character " ".
         // Emit the text (as specified by the NullTerminatedUnicodeString rule in 2.2.12), and then the text "?>".
-        let concat = pi.name.as_str().to_owned() + pi.data.as_ref(); // only `String` supports concatenation.
-        let event = Event::PI(BytesText::from_plain_str(concat.as_str()));
+        self.pending_whitespace = None;
+        self.write_pending_start()?;
+        let concat = if pi.data.is_empty() {
+            pi.name.as_str().to_owned()
+        } else {
+            format!("{} {}", pi.name.as_str(), pi.data)
+        };
+        // PI data is written as is, like CDATA.
+        let event = Event::PI(BytesText::from_escaped_str(concat.as_str()));
         self.writer.write_event(event)?;
 
         Ok(())
@@ -151,6 +520,10 @@ impl<W: Write> BinXmlOutput for XmlOutput<W> {
 
     fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
         trace!("visit_start_of_stream");
+        if !self.emit_xml_declaration {
+            return Ok(());
+        }
+
         let event = BytesDecl::new(b"1.0", Some(b"utf-8"), None);
 
         self.writer.write_event(Event::Decl(event))?;
@@ -158,3 +531,180 @@ impl<W: Write> BinXmlOutput for XmlOutput<W> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element(name: &str) -> XmlElement<'static> {
+        XmlElement {
+            name: Cow::Owned(BinXmlName::from_str(name)),
+            attributes: vec![],
+            offset: 0,
+        }
+    }
+
+    #[test]
+    fn test_entity_text() {
+        assert_eq!(
+            entity_text(&BinXmlName::from_str("amp")).as_deref(),
+            Some("&")
+        );
+        assert_eq!(
+            entity_text(&BinXmlName::from_str("lt")).as_deref(),
+            Some("<")
+        );
+        assert_eq!(entity_text(&BinXmlName::from_str("nbsp")), None);
+    }
+
+    #[test]
+    fn test_cdata_entity_reference_and_pi() {
+        let settings = ParserSettings::new().indent(false);
+        let mut output = XmlOutput::with_writer(vec![], &settings);
+        let data = element("Data");
+
+        output.visit_start_of_stream().unwrap();
+        output
+            .visit_processing_instruction(&BinXmlPI {
+                name: Cow::Owned(BinXmlName::from_str("xml-stylesheet")),
+                data: Cow::Borrowed("href=\"style.xsl\""),
+            })
+            .unwrap();
+        output.visit_open_start_element(&data).unwrap();
+        output
+            .visit_cdata_section(Cow::Borrowed("<b>&</b>"))
+            .unwrap();
+        output
+            .visit_entity_reference(&BinXmlName::from_str("quot"))
+            .unwrap();
+        output.visit_close_element(&data).unwrap();
+        output.visit_end_of_stream().unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.into_writer()).unwrap(),
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+             <?xml-stylesheet href=\"style.xsl\"?>\
+             <Data><![CDATA[<b>&</b>]]>&quot;</Data>"
+        );
+    }
+
+    #[test]
+    fn test_cdata_terminator_is_split() {
+        let settings = ParserSettings::new().indent(false);
+        let mut output = XmlOutput::with_writer(vec![], &settings);
+        let data = element("Data");
+
+        output.visit_open_start_element(&data).unwrap();
+        output
+            .visit_cdata_section(Cow::Borrowed("a]]>b]]>"))
+            .unwrap();
+        output.visit_close_element(&data).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.into_writer()).unwrap(),
+            "<Data><![CDATA[a]]]]><![CDATA[>b]]]]><![CDATA[>]]></Data>"
+        );
+    }
+
+    #[test]
+    fn test_compact_drops_whitespace_between_elements() {
+        let settings = ParserSettings::new().indent(true).xml_compact(true);
+        let mut output = XmlOutput::with_writer(vec![], &settings);
+        let (event, data) = (element("Event"), element("Data"));
+        let text = |s: &str| BinXmlValue::StringType(s.to_owned());
+
+        output.visit_start_of_stream().unwrap();
+        output.visit_open_start_element(&event).unwrap();
+        output.visit_characters(&text("\n  ")).unwrap();
+        output.visit_open_start_element(&data).unwrap();
+        output.visit_characters(&text(" a\t b\n")).unwrap();
+        output.visit_close_element(&data).unwrap();
+        output.visit_characters(&text("\n  ")).unwrap();
+        output.visit_open_start_element(&data).unwrap();
+        output.visit_characters(&text(" ")).unwrap();
+        output.visit_close_element(&data).unwrap();
+        output.visit_characters(&text("\n")).unwrap();
+        output.visit_close_element(&event).unwrap();
+        output.visit_end_of_stream().unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.into_writer()).unwrap(),
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+             <Event><Data> a\t b\n</Data><Data> </Data></Event>"
+        );
+    }
+
+    #[test]
+    fn test_only_null_attributes_are_written_as_empty_strings() {
+        use crate::model::xml::XmlAttribute;
+
+        let attribute = |name: &str, value: BinXmlValue<'static>| XmlAttribute {
+            name: Cow::Owned(BinXmlName::from_str(name)),
+            value: Cow::Owned(value),
+        };
+        let security = XmlElement {
+            attributes: vec![
+                attribute("UserID", BinXmlValue::NullType),
+                attribute("Empty", BinXmlValue::StringType(String::new())),
+            ],
+            ..element("Security")
+        };
+
+        let write = |null_value: NullRendering| {
+            let settings = ParserSettings::new().indent(false).null_value(null_value);
+            let mut output = XmlOutput::with_writer(vec![], &settings);
+            output.visit_open_start_element(&security).unwrap();
+            output.visit_close_element(&security).unwrap();
+
+            String::from_utf8(output.into_writer()).unwrap()
+        };
+
+        assert_eq!(
+            write(NullRendering::EmptyString),
+            "<Security UserID=\"\"></Security>"
+        );
+        assert_eq!(write(NullRendering::JsonNull), "<Security></Security>");
+    }
+
+    #[test]
+    fn test_include_event_namespace() {
+        use crate::model::xml::XmlAttribute;
+
+        let with_xmlns = |name: &str, xmlns: &str| XmlElement {
+            attributes: vec![XmlAttribute {
+                name: Cow::Owned(BinXmlName::from_str("xmlns")),
+                value: Cow::Owned(BinXmlValue::StringType(xmlns.to_owned())),
+            }],
+            ..element(name)
+        };
+        let write = |elements: &[XmlElement]| {
+            let settings = ParserSettings::new()
+                .indent(false)
+                .include_event_namespace(true);
+            let mut output = XmlOutput::with_writer(vec![], &settings);
+            for element in elements {
+                output.visit_open_start_element(element).unwrap();
+            }
+            for element in elements.iter().rev() {
+                output.visit_close_element(element).unwrap();
+            }
+
+            String::from_utf8(output.into_writer()).unwrap()
+        };
+
+        assert_eq!(
+            write(&[element("Event"), element("Event")]),
+            format!(
+                "<Event xmlns=\"{}\"><Event></Event></Event>",
+                EVENT_NAMESPACE
+            )
+        );
+
+        // An existing namespace is kept, and only the `<Event>` root gets one.
+        assert_eq!(
+            write(&[with_xmlns("Event", "urn:other")]),
+            "<Event xmlns=\"urn:other\"></Event>"
+        );
+        assert_eq!(write(&[element("Events")]), "<Events></Events>");
+    }
+}
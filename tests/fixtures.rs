@@ -70,6 +70,12 @@ pub fn sample_with_multiple_xml_fragments() -> PathBuf {
         .join("E_Windows_system32_winevt_logs_Microsoft-Windows-Shell-Core%4Operational.evtx")
 }
 
+/// The first chunk of `security.evtx`, where the last record has a second root element
+/// (`<Extra>second root</Extra>`) after its `<Event>`.
+pub fn sample_with_multiple_root_fragments() -> PathBuf {
+    samples_dir().join("multiple_root_fragments.evtx")
+}
+
 pub fn sample_with_binxml_as_substitution_tokens_and_pi_target() -> PathBuf {
     samples_dir().join("E_Windows_system32_winevt_logs_Microsoft-Windows-CAPI2%4Operational.evtx")
 }
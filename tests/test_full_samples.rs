@@ -1,9 +1,13 @@
 mod fixtures;
 
-use evtx::{EvtxParser, ParserSettings};
+use evtx::err::{ChunkError, EvtxError};
+use evtx::{EvtxParser, ParserSettings, ZeroPaddedChunk};
 use fixtures::*;
 use log::Level;
+use std::fs::File;
+use std::io::BufReader;
 use std::path::Path;
+use std::sync::{Arc, Mutex};
 
 /// Tests an .evtx file, asserting the number of parsed records matches `count`.
 fn test_full_sample(path: impl AsRef<Path>, ok_count: usize, err_count: usize) {
@@ -115,6 +119,124 @@ fn test_dirty_sample_with_a_bad_checksum() {
     test_full_sample(sample_with_a_bad_checksum(), 1910, 4)
 }
 
+#[test]
+fn test_dirty_sample_with_a_bad_checksum_reports_errors_to_on_error() {
+    ensure_env_logger_initialized();
+    let errors = Arc::new(Mutex::new(vec![]));
+    let errors_in_callback = Arc::clone(&errors);
+
+    let mut parser = EvtxParser::from_path(sample_with_a_bad_checksum())
+        .unwrap()
+        .on_error(Box::new(move |err| {
+            errors_in_callback.lock().unwrap().push(err.to_string())
+        }));
+
+    assert_eq!(parser.records_ok().count(), 1910);
+
+    let errors = errors.lock().unwrap();
+    assert_eq!(errors.len(), 4);
+}
+
+#[test]
+fn test_records_carry_the_chunk_they_were_read_from() {
+    ensure_env_logger_initialized();
+    let mut parser = EvtxParser::from_path(sample_with_a_bad_checksum()).unwrap();
+
+    // Checksums are not calculated unless they are validated.
+    let records: Vec<_> = parser.records().filter_map(|r| r.ok()).collect();
+    assert!(records.iter().all(|r| r.chunk_number.is_some()));
+    assert!(records.iter().all(|r| r.chunk_checksum_valid.is_none()));
+
+    // When validating checksums, records from bad chunks are never yielded.
+    let mut parser = parser.with_configuration(ParserSettings::new().validate_checksums(true));
+    assert!(parser
+        .records_json()
+        .filter_map(|r| r.ok())
+        .all(|r| r.chunk_checksum_valid == Some(true)));
+}
+
+#[test]
+fn test_iteration_resumes_after_a_bad_chunk_magic() {
+    ensure_env_logger_initialized();
+    let mut parser = EvtxParser::from_path(sample_with_a_bad_chunk_magic()).unwrap();
+
+    let chunks: Vec<_> = parser
+        .chunks()
+        .map(|chunk| match chunk {
+            Ok(chunk) => Ok(chunk.chunk_number().unwrap()),
+            Err(EvtxError::FailedToParseChunk {
+                chunk_id,
+                source: ChunkError::FailedToParseChunkHeader(_),
+            }) => Err(chunk_id),
+            Err(err) => panic!("Unexpected error: {}", err),
+        })
+        .collect();
+
+    // Chunks 1 to 5 are empty, and the file is zero padded after chunk 10.
+    assert_eq!(chunks, vec![Ok(0), Err(6), Ok(7), Ok(8), Ok(9), Err(10)]);
+}
+
+#[test]
+fn test_bad_chunk_checksum_reports_the_checksums() {
+    ensure_env_logger_initialized();
+    let mut parser = EvtxParser::from_path(sample_with_a_bad_checksum())
+        .unwrap()
+        .with_configuration(ParserSettings::new().validate_checksums(true));
+
+    let mismatches: Vec<_> = parser
+        .records()
+        .filter_map(|r| match r {
+            Err(EvtxError::FailedToParseChunk {
+                chunk_id,
+                source:
+                    ChunkError::InvalidChunkChecksum {
+                        chunk_number,
+                        expected,
+                        found,
+                    },
+            }) => Some((chunk_id, chunk_number, expected, found)),
+            _ => None,
+        })
+        .collect();
+
+    assert!(!mismatches.is_empty());
+    for (chunk_id, chunk_number, expected, found) in mismatches {
+        assert_eq!(chunk_id, chunk_number);
+        assert_ne!(expected, found);
+    }
+}
+
+#[test]
+fn test_dirty_sample_without_computing_checksums() {
+    ensure_env_logger_initialized();
+    let mut parser = EvtxParser::from_path(sample_with_a_bad_checksum())
+        .unwrap()
+        .with_configuration(
+            ParserSettings::new()
+                .validate_checksums(true)
+                .compute_checksums(false),
+        );
+
+    let records: Vec<_> = parser.records().filter_map(|r| r.ok()).collect();
+    assert_eq!(records.len(), 1910);
+    assert!(records.iter().all(|r| r.chunk_checksum_valid.is_none()));
+}
+
+#[test]
+fn test_dirty_sample_with_a_bad_checksum_in_strict_mode() {
+    ensure_env_logger_initialized();
+    let mut parser = EvtxParser::from_path(sample_with_a_bad_checksum())
+        .unwrap()
+        .with_configuration(ParserSettings::new().strict(true));
+
+    let results: Vec<_> = parser.records().collect();
+    let (last, records) = results.split_last().unwrap();
+
+    assert!(last.is_err());
+    assert!(records.iter().all(|r| r.is_ok()));
+    assert!(records.len() < 1910);
+}
+
 #[test]
 fn test_dirty_sample_with_a_bad_checksum_2() {
     // TODO: investigate 2 failing records
@@ -126,6 +248,23 @@ fn test_dirty_sample_with_a_chunk_past_zeros() {
     test_full_sample(sample_with_a_chunk_past_zeroes(), 1160, 0)
 }
 
+#[test]
+fn test_dirty_sample_with_a_chunk_past_zeros_stops_at_padding() {
+    ensure_env_logger_initialized();
+    let mut parser = EvtxParser::from_path(sample_with_a_chunk_past_zeroes()).unwrap();
+
+    // Chunks 10 and 11 are zeroed, chunk 12 is the chunk past them and the rest is padding.
+    let chunk_numbers: Vec<u64> = parser
+        .chunks()
+        .map(|c| c.unwrap().chunk_number().unwrap())
+        .collect();
+    assert_eq!(chunk_numbers.last(), Some(&12));
+    assert_eq!(
+        parser.zero_padding(),
+        Some(ZeroPaddedChunk { chunk_number: 13 })
+    );
+}
+
 #[test]
 fn test_dirty_sample_with_a_bad_chunk_magic() {
     test_full_sample(sample_with_a_bad_chunk_magic(), 270, 5)
@@ -175,3 +314,43 @@ fn test_sample_with_no_crc32() {
         0,
     )
 }
+
+#[test]
+fn test_parses_from_arbitrary_read_seek_source() {
+    ensure_env_logger_initialized();
+    let reader = BufReader::new(File::open(regular_sample()).unwrap());
+    let mut parser = EvtxParser::from_read_seek(reader).unwrap();
+
+    let records: Vec<_> = parser.records().map(|r| r.unwrap().event_record_id).collect();
+    let expected: Vec<_> = EvtxParser::from_path(regular_sample())
+        .unwrap()
+        .records()
+        .map(|r| r.unwrap().event_record_id)
+        .collect();
+
+    assert_eq!(records.len(), 2261);
+    assert_eq!(records, expected);
+}
+
+#[test]
+fn test_template_misses_are_reported() {
+    ensure_env_logger_initialized();
+    let template_misses = |path| {
+        let misses = Arc::new(Mutex::new(vec![]));
+        let reported = Arc::clone(&misses);
+        let settings = ParserSettings::new().on_template_miss(Box::new(move |offset| {
+            reported.lock().unwrap().push(offset)
+        }));
+
+        let mut parser = EvtxParser::from_path(path)
+            .unwrap()
+            .with_configuration(settings);
+        for _ in parser.records() {}
+
+        let misses = misses.lock().unwrap().clone();
+        misses
+    };
+
+    assert!(template_misses(regular_sample()).is_empty());
+    assert_eq!(template_misses(sample_with_a_bad_checksum_2()).len(), 1);
+}
@@ -1,8 +1,18 @@
 mod fixtures;
 use fixtures::*;
 
-use evtx::{EvtxParser, ParserSettings};
+use evtx::binxml::name::BinXmlName;
+use evtx::binxml::value_variant::BinXmlValue;
+use evtx::err::{EvtxError, SerializationError, SerializationResult};
+use evtx::model::xml::{BinXmlPI, XmlElement};
+use evtx::{
+    BinXmlOutput, BinaryEncoding, EvtxParser, GuidFormat, IndentStyle, MultiFragmentMode,
+    NullRendering, ParserSettings,
+};
 use pretty_assertions::assert_eq;
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::HashMap;
 
 #[test]
 fn test_event_xml_sample() {
@@ -276,3 +286,664 @@ fn test_event_json_sample_with_separate_json_attributes() {
             .collect::<String>()
     );
 }
+
+#[test]
+fn test_event_with_binary_data_respects_binary_encoding() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/system.evtx");
+
+    for (encoding, expected) in [
+        (BinaryEncoding::HexUpper, "43006C00640046006C0074000000"),
+        (BinaryEncoding::Hex, "43006c00640046006c0074000000"),
+        (BinaryEncoding::Base64, "QwBsAGQARgBsAHQAAAA="),
+        (BinaryEncoding::Base64NoPad, "QwBsAGQARgBsAHQAAAA"),
+    ]
+    .iter()
+    {
+        let settings = ParserSettings::new().binary_encoding(*encoding);
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(settings);
+
+        assert!(
+            parser
+                .records_json()
+                .any(|r| r.unwrap().data.contains(&format!("\"Binary\": \"{}\"", expected))),
+            "JSON: {:?}",
+            encoding
+        );
+        assert!(
+            parser
+                .records()
+                .any(|r| r.unwrap().data.contains(&format!("<Binary>{}</Binary>", expected))),
+            "XML: {:?}",
+            encoding
+        );
+    }
+}
+
+#[test]
+fn test_empty_binary_data_is_an_empty_string() {
+    for encoding in [
+        BinaryEncoding::HexUpper,
+        BinaryEncoding::Hex,
+        BinaryEncoding::Base64,
+        BinaryEncoding::Base64NoPad,
+    ]
+    .iter()
+    {
+        assert_eq!(encoding.encode(&[]), "");
+    }
+}
+
+#[test]
+fn test_well_known_sids_are_resolved() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+    let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+        .unwrap()
+        .with_configuration(ParserSettings::new().resolve_well_known_sids(true));
+
+    let json: Vec<String> = parser.records_json().map(|r| r.unwrap().data).collect();
+    assert!(json
+        .iter()
+        .any(|r| r.contains("\"SubjectUserSid\": \"Local System\"")));
+    assert!(json.iter().all(|r| !r.contains("\"S-1-5-18\"")));
+
+    let xml: Vec<String> = parser.records().map(|r| r.unwrap().data).collect();
+    assert!(xml
+        .iter()
+        .any(|r| r.contains("<Data Name=\"SubjectUserSid\">Local System</Data>")));
+    assert!(xml.iter().all(|r| !r.contains(">S-1-5-18<")));
+}
+
+#[test]
+fn test_well_known_sids_are_not_resolved_by_default() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+    let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+    assert!(parser
+        .records_json()
+        .any(|r| r.unwrap().data.contains("\"SubjectUserSid\": \"S-1-5-18\"")));
+}
+
+#[test]
+fn test_large_ints_as_strings() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+
+    let first_record = |settings: ParserSettings| {
+        EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(settings)
+            .records_json_value()
+            .next()
+            .unwrap()
+            .unwrap()
+            .data
+    };
+
+    let json = first_record(ParserSettings::new().large_ints_as_strings(true));
+    assert_eq!(json["Event"]["System"]["EventRecordID"], "1");
+    assert_eq!(json["Event"]["System"]["EventID"], 4608);
+
+    let json = first_record(ParserSettings::new());
+    assert_eq!(json["Event"]["System"]["EventRecordID"], 1);
+
+    let xml = EvtxParser::from_buffer(evtx_file.to_vec())
+        .unwrap()
+        .with_configuration(ParserSettings::new().large_ints_as_strings(true))
+        .records()
+        .next()
+        .unwrap()
+        .unwrap()
+        .data;
+    assert!(xml.contains("<EventRecordID>1</EventRecordID>"));
+}
+
+#[test]
+fn test_field_renames() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+    let renames: HashMap<String, String> = [("TimeCreated", "@timestamp"), ("SystemTime", "time")]
+        .iter()
+        .map(|(from, to)| (from.to_string(), to.to_string()))
+        .collect();
+    let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+        .unwrap()
+        .with_configuration(ParserSettings::new().field_renames(renames));
+
+    let json = parser.records_json_value().next().unwrap().unwrap().data;
+    let system = &json["Event"]["System"];
+    assert!(system.get("TimeCreated").is_none());
+    assert!(system["@timestamp"]["#attributes"]["time"].is_string());
+    // Names which are not mapped are kept.
+    assert_eq!(system["EventRecordID"], 1);
+
+    // XML is not renamed, `@timestamp` is not a valid XML name.
+    let xml = parser.records().next().unwrap().unwrap().data;
+    assert!(xml.contains("<TimeCreated SystemTime=\""));
+}
+
+#[test]
+fn test_level_names_are_rendered() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/system.evtx");
+    let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+        .unwrap()
+        .with_configuration(ParserSettings::new().render_level_names(true));
+
+    let xml: Vec<String> = parser.records().map(|r| r.unwrap().data).collect();
+    assert!(xml
+        .iter()
+        .any(|r| r.contains("<Level Name=\"Warning\">3</Level>")));
+    assert!(xml.iter().all(|r| !r.contains("<Level>3</Level>")));
+
+    let json: Vec<String> = parser.records_json().map(|r| r.unwrap().data).collect();
+    assert!(json.iter().any(|r| r.contains("\"Name\": \"Error\"")));
+
+    let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+    assert!(parser
+        .records()
+        .any(|r| r.unwrap().data.contains("<Level>3</Level>")));
+}
+
+#[test]
+fn test_keywords_are_decoded() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+    let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+        .unwrap()
+        .with_configuration(ParserSettings::new().decode_keywords(true));
+
+    let xml: Vec<String> = parser.records().map(|r| r.unwrap().data).collect();
+    assert!(xml.iter().any(|r| r
+        .contains("<Keywords Names=\"AuditSuccess\">0x8020000000000000</Keywords>")));
+
+    let json: Vec<serde_json::Value> = parser
+        .records_json_value()
+        .map(|r| r.unwrap().data)
+        .collect();
+    assert!(json.iter().any(|r| r["Event"]["System"]["Keywords"]
+        == serde_json::json!({
+            "#attributes": { "Names": ["AuditSuccess"] },
+            "#text": "0x8020000000000000"
+        })));
+}
+
+#[test]
+fn test_provider_guid_respects_guid_format() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+
+    for (format, expected) in [
+        (GuidFormat::Braces, "{54849625-5478-4994-A5BA-3E3B0328C30D}"),
+        (GuidFormat::Hyphenated, "54849625-5478-4994-a5ba-3e3b0328c30d"),
+        (GuidFormat::HyphenatedUpper, "54849625-5478-4994-A5BA-3E3B0328C30D"),
+        (GuidFormat::Urn, "urn:uuid:54849625-5478-4994-a5ba-3e3b0328c30d"),
+    ]
+    .iter()
+    {
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(ParserSettings::new().guid_format(*format));
+
+        let first_record = parser
+            .records()
+            .next()
+            .expect("to have records")
+            .expect("record to parse correctly");
+        assert!(
+            first_record.data.contains(&format!(
+                "<Provider Name=\"Microsoft-Windows-Security-Auditing\" Guid=\"{}\">",
+                expected
+            )),
+            "XML: {:?}",
+            format
+        );
+
+        let first_record = parser
+            .records_json()
+            .next()
+            .expect("to have records")
+            .expect("record to parse correctly");
+        assert!(
+            first_record
+                .data
+                .contains(&format!("\"Guid\": \"{}\"", expected)),
+            "JSON: {:?}",
+            format
+        );
+    }
+}
+
+#[test]
+fn test_event_xml_sample_without_declaration() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+    let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+        .unwrap()
+        .with_configuration(ParserSettings::new().emit_xml_declaration(false));
+
+    let first_record = parser
+        .records()
+        .next()
+        .expect("to have records")
+        .expect("record to parse correctly");
+
+    assert!(first_record.data.starts_with("<Event "));
+    assert_eq!(
+        first_record.data.lines().map(str::trim).collect::<String>(),
+        include_str!("../samples/security_event_1.xml")
+            .lines()
+            .skip(1)
+            .map(str::trim)
+            .collect::<String>()
+    );
+}
+
+#[test]
+fn test_event_xml_sample_with_indent_style() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+    let first_record_xml = |settings: ParserSettings| {
+        EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(settings)
+            .records()
+            .next()
+            .expect("to have records")
+            .expect("record to parse correctly")
+            .data
+    };
+
+    let default = first_record_xml(ParserSettings::new());
+    assert!(default.contains("\n  <System>\n    <Provider "));
+
+    let four_spaces = first_record_xml(ParserSettings::new().indent_style(IndentStyle::Spaces(4)));
+    assert!(four_spaces.contains("\n    <System>\n        <Provider "));
+
+    let tabs = first_record_xml(ParserSettings::new().indent_style(IndentStyle::Tab));
+    assert!(tabs.contains("\n\t<System>\n\t\t<Provider "));
+
+    let no_indent = first_record_xml(
+        ParserSettings::new()
+            .indent(false)
+            .indent_style(IndentStyle::Tab),
+    );
+    assert!(!no_indent.contains('\n'));
+    assert!(!no_indent.contains('\t'));
+}
+
+#[test]
+fn test_records_into_struct() {
+    #[derive(Debug, Deserialize)]
+    struct Record {
+        #[serde(rename = "Event")]
+        event: Event,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Event {
+        #[serde(rename = "System")]
+        system: System,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct System {
+        #[serde(rename = "EventID")]
+        event_id: u32,
+        channel: String,
+        computer: String,
+    }
+
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+    let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+    let records: Vec<_> = parser
+        .records_into_struct::<Record>()
+        .map(|r| r.expect("record to deserialize"))
+        .collect();
+
+    assert_eq!(records.len(), 2261);
+    assert_eq!(records[0].event_record_id, 1);
+    assert_eq!(records[0].data.event.system.event_id, 4608);
+    assert_eq!(records[0].data.event.system.channel, "Security");
+    assert_eq!(records[0].data.event.system.computer, "37L4247F27-25");
+}
+
+#[test]
+fn test_records_into_struct_reports_the_failing_record() {
+    #[derive(Debug, Deserialize)]
+    struct Missing {
+        #[allow(dead_code)]
+        does_not_exist: u32,
+    }
+
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+    let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+    let err = parser
+        .records_into_struct::<Missing>()
+        .next()
+        .expect("to have records")
+        .expect_err("record to fail deserialization");
+
+    match err {
+        EvtxError::FailedToParseRecord { record_id, source } => {
+            assert_eq!(record_id, 1);
+            assert!(matches!(
+                *source,
+                EvtxError::SerializationError(SerializationError::JsonError { .. })
+            ));
+        }
+        other => panic!("Unexpected error {:?}", other),
+    }
+}
+
+#[test]
+fn test_event_xml_sample_with_self_closing_empty_elements() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+    let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+        .unwrap()
+        .with_configuration(ParserSettings::new().self_closing_empty_elements(true));
+
+    let first_record = parser
+        .records()
+        .next()
+        .expect("to have records")
+        .expect("record to parse correctly");
+
+    let expected = include_str!("../samples/security_event_1.xml")
+        .lines()
+        .map(str::trim)
+        .collect::<String>()
+        .replace("\"></Provider>", "\"/>")
+        .replace("\"></TimeCreated>", "\"/>")
+        .replace("\"></Execution>", "\"/>")
+        .replace("<Correlation></Correlation>", "<Correlation/>")
+        .replace("<Security></Security>", "<Security/>")
+        .replace("<EventData></EventData>", "<EventData/>");
+
+    assert_eq!(
+        first_record.data.lines().map(str::trim).collect::<String>(),
+        expected
+    );
+}
+
+#[test]
+fn test_sort_event_data() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+    let data_names = |settings: ParserSettings| {
+        EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(settings)
+            .records()
+            .map(|r| {
+                r.unwrap()
+                    .data
+                    .split("<Data Name=\"")
+                    .skip(1)
+                    .map(|data| data.split('"').next().unwrap().to_owned())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let unsorted = data_names(ParserSettings::new());
+    let sorted = data_names(ParserSettings::new().sort_event_data(true));
+
+    assert!(unsorted.iter().any(|names| !names.windows(2).all(|w| w[0] <= w[1])));
+    for (unsorted, sorted) in unsorted.into_iter().zip(sorted) {
+        let mut expected = unsorted;
+        expected.sort();
+        assert_eq!(sorted, expected);
+    }
+}
+
+#[test]
+fn test_event_sample_with_omit_empty_elements() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+    let first_record = |json: bool| {
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(ParserSettings::new().omit_empty_elements(true));
+        let record = if json {
+            parser.records_json().next()
+        } else {
+            parser.records().next()
+        };
+
+        record
+            .expect("to have records")
+            .expect("record to parse correctly")
+            .data
+    };
+
+    // Elements with attributes are kept, even though they have no content.
+    let expected = include_str!("../samples/security_event_1.xml")
+        .lines()
+        .map(str::trim)
+        .collect::<String>()
+        .replace("<Correlation></Correlation>", "")
+        .replace("<Security></Security>", "")
+        .replace("<EventData></EventData>", "");
+
+    assert_eq!(
+        first_record(false).lines().map(str::trim).collect::<String>(),
+        expected
+    );
+
+    let json: serde_json::Value = serde_json::from_str(&first_record(true)).unwrap();
+    let system = json["Event"]["System"].as_object().unwrap();
+    assert!(!system.contains_key("Correlation"));
+    assert!(!system.contains_key("Security"));
+    assert!(system.contains_key("Provider"));
+    assert!(!json["Event"].as_object().unwrap().contains_key("EventData"));
+}
+
+#[test]
+fn test_null_values_rendering() {
+    ensure_env_logger_initialized();
+    // The first record has a null `<Binary>` and a null `UserID` attribute of `<Security>`.
+    let evtx_file = include_bytes!("../samples/system.evtx");
+    let first_record = |null_value: NullRendering| {
+        let settings = ParserSettings::new().null_value(null_value);
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec())
+            .unwrap()
+            .with_configuration(settings);
+
+        let xml = parser.records().next().unwrap().unwrap().data;
+        let json = parser.records_json_value().next().unwrap().unwrap().data;
+
+        (xml, json)
+    };
+
+    let (xml, json) = first_record(NullRendering::JsonNull);
+    assert!(xml.contains("<Binary></Binary>"));
+    assert!(xml.contains("<Security>"));
+    assert_eq!(json["Event"]["EventData"]["Binary"], serde_json::Value::Null);
+    assert_eq!(json["Event"]["System"]["Security"], serde_json::Value::Null);
+
+    let (xml, json) = first_record(NullRendering::EmptyString);
+    assert!(xml.contains("<Binary></Binary>"));
+    assert!(xml.contains("<Security UserID=\"\">"));
+    assert_eq!(json["Event"]["EventData"]["Binary"], "");
+    assert_eq!(
+        json["Event"]["System"]["Security"]["#attributes"]["UserID"],
+        ""
+    );
+
+    let (xml, json) = first_record(NullRendering::OmitKey);
+    assert!(!xml.contains("<Binary>"));
+    assert!(xml.contains("<Security>"));
+    assert!(!json["Event"]["EventData"]
+        .as_object()
+        .unwrap()
+        .contains_key("Binary"));
+    assert_eq!(json["Event"]["System"]["Security"], serde_json::Value::Null);
+}
+
+/// The XML and the JSON value of every record of the file.
+fn records_with_multi_fragment_mode(
+    path: std::path::PathBuf,
+    mode: MultiFragmentMode,
+) -> (Vec<String>, Vec<serde_json::Value>) {
+    let settings = ParserSettings::new().multi_fragment(mode);
+    let mut parser = EvtxParser::from_path(path)
+        .unwrap()
+        .with_configuration(settings);
+
+    let xml: Vec<String> = parser.records().map(|r| r.unwrap().data).collect();
+    let json: Vec<serde_json::Value> = parser
+        .records_json_value()
+        .map(|r| r.unwrap().data)
+        .collect();
+
+    (xml, json)
+}
+
+#[test]
+fn test_multi_fragment_modes() {
+    ensure_env_logger_initialized();
+    let records = |mode: MultiFragmentMode| {
+        records_with_multi_fragment_mode(sample_with_multiple_xml_fragments(), mode)
+    };
+
+    // The nested binxml fragments of the records are expanded into a single root element.
+    let (xml, json) = records(MultiFragmentMode::Concatenate);
+    assert_eq!(xml.len(), 1146);
+    assert!(json.iter().all(|record| record["Event"].is_object()));
+
+    let (first_only_xml, first_only_json) = records(MultiFragmentMode::FirstOnly);
+    assert_eq!(first_only_xml, xml);
+    assert_eq!(first_only_json, json);
+
+    let (wrapped_xml, wrapped_json) = records(MultiFragmentMode::WrapInArray);
+    assert_eq!(wrapped_xml, xml);
+    for (wrapped, record) in wrapped_json.into_iter().zip(json) {
+        assert_eq!(wrapped, serde_json::Value::Array(vec![record]));
+    }
+}
+
+#[test]
+fn test_multi_fragment_modes_with_multiple_roots() {
+    ensure_env_logger_initialized();
+    let last_record = |mode: MultiFragmentMode| {
+        let (mut xml, mut json) =
+            records_with_multi_fragment_mode(sample_with_multiple_root_fragments(), mode);
+        assert_eq!(xml.len(), 91);
+
+        (xml.pop().unwrap(), json.pop().unwrap())
+    };
+
+    let (xml, json) = last_record(MultiFragmentMode::Concatenate);
+    assert!(xml.trim_end().ends_with("</Event>\n<Extra>second root</Extra>"));
+    assert!(json["Event"].is_object());
+    assert_eq!(json["Extra"], "second root");
+
+    let (first_only_xml, first_only_json) = last_record(MultiFragmentMode::FirstOnly);
+    assert!(first_only_xml.trim_end().ends_with("</Event>"));
+    assert!(!first_only_xml.contains("Extra"));
+    assert_eq!(first_only_json["Event"], json["Event"]);
+    assert_eq!(first_only_json.as_object().unwrap().len(), 1);
+
+    let (wrapped_xml, wrapped_json) = last_record(MultiFragmentMode::WrapInArray);
+    assert_eq!(wrapped_xml, xml);
+    assert_eq!(
+        wrapped_json,
+        serde_json::json!([{ "Event": json["Event"] }, { "Extra": "second root" }])
+    );
+}
+
+#[test]
+fn test_attribute_order_is_stable() {
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+    let first_record = |json: bool| {
+        let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+        let record = if json {
+            parser.records_json().next()
+        } else {
+            parser.records().next()
+        };
+
+        record
+            .expect("to have records")
+            .expect("record to parse correctly")
+            .data
+    };
+
+    // XML attributes are written in the order they appear in the binxml.
+    let xml = first_record(false);
+    assert!(xml.contains(
+        "<Provider Name=\"Microsoft-Windows-Security-Auditing\" Guid=\"54849625-5478-4994-A5BA-3E3B0328C30D\">"
+    ));
+    assert!(xml.contains("<Execution ProcessID=\"456\" ThreadID=\"460\">"));
+    assert_eq!(first_record(false), xml);
+
+    // JSON objects are always written with their keys in the same order.
+    let json = first_record(true);
+    assert_eq!(first_record(true), json);
+}
+
+#[test]
+fn test_records_to_custom_output() {
+    /// Collects the names of the elements of a record.
+    #[derive(Default)]
+    struct ElementNames(Vec<String>);
+
+    impl BinXmlOutput for ElementNames {
+        fn visit_end_of_stream(&mut self) -> SerializationResult<()> {
+            Ok(())
+        }
+        fn visit_open_start_element(&mut self, element: &XmlElement) -> SerializationResult<()> {
+            self.0.push(element.name.as_str().to_owned());
+            Ok(())
+        }
+        fn visit_close_element(&mut self, _: &XmlElement) -> SerializationResult<()> {
+            Ok(())
+        }
+        fn visit_characters(&mut self, _: &BinXmlValue) -> SerializationResult<()> {
+            Ok(())
+        }
+        fn visit_cdata_section(&mut self, _: Cow<'_, str>) -> SerializationResult<()> {
+            Ok(())
+        }
+        fn visit_entity_reference(&mut self, _: &BinXmlName) -> SerializationResult<()> {
+            Ok(())
+        }
+        fn visit_character_reference(&mut self, _: Cow<'_, str>) -> SerializationResult<()> {
+            Ok(())
+        }
+        fn visit_processing_instruction(&mut self, _: &BinXmlPI) -> SerializationResult<()> {
+            Ok(())
+        }
+        fn visit_start_of_stream(&mut self) -> SerializationResult<()> {
+            Ok(())
+        }
+    }
+
+    ensure_env_logger_initialized();
+    let evtx_file = include_bytes!("../samples/security.evtx");
+    let mut parser = EvtxParser::from_buffer(evtx_file.to_vec()).unwrap();
+
+    let records: Vec<_> = parser
+        .records_to_output(ElementNames::default)
+        .map(|r| r.expect("record to parse correctly"))
+        .collect();
+
+    assert_eq!(records.len(), 2261);
+    assert_eq!(records[0].event_record_id, 1);
+    assert_eq!(
+        &records[0].data.0[..4],
+        &["Event", "System", "Provider", "EventID"]
+    );
+}